@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+
+use crate::trace::Trace;
+
+// LineStats holds the execution statistics gathered for a single source line
+struct LineStats {
+    // count is the number of times an instruction from the line was executed
+    count: usize,
+    // calculator is the value of the calculator after the last execution,
+    // shown signed if the trace was recorded in signed mode
+    calculator: Option<String>,
+    // taken is the number of times a branch on the line was taken
+    taken: usize,
+}
+
+// annotate appends the execution count and last seen calculator value to
+// every source line that assembled into a mailbox, using the source map to
+// tie each traced address back to the line it came from. Branches also show
+// how many times they were taken.
+pub fn annotate(source: &[String], source_map: &[usize], trace: &Trace) -> Vec<String> {
+    let mut stats: HashMap<usize, LineStats> = HashMap::new();
+    for line in source_map {
+        stats.insert(
+            *line,
+            LineStats {
+                count: 0,
+                calculator: None,
+                taken: 0,
+            },
+        );
+    }
+    for event in &trace.events {
+        let line = match source_map.get(event.address as usize) {
+            Some(line) => line,
+            None => continue,
+        };
+        if let Some(stat) = stats.get_mut(line) {
+            stat.count += 1;
+            stat.calculator = Some(match event.signed {
+                Some(signed) => signed.to_string(),
+                None => format!("{:03}", event.calculator),
+            });
+            if event.branch.as_ref().is_some_and(|branch| branch.taken) {
+                stat.taken += 1;
+            }
+        }
+    }
+    // Lines holding a branch, known from the trace recording its decisions
+    let branches = trace
+        .events
+        .iter()
+        .filter(|event| event.branch.is_some())
+        .filter_map(|event| source_map.get(event.address as usize).copied())
+        .collect::<Vec<usize>>();
+    // Pad the annotations so they line up in a column after the longest line
+    let width = source.iter().map(|line| line.len()).max().unwrap_or(0);
+    source
+        .iter()
+        .enumerate()
+        .map(|(i, line)| match stats.get(&(i + 1)) {
+            Some(LineStats { count: 0, .. }) => {
+                format!("{:width$}  # never executed", line, width = width)
+            }
+            Some(LineStats {
+                count,
+                calculator: Some(calculator),
+                taken,
+            }) => {
+                let mut annotation = format!(
+                    "{:width$}  # executed {} times, calculator {}",
+                    line,
+                    count,
+                    calculator,
+                    width = width
+                );
+                if branches.contains(&(i + 1)) {
+                    annotation.push_str(&format!(", taken {} times", taken));
+                }
+                annotation
+            }
+            _ => line.to_string(),
+        })
+        .collect()
+}
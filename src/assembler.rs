@@ -1,28 +1,47 @@
 use regex::Regex;
-use std::{collections::HashMap, fmt};
+use std::{collections::BTreeMap, fmt};
 
 use crate::{
-    logger::{LogLevel, Logger},
+    logger::{Component, LogLevel, Logger},
+    macros,
     numbers::ThreeDigitNumber,
+    program::{Assertion, Comparison, Diagnostic, Operand, Program, Span},
+    suggest::closest,
+    syntax,
 };
 
 // AssemblerError is used to indicate an error with the assembler
+#[derive(Debug, PartialEq)]
 pub enum AssemblerError {
-    InvalidOpcode(String),
-    InvalidLabel(String),
+    // InvalidOpcode and InvalidLabel hold the closest valid word, if any
+    InvalidOpcode(String, Option<String>),
+    InvalidLabel(String, Option<String>),
     InvalidNumberOfMneumonics(usize, String),
     EmptyInput,
     TooManyLinesOfInput(usize),
+    InvalidAssertion(usize, String),
+    InvalidValue(String),
+    // InvalidMacro is a macro that cannot be defined or expanded
+    InvalidMacro(String),
+    // DanglingLabel is a label on a line of its own with no instruction after
+    // it to label
+    DanglingLabel(String),
+    // Located is an error along with where in the source it was found
+    Located(Span, Box<AssemblerError>),
 }
 
 // Implement the display trait for easy printing.
 impl fmt::Display for AssemblerError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            AssemblerError::InvalidOpcode(opcode) => {
-                write!(f, "invalid opcode: got {}", opcode)
+            AssemblerError::InvalidOpcode(opcode, suggestion) => {
+                write!(f, "invalid opcode: got {}", opcode)?;
+                write_suggestion(f, suggestion)
+            }
+            AssemblerError::InvalidLabel(label, suggestion) => {
+                write!(f, "invalid label: got {}", label)?;
+                write_suggestion(f, suggestion)
             }
-            AssemblerError::InvalidLabel(label) => write!(f, "invalid label: got {}", label),
             AssemblerError::InvalidNumberOfMneumonics(index, line) => {
                 write!(
                     f,
@@ -34,10 +53,118 @@ impl fmt::Display for AssemblerError {
             AssemblerError::TooManyLinesOfInput(lines) => {
                 write!(f, "too many lines of input: got {}", lines)
             }
+            AssemblerError::InvalidAssertion(_, assertion) => {
+                write!(f, "invalid assertion: {}", assertion)
+            }
+            AssemblerError::InvalidValue(value) => write!(f, "invalid value: got {}", value),
+            AssemblerError::InvalidMacro(value) => write!(f, "invalid macro: {}", value),
+            AssemblerError::DanglingLabel(label) => {
+                write!(
+                    f,
+                    "label {} must be followed by an unlabelled instruction",
+                    label
+                )
+            }
+            AssemblerError::Located(span, err) => write!(f, "{}: {}", span, err),
         }
     }
 }
 
+// Implement the error trait so the error can be used with the ? operator
+// alongside other errors.
+impl std::error::Error for AssemblerError {}
+
+// write_suggestion appends the closest valid word to an error message
+fn write_suggestion(f: &mut fmt::Formatter<'_>, suggestion: &Option<String>) -> fmt::Result {
+    match suggestion {
+        Some(suggestion) => write!(f, ", did you mean {}?", suggestion),
+        None => Ok(()),
+    }
+}
+
+impl AssemblerError {
+    // code returns a short name for the kind of error that stays the same
+    // whatever its message, for tools reading JSON diagnostics
+    pub fn code(&self) -> &'static str {
+        match self {
+            AssemblerError::InvalidOpcode(..) => "invalid-opcode",
+            AssemblerError::InvalidLabel(..) => "invalid-label",
+            AssemblerError::InvalidNumberOfMneumonics(..) => "invalid-mnemonics",
+            AssemblerError::EmptyInput => "empty-input",
+            AssemblerError::TooManyLinesOfInput(_) => "too-many-lines",
+            AssemblerError::InvalidAssertion(..) => "invalid-assertion",
+            AssemblerError::InvalidValue(_) => "invalid-value",
+            AssemblerError::InvalidMacro(_) => "invalid-macro",
+            AssemblerError::DanglingLabel(_) => "dangling-label",
+            AssemblerError::Located(_, err) => err.code(),
+        }
+    }
+
+    // fix returns the word that caused the error and the closest valid word
+    // to replace it with, if there is one
+    pub fn fix(&self) -> Option<(&str, &str)> {
+        match self {
+            AssemblerError::InvalidOpcode(word, Some(suggestion))
+            | AssemblerError::InvalidLabel(word, Some(suggestion)) => {
+                Some((word.as_str(), suggestion.as_str()))
+            }
+            AssemblerError::Located(_, err) => err.fix(),
+            _ => None,
+        }
+    }
+
+    // word returns the word of source the error is about, if it is about
+    // a single word rather than a whole line
+    fn word(&self) -> Option<&str> {
+        match self {
+            AssemblerError::InvalidOpcode(word, _)
+            | AssemblerError::InvalidLabel(word, _)
+            | AssemblerError::InvalidValue(word) => Some(word.as_str()),
+            _ => None,
+        }
+    }
+}
+
+// locate finds where in a line of source an error is: the word it is about,
+// or every word of the line otherwise
+fn locate(line: usize, text: &str, err: &AssemblerError) -> Span {
+    let tokens = syntax::tokenize_line(line, text)
+        .tokens
+        .into_iter()
+        .filter(|token| token.kind != syntax::TokenKind::Comment)
+        .collect::<Vec<syntax::Token>>();
+    let word = err
+        .word()
+        .and_then(|word| tokens.iter().find(|token| token.text == word));
+    match (word, tokens.first(), tokens.last()) {
+        (Some(token), _, _) => Span {
+            line,
+            start: token.start,
+            end: token.end,
+        },
+        (None, Some(first), Some(last)) => Span {
+            line,
+            start: first.start,
+            end: last.end,
+        },
+        _ => Span {
+            line,
+            start: 0,
+            end: 0,
+        },
+    }
+}
+
+// MNEMONICS are the names of every opcode
+pub const MNEMONICS: [&str; 11] = [
+    "ADD", "SUB", "STO", "LDA", "BR", "BRZ", "BRP", "IN", "OUT", "HLT", "DAT",
+];
+
+// ALIASES are the names other simulators give some opcodes, each along with
+// the mnemonic it stands for
+pub const ALIASES: [(&str, &str); 4] =
+    [("STA", "STO"), ("BRA", "BR"), ("INP", "IN"), ("COB", "HLT")];
+
 // OPCODES are the opcodes for the LMC
 enum OPCODES {
     ADD, // 1xx ADDITION
@@ -71,9 +198,15 @@ impl OPCODES {
         }
     }
 
-    // from_str converts a string to an opcode
+    // from_str converts a string to an opcode, ignoring case and accepting
+    // any of the aliases in place of the mnemonic
     pub fn from_str(opcode: &str) -> Result<OPCODES, AssemblerError> {
-        match opcode {
+        let upper = opcode.to_ascii_uppercase();
+        let mnemonic = ALIASES
+            .iter()
+            .find(|(alias, _)| *alias == upper)
+            .map_or(upper.as_str(), |(_, mnemonic)| mnemonic);
+        match mnemonic {
             "ADD" => Ok(OPCODES::ADD),
             "SUB" => Ok(OPCODES::SUB),
             "STO" => Ok(OPCODES::STO),
@@ -85,197 +218,408 @@ impl OPCODES {
             "OUT" => Ok(OPCODES::OUT),
             "HLT" => Ok(OPCODES::HLT),
             "DAT" => Ok(OPCODES::DAT),
-            _ => Err(AssemblerError::InvalidOpcode(opcode.to_string())),
+            _ => Err(AssemblerError::InvalidOpcode(
+                opcode.to_string(),
+                closest(opcode, MNEMONICS),
+            )),
+        }
+    }
+}
+
+// is_mnemonic returns whether the word is an opcode or pseudo-instruction
+pub fn is_mnemonic(word: &str) -> bool {
+    word == "ASSERT"
+        || word.eq_ignore_ascii_case("MACRO")
+        || word.eq_ignore_ascii_case("ENDM")
+        || OPCODES::from_str(word).is_ok()
+}
+
+// strip_label_colon removes the colon that many textbooks end a label with,
+// e.g. LOOP: ADD ONE, so that the rest of the assembler never sees it
+fn strip_label_colon(line: &str) -> String {
+    let mut parts = line.splitn(2, char::is_whitespace);
+    match (parts.next(), parts.next()) {
+        (Some(label), Some(rest)) if label.len() > 1 && label.ends_with(':') => {
+            format!("{} {}", &label[..label.len() - 1], rest.trim_start())
+        }
+        _ => line.to_string(),
+    }
+}
+
+// attach_lone_labels moves each label written with a colon on a line of its
+// own, e.g. LOOP: followed by ADD ONE on the next line, onto the instruction
+// that follows it, skipping any ASSERT in between. The instruction keeps its
+// own line number. A lone label followed by another label, or by nothing, is
+// returned as an error along with its line number.
+fn attach_lone_labels(
+    lines: Vec<(usize, String)>,
+) -> Result<Vec<(usize, String)>, (usize, AssemblerError)> {
+    let mut attached = Vec::new();
+    let mut pending: Option<(usize, String)> = None;
+    for (line, text) in lines {
+        let words = text.split_whitespace().collect::<Vec<&str>>();
+        let lone = match words.as_slice() {
+            [label] if label.len() > 1 && label.ends_with(':') => {
+                Some(label[..label.len() - 1].to_string())
+            }
+            _ => None,
+        };
+        let labelled = match words.as_slice() {
+            [first, second, ..] => !is_mnemonic(first) && is_mnemonic(second),
+            _ => false,
+        };
+        let text = match pending.take() {
+            Some(label) if words.first() == Some(&"ASSERT") => {
+                pending = Some(label);
+                text
+            }
+            Some((line, label)) if lone.is_some() || labelled => {
+                return Err((line, AssemblerError::DanglingLabel(label)))
+            }
+            Some((_, label)) => format!("{} {}", label, text),
+            None => text,
+        };
+        match lone {
+            Some(label) => pending = Some((line, label)),
+            None => attached.push((line, text)),
         }
     }
+    match pending {
+        Some((line, label)) => Err((line, AssemblerError::DanglingLabel(label))),
+        None => Ok(attached),
+    }
+}
+
+// resolve_operand returns the mailbox address an operand refers to, either
+// the index of a label or a literal 2-digit address such as 97
+fn resolve_operand(
+    labels: &BTreeMap<String, usize>,
+    operand: &str,
+) -> Result<usize, AssemblerError> {
+    if let Some(index) = labels.get(operand) {
+        return Ok(*index);
+    }
+    match operand.parse::<usize>() {
+        Ok(address) if address <= 99 => Ok(address),
+        _ => Err(AssemblerError::InvalidLabel(
+            operand.to_string(),
+            closest(operand, labels.keys().map(|label| label.as_str())),
+        )),
+    }
 }
 
 // Assembler is used to assemble LMC programs
 pub struct Assembler {
     // logger is used to log messages to the console
     logger: Logger,
+    // partial is whether to assemble what it can of invalid source, leaving
+    // a HLT in the mailbox of each line that fails with a diagnostic for it
+    partial: bool,
 }
 
 impl Assembler {
     // new creates a new Assembler instance
     pub fn new(verbose: bool, debug: bool) -> Self {
+        let mut logger = Logger::new(verbose, debug);
+        logger.set_component(Component::ASM);
         Assembler {
-            logger: Logger::new(verbose, debug),
+            logger,
+            partial: false,
         }
     }
 
-    // assemble assembles a program in the form of a vector of strings
-    // into a vector of ThreeDigitNumbers representing the LMC's mailboxes
-    pub fn assemble(
-        self: &Self,
-        input: &mut Vec<String>,
-    ) -> Result<Vec<ThreeDigitNumber>, AssemblerError> {
+    // set_partial sets whether to recover from errors in individual lines
+    pub fn set_partial(&mut self, partial: bool) {
+        self.partial = partial;
+    }
+
+    // assemble_program assembles a program in the form of a vector of strings
+    // into the ThreeDigitNumbers representing the LMC's mailboxes, along with
+    // the metadata gathered along the way: the source map, giving the 1-based
+    // source line number of each mailbox, and any ASSERT pseudo-instructions
+    pub fn assemble_program(&self, input: &mut [String]) -> Result<Program, AssemblerError> {
         self.logger
             .log(&LogLevel::Info, "assembling program into machine code...");
-        // Create a hashmap for labels
-        let mut labels: HashMap<String, usize> = HashMap::new();
+        // Create a map for labels
+        let mut labels: BTreeMap<String, usize> = BTreeMap::new();
         // Compile a regex to strip comments
         let comment_regex = Regex::new(r"#.*$").unwrap();
-        // Strip comments and trim whitespace left over
+        // Strip comments and trim whitespace left over, keeping track of the
+        // original line number of each line for the source map
         self.logger.log(&LogLevel::Debug, "stripping comments...");
-        let mut stripped_input = input
-            .into_iter()
-            .map(|line| comment_regex.replace_all(line, "").trim().to_string())
-            .collect::<Vec<String>>();
+        let mut numbered_input = input
+            .iter_mut()
+            .enumerate()
+            .map(|(i, line)| {
+                let line = comment_regex.replace_all(line, "");
+                (i + 1, strip_label_colon(line.trim()))
+            })
+            .collect::<Vec<(usize, String)>>();
         // Remove empty lines
         self.logger.log(&LogLevel::Debug, "removing empty lines...");
-        stripped_input.retain(|line| line.len() > 0);
-        if stripped_input.len() == 0 {
+        numbered_input.retain(|(_, line)| !line.is_empty());
+        let numbered_input = attach_lone_labels(numbered_input).map_err(|(line, err)| {
+            AssemblerError::Located(locate(line, &input[line - 1], &err), Box::new(err))
+        })?;
+        // Expand macros, every line of an expansion keeping the line number
+        // of the invocation
+        self.logger.log(&LogLevel::Debug, "expanding macros...");
+        let mut numbered_input = macros::expand(numbered_input).map_err(|(line, err)| {
+            AssemblerError::Located(locate(line, &input[line - 1], &err), Box::new(err))
+        })?;
+        // Pull out ASSERT pseudo-instructions, they do not occupy a mailbox and
+        // instead guard the instruction that follows them
+        self.logger
+            .log(&LogLevel::Debug, "collecting assertions...");
+        let mut pending_assertions: Vec<(usize, usize, String)> = Vec::new();
+        numbered_input.retain(|(line_number, line)| match line.strip_prefix("ASSERT") {
+            Some(expression)
+                if expression.is_empty() || expression.starts_with(char::is_whitespace) =>
+            {
+                pending_assertions.push((*line_number, 0, expression.trim().to_string()));
+                false
+            }
+            _ => true,
+        });
+        for assertion in pending_assertions.iter_mut() {
+            assertion.1 = numbered_input
+                .iter()
+                .filter(|(line_number, _)| *line_number < assertion.0)
+                .count();
+        }
+        let (mut source_map, mut stripped_input): (Vec<usize>, Vec<String>) =
+            numbered_input.into_iter().unzip();
+        if stripped_input.is_empty() {
             return Err(AssemblerError::EmptyInput);
         }
+        let mut diagnostics = Vec::new();
         // Check for too many lines of input
         if stripped_input.len() > 100 {
-            return Err(AssemblerError::TooManyLinesOfInput(stripped_input.len()));
+            let line = source_map[100];
+            self.recover(
+                &mut diagnostics,
+                input,
+                line,
+                AssemblerError::TooManyLinesOfInput(stripped_input.len()),
+            )?;
+            stripped_input.truncate(100);
+            source_map.truncate(100);
         }
+        // Track which lines could not be assembled, they are left as HLT
+        let mut invalid = vec![false; stripped_input.len()];
         // TODO: Simplify and optimise this 2-pass strategy
         self.logger.log(&LogLevel::Info, "starting first pass...");
-        for i in 0..stripped_input.len() {
-            let line = &stripped_input[i];
-            // Split the line into its parts. A line of LMC assembly can have up to 3 distinct
-            // parts: a label, an opcode, and an operand. The label is optional, but the opcode
-            // is not. Depending on the opcode, the operand may be optional.
-            let parts = line.split_whitespace().collect::<Vec<&str>>();
-            match parts.len() {
-                1 => {} // Just an opcode - no labels or operands
-                // Two parts - either a label and an opcode, or an opcode and an operand
-                2 => {
-                    // Check if the first part is an opcode
-                    match OPCODES::from_str(parts[0]) {
-                        Ok(_) => {} // No label to collect
-                        Err(_) => {
-                            // The first part is not an opcode, so it must be a label
-                            // add the label to the hashmap with its index for later use
-                            match OPCODES::from_str(parts[1]) {
-                                Ok(_) => {
-                                    labels.insert(parts[0].to_string(), i);
-                                    self.logger.log(
-                                        &LogLevel::Debug,
-                                        format!("inserting label {} at index {}", parts[0], i)
-                                            .as_str(),
-                                    );
-                                }
-                                Err(e) => return Err(e),
-                            };
-                        }
-                    };
-                }
-                // Three parts - a label, an opcode, and an operand
-                3 => {
-                    // Insert the label into the hashmap with its index for later use
-                    labels.insert(parts[0].to_string(), i);
+        for (i, line) in stripped_input.iter().enumerate() {
+            match self.collect_label(line) {
+                Ok(Some(label)) => {
                     self.logger.log(
                         &LogLevel::Debug,
-                        format!("inserting label {} at index {}", parts[0], i).as_str(),
+                        format!("inserting label {} at index {}", label, i).as_str(),
                     );
+                    labels.insert(label, i);
                 }
-                // Anything else is invalid
-                n => {
-                    return Err(AssemblerError::InvalidNumberOfMneumonics(
-                        n,
-                        line.to_string(),
-                    ));
+                Ok(None) => {}
+                Err(err) => {
+                    self.recover(&mut diagnostics, input, source_map[i], err)?;
+                    invalid[i] = true;
                 }
             }
         }
+        // Resolve the operands of each assertion now all labels are known
+        let mut assertions = Vec::new();
+        for (line, address, expression) in pending_assertions {
+            match self.parse_assertion(line, address, &expression, &labels) {
+                Ok(assertion) => assertions.push(assertion),
+                Err(err) => self.recover(&mut diagnostics, input, line, err)?,
+            }
+        }
         self.logger.log(&LogLevel::Info, "starting second pass...");
         let mut result = vec![ThreeDigitNumber::new(0).unwrap(); stripped_input.len()];
+        // Track which mailboxes hold data rather than instructions
+        let mut data = vec![false; stripped_input.len()];
         for (i, line) in stripped_input.iter().enumerate() {
-            let parts = line.split_whitespace().collect::<Vec<&str>>();
-            let opcode: OPCODES;
-            match parts.len() {
-                // One part - just an opcode
-                1 => {
-                    // Convert the opcode to a ThreeDigitNumber and add it to the result
-                    // vector at the current index
-                    opcode = OPCODES::from_str(parts[0])?;
-                    result[i] = opcode.to_number();
-                    self.logger.log(
-                        &LogLevel::Debug,
-                        format!("{}:\t{}", i, opcode.to_number()).as_str(),
-                    );
+            if invalid[i] {
+                continue;
+            }
+            match self.assemble_line(line, &labels) {
+                Ok((value, is_data)) => {
+                    result[i] = value;
+                    data[i] = is_data;
+                    self.logger
+                        .log(&LogLevel::Debug, format!("{}:\t{}", i, value).as_str());
                 }
-                // Two parts - either a label and an opcode, or an opcode and an operand
-                2 => {
-                    match OPCODES::from_str(parts[0]) {
-                        // First part is an opcode
-                        Ok(_) => {
-                            // Retrieve the opcode and and the index the operand refers to
-                            // from the hashmap of labels
-                            opcode = OPCODES::from_str(parts[0])?;
-                            let label = match labels.get(parts[1]) {
-                                Some(i) => i,
-                                None => {
-                                    return Err(AssemblerError::InvalidLabel(parts[1].to_string()));
-                                }
-                            };
-                            // Convert the index to a ThreeDigitNumber and add it to the
-                            // opcode to get the final instruction's ThreeDigitNumber value
-                            let value = ThreeDigitNumber::new(*label as i16).unwrap();
-                            let instruction = opcode.to_number() + value;
-                            result[i] = instruction.unwrap();
-                            self.logger.log(
-                                &LogLevel::Debug,
-                                format!("{}:\t{}", i, (opcode.to_number() + value).unwrap())
-                                    .as_str(),
-                            );
-                        }
-                        // First part is a label
-                        Err(_) => {
-                            // Retrieve the opcode and convert it to a ThreeDigitNumber
-                            match OPCODES::from_str(parts[1]) {
-                                Ok(op) => {
-                                    result[i] = op.to_number();
-                                }
-                                Err(e) => return Err(e),
-                            };
-                        }
-                    };
+                Err(err) => self.recover(&mut diagnostics, input, source_map[i], err)?,
+            }
+        }
+
+        Ok(Program {
+            instructions: result,
+            source_map,
+            assertions,
+            data,
+            labels,
+            diagnostics,
+        })
+    }
+
+    // recover records the error against where it was found in the source as
+    // a diagnostic when assembling partially, otherwise it is returned along
+    // with where it was found to stop assembly
+    fn recover(
+        &self,
+        diagnostics: &mut Vec<Diagnostic>,
+        source: &[String],
+        line: usize,
+        err: AssemblerError,
+    ) -> Result<(), AssemblerError> {
+        let span = locate(line, &source[line - 1], &err);
+        if !self.partial {
+            return Err(AssemblerError::Located(span, Box::new(err)));
+        }
+        self.logger
+            .log(&LogLevel::Debug, format!("{}: {}", span, err).as_str());
+        diagnostics.push(Diagnostic {
+            span,
+            code: err.code(),
+            message: err.to_string(),
+        });
+        Ok(())
+    }
+
+    // collect_label returns the label defined by a line, if any
+    fn collect_label(&self, line: &str) -> Result<Option<String>, AssemblerError> {
+        // Split the line into its parts. A line of LMC assembly can have up to 3 distinct
+        // parts: a label, an opcode, and an operand. The label is optional, but the opcode
+        // is not. Depending on the opcode, the operand may be optional.
+        let parts = line.split_whitespace().collect::<Vec<&str>>();
+        match parts.len() {
+            1 => Ok(None), // Just an opcode - no labels or operands
+            // Two parts - either a label and an opcode, or an opcode and an operand
+            2 => match OPCODES::from_str(parts[0]) {
+                Ok(_) => Ok(None), // No label to collect
+                // The first part is not an opcode, so it must be a label
+                Err(first) => match OPCODES::from_str(parts[1]) {
+                    Ok(_) => Ok(Some(parts[0].to_string())),
+                    // Neither part is an opcode, if the first looks like a
+                    // misspelt opcode report that rather than the operand
+                    Err(_) if first.fix().is_some() => Err(first),
+                    Err(e) => Err(e),
+                },
+            },
+            // Three parts - a label, an opcode, and an operand
+            3 => Ok(Some(parts[0].to_string())),
+            // Anything else is invalid
+            n => Err(AssemblerError::InvalidNumberOfMneumonics(
+                n,
+                line.to_string(),
+            )),
+        }
+    }
+
+    // assemble_line converts a line into the value of its mailbox, along with
+    // whether it holds data rather than an instruction
+    fn assemble_line(
+        &self,
+        line: &str,
+        labels: &BTreeMap<String, usize>,
+    ) -> Result<(ThreeDigitNumber, bool), AssemblerError> {
+        let parts = line.split_whitespace().collect::<Vec<&str>>();
+        match parts.len() {
+            // One part - just an opcode
+            1 => {
+                let opcode = OPCODES::from_str(parts[0])?;
+                Ok((opcode.to_number(), matches!(opcode, OPCODES::DAT)))
+            }
+            // Two parts - either a label and an opcode, or an opcode and an operand
+            2 => match OPCODES::from_str(parts[0]) {
+                // First part is an opcode, add the index the operand refers to
+                Ok(opcode) => {
+                    let label = resolve_operand(labels, parts[1])?;
+                    let value = ThreeDigitNumber::new(label as i16).unwrap();
+                    let instruction = (opcode.to_number() + value).unwrap();
+                    Ok((instruction, matches!(opcode, OPCODES::DAT)))
                 }
-                // Three parts - a label, an opcode, and an operand
-                3 => {
-                    // Retrieve the opcode
-                    opcode = OPCODES::from_str(parts[1])?;
-                    match opcode {
-                        // DAT is a special case and is used to signify a data storage location
-                        // rather than an instruction. The operand is the value to store in the
-                        // mailbox at the current index.
-                        OPCODES::DAT => {
-                            let value =
-                                ThreeDigitNumber::new(parts[2].parse::<i16>().unwrap()).unwrap();
-                            result[i] = value;
-                            self.logger
-                                .log(&LogLevel::Debug, format!("{}:\t{}", i, value).as_str());
-                            continue;
-                        }
-                        // Otherwise, the operand is an index to a label in the hashmap so continue
-                        _ => {}
-                    };
-                    // Retrieve the index the operand refers to from the hashmap of labels
-                    let label = match labels.get(parts[2]) {
-                        Some(i) => i,
-                        None => {
-                            return Err(AssemblerError::InvalidLabel(parts[2].to_string()));
-                        }
-                    };
-                    // Convert the index to a ThreeDigitNumber and add it to the opcode to get
-                    // the final instruction's ThreeDigitNumber value and add it to the result
-                    let value = ThreeDigitNumber::new(*label as i16).unwrap();
-                    let instruction = opcode.to_number() + value;
-                    result[i] = instruction.unwrap();
-                    self.logger.log(
-                        &LogLevel::Debug,
-                        format!("{}:\t{}", i, (opcode.to_number() + value).unwrap()).as_str(),
-                    );
+                // First part is a label
+                Err(_) => {
+                    let opcode = OPCODES::from_str(parts[1])?;
+                    Ok((opcode.to_number(), matches!(opcode, OPCODES::DAT)))
+                }
+            },
+            // Three parts - a label, an opcode, and an operand
+            3 => {
+                let opcode = OPCODES::from_str(parts[1])?;
+                // DAT is a special case and is used to signify a data storage location
+                // rather than an instruction. The operand is the value to store in the
+                // mailbox at the current index. Otherwise, the operand is an index to a
+                // label in the hashmap
+                // a negative value is stored in ten's complement, for --signed
+                if let OPCODES::DAT = opcode {
+                    let value = parts[2]
+                        .parse::<i16>()
+                        .ok()
+                        .and_then(|value| match value < 0 {
+                            true => ThreeDigitNumber::from_signed(value).ok(),
+                            false => ThreeDigitNumber::new(value).ok(),
+                        })
+                        .ok_or_else(|| AssemblerError::InvalidValue(parts[2].to_string()))?;
+                    return Ok((value, true));
                 }
-                _ => {}
+                let label = resolve_operand(labels, parts[2])?;
+                let value = ThreeDigitNumber::new(label as i16).unwrap();
+                Ok(((opcode.to_number() + value).unwrap(), false))
             }
+            n => Err(AssemblerError::InvalidNumberOfMneumonics(
+                n,
+                line.to_string(),
+            )),
         }
+    }
 
-        Ok(result)
+    // parse_assertion parses the expression of an ASSERT pseudo-instruction in
+    // the form <operand> <comparison> <operand> where each operand is either ACC
+    // for the calculator, a label for the mailbox it refers to or a literal
+    fn parse_assertion(
+        &self,
+        line: usize,
+        address: usize,
+        expression: &str,
+        labels: &BTreeMap<String, usize>,
+    ) -> Result<Assertion, AssemblerError> {
+        let invalid = || AssemblerError::InvalidAssertion(line, expression.to_string());
+        let parts = expression.split_whitespace().collect::<Vec<&str>>();
+        if parts.len() != 3 {
+            return Err(invalid());
+        }
+        let operand = |part: &str| -> Result<Operand, AssemblerError> {
+            if part == "ACC" {
+                return Ok(Operand::Calculator);
+            }
+            if let Ok(value) = part.parse::<i16>() {
+                return match ThreeDigitNumber::new(value) {
+                    Ok(_) => Ok(Operand::Literal(value)),
+                    Err(_) => Err(invalid()),
+                };
+            }
+            match labels.get(part) {
+                Some(address) => Ok(Operand::Mailbox(*address)),
+                None => Err(AssemblerError::InvalidLabel(
+                    part.to_string(),
+                    closest(part, labels.keys().map(|label| label.as_str())),
+                )),
+            }
+        };
+        let assertion = Assertion {
+            address,
+            line,
+            expression: expression.to_string(),
+            left: operand(parts[0])?,
+            comparison: Comparison::from_str(parts[1]).ok_or_else(invalid)?,
+            right: operand(parts[2])?,
+        };
+        self.logger.log(
+            &LogLevel::Debug,
+            format!("{}:\t{}", address, assertion).as_str(),
+        );
+        Ok(assertion)
     }
 }
@@ -0,0 +1,183 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt, fs,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{program, schema::BUNDLE_SCHEMA};
+
+// PROGRESS_FILE is the student's own progress through a problem set, which
+// is never packed into a bundle handed to others
+const PROGRESS_FILE: &str = "lmc-progress.toml";
+
+// BundleError is used to indicate an error packing or unpacking a bundle
+#[derive(Debug, PartialEq)]
+pub enum BundleError {
+    IOError(String),
+    InvalidBundle(String),
+    NotText(String),
+    UnsafePath(String),
+    HashMismatch(String),
+    AlreadyExists(String),
+}
+
+// Implement the display trait for easy printing.
+impl fmt::Display for BundleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BundleError::IOError(value) => write!(f, "IO error: {}", value),
+            BundleError::InvalidBundle(value) => write!(f, "invalid bundle: {}", value),
+            BundleError::NotText(path) => write!(f, "{} is not a text file", path),
+            BundleError::UnsafePath(path) => {
+                write!(f, "{} would be unpacked outside the directory", path)
+            }
+            BundleError::HashMismatch(path) => {
+                write!(f, "{} does not match its hash: the bundle is corrupt", path)
+            }
+            BundleError::AlreadyExists(path) => write!(f, "{} already exists", path),
+        }
+    }
+}
+
+// Implement the error trait so the error can be used with the ? operator
+// alongside other errors.
+impl std::error::Error for BundleError {}
+
+// BundleFile is a single file of a bundle along with the hash of its contents
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleFile {
+    // path is relative to the bundled directory, with / between components
+    pub path: String,
+    // hash is the FNV-1a hash of the contents in hex
+    pub hash: String,
+    pub contents: String,
+}
+
+// Bundle is a whole lab in one file: its programs, tests, exercise specs and
+// config, so that it can be handed out and opened with a single command
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bundle {
+    pub schema: String,
+    // name is the name of the bundled directory
+    pub name: String,
+    pub files: Vec<BundleFile>,
+}
+
+// hash returns the hash of the contents as written in a bundle
+fn hash(contents: &str) -> String {
+    format!("{:08x}", program::fnv1a(contents.bytes()))
+}
+
+impl Bundle {
+    // pack collects every file under the directory, leaving out hidden files
+    // and the progress file, in order of their paths
+    pub fn pack(dir: &Path) -> Result<Bundle, BundleError> {
+        let mut files = Vec::new();
+        collect(dir, Path::new(""), &mut files)?;
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        let name = fs::canonicalize(dir)
+            .ok()
+            .and_then(|dir| {
+                dir.file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+            })
+            .unwrap_or_else(|| "bundle".to_string());
+        Ok(Bundle {
+            schema: BUNDLE_SCHEMA.to_string(),
+            name,
+            files,
+        })
+    }
+
+    // load reads a bundle written by save
+    pub fn load(path: &Path) -> Result<Bundle, BundleError> {
+        let text = fs::read_to_string(path).map_err(|e| BundleError::IOError(e.to_string()))?;
+        let bundle: Bundle =
+            serde_json::from_str(&text).map_err(|e| BundleError::InvalidBundle(e.to_string()))?;
+        if bundle.schema != BUNDLE_SCHEMA {
+            return Err(BundleError::InvalidBundle(format!(
+                "unsupported schema {}",
+                bundle.schema
+            )));
+        }
+        Ok(bundle)
+    }
+
+    // save writes the bundle as JSON
+    pub fn save(&self, path: &Path) -> Result<(), BundleError> {
+        let text = serde_json::to_string_pretty(self)
+            .map_err(|e| BundleError::InvalidBundle(e.to_string()))?;
+        fs::write(path, text + "\n").map_err(|e| BundleError::IOError(e.to_string()))
+    }
+
+    // verify checks every file against its hash and that none of them would
+    // be written outside the directory the bundle is unpacked into
+    pub fn verify(&self) -> Result<(), BundleError> {
+        for file in &self.files {
+            let safe = Path::new(&file.path)
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)));
+            if !safe || file.path.is_empty() {
+                return Err(BundleError::UnsafePath(file.path.clone()));
+            }
+            if hash(&file.contents) != file.hash {
+                return Err(BundleError::HashMismatch(file.path.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    // unpack verifies the bundle then writes every file into the directory,
+    // refusing to overwrite any file already there, and returns their paths
+    pub fn unpack(&self, dir: &Path) -> Result<Vec<PathBuf>, BundleError> {
+        self.verify()?;
+        let paths = self
+            .files
+            .iter()
+            .map(|file| dir.join(&file.path))
+            .collect::<Vec<PathBuf>>();
+        if let Some(path) = paths.iter().find(|path| path.exists()) {
+            return Err(BundleError::AlreadyExists(path.display().to_string()));
+        }
+        for (file, path) in self.files.iter().zip(&paths) {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).map_err(|e| BundleError::IOError(e.to_string()))?;
+            }
+            fs::write(path, &file.contents).map_err(|e| BundleError::IOError(e.to_string()))?;
+        }
+        Ok(paths)
+    }
+}
+
+// collect adds every file under the directory to the list, its path given
+// relative to the directory being packed
+fn collect(dir: &Path, relative: &Path, files: &mut Vec<BundleFile>) -> Result<(), BundleError> {
+    let entries = fs::read_dir(dir).map_err(|e| BundleError::IOError(e.to_string()))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| BundleError::IOError(e.to_string()))?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with('.') || name == PROGRESS_FILE {
+            continue;
+        }
+        let path = entry.path();
+        let relative = relative.join(&name);
+        if path.is_dir() {
+            collect(&path, &relative, files)?;
+            continue;
+        }
+        let display = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let bytes = fs::read(&path).map_err(|e| BundleError::IOError(e.to_string()))?;
+        let contents =
+            String::from_utf8(bytes).map_err(|_| BundleError::NotText(display.clone()))?;
+        files.push(BundleFile {
+            path: display,
+            hash: hash(&contents),
+            contents,
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, io, path::Path};
+
+use crate::{program, version};
+
+// CACHE_FILE is the name of the cache kept in the output directory
+pub const CACHE_FILE: &str = ".lmc-cache.json";
+
+// Cache remembers what each output file of a directory was assembled from,
+// so that assembling the directory again can skip the files that have not
+// changed since
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cache {
+    // entries maps the path of each source file, relative to the directory
+    // assembled, to the key it was last assembled with
+    #[serde(default)]
+    entries: BTreeMap<String, String>,
+}
+
+impl Cache {
+    // load reads the cache, a missing or unreadable cache being empty so that
+    // every file is assembled again
+    pub fn load(path: &Path) -> Cache {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    // save writes the cache as JSON
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let text = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, text + "\n")
+    }
+
+    // key hashes the source along with the assembler options and the build
+    // of lmc, any of which changing means the file must be assembled again
+    pub fn key(source: &[String], options: &str) -> String {
+        let text = format!(
+            "{} {}\n{}\n{}",
+            version::VERSION,
+            version::COMMIT,
+            options,
+            source.join("\n")
+        );
+        format!("{:08x}", program::fnv1a(text.bytes()))
+    }
+
+    // is_fresh returns whether the file was last assembled with the key
+    pub fn is_fresh(&self, file: &str, key: &str) -> bool {
+        self.entries.get(file).is_some_and(|cached| cached == key)
+    }
+
+    // insert records that the file was assembled with the key
+    pub fn insert(&mut self, file: &str, key: &str) {
+        self.entries.insert(file.to_string(), key.to_string());
+    }
+
+    // remove forgets the file, so that it is assembled next time
+    pub fn remove(&mut self, file: &str) {
+        self.entries.remove(file);
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+
+use crate::{instruction::Instruction, program::Program};
+
+// Canonical is a program written out in normal form, so that two programs
+// differing only in their label names, comments, spacing or where the DAT
+// cells sit give the same source
+#[derive(Debug, PartialEq, Clone)]
+pub struct Canonical {
+    pub source: Vec<String>,
+    // relocated is whether the data cells were moved after the code, which
+    // is only done when it cannot change what the program does
+    pub relocated: bool,
+}
+
+// data_cells returns which mailboxes hold data. Machine code has no DAT marks
+// so any mailbox used by ADD, SUB, STO or LDA is taken to be data, as is any
+// mailbox that does not hold an instruction.
+pub fn data_cells(program: &Program) -> Vec<bool> {
+    if !program.data.is_empty() {
+        return (0..program.instructions.len())
+            .map(|address| program.is_data(address))
+            .collect();
+    }
+    let decoded = decode(program);
+    (0..decoded.len())
+        .map(|address| {
+            matches!(decoded[address], Instruction::Invalid(_))
+                || decoded.iter().any(|instruction| {
+                    matches!(
+                        instruction,
+                        Instruction::Add(operand)
+                            | Instruction::Sub(operand)
+                            | Instruction::Sto(operand)
+                            | Instruction::Lda(operand) if *operand == address
+                    )
+                })
+        })
+        .collect()
+}
+
+// data_order numbers the data cells in the order the code first refers to
+// them, data no instruction refers to being left out
+pub fn data_order(program: &Program, data: &[bool]) -> HashMap<usize, usize> {
+    let mut order = HashMap::new();
+    for (address, instruction) in decode(program).iter().enumerate() {
+        if data[address] {
+            continue;
+        }
+        if let Some(operand) = instruction.operand() {
+            if data.get(operand).copied().unwrap_or(false) {
+                let next = order.len();
+                order.entry(operand).or_insert(next);
+            }
+        }
+    }
+    order
+}
+
+// is_relocatable returns whether the data cells can be moved after the code
+// without changing what the program does: the program must start with an
+// instruction, no instruction may treat code as data or branch into data,
+// and none may run on into data or off the end of the code
+pub fn is_relocatable(program: &Program, data: &[bool]) -> bool {
+    let decoded = decode(program);
+    if data.first().copied().unwrap_or(false) {
+        return false;
+    }
+    let code = (0..decoded.len())
+        .filter(|address| !data[*address])
+        .collect::<Vec<usize>>();
+    for (index, address) in code.iter().enumerate() {
+        let instruction = decoded[*address];
+        if let Some(operand) = instruction.operand() {
+            let refers_to_data = data.get(operand).copied().unwrap_or(false);
+            if operand < decoded.len() && instruction.is_branch() == refers_to_data {
+                return false;
+            }
+        }
+        let falls_through = !matches!(instruction, Instruction::Hlt | Instruction::Br(_));
+        let next_is_code = code.get(index + 1) == Some(&(address + 1));
+        if falls_through && !next_is_code && data.iter().any(|is_data| *is_data) {
+            return false;
+        }
+    }
+    true
+}
+
+// canonicalize writes the program out in normal form. Instructions keep their
+// order, the mailboxes they refer to being labelled positionally: L1, L2...
+// for code in address order and D1, D2... for data in the order the code
+// first uses it. When it is safe to, the data cells are moved after the code
+// in that same order. Comments and assertions are left out.
+pub fn canonicalize(program: &Program) -> Canonical {
+    let decoded = decode(program);
+    let data = data_cells(program);
+    let order = data_order(program, &data);
+    let relocated = is_relocatable(program, &data);
+    let mut layout = (0..decoded.len()).collect::<Vec<usize>>();
+    if relocated {
+        let data_rank = |address: usize| order.get(&address).copied().unwrap_or(usize::MAX);
+        layout.sort_by_key(|address| (data[*address], data_rank(*address), *address));
+    }
+    let referenced = decoded
+        .iter()
+        .enumerate()
+        .filter(|(address, _)| !data[*address])
+        .filter_map(|(_, instruction)| instruction.operand())
+        .filter(|operand| *operand < decoded.len())
+        .collect::<Vec<usize>>();
+    let mut labels = HashMap::new();
+    for address in layout.iter().filter(|address| !data[**address]) {
+        if referenced.contains(address) {
+            labels.insert(*address, format!("L{}", labels.len() + 1));
+        }
+    }
+    for (address, index) in &order {
+        labels.insert(*address, format!("D{}", index + 1));
+    }
+    let source = layout
+        .iter()
+        .map(|address| {
+            let label = labels.get(address).map_or("", |label| label.as_str());
+            let instruction = decoded[*address];
+            let line = match (data[*address], instruction.operand()) {
+                (true, _) => format!(
+                    "{:7} {:7} {}",
+                    label,
+                    "DAT",
+                    program.instructions[*address].value()
+                ),
+                (false, Some(operand)) => {
+                    let operand = labels
+                        .get(&operand)
+                        .cloned()
+                        .unwrap_or_else(|| format!("{:02}", operand));
+                    format!("{:7} {:7} {}", label, instruction.mnemonic(), operand)
+                }
+                (false, None) => format!("{:7} {}", label, instruction.mnemonic()),
+            };
+            line.trim_end().to_string()
+        })
+        .collect();
+    Canonical { source, relocated }
+}
+
+// decode decodes every mailbox of the program
+fn decode(program: &Program) -> Vec<Instruction> {
+    program
+        .instructions
+        .iter()
+        .map(|value| Instruction::decode(*value))
+        .collect()
+}
@@ -0,0 +1,179 @@
+use std::fmt;
+
+use lmc::suggest;
+
+// COMMANDS are the commands lmc accepts
+pub const COMMANDS: [&str; 29] = [
+    "assemble",
+    "execute",
+    "run",
+    "resume",
+    "debug",
+    "tui",
+    "batch",
+    "schedule",
+    "pipe",
+    "tournament",
+    "exercise",
+    "bundle",
+    "play",
+    "annotate",
+    "profile",
+    "metrics",
+    "similarity",
+    "canonicalize",
+    "grep",
+    "refactor",
+    "patch",
+    "format",
+    "parse",
+    "selftest",
+    "verify-vm",
+    "conformance",
+    "compare-modes",
+    "highlight",
+    "schema",
+];
+
+// FLAGS are the flags lmc accepts, without their leading dashes or values
+pub const FLAGS: [&str; 74] = [
+    "h",
+    "help",
+    "version",
+    "v",
+    "verbose",
+    "d",
+    "debug",
+    "source-format",
+    "annotate",
+    "listing",
+    "debug-info",
+    "pad-to",
+    "fill",
+    "dry-run",
+    "all",
+    "out",
+    "force",
+    "diagnostics",
+    "fix",
+    "partial",
+    "iterations",
+    "seed",
+    "replay",
+    "fuzz",
+    "in-place",
+    "set",
+    "replace",
+    "to",
+    "explain",
+    "checked",
+    "strict",
+    "teaching",
+    "signed",
+    "uninitialized",
+    "inputs",
+    "cycle-inputs",
+    "random-inputs",
+    "outputs",
+    "poison",
+    "counter-wrap",
+    "start-at",
+    "entry",
+    "print-state",
+    "checkpoint",
+    "stats",
+    "prompt",
+    "no-prompt",
+    "input-retries",
+    "max-cycles",
+    "max-inputs",
+    "max-outputs",
+    "max-time",
+    "max-mailboxes",
+    "devices",
+    "keyboard",
+    "verify-checksum",
+    "slice",
+    "spec",
+    "progress",
+    "format",
+    "dialect",
+    "suite",
+    "a",
+    "b",
+    "test",
+    "report",
+    "baseline",
+    "threshold",
+    "samples",
+    "matrix",
+    "record-session",
+    "trace",
+    "speed",
+    "plain",
+];
+
+// CliError is used to indicate a command line that lmc does not understand,
+// along with the nearest command or flag it could have meant
+#[derive(Debug, PartialEq)]
+pub enum CliError {
+    UnknownCommand(String, Option<String>),
+    UnknownFlag(String, Option<String>),
+}
+
+// Implement the display trait for easy printing.
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::UnknownCommand(command, suggestion) => {
+                write!(f, "unknown command '{}'", command)?;
+                match suggestion {
+                    Some(suggestion) => write!(f, ", did you mean '{}'?", suggestion),
+                    None => write!(f, ", see lmc --help"),
+                }
+            }
+            CliError::UnknownFlag(flag, suggestion) => {
+                write!(f, "unknown flag '{}'", dashed(flag))?;
+                match suggestion {
+                    Some(suggestion) => write!(f, ", did you mean '{}'?", dashed(suggestion)),
+                    None => write!(f, ", see lmc --help"),
+                }
+            }
+        }
+    }
+}
+
+// dashed writes a flag as it is typed, with one dash for a single letter
+fn dashed(flag: &str) -> String {
+    match flag.len() {
+        1 => format!("-{}", flag),
+        _ => format!("--{}", flag),
+    }
+}
+
+// check_command returns an error naming the nearest command if the command
+// is not one lmc accepts
+pub fn check_command(command: &str) -> Result<(), CliError> {
+    if COMMANDS.contains(&command) {
+        return Ok(());
+    }
+    Err(CliError::UnknownCommand(
+        command.to_string(),
+        suggest::closest(command, COMMANDS),
+    ))
+}
+
+// check_flags returns an error for the first flag lmc does not accept, given
+// without leading dashes, naming the nearest flag
+pub fn check_flags(flags: &[String]) -> Result<(), CliError> {
+    for flag in flags {
+        let name = flag.split('=').next().unwrap_or("");
+        if !FLAGS.contains(&name) {
+            return Err(CliError::UnknownFlag(
+                name.to_string(),
+                suggest::closest(name, FLAGS),
+            ));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,88 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    env, fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::debugger::Layout;
+
+// ConfigError is used to indicate an error reading or writing the config file
+#[derive(Debug, PartialEq)]
+pub enum ConfigError {
+    IOError(String),
+    InvalidConfig(String),
+}
+
+// Implement the display trait for easy printing.
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::IOError(value) => write!(f, "IO error: {}", value),
+            ConfigError::InvalidConfig(value) => write!(f, "invalid config: {}", value),
+        }
+    }
+}
+
+// Implement the error trait so the error can be used with the ? operator
+// alongside other errors.
+impl std::error::Error for ConfigError {}
+
+// Config holds the preferences kept between runs, every section being
+// optional in the file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Config {
+    // debugger is the layout of the panels the debugger shows
+    #[serde(default)]
+    pub debugger: Layout,
+    // tui is the layout of the panels the tui shows, kept apart from the
+    // debugger's as a full screen has room for more of them
+    #[serde(default = "Layout::full_screen")]
+    pub tui: Layout,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            debugger: Layout::default(),
+            tui: Layout::full_screen(),
+        }
+    }
+}
+
+// path returns where the config file is kept: the file named by LMC_CONFIG,
+// otherwise lmc/config.toml in the user's config directory
+pub fn path() -> Option<PathBuf> {
+    if let Ok(path) = env::var("LMC_CONFIG") {
+        return Some(PathBuf::from(path));
+    }
+    let dir = match env::var("XDG_CONFIG_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var("HOME").ok()?).join(".config"),
+    };
+    Some(dir.join("lmc").join("config.toml"))
+}
+
+// history_path returns where the history of the commands typed into the
+// debugger is kept, beside the config file
+pub fn history_path() -> Option<PathBuf> {
+    Some(path()?.parent()?.join("history"))
+}
+
+// load reads the config file, a missing file giving the defaults
+pub fn load(path: &Path) -> Result<Config, ConfigError> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+        Err(err) => return Err(ConfigError::IOError(err.to_string())),
+    };
+    toml::from_str(&text).map_err(|e| ConfigError::InvalidConfig(e.to_string()))
+}
+
+// save writes the config file, creating its directory if need be
+pub fn save(path: &Path, config: &Config) -> Result<(), ConfigError> {
+    let text = toml::to_string(config).map_err(|e| ConfigError::InvalidConfig(e.to_string()))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| ConfigError::IOError(e.to_string()))?;
+    }
+    fs::write(path, text).map_err(|e| ConfigError::IOError(e.to_string()))
+}
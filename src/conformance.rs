@@ -0,0 +1,319 @@
+use serde::{Deserialize, Deserializer};
+use std::{collections::BTreeMap, fmt, fs};
+
+use crate::{
+    devices::{CycleCounter, Display, Rng},
+    lmc::{CounterWrap, HaltReason, RunResult, LMC},
+    numbers::ThreeDigitNumber,
+    oracle::{self, Halt, Outcome},
+    program::Program,
+    schema::CONFORMANCE_SCHEMA,
+};
+
+// CONFORMANCE_SEED seeds the random number generator of the extended dialect
+// so that the values it reads are the same on every run
+const CONFORMANCE_SEED: u64 = 1;
+
+// ConformanceError is used to indicate a conformance suite that could not be
+// read
+#[derive(Debug, PartialEq)]
+pub enum ConformanceError {
+    IOError(String),
+    InvalidSuite(String),
+}
+
+// Implement the display trait for easy printing.
+impl fmt::Display for ConformanceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConformanceError::IOError(value) => write!(f, "IO error: {}", value),
+            ConformanceError::InvalidSuite(value) => {
+                write!(f, "invalid conformance suite: {}", value)
+            }
+        }
+    }
+}
+
+// Dialect is a variant of the LMC, each with a conformance suite pinning down
+// how it behaves
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Dialect {
+    // Classic is the LMC as described by the instruction set alone
+    Classic,
+    // Extended maps the standard devices to mailboxes 97-99
+    Extended,
+    // Higginson reads and writes negative numbers, stored in ten's
+    // complement, as the Higginson online simulator lets students do
+    Higginson,
+}
+
+impl Dialect {
+    // from_str converts a string to a dialect
+    pub fn from_str(dialect: &str) -> Option<Dialect> {
+        match dialect {
+            "classic" => Some(Dialect::Classic),
+            "extended" => Some(Dialect::Extended),
+            "higginson" => Some(Dialect::Higginson),
+            _ => None,
+        }
+    }
+
+    // all returns every dialect
+    pub fn all() -> [Dialect; 3] {
+        [Dialect::Classic, Dialect::Extended, Dialect::Higginson]
+    }
+
+    // suite returns the conformance suite shipped for the dialect
+    pub fn suite(&self) -> &'static str {
+        match self {
+            Dialect::Classic => include_str!("../conformance/classic.json"),
+            Dialect::Extended => include_str!("../conformance/extended.json"),
+            Dialect::Higginson => include_str!("../conformance/higginson.json"),
+        }
+    }
+
+    // configure sets up the LMC to behave as the dialect
+    pub fn configure(&self, lmc: &mut LMC) {
+        lmc.set_counter_wrap(CounterWrap::Wrap);
+        match self {
+            Dialect::Classic => {}
+            Dialect::Extended => {
+                lmc.attach_device(97, Box::new(Display::new()));
+                lmc.attach_device(98, Box::new(CycleCounter));
+                lmc.attach_device(99, Box::new(Rng::new(CONFORMANCE_SEED)));
+            }
+            Dialect::Higginson => lmc.set_signed(true),
+        }
+    }
+
+    // signed returns whether inputs and outputs are written as negative
+    // numbers rather than their ten's complement
+    pub fn signed(&self) -> bool {
+        *self == Dialect::Higginson
+    }
+}
+
+// Implement the display trait for easy printing.
+impl fmt::Display for Dialect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Dialect::Classic => write!(f, "classic"),
+            Dialect::Extended => write!(f, "extended"),
+            Dialect::Higginson => write!(f, "higginson"),
+        }
+    }
+}
+
+// Expect is what a conformance case expects of the run, any part left out is
+// not checked
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Expect {
+    // halt is how the run ends: halted, max_cycles or error
+    halt: String,
+    outputs: Option<Vec<i16>>,
+    calculator: Option<i16>,
+    // flag is NEG, OVERFLOW or null for no flag
+    #[serde(default, deserialize_with = "present")]
+    flag: Option<Option<String>>,
+    cycles: Option<usize>,
+    counter: Option<usize>,
+    // mailboxes maps addresses to the values they hold once the run ends
+    #[serde(default)]
+    mailboxes: BTreeMap<usize, i16>,
+}
+
+// present deserializes a field that may be null, so that a field given as
+// null can be told apart from one left out
+fn present<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Option<String>>, D::Error> {
+    Option::<String>::deserialize(deserializer).map(Some)
+}
+
+// Case is a single program run along with what it is expected to do
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Case {
+    name: String,
+    #[serde(default)]
+    description: String,
+    // program is the machine code loaded from mailbox 00
+    program: Vec<i16>,
+    #[serde(default)]
+    inputs: Vec<i16>,
+    #[serde(default = "default_max_cycles")]
+    max_cycles: usize,
+    expect: Expect,
+}
+
+// default_max_cycles is the most cycles a case runs for unless it says
+fn default_max_cycles() -> usize {
+    1000
+}
+
+// CaseResult is the outcome of a single conformance case, every way the run
+// differed from what was expected
+#[derive(Debug, Clone)]
+pub struct CaseResult {
+    pub name: String,
+    pub description: String,
+    pub differences: Vec<String>,
+}
+
+// ConformanceSuite is a machine-readable list of programs and what each is
+// expected to do on a dialect of the LMC
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConformanceSuite {
+    schema: String,
+    // dialect names the dialect the suite was written for
+    dialect: String,
+    cases: Vec<Case>,
+}
+
+impl ConformanceSuite {
+    // parse reads a suite from its JSON text
+    pub fn parse(text: &str) -> Result<Self, ConformanceError> {
+        let suite: ConformanceSuite = serde_json::from_str(text)
+            .map_err(|e| ConformanceError::InvalidSuite(e.to_string()))?;
+        if suite.schema != CONFORMANCE_SCHEMA {
+            return Err(ConformanceError::InvalidSuite(format!(
+                "unsupported schema {}, expected {}",
+                suite.schema, CONFORMANCE_SCHEMA
+            )));
+        }
+        Ok(suite)
+    }
+
+    // load reads a suite from a JSON file
+    pub fn load(path: &str) -> Result<Self, ConformanceError> {
+        let text =
+            fs::read_to_string(path).map_err(|e| ConformanceError::IOError(e.to_string()))?;
+        ConformanceSuite::parse(&text)
+    }
+
+    // dialect returns the dialect the suite was written for
+    pub fn dialect(&self) -> Result<Dialect, ConformanceError> {
+        Dialect::from_str(&self.dialect).ok_or_else(|| {
+            ConformanceError::InvalidSuite(format!("unknown dialect {}", self.dialect))
+        })
+    }
+
+    // size returns the number of cases in the suite
+    pub fn size(&self) -> usize {
+        self.cases.len()
+    }
+
+    // run runs every case on an LMC configured as the dialect, returning how
+    // each differed from what was expected
+    pub fn run(&self, dialect: Dialect) -> Result<Vec<CaseResult>, ConformanceError> {
+        self.cases
+            .iter()
+            .map(|case| run_case(case, dialect))
+            .collect()
+    }
+}
+
+// run_case runs a single case, comparing everything it expects
+fn run_case(case: &Case, dialect: Dialect) -> Result<CaseResult, ConformanceError> {
+    let invalid = |value: i16| {
+        ConformanceError::InvalidSuite(format!("{}: {} is out of range", case.name, value))
+    };
+    let instructions = case
+        .program
+        .iter()
+        .map(|value| ThreeDigitNumber::new(*value).map_err(|_| invalid(*value)))
+        .collect::<Result<Vec<ThreeDigitNumber>, ConformanceError>>()?;
+    let inputs = case
+        .inputs
+        .iter()
+        .map(|value| match dialect.signed() {
+            true => ThreeDigitNumber::from_signed(*value).map_err(|_| invalid(*value)),
+            false => ThreeDigitNumber::new(*value).map_err(|_| invalid(*value)),
+        })
+        .collect::<Result<Vec<ThreeDigitNumber>, ConformanceError>>()?;
+    let mut lmc = LMC::new(false, false, true, case.max_cycles);
+    dialect.configure(&mut lmc);
+    // a case needing more input than it was given ends in an error rather
+    // than waiting on stdin
+    lmc.set_io_limits(Some(inputs.len()), None);
+    lmc.load_input(&inputs);
+    let result = match lmc.load_program(&Program::from_instructions(instructions)) {
+        Ok(()) => lmc.execute_program(),
+        Err(err) => RunResult {
+            reason: HaltReason::Error(err),
+            cycles: 0,
+        },
+    };
+    let mut outcome = oracle::observe(&lmc, result);
+    if dialect.signed() {
+        outcome.outputs = outcome
+            .outputs
+            .iter()
+            .map(|value| ThreeDigitNumber::new(*value).unwrap().signed_value())
+            .collect();
+    }
+    Ok(CaseResult {
+        name: case.name.clone(),
+        description: case.description.clone(),
+        differences: compare(&case.expect, &outcome),
+    })
+}
+
+// compare describes every way the outcome differs from what was expected
+fn compare(expect: &Expect, outcome: &Outcome) -> Vec<String> {
+    let mut differences = Vec::new();
+    let mut check = |what: &str, expected: String, got: String| {
+        if expected != got {
+            differences.push(format!("{}: expected {}, got {}", what, expected, got));
+        }
+    };
+    let halt = match outcome.halt {
+        Halt::Halted => "halted",
+        Halt::MaxCycles => "max_cycles",
+        Halt::Error => "error",
+    };
+    check("halt", expect.halt.clone(), halt.to_string());
+    if let Some(outputs) = &expect.outputs {
+        check(
+            "outputs",
+            format!("{:?}", outputs),
+            format!("{:?}", outcome.outputs),
+        );
+    }
+    if let Some(calculator) = expect.calculator {
+        check(
+            "calculator",
+            calculator.to_string(),
+            outcome.calculator.to_string(),
+        );
+    }
+    if let Some(flag) = &expect.flag {
+        let name = |flag: Option<String>| flag.unwrap_or_else(|| "none".to_string());
+        check(
+            "flag",
+            name(flag.clone()),
+            name(outcome.flag.map(|flag| flag.to_string())),
+        );
+    }
+    if let Some(cycles) = expect.cycles {
+        check("cycles", cycles.to_string(), outcome.cycles.to_string());
+    }
+    if let Some(counter) = expect.counter {
+        check(
+            "counter",
+            format!("{:02}", counter),
+            format!("{:02}", outcome.counter),
+        );
+    }
+    for (address, value) in &expect.mailboxes {
+        check(
+            &format!("mailbox {:02}", address),
+            format!("{:03}", value),
+            outcome
+                .mailboxes
+                .get(*address)
+                .map_or("nothing".to_string(), |value| format!("{:03}", value)),
+        );
+    }
+    differences
+}
@@ -0,0 +1,817 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeSet, fmt, path::PathBuf};
+
+use crate::{
+    config,
+    devices::KeyboardHandle,
+    instruction::Instruction,
+    lmc::{join_numbers, StepResult, LMC},
+    program::Program,
+};
+
+// DEBUGGER_HELP lists the commands the debugger understands
+const DEBUGGER_HELP: &str = "\
+step, s [n]        execute the next n instructions, default 1
+continue, c        execute until a breakpoint or the program stops
+print, p <box>     show a mailbox, given as an address or label
+registers, r       show the counter, calculator, flag and output
+list, l            show the code around the next instruction
+break, b <where>   stop before a source line, or a mailbox in machine view
+delete, d <where>  remove a breakpoint
+view, v            switch between the source and machine code views
+memory, m          show every mailbox as a grid, the cursor in brackets
+goto, g <box>      move the cursor to a mailbox
+/<query>           move the cursor to the next mailbox holding a value,
+                   mnemonic or label, / alone repeats the last search
+bookmark, k [box]  bookmark the mailbox at the cursor, or unbookmark it
+bookmarks          list the bookmarked mailboxes
+devices            show the devices attached with --devices
+key <c>            press a key on the keyboard attached with --keyboard
+layout             show the panels shown after each step and their sizes
+show <panel>       show a panel after each step, after those already shown
+hide <panel>       stop showing a panel after each step
+resize <panel> <n> set the lines a panel takes: source, memory, io or trace
+help, h            show this help
+quit, q            stop debugging";
+
+// commands returns the name and short name of every command in
+// DEBUGGER_HELP, for completing what is typed at the prompt
+pub fn commands() -> Vec<String> {
+    DEBUGGER_HELP
+        .lines()
+        .filter(|line| !line.starts_with([' ', '/']))
+        .filter_map(|line| line.split("  ").next())
+        .flat_map(|names| names.split(", "))
+        .filter_map(|name| name.split_whitespace().next())
+        .map(String::from)
+        .collect()
+}
+
+// View is how the debugger shows the program, as the assembly it came from or
+// as the machine code in the mailboxes
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum View {
+    Source,
+    Machine,
+}
+
+// Panel is a part of the debugger's display that can be shown after every
+// step, in the order the layout lists them
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Panel {
+    Registers,
+    Source,
+    Memory,
+    Io,
+    Trace,
+}
+
+impl Panel {
+    // from_str converts a panel name to a panel
+    pub fn from_str(name: &str) -> Option<Panel> {
+        match name {
+            "registers" => Some(Panel::Registers),
+            "source" => Some(Panel::Source),
+            "memory" => Some(Panel::Memory),
+            "io" => Some(Panel::Io),
+            "trace" => Some(Panel::Trace),
+            _ => None,
+        }
+    }
+}
+
+// Implement the display trait for easy printing.
+impl fmt::Display for Panel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Panel::Registers => write!(f, "registers"),
+            Panel::Source => write!(f, "source"),
+            Panel::Memory => write!(f, "memory"),
+            Panel::Io => write!(f, "io"),
+            Panel::Trace => write!(f, "trace"),
+        }
+    }
+}
+
+// Layout is which panels the debugger shows after every step and how many
+// lines each takes, kept in the config file so that a small terminal or a
+// projector only shows what is needed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Layout {
+    pub panels: Vec<Panel>,
+    // source_lines is the number of lines shown either side of the next
+    // instruction, by the source panel and list
+    pub source_lines: usize,
+    // memory_rows is the number of rows of the grid the memory panel shows,
+    // those around the cursor
+    pub memory_rows: usize,
+    // io_values is the number of the most recent outputs the io panel shows
+    pub io_values: usize,
+    // trace_lines is the number of the most recent instructions the trace
+    // panel shows
+    pub trace_lines: usize,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout {
+            panels: vec![Panel::Registers],
+            source_lines: 3,
+            memory_rows: 10,
+            io_values: 10,
+            trace_lines: 5,
+        }
+    }
+}
+
+impl Layout {
+    // full_screen returns the layout the tui starts with, which has room to
+    // show the source, memory and baskets along with the registers
+    pub fn full_screen() -> Self {
+        Layout {
+            panels: vec![Panel::Registers, Panel::Source, Panel::Memory, Panel::Io],
+            ..Layout::default()
+        }
+    }
+}
+
+// Debugger runs a program loaded into an LMC one command at a time, so that
+// the fetch-execute cycle can be followed instruction by instruction
+pub struct Debugger {
+    lmc: LMC,
+    program: Program,
+    // source is the assembly the program came from, empty for machine code
+    source: Vec<String>,
+    view: View,
+    // breakpoints are the mailboxes continue stops before
+    breakpoints: BTreeSet<usize>,
+    // cursor is the mailbox selected in the memory grid
+    cursor: usize,
+    // query is the last search, repeated by a search for nothing
+    query: Option<String>,
+    bookmarks: BTreeSet<usize>,
+    layout: Layout,
+    // config is the config file the layout is saved to when it changes
+    config: Option<PathBuf>,
+    // history holds the address of every instruction executed, oldest first
+    history: Vec<usize>,
+    // plain shows everything as linear text without grids, markers or
+    // aligned columns, for screen readers
+    plain: bool,
+    // keyboard presses keys on the keyboard device, if one is attached,
+    // since stdin is read for the debugger's commands
+    keyboard: Option<KeyboardHandle>,
+    // stopped describes how the run ended once it has
+    stopped: Option<String>,
+}
+
+impl Debugger {
+    // new creates a debugger for the LMC, which the program must already be
+    // loaded into, ready to execute its first instruction
+    pub fn new(mut lmc: LMC, program: Program) -> Self {
+        lmc.start();
+        Debugger {
+            lmc,
+            program,
+            source: Vec::new(),
+            view: View::Machine,
+            breakpoints: BTreeSet::new(),
+            cursor: 0,
+            query: None,
+            bookmarks: BTreeSet::new(),
+            layout: Layout::default(),
+            config: None,
+            history: Vec::new(),
+            plain: false,
+            keyboard: None,
+            stopped: None,
+        }
+    }
+
+    // set_source gives the assembly the program was assembled from, switching
+    // to the source view if the program maps its mailboxes to source lines
+    pub fn set_source(&mut self, source: Vec<String>) {
+        self.source = source;
+        if self.has_source() {
+            self.view = View::Source;
+        }
+    }
+
+    // set_layout sets the panels shown after every step and their sizes
+    pub fn set_layout(&mut self, layout: Layout) {
+        self.layout = layout;
+    }
+
+    // set_config sets the config file the layout is saved to whenever it is
+    // changed, so that it is kept for the next time
+    pub fn set_config(&mut self, path: PathBuf) {
+        self.config = Some(path);
+    }
+
+    // set_plain sets whether to show everything as plain linear text, one
+    // short line for each thing shown, that a screen reader reads well
+    pub fn set_plain(&mut self, plain: bool) {
+        self.plain = plain;
+    }
+
+    // set_keyboard gives the handle of the keyboard device attached to the
+    // LMC, so that key presses it commands
+    pub fn set_keyboard(&mut self, keyboard: KeyboardHandle) {
+        self.keyboard = Some(keyboard);
+    }
+
+    // view returns how the debugger currently shows the program
+    pub fn view(&self) -> View {
+        self.view
+    }
+
+    // lmc returns the LMC being debugged
+    pub fn lmc(&self) -> &LMC {
+        &self.lmc
+    }
+
+    // lmc_mut returns the LMC being debugged, to give it inputs
+    pub fn lmc_mut(&mut self) -> &mut LMC {
+        &mut self.lmc
+    }
+
+    // layout returns the panels shown after every step and their sizes
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    // cursor returns the mailbox selected in the memory grid
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    // set_cursor selects a mailbox in the memory grid
+    pub fn set_cursor(&mut self, address: usize) {
+        self.cursor = address.min(99);
+    }
+
+    // bookmarked returns the bookmarked mailboxes
+    pub fn bookmarked(&self) -> &BTreeSet<usize> {
+        &self.bookmarks
+    }
+
+    // stopped returns how the run ended, once it has
+    pub fn stopped(&self) -> Option<&String> {
+        self.stopped.as_ref()
+    }
+
+    // has_source returns whether the source view is available
+    fn has_source(&self) -> bool {
+        !self.source.is_empty() && !self.program.source_map.is_empty()
+    }
+
+    // command runs a single command typed at the debugger prompt and returns
+    // what it shows, or None for quit
+    pub fn command(&mut self, line: &str) -> Option<String> {
+        if let Some(query) = line.trim().strip_prefix('/') {
+            return Some(self.search(query.trim()));
+        }
+        let words = line.split_whitespace().collect::<Vec<&str>>();
+        let reply = match words.as_slice() {
+            [] => String::new(),
+            ["step" | "s"] => self.run(1, true),
+            ["step" | "s", count] => match count.parse::<usize>() {
+                Ok(count) => self.run(count, true),
+                Err(_) => format!("invalid number of steps: {}", count),
+            },
+            // continue only shows where the run ended
+            ["continue" | "c"] => self.run(usize::MAX, false),
+            ["print" | "p", mailbox] => self.print(mailbox),
+            ["registers" | "r"] => self.registers(),
+            ["list" | "l"] => self.list(),
+            ["break" | "b", place] => match self.resolve(place) {
+                Ok(address) => {
+                    self.breakpoints.insert(address);
+                    format!("breakpoint at {}", self.describe(address))
+                }
+                Err(err) => err,
+            },
+            ["delete" | "d", place] => match self.resolve(place) {
+                Ok(address) if self.breakpoints.remove(&address) => {
+                    format!("deleted the breakpoint at {}", self.describe(address))
+                }
+                Ok(address) => format!("no breakpoint at {}", self.describe(address)),
+                Err(err) => err,
+            },
+            ["view" | "v"] => self.toggle_view(),
+            ["memory" | "m"] => self.memory(10),
+            ["goto" | "g", mailbox] => match self.goto(mailbox) {
+                Ok(()) => self.memory(10),
+                Err(err) => err,
+            },
+            ["bookmark" | "k"] => self.bookmark(self.cursor),
+            ["bookmark" | "k", mailbox] => match self.program.resolve_address(mailbox) {
+                Some(address) => self.bookmark(address),
+                None => format!("unknown mailbox: {}", mailbox),
+            },
+            ["bookmarks"] => self.bookmarks(),
+            ["devices"] => match self.lmc.render_devices() {
+                renderings if renderings.is_empty() => "no device has anything to show".to_string(),
+                renderings => renderings.concat().trim_end().to_string(),
+            },
+            ["key", key] => match key.chars().next() {
+                Some(key) => self.press(key),
+                None => String::new(),
+            },
+            ["layout"] => self.describe_layout(),
+            ["show" | "hide" | "resize", panel, ..] if Panel::from_str(panel).is_none() => {
+                format!(
+                    "unknown panel: {}, one of registers, source, memory, io or trace",
+                    panel
+                )
+            }
+            ["show", panel] => self.show_panel(Panel::from_str(panel).unwrap()),
+            ["hide", panel] => self.hide_panel(Panel::from_str(panel).unwrap()),
+            ["resize", panel, lines] => match lines.parse() {
+                Ok(lines) => self.resize_panel(Panel::from_str(panel).unwrap(), lines),
+                Err(_) => format!("invalid number of lines: {}", lines),
+            },
+            ["help" | "h"] => DEBUGGER_HELP.to_string(),
+            ["quit" | "q"] => return None,
+            _ => format!("unknown command: {}, type help for a list", line.trim()),
+        };
+        Some(reply)
+    }
+
+    // run executes up to count instructions, showing each one executed if
+    // asked to and how the run ended if it did. Running to the end stops
+    // before any breakpoint reached after the first instruction.
+    fn run(&mut self, count: usize, show: bool) -> String {
+        if let Some(stopped) = &self.stopped {
+            return format!("the program has {}", stopped);
+        }
+        let mut lines = Vec::new();
+        for i in 0..count {
+            let address = self.lmc.state().counter.value() as usize;
+            if !show && i > 0 && self.breakpoints.contains(&address) {
+                lines.push(format!("breakpoint at {}", self.describe(address)));
+                break;
+            }
+            if show {
+                lines.push(self.instruction(address));
+            }
+            if let Some(stopped) = self.step() {
+                lines.push(format!(
+                    "{} after {} cycles",
+                    stopped,
+                    self.lmc.state().cycle
+                ));
+                break;
+            }
+        }
+        lines.extend(self.layout.panels.iter().map(|panel| self.panel(*panel)));
+        lines.join("\n")
+    }
+
+    // step executes the instruction at the counter, returning how the run
+    // ended if it did
+    pub fn step(&mut self) -> Option<String> {
+        let address = self.lmc.state().counter.value() as usize;
+        self.history.push(address);
+        let stopped = match self.lmc.step() {
+            Ok(StepResult::Running) => return None,
+            Ok(StepResult::Halted) => format!("halted at {:02}", address),
+            Err(err) => format!("stopped: {}", err),
+        };
+        self.stopped = Some(stopped.clone());
+        Some(stopped)
+    }
+
+    // panel shows one panel of the layout
+    pub fn panel(&self, panel: Panel) -> String {
+        match panel {
+            Panel::Registers => self.registers(),
+            Panel::Source => self.list(),
+            Panel::Memory => self.memory(self.layout.memory_rows),
+            Panel::Io => self.io(),
+            Panel::Trace => self.trace(),
+        }
+    }
+
+    // show_panel shows a panel after each step, after those already shown
+    pub fn show_panel(&mut self, panel: Panel) -> String {
+        self.layout.panels.retain(|shown| *shown != panel);
+        self.layout.panels.push(panel);
+        self.save_layout()
+    }
+
+    // hide_panel stops showing a panel after each step
+    pub fn hide_panel(&mut self, panel: Panel) -> String {
+        self.layout.panels.retain(|shown| *shown != panel);
+        self.save_layout()
+    }
+
+    // resize_panel sets the lines a panel takes, see Layout
+    pub fn resize_panel(&mut self, panel: Panel, lines: usize) -> String {
+        match panel {
+            Panel::Registers => return "the registers panel has a fixed size".to_string(),
+            Panel::Source => self.layout.source_lines = lines,
+            Panel::Memory => self.layout.memory_rows = lines,
+            Panel::Io => self.layout.io_values = lines,
+            Panel::Trace => self.layout.trace_lines = lines,
+        }
+        self.save_layout()
+    }
+
+    // press presses a key on the keyboard device
+    pub fn press(&self, key: char) -> String {
+        match &self.keyboard {
+            Some(keyboard) => {
+                keyboard.press(key as u32 as u16);
+                format!("pressed {}", key)
+            }
+            None => "no keyboard is attached, see --keyboard".to_string(),
+        }
+    }
+
+    // io shows the inputs still waiting in the input tray and the most
+    // recent outputs
+    fn io(&self) -> String {
+        let state = self.lmc.state();
+        let skip = state.outputs.len().saturating_sub(self.layout.io_values);
+        format!(
+            "inputs: {}  outputs: {}",
+            join_numbers(&state.inputs),
+            join_numbers(&state.outputs[skip..])
+        )
+    }
+
+    // trace shows the most recent instructions executed, oldest first
+    fn trace(&self) -> String {
+        let skip = self.history.len().saturating_sub(self.layout.trace_lines);
+        match self.history.len() {
+            0 => "trace: -".to_string(),
+            _ => self.history[skip..]
+                .iter()
+                .map(|address| format!("trace {}", self.instruction(*address)))
+                .collect::<Vec<String>>()
+                .join("\n"),
+        }
+    }
+
+    // describe_layout lists the panels shown after each step and the lines
+    // each takes
+    pub fn describe_layout(&self) -> String {
+        let panels = match self.layout.panels.is_empty() {
+            true => "none".to_string(),
+            false => self
+                .layout
+                .panels
+                .iter()
+                .map(|panel| panel.to_string())
+                .collect::<Vec<String>>()
+                .join(", "),
+        };
+        format!(
+            "panels: {}\nsource: {} lines either side  memory: {} rows  io: {} outputs  trace: {} instructions",
+            panels,
+            self.layout.source_lines,
+            self.layout.memory_rows,
+            self.layout.io_values,
+            self.layout.trace_lines
+        )
+    }
+
+    // save_layout writes the layout to the config file, if there is one, and
+    // shows the layout
+    fn save_layout(&self) -> String {
+        let layout = self.describe_layout();
+        let path = match &self.config {
+            Some(path) => path,
+            None => return layout,
+        };
+        let saved = config::load(path).and_then(|mut saved| {
+            saved.debugger = self.layout.clone();
+            config::save(path, &saved)
+        });
+        match saved {
+            Ok(()) => layout,
+            Err(err) => format!("{}\nlayout not saved: {}", layout, err),
+        }
+    }
+
+    // resolve returns the mailbox a breakpoint is placed at: a source line
+    // number or label in the source view, a mailbox in the machine view
+    fn resolve(&self, place: &str) -> Result<usize, String> {
+        if self.view == View::Machine || self.program.labels.contains_key(place) {
+            return self
+                .program
+                .resolve_address(place)
+                .ok_or(format!("unknown mailbox: {}", place));
+        }
+        let line = place
+            .parse::<usize>()
+            .map_err(|_| format!("unknown line: {}", place))?;
+        // a line may hold no instruction, such as a comment
+        self.program
+            .source_map
+            .iter()
+            .position(|mapped| *mapped == line)
+            .ok_or(format!("line {} has no instruction", line))
+    }
+
+    // describe names a mailbox as the current view shows it
+    fn describe(&self, address: usize) -> String {
+        match (self.view, self.program.source_map.get(address)) {
+            (View::Source, Some(line)) => format!("line {}", line),
+            _ => format!("{:02}", address),
+        }
+    }
+
+    // instruction shows the instruction in a mailbox, as the source line it
+    // was assembled from in the source view
+    fn instruction(&self, address: usize) -> String {
+        if self.view == View::Source {
+            if let Some(line) = self.program.source_map.get(address) {
+                let text = self.source.get(line - 1).map_or("", |text| text.trim());
+                return match self.plain {
+                    true => words(&format!("line {}: {}{}", line, text, self.preview(address))),
+                    false => format!("{:>3}: {}{}", line, text, self.preview(address)),
+                };
+            }
+        }
+        self.machine_code(address)
+    }
+
+    // list shows the lines around the next instruction to execute, marking
+    // it with => and any breakpoints with *, or with words when plain
+    fn list(&self) -> String {
+        let address = self.lmc.state().counter.value() as usize;
+        let source_line = self.program.source_map.get(address).copied();
+        let (current, last) = match (self.view, source_line) {
+            (View::Source, Some(line)) => (line, self.source.len()),
+            _ => (address + 1, 100),
+        };
+        let context = self.layout.source_lines;
+        let first = current.saturating_sub(context).max(1);
+        (first..=(current + context).min(last))
+            .map(|number| {
+                let marker = if number == current { "=>" } else { "  " };
+                let (place, number_text, text, breakpoint) = match (self.view, source_line) {
+                    (View::Source, Some(_)) => {
+                        let address = self
+                            .program
+                            .source_map
+                            .iter()
+                            .position(|line| *line == number);
+                        let preview =
+                            address.map_or(String::new(), |address| self.preview(address));
+                        (
+                            format!("line {}", number),
+                            format!("{:>3}  ", number),
+                            format!("{}{}", self.source[number - 1], preview),
+                            address.is_some_and(|address| self.breakpoints.contains(&address)),
+                        )
+                    }
+                    _ => (
+                        format!("mailbox {:02}", number - 1),
+                        format!("{:02}: ", number - 1),
+                        self.contents(number - 1),
+                        self.breakpoints.contains(&(number - 1)),
+                    ),
+                };
+                // plain names the place and says what is marked in words
+                if self.plain {
+                    let next = if number == current { ", next" } else { "" };
+                    let breakpoint = if breakpoint { ", breakpoint" } else { "" };
+                    return words(&format!("{}{}{}: {}", place, next, breakpoint, text));
+                }
+                let breakpoint = if breakpoint { "*" } else { " " };
+                format!("{}{} {}{}", marker, breakpoint, number_text, text)
+                    .trim_end()
+                    .to_string()
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    // machine_code shows the value in a mailbox and the instruction it
+    // decodes to
+    pub fn machine_code(&self, address: usize) -> String {
+        match self.plain {
+            true => words(&format!(
+                "mailbox {:02}: {}",
+                address,
+                self.contents(address)
+            )),
+            false => format!("{:02}: {}", address, self.contents(address)),
+        }
+    }
+
+    // contents shows the value in a mailbox, the instruction it decodes to
+    // and a preview of the data cell it refers to
+    fn contents(&self, address: usize) -> String {
+        let value = self.lmc.state().mailboxes[address];
+        format!(
+            "{}  {}{}",
+            value,
+            Instruction::decode(value),
+            self.preview(address)
+        )
+    }
+
+    // preview shows the current value of the data cell the instruction in a
+    // mailbox refers to, if it refers to one, so it need not be printed
+    fn preview(&self, address: usize) -> String {
+        let mailboxes = self.lmc.state().mailboxes;
+        let instruction = Instruction::decode(mailboxes[address]);
+        match self.program.data_preview(instruction, &mailboxes) {
+            Some(preview) => format!("  {}", preview),
+            None => String::new(),
+        }
+    }
+
+    // toggle_view switches between the source and machine code views, staying
+    // in the machine code view when there is no source
+    pub fn toggle_view(&mut self) -> String {
+        self.view = match self.view {
+            View::Machine if self.has_source() => View::Source,
+            View::Machine => return "no source to show, staying in machine view".to_string(),
+            View::Source => View::Machine,
+        };
+        match self.view {
+            View::Source => "showing the source".to_string(),
+            View::Machine => "showing the machine code".to_string(),
+        }
+    }
+
+    // print shows the value of a mailbox and the instruction it decodes to
+    fn print(&self, mailbox: &str) -> String {
+        match self.program.resolve_address(mailbox) {
+            Some(address) => self.machine_code(address),
+            None => format!("unknown mailbox: {}", mailbox),
+        }
+    }
+
+    // memory shows the mailboxes as a grid of ten to a row, as many rows
+    // around the cursor as asked for, with the mailbox at the cursor in
+    // brackets
+    fn memory(&self, rows: usize) -> String {
+        let mailboxes = self.lmc.state().mailboxes;
+        let rows = rows.min(10);
+        let first = (self.cursor / 10).saturating_sub(rows / 2).min(10 - rows);
+        // plain lists the mailboxes holding anything in place of a grid
+        if self.plain {
+            let held = (first * 10..(first + rows) * 10)
+                .filter(|address| mailboxes[*address].value() != 0)
+                .map(|address| format!("{:02} is {}", address, mailboxes[address]))
+                .collect::<Vec<String>>();
+            let held = match held.is_empty() {
+                true => "none".to_string(),
+                false => held.join(", "),
+            };
+            return format!(
+                "cursor at {}\nmailboxes holding values: {}",
+                self.machine_code(self.cursor),
+                held
+            );
+        }
+        let mut lines = vec![format!(
+            "   {}",
+            (0..10)
+                .map(|column| format!("  {}  ", column))
+                .collect::<String>()
+                .trim_end()
+        )];
+        for row in first..first + rows {
+            let cells = (0..10)
+                .map(|column| {
+                    let address = row * 10 + column;
+                    match address == self.cursor {
+                        true => format!("[{}]", mailboxes[address]),
+                        false => format!(" {} ", mailboxes[address]),
+                    }
+                })
+                .collect::<String>();
+            lines.push(format!("{}0 {}", row, cells.trim_end()));
+        }
+        lines.push(format!("cursor: {}", self.machine_code(self.cursor)));
+        lines.join("\n")
+    }
+
+    // search moves the cursor like find, showing the memory grid around it
+    fn search(&mut self, query: &str) -> String {
+        match self.find(query) {
+            Ok(found) => format!("{}\n{}", found, self.memory(10)),
+            Err(err) => err,
+        }
+    }
+
+    // goto moves the cursor to a mailbox, given as an address or label
+    pub fn goto(&mut self, mailbox: &str) -> Result<(), String> {
+        match self.program.resolve_address(mailbox) {
+            Some(address) => {
+                self.cursor = address;
+                Ok(())
+            }
+            None => Err(format!("unknown mailbox: {}", mailbox)),
+        }
+    }
+
+    // find moves the cursor to the next mailbox after it matching the query,
+    // wrapping around to 00, and says which match it is. A query is a value
+    // such as 901, a mnemonic such as ADD, matching instructions but not
+    // data, or a label. An empty query repeats the last one.
+    pub fn find(&mut self, query: &str) -> Result<String, String> {
+        let query = match (query.is_empty(), &self.query) {
+            (false, _) => query.to_string(),
+            (true, Some(query)) => query.clone(),
+            (true, None) => return Err("nothing to search for".to_string()),
+        };
+        self.query = Some(query.clone());
+        let mailboxes = self.lmc.state().mailboxes;
+        let matches = (0..100)
+            .filter(|address| match query.parse::<i16>() {
+                Ok(value) => mailboxes[*address].value() == value,
+                Err(_) => {
+                    self.program.label_of(*address) == Some(query.as_str())
+                        || (!self.program.is_data(*address)
+                            && Instruction::decode(mailboxes[*address])
+                                .mnemonic()
+                                .eq_ignore_ascii_case(&query))
+                }
+            })
+            .collect::<Vec<usize>>();
+        let next = matches
+            .iter()
+            .find(|address| **address > self.cursor)
+            .or(matches.first());
+        match next {
+            Some(address) => {
+                self.cursor = *address;
+                Ok(format!(
+                    "{} of {} matches",
+                    matches
+                        .iter()
+                        .position(|found| found == address)
+                        .unwrap_or(0)
+                        + 1,
+                    matches.len()
+                ))
+            }
+            None => Err(format!("no mailbox matches {}", query)),
+        }
+    }
+
+    // bookmark bookmarks a mailbox, or removes the bookmark if it has one
+    pub fn bookmark(&mut self, address: usize) -> String {
+        match self.bookmarks.remove(&address) {
+            true => format!("removed the bookmark at {:02}", address),
+            false => {
+                self.bookmarks.insert(address);
+                format!("bookmarked {:02}", address)
+            }
+        }
+    }
+
+    // bookmarks shows every bookmarked mailbox along with its value
+    fn bookmarks(&self) -> String {
+        if self.bookmarks.is_empty() {
+            return "no bookmarks".to_string();
+        }
+        self.bookmarks
+            .iter()
+            .map(|address| self.machine_code(*address))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    // registers shows the counter, calculator, flag and output tray along
+    // with the number of cycles executed so far
+    fn registers(&self) -> String {
+        let state = self.lmc.state();
+        if self.plain {
+            let flag = state
+                .flag
+                .map_or("none".to_string(), |flag| flag.to_string());
+            let output = state
+                .output
+                .map_or("none".to_string(), |number| number.to_string());
+            return format!(
+                "counter {}, calculator {}, flag {}, output {}, cycle {}",
+                state.counter, state.calculator, flag, output, state.cycle
+            );
+        }
+        let flag = state.flag.map_or("-".to_string(), |flag| flag.to_string());
+        let output = state
+            .output
+            .map_or("-".to_string(), |number| number.to_string());
+        format!(
+            "counter: {}  calculator: {}  flag: {}  output: {}  cycle: {}",
+            state.counter, state.calculator, flag, output, state.cycle
+        )
+    }
+}
+
+// words joins the words of the text with single spaces, as the plain view
+// shows text so that a screen reader does not read out runs of spaces
+fn words(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
@@ -0,0 +1,159 @@
+use serde::{Deserialize, Serialize};
+use std::{fmt, fs::File, io::BufReader};
+
+use crate::{
+    numbers::ThreeDigitNumber,
+    program::{Checksum, Program},
+    schema::DEBUG_INFO_SCHEMA,
+};
+
+// DebugInfoError is used to indicate an error reading or writing debug info
+#[derive(Debug, PartialEq)]
+pub enum DebugInfoError {
+    IOError(String),
+    InvalidDebugInfo(String),
+}
+
+// Implement the display trait for easy printing.
+impl fmt::Display for DebugInfoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DebugInfoError::IOError(value) => write!(f, "IO error: {}", value),
+            DebugInfoError::InvalidDebugInfo(value) => write!(f, "invalid debug info: {}", value),
+        }
+    }
+}
+
+// Implement the error trait so the error can be used with the ? operator
+// alongside other errors.
+impl std::error::Error for DebugInfoError {}
+
+// MailboxInfo is where in the source a mailbox was assembled from
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct MailboxInfo {
+    pub address: usize,
+    // line is the 1-based source line number and text the line itself
+    pub line: usize,
+    pub text: String,
+    // label is the label naming the mailbox, if any
+    pub label: Option<String>,
+}
+
+// DebugInfo is written beside an assembled image to tie each of its
+// mailboxes back to the source, so that machine code can be run and stepped
+// through with the source it came from in view
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct DebugInfo {
+    pub schema: String,
+    // source is the path of the source file that was assembled
+    pub source: String,
+    // checksum is the checksum line of the image, so that debug info is never
+    // used with an image it was not written for
+    pub checksum: String,
+    // mailboxes lists every mailbox assembled from a line of source, those
+    // added by padding being left out
+    pub mailboxes: Vec<MailboxInfo>,
+}
+
+impl DebugInfo {
+    // new creates the debug info of the program assembled from the source
+    // file, read as the lines given
+    pub fn new(source_file: &str, source: &[String], program: &Program) -> Self {
+        let mailboxes = program
+            .source_map
+            .iter()
+            .enumerate()
+            .map(|(address, line)| MailboxInfo {
+                address,
+                line: *line,
+                text: source
+                    .get(line - 1)
+                    .map_or("", |text| text.trim_end())
+                    .to_string(),
+                label: program.label_of(address).map(|label| label.to_string()),
+            })
+            .collect();
+        DebugInfo {
+            schema: DEBUG_INFO_SCHEMA.to_string(),
+            source: source_file.to_string(),
+            checksum: Checksum::of(&program.instructions).to_string(),
+            mailboxes,
+        }
+    }
+
+    // save writes the debug info to the given file as JSON
+    pub fn save(&self, path: &str) -> Result<(), DebugInfoError> {
+        let file = File::create(path).map_err(|e| DebugInfoError::IOError(e.to_string()))?;
+        serde_json::to_writer_pretty(file, self).map_err(|e| DebugInfoError::IOError(e.to_string()))
+    }
+
+    // load reads debug info previously written by save
+    pub fn load(path: &str) -> Result<Self, DebugInfoError> {
+        let file = File::open(path).map_err(|e| DebugInfoError::IOError(e.to_string()))?;
+        let info: DebugInfo = serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| DebugInfoError::InvalidDebugInfo(e.to_string()))?;
+        if info.schema != DEBUG_INFO_SCHEMA {
+            return Err(DebugInfoError::InvalidDebugInfo(format!(
+                "unsupported schema {}, expected {}",
+                info.schema, DEBUG_INFO_SCHEMA
+            )));
+        }
+        if let Some(mailbox) = info.mailboxes.iter().find(|mailbox| mailbox.line == 0) {
+            return Err(DebugInfoError::InvalidDebugInfo(format!(
+                "mailbox {:02} is on line 0, lines start at 1",
+                mailbox.address
+            )));
+        }
+        Ok(info)
+    }
+
+    // verify checks that the debug info was written for the instructions,
+    // returning a description of the problem if it was not
+    pub fn verify(&self, instructions: &[ThreeDigitNumber]) -> Option<String> {
+        match Checksum::parse(&self.checksum) {
+            Some(checksum) if checksum == Checksum::of(instructions) => None,
+            Some(_) => Some("was written for a different image".to_string()),
+            None => Some(format!("has an invalid checksum: {}", self.checksum)),
+        }
+    }
+
+    // apply gives a program loaded from machine code the source map and
+    // symbol table it was assembled with
+    pub fn apply(&self, program: &mut Program) {
+        // the source map covers the mailboxes from 00 up to the first one
+        // without a source line
+        program.source_map = (0..program.instructions.len())
+            .map_while(|address| self.mailbox(address).map(|mailbox| mailbox.line))
+            .collect();
+        for mailbox in &self.mailboxes {
+            if let Some(label) = &mailbox.label {
+                program.labels.insert(label.clone(), mailbox.address);
+            }
+        }
+    }
+
+    // source_lines returns the source as far as the debug info knows it: the
+    // line of every mailbox in its place, the lines between left blank
+    pub fn source_lines(&self) -> Vec<String> {
+        let length = self.mailboxes.iter().map(|mailbox| mailbox.line).max();
+        let mut lines = vec![String::new(); length.unwrap_or(0)];
+        for mailbox in &self.mailboxes {
+            lines[mailbox.line - 1] = mailbox.text.clone();
+        }
+        lines
+    }
+
+    // context shows where in the source the mailbox came from, e.g.:
+    // programs/subloop.asm:4: loop    BRZ     exit
+    pub fn context(&self, address: usize) -> Option<String> {
+        self.mailbox(address)
+            .map(|mailbox| format!("{}:{}: {}", self.source, mailbox.line, mailbox.text))
+    }
+
+    // mailbox returns what is known about the mailbox at the address
+    fn mailbox(&self, address: usize) -> Option<&MailboxInfo> {
+        self.mailboxes
+            .iter()
+            .find(|mailbox| mailbox.address == address)
+    }
+}
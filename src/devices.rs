@@ -0,0 +1,227 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::numbers::ThreeDigitNumber;
+
+// Device is a peripheral mapped onto a mailbox. Any instruction that reads
+// from or writes to the mailbox is routed to the device instead of memory.
+pub trait Device {
+    // name returns a short human readable name for the device
+    fn name(&self) -> &str;
+
+    // read returns the value the device presents at the given cycle
+    fn read(&mut self, cycle: usize) -> ThreeDigitNumber;
+
+    // write hands a value stored by STO to the device
+    fn write(&mut self, value: ThreeDigitNumber);
+
+    // render returns a textual rendering of the device's output if it has any
+    fn render(&self) -> Option<String> {
+        None
+    }
+}
+
+// CycleCounter presents the number of fetch-execute cycles executed so far,
+// modulo 1000. Writes are ignored.
+pub struct CycleCounter;
+
+impl Device for CycleCounter {
+    fn name(&self) -> &str {
+        "cycle counter"
+    }
+
+    fn read(&mut self, cycle: usize) -> ThreeDigitNumber {
+        ThreeDigitNumber::new((cycle % 1000) as i16).unwrap()
+    }
+
+    fn write(&mut self, _value: ThreeDigitNumber) {}
+}
+
+// Rng presents a new pseudo-random number from 000-999 on every read, writing
+// to it reseeds the generator with the value written.
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    // new creates a random number generator from the given seed
+    pub fn new(seed: u64) -> Self {
+        // xorshift is stuck at zero forever so avoid a zero state
+        Rng { state: seed.max(1) }
+    }
+
+    // random_seed returns a seed taken from the clock, for runs not given
+    // one. Whoever uses it should show the seed so the run can be repeated.
+    pub fn random_seed() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos() as u64)
+            .unwrap_or(1)
+    }
+
+    // case_seed returns the seed of the case numbered case in a run with the
+    // given seed, so that every generated case can be replayed on its own
+    pub fn case_seed(seed: u64, case: usize) -> u64 {
+        // splitmix64 spreads the seeds of neighbouring cases far apart
+        let mut z = seed.wrapping_add((case as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // range returns a pseudo-random number between min and max inclusive
+    pub fn range(&mut self, min: i16, max: i16) -> i16 {
+        let span = (max - min).max(0) as u64 + 1;
+        min + (self.next() % span) as i16
+    }
+
+    // next advances the xorshift64 generator and returns the new state
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+}
+
+impl Device for Rng {
+    fn name(&self) -> &str {
+        "random number generator"
+    }
+
+    fn read(&mut self, _cycle: usize) -> ThreeDigitNumber {
+        ThreeDigitNumber::new((self.next() % 1000) as i16).unwrap()
+    }
+
+    fn write(&mut self, value: ThreeDigitNumber) {
+        self.state = (value.value() as u64).max(1);
+    }
+}
+
+// DISPLAY_SHADES maps a display cell's digit to the character drawn for it
+const DISPLAY_SHADES: [char; 10] = [' ', '.', ':', '-', '=', '+', '*', '#', '%', '@'];
+
+// Display is a 10x10 grid of cells each holding a digit from 0-9. Storing a
+// value vpp sets the cell at position pp (row p, column p) to the digit v,
+// reading returns the number of non-blank cells.
+pub struct Display {
+    cells: [u8; 100],
+    // dirty is set once any cell has been written
+    dirty: bool,
+}
+
+impl Display {
+    // new creates a blank display
+    pub fn new() -> Self {
+        Display {
+            cells: [0; 100],
+            dirty: false,
+        }
+    }
+}
+
+impl Default for Display {
+    fn default() -> Self {
+        Display::new()
+    }
+}
+
+impl Device for Display {
+    fn name(&self) -> &str {
+        "display"
+    }
+
+    fn read(&mut self, _cycle: usize) -> ThreeDigitNumber {
+        let lit = self.cells.iter().filter(|cell| **cell != 0).count();
+        ThreeDigitNumber::new(lit as i16).unwrap()
+    }
+
+    fn write(&mut self, value: ThreeDigitNumber) {
+        let position = (value.value() % 100) as usize;
+        self.cells[position] = (value.value() / 100) as u8;
+        self.dirty = true;
+    }
+
+    fn render(&self) -> Option<String> {
+        if !self.dirty {
+            return None;
+        }
+        let border = format!("+{}+\n", "-".repeat(20));
+        let mut text = border.clone();
+        for row in self.cells.chunks(10) {
+            text.push('|');
+            for cell in row {
+                // draw each cell twice as wide to keep the grid roughly square
+                let shade = DISPLAY_SHADES[*cell as usize];
+                text.push(shade);
+                text.push(shade);
+            }
+            text.push_str("|\n");
+        }
+        text.push_str(&border);
+        Some(text)
+    }
+}
+
+// KeyboardHandle is used by a front-end to report key presses to a Keyboard
+// device, it can be cloned and sent to another thread
+#[derive(Clone, Default)]
+pub struct KeyboardHandle {
+    last_key: Arc<Mutex<Option<u16>>>,
+}
+
+impl KeyboardHandle {
+    // press records the key code as the last key pressed, codes above 999
+    // cannot be stored in a mailbox so are ignored
+    pub fn press(&self, code: u16) {
+        if code > 999 {
+            return;
+        }
+        if let Ok(mut last_key) = self.last_key.lock() {
+            *last_key = Some(code);
+        }
+    }
+
+    // take returns the last key pressed clearing it so that each key press
+    // is only observed once
+    fn take(&self) -> Option<u16> {
+        match self.last_key.lock() {
+            Ok(mut last_key) => last_key.take(),
+            Err(_) => None,
+        }
+    }
+}
+
+// Keyboard presents the code of the last key pressed when read, or 000 if no
+// key has been pressed since the last read. Writes are ignored.
+pub struct Keyboard {
+    handle: KeyboardHandle,
+}
+
+impl Keyboard {
+    // new creates a keyboard device along with the handle used to feed it
+    pub fn new() -> (Self, KeyboardHandle) {
+        let handle = KeyboardHandle::default();
+        (
+            Keyboard {
+                handle: handle.clone(),
+            },
+            handle,
+        )
+    }
+}
+
+impl Device for Keyboard {
+    fn name(&self) -> &str {
+        "keyboard"
+    }
+
+    fn read(&mut self, _cycle: usize) -> ThreeDigitNumber {
+        let code = self.handle.take().unwrap_or(0);
+        ThreeDigitNumber::new(code as i16).unwrap()
+    }
+
+    fn write(&mut self, _value: ThreeDigitNumber) {}
+}
@@ -0,0 +1,103 @@
+use rustyline::{
+    completion::Completer, error::ReadlineError, highlight::Highlighter, hint::Hinter,
+    history::DefaultHistory, validate::Validator, Context, Editor, Helper,
+};
+use std::{
+    io::{stdin, IsTerminal},
+    path::PathBuf,
+};
+
+// Words are what a line can be completed with: a command as the first word
+// of the line and a label after it
+struct Words {
+    commands: Vec<String>,
+    labels: Vec<String>,
+}
+
+impl Completer for Words {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let start = line[..pos]
+            .rfind(char::is_whitespace)
+            .map_or(0, |index| index + 1);
+        let words = match line[..start].trim().is_empty() {
+            true => &self.commands,
+            false => &self.labels,
+        };
+        let candidates = words
+            .iter()
+            .filter(|word| word.starts_with(&line[start..pos]))
+            .cloned()
+            .collect();
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for Words {
+    type Hint = String;
+}
+
+impl Highlighter for Words {}
+
+impl Validator for Words {}
+
+impl Helper for Words {}
+
+// LineEditor reads the commands typed at a prompt with the line editing a
+// shell has: the arrow keys move through the line and the history, which is
+// kept between runs, and tab completes commands and labels. Lines piped in
+// rather than typed are read as they are and left out of the history.
+pub struct LineEditor {
+    editor: Editor<Words, DefaultHistory>,
+    // history is the file the history is kept in, if there is one
+    history: Option<PathBuf>,
+}
+
+impl LineEditor {
+    // new creates an editor completing the commands and labels given,
+    // reading the history from the file if it can
+    pub fn new(commands: Vec<String>, labels: Vec<String>, history: Option<PathBuf>) -> Self {
+        let mut editor = Editor::new().expect("the line editor could not be created");
+        editor.set_helper(Some(Words { commands, labels }));
+        let history = history.filter(|_| stdin().is_terminal());
+        if let Some(path) = &history {
+            // a missing or unreadable history starts a new one
+            let _ = editor.load_history(path);
+        }
+        LineEditor { editor, history }
+    }
+
+    // read_line shows the prompt and returns the line typed, or None at the
+    // end of stdin. Ctrl-C abandons the line being typed, returning an empty
+    // one.
+    pub fn read_line(&mut self, prompt: &str) -> Option<String> {
+        match self.editor.readline(prompt) {
+            Ok(line) => {
+                if self.history.is_some() && !line.trim().is_empty() {
+                    let _ = self.editor.add_history_entry(line.as_str());
+                }
+                Some(line)
+            }
+            Err(ReadlineError::Interrupted) => Some(String::new()),
+            Err(_) => None,
+        }
+    }
+}
+
+// Implement the drop trait so the history is saved however the prompt ends.
+impl Drop for LineEditor {
+    fn drop(&mut self) {
+        if let Some(path) = &self.history {
+            if let Some(dir) = path.parent() {
+                let _ = std::fs::create_dir_all(dir);
+            }
+            let _ = self.editor.save_history(path);
+        }
+    }
+}
@@ -0,0 +1,250 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt, fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    numbers::ThreeDigitNumber,
+    program::Program,
+    tournament::{self, CaseSpec},
+};
+
+// ExerciseError is used to indicate an error checking an exercise or keeping
+// track of progress through a problem set
+#[derive(Debug, PartialEq)]
+pub enum ExerciseError {
+    IOError(String),
+    InvalidSpec(String),
+    InvalidProgress(String),
+}
+
+// Implement the display trait for easy printing.
+impl fmt::Display for ExerciseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExerciseError::IOError(value) => write!(f, "IO error: {}", value),
+            ExerciseError::InvalidSpec(value) => write!(f, "invalid exercise: {}", value),
+            ExerciseError::InvalidProgress(value) => {
+                write!(f, "invalid progress file: {}", value)
+            }
+        }
+    }
+}
+
+// Implement the error trait so the error can be used with the ? operator
+// alongside other errors.
+impl std::error::Error for ExerciseError {}
+
+// ExerciseSpec describes a single exercise of a problem set: what to write
+// and the cases a solution must pass
+#[derive(Debug, Deserialize)]
+pub struct ExerciseSpec {
+    // name identifies the exercise in the progress file, the spec's file
+    // name being used if it is not given
+    pub name: Option<String>,
+    // description is shown to the student when the exercise is checked
+    pub description: Option<String>,
+    // max_cycles is the cycle budget a solution gets for each case
+    pub max_cycles: usize,
+    pub cases: Vec<CaseSpec>,
+}
+
+// load_spec reads and parses a TOML exercise spec, naming it after the file
+// if it does not name itself
+pub fn load_spec(path: &Path) -> Result<ExerciseSpec, ExerciseError> {
+    let text = fs::read_to_string(path).map_err(|e| ExerciseError::IOError(e.to_string()))?;
+    let mut spec: ExerciseSpec =
+        toml::from_str(&text).map_err(|e| ExerciseError::InvalidSpec(e.to_string()))?;
+    if spec.name.is_none() {
+        spec.name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string());
+    }
+    Ok(spec)
+}
+
+// Attempt is the outcome of checking a solution against an exercise
+#[derive(Debug, PartialEq)]
+pub struct Attempt {
+    pub passed: usize,
+    pub total: usize,
+    // cycles is the total taken over every case, only known when they all pass
+    pub cycles: Option<usize>,
+}
+
+impl Attempt {
+    // is_solved returns whether the solution passed every case
+    pub fn is_solved(&self) -> bool {
+        self.passed == self.total
+    }
+}
+
+// check runs the program against every case of the exercise
+pub fn check(spec: &ExerciseSpec, program: &Program) -> Result<Attempt, ExerciseError> {
+    let mut attempt = Attempt {
+        passed: 0,
+        total: spec.cases.len(),
+        cycles: None,
+    };
+    let mut cycles = 0;
+    for case in &spec.cases {
+        let inputs = to_numbers(&case.inputs)?;
+        let expected = to_numbers(&case.expected)?;
+        if let Some((outputs, taken)) = tournament::run_case(program, &inputs, spec.max_cycles) {
+            if outputs == expected {
+                attempt.passed += 1;
+                cycles += taken;
+            }
+        }
+    }
+    if attempt.is_solved() {
+        attempt.cycles = Some(cycles);
+    }
+    Ok(attempt)
+}
+
+// to_numbers converts the raw values of a case into 3-digit numbers
+fn to_numbers(values: &[i16]) -> Result<Vec<ThreeDigitNumber>, ExerciseError> {
+    values
+        .iter()
+        .map(|value| {
+            ThreeDigitNumber::new(*value).map_err(|e| ExerciseError::InvalidSpec(e.to_string()))
+        })
+        .collect()
+}
+
+// Record is what is known of a student's work on a single exercise, the
+// times being seconds since the Unix epoch
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    pub attempts: usize,
+    pub last_attempt: u64,
+    // solved is when the exercise first passed every case
+    pub solved: Option<u64>,
+    // best_cycles is the fewest cycles any passing solution took in total
+    pub best_cycles: Option<usize>,
+}
+
+// Progress is every exercise of a problem set that has been attempted, kept
+// in a local file between runs
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Progress {
+    #[serde(default)]
+    pub exercises: BTreeMap<String, Record>,
+}
+
+impl Progress {
+    // load reads the progress file, a missing file meaning nothing has been
+    // attempted yet
+    pub fn load(path: &Path) -> Result<Progress, ExerciseError> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Progress::default())
+            }
+            Err(err) => return Err(ExerciseError::IOError(err.to_string())),
+        };
+        toml::from_str(&text).map_err(|e| ExerciseError::InvalidProgress(e.to_string()))
+    }
+
+    // save writes the progress file
+    pub fn save(&self, path: &Path) -> Result<(), ExerciseError> {
+        let text =
+            toml::to_string(self).map_err(|e| ExerciseError::InvalidProgress(e.to_string()))?;
+        fs::write(path, text).map_err(|e| ExerciseError::IOError(e.to_string()))
+    }
+
+    // record adds an attempt at the exercise made at the time given, keeping
+    // the first time it was solved and the best cycle count
+    pub fn record(&mut self, name: &str, attempt: &Attempt, time: u64) {
+        let record = self.exercises.entry(name.to_string()).or_default();
+        record.attempts += 1;
+        record.last_attempt = time;
+        if attempt.is_solved() && record.solved.is_none() {
+            record.solved = Some(time);
+        }
+        if let Some(cycles) = attempt.cycles {
+            record.best_cycles = Some(record.best_cycles.map_or(cycles, |best| best.min(cycles)));
+        }
+    }
+
+    // status lists every exercise attempted with whether it has been solved,
+    // when, and its best cycle count, followed by the number solved
+    pub fn status(&self) -> String {
+        let width = self
+            .exercises
+            .keys()
+            .map(|name| name.len())
+            .max()
+            .unwrap_or(0);
+        let mut text = String::new();
+        for (name, record) in &self.exercises {
+            let state = match (record.solved, record.best_cycles) {
+                (Some(solved), Some(cycles)) => {
+                    format!("solved {}  best {} cycles", format_time(solved), cycles)
+                }
+                _ => "unsolved".to_string(),
+            };
+            text.push_str(&format!(
+                "{:width$}  {:<40}  attempts: {}  last: {}\n",
+                name,
+                state,
+                record.attempts,
+                format_time(record.last_attempt),
+                width = width
+            ));
+        }
+        let solved = self
+            .exercises
+            .values()
+            .filter(|record| record.solved.is_some())
+            .count();
+        text.push_str(&format!(
+            "{}/{} exercises solved\n",
+            solved,
+            self.exercises.len()
+        ));
+        text
+    }
+}
+
+// now returns the current time in seconds since the Unix epoch
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+// format_time shows seconds since the Unix epoch as a UTC date and time, e.g.
+// 2024-03-09 14:05
+pub fn format_time(time: u64) -> String {
+    let (days, seconds) = ((time / 86400) as i64, time % 86400);
+    // convert days since the epoch to a civil date, see
+    // https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60
+    )
+}
@@ -0,0 +1,250 @@
+use std::fmt;
+
+// ExprError is used to indicate an expression that could not be parsed or
+// evaluated
+#[derive(Debug, PartialEq)]
+pub enum ExprError {
+    InvalidExpression(String),
+    UnknownInput(usize),
+    DivisionByZero,
+}
+
+// Implement the display trait for easy printing.
+impl fmt::Display for ExprError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExprError::InvalidExpression(value) => write!(f, "invalid expression: {}", value),
+            ExprError::UnknownInput(index) => write!(f, "unknown input: in{}", index),
+            ExprError::DivisionByZero => write!(f, "division by zero"),
+        }
+    }
+}
+
+// Expr is an integer expression over the inputs of a test, such as
+// min(in1, in2) or (in1 + in2) % 1000, used to compute the output expected
+// for any inputs
+#[derive(Debug, PartialEq, Clone)]
+pub enum Expr {
+    Number(i64),
+    // Input is the 1-based index of an input, written in1, in2, ...
+    Input(usize),
+    Binary(Box<Expr>, char, Box<Expr>),
+    Negate(Box<Expr>),
+    // Call is one of the functions min, max and abs
+    Call(String, Vec<Expr>),
+}
+
+impl Expr {
+    // parse reads an expression of numbers, inputs, + - * / %, parentheses
+    // and the functions min, max and abs
+    pub fn parse(text: &str) -> Result<Expr, ExprError> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser { tokens, next: 0 };
+        let expr = parser.sum()?;
+        match parser.tokens.get(parser.next) {
+            Some(token) => Err(ExprError::InvalidExpression(format!(
+                "unexpected {} in {}",
+                token, text
+            ))),
+            None => Ok(expr),
+        }
+    }
+
+    // eval evaluates the expression with the inputs given in order
+    pub fn eval(&self, inputs: &[i64]) -> Result<i64, ExprError> {
+        match self {
+            Expr::Number(value) => Ok(*value),
+            Expr::Input(index) => inputs
+                .get(index.wrapping_sub(1))
+                .copied()
+                .ok_or(ExprError::UnknownInput(*index)),
+            Expr::Negate(expr) => Ok(-expr.eval(inputs)?),
+            Expr::Binary(left, op, right) => {
+                let (left, right) = (left.eval(inputs)?, right.eval(inputs)?);
+                match op {
+                    '+' => Ok(left + right),
+                    '-' => Ok(left - right),
+                    '*' => Ok(left * right),
+                    '/' | '%' if right == 0 => Err(ExprError::DivisionByZero),
+                    '/' => Ok(left / right),
+                    _ => Ok(left.rem_euclid(right)),
+                }
+            }
+            Expr::Call(name, args) => {
+                let values = args
+                    .iter()
+                    .map(|arg| arg.eval(inputs))
+                    .collect::<Result<Vec<i64>, ExprError>>()?;
+                match (name.as_str(), values.as_slice()) {
+                    ("abs", [value]) => Ok(value.abs()),
+                    ("min", [_, ..]) => Ok(*values.iter().min().unwrap()),
+                    ("max", [_, ..]) => Ok(*values.iter().max().unwrap()),
+                    _ => Err(ExprError::InvalidExpression(format!(
+                        "{} does not take {} arguments",
+                        name,
+                        values.len()
+                    ))),
+                }
+            }
+        }
+    }
+}
+
+// split_top_level splits the text at every comma that is not inside
+// parentheses, so that a list of expressions may call min or max
+pub fn split_top_level(text: &str) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut depth = 0;
+    for c in text.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(String::new());
+                continue;
+            }
+            _ => {}
+        }
+        parts.last_mut().unwrap().push(c);
+    }
+    parts
+}
+
+// Token is a single word or symbol of an expression
+#[derive(Debug, PartialEq, Clone)]
+enum Token {
+    Number(i64),
+    Name(String),
+    Symbol(char),
+}
+
+// Implement the display trait for easy printing.
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Number(value) => write!(f, "{}", value),
+            Token::Name(name) => write!(f, "{}", name),
+            Token::Symbol(symbol) => write!(f, "{}", symbol),
+        }
+    }
+}
+
+// tokenize splits the text into numbers, names and symbols
+fn tokenize(text: &str) -> Result<Vec<Token>, ExprError> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() {
+            let mut number = String::new();
+            while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit()) {
+                number.push(c);
+                chars.next();
+            }
+            let value = number
+                .parse()
+                .map_err(|_| ExprError::InvalidExpression(number.clone()))?;
+            tokens.push(Token::Number(value));
+        } else if c.is_ascii_alphabetic() {
+            let mut name = String::new();
+            while let Some(&c) = chars.peek().filter(|c| c.is_ascii_alphanumeric()) {
+                name.push(c);
+                chars.next();
+            }
+            tokens.push(Token::Name(name));
+        } else if "+-*/%(),".contains(c) {
+            tokens.push(Token::Symbol(c));
+            chars.next();
+        } else {
+            return Err(ExprError::InvalidExpression(format!(
+                "unexpected {} in {}",
+                c, text
+            )));
+        }
+    }
+    Ok(tokens)
+}
+
+// Parser is a recursive descent parser over the tokens of an expression
+struct Parser {
+    tokens: Vec<Token>,
+    next: usize,
+}
+
+impl Parser {
+    // symbol consumes the next token if it is one of the symbols
+    fn symbol(&mut self, symbols: &str) -> Option<char> {
+        match self.tokens.get(self.next) {
+            Some(Token::Symbol(c)) if symbols.contains(*c) => {
+                self.next += 1;
+                Some(*c)
+            }
+            _ => None,
+        }
+    }
+
+    // expect consumes the symbol or fails
+    fn expect(&mut self, symbol: char) -> Result<(), ExprError> {
+        self.symbol(&symbol.to_string())
+            .map(|_| ())
+            .ok_or_else(|| ExprError::InvalidExpression(format!("expected {}", symbol)))
+    }
+
+    // sum parses terms separated by + and -
+    fn sum(&mut self) -> Result<Expr, ExprError> {
+        let mut expr = self.product()?;
+        while let Some(op) = self.symbol("+-") {
+            expr = Expr::Binary(Box::new(expr), op, Box::new(self.product()?));
+        }
+        Ok(expr)
+    }
+
+    // product parses factors separated by *, / and %
+    fn product(&mut self) -> Result<Expr, ExprError> {
+        let mut expr = self.factor()?;
+        while let Some(op) = self.symbol("*/%") {
+            expr = Expr::Binary(Box::new(expr), op, Box::new(self.factor()?));
+        }
+        Ok(expr)
+    }
+
+    // factor parses a number, input, call, negation or parenthesised sum
+    fn factor(&mut self) -> Result<Expr, ExprError> {
+        if self.symbol("-").is_some() {
+            return Ok(Expr::Negate(Box::new(self.factor()?)));
+        }
+        if self.symbol("(").is_some() {
+            let expr = self.sum()?;
+            self.expect(')')?;
+            return Ok(expr);
+        }
+        let token = self.tokens.get(self.next).cloned();
+        self.next += 1;
+        match token {
+            Some(Token::Number(value)) => Ok(Expr::Number(value)),
+            Some(Token::Name(name)) if self.symbol("(").is_some() => {
+                let mut args = vec![self.sum()?];
+                while self.symbol(",").is_some() {
+                    args.push(self.sum()?);
+                }
+                self.expect(')')?;
+                Ok(Expr::Call(name, args))
+            }
+            Some(Token::Name(name)) => match name.strip_prefix("in").map(str::parse::<usize>) {
+                Some(Ok(index)) => Ok(Expr::Input(index)),
+                _ => Err(ExprError::InvalidExpression(format!(
+                    "unknown name {}",
+                    name
+                ))),
+            },
+            Some(token) => Err(ExprError::InvalidExpression(format!(
+                "unexpected {}",
+                token
+            ))),
+            None => Err(ExprError::InvalidExpression(
+                "unexpected end of expression".to_string(),
+            )),
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use std::{
+    fmt,
+    panic::{self, AssertUnwindSafe},
+};
+
+use crate::{
+    assembler::{Assembler, MNEMONICS},
+    devices::Rng,
+    lmc::{CounterWrap, LMC},
+    numbers::ThreeDigitNumber,
+    program::Program,
+    source::{self, SourceFormat},
+    syntax, text,
+};
+
+// FUZZ_MAX_CYCLES is the most cycles a fuzzed program runs for, enough to
+// reach most of its instructions without making each case slow
+const FUZZ_MAX_CYCLES: usize = 1000;
+
+// WORDS are the pieces structured source is built from, so that most lines
+// get past tokenizing and exercise the assembler's deeper checks
+const WORDS: [&str; 14] = [
+    "ASSERT", "ACC", "==", "<=", ">", "loop", "done", "x", "#", "99", "100", "-1", "07", "\t",
+];
+
+// FuzzFailure is a case that made the assembler or VM panic rather than
+// return an error
+#[derive(Debug, Clone)]
+pub struct FuzzFailure {
+    // target is the part of lmc that panicked, assembler or vm
+    pub target: &'static str,
+    // seed is the seed of the case, replaying it generates the same input
+    pub seed: u64,
+    // input is the source or mailbox values that caused the panic
+    pub input: String,
+    pub message: String,
+}
+
+// Implement the display trait for easy printing.
+impl fmt::Display for FuzzFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} panicked: {}\ninput:\n{}",
+            self.target, self.message, self.input
+        )
+    }
+}
+
+// Fuzzer throws random input at the assembler and VM checking that every
+// problem surfaces as an error instead of a panic
+pub struct Fuzzer {
+    seed: u64,
+    // debug shows every step the assembler and VM take on each case
+    debug: bool,
+    failures: Vec<FuzzFailure>,
+}
+
+impl Fuzzer {
+    // new creates a fuzzer whose cases are generated from the seed, so a run
+    // can be repeated exactly by passing the same seed
+    pub fn new(seed: u64) -> Self {
+        Fuzzer {
+            seed,
+            debug: false,
+            failures: Vec::new(),
+        }
+    }
+
+    // set_debug sets whether every step of each case is shown
+    pub fn set_debug(&mut self, debug: bool) {
+        self.debug = debug;
+    }
+
+    // run generates and checks the number of cases for each target, returning
+    // the cases that panicked
+    pub fn run(&mut self, iterations: usize) -> &[FuzzFailure] {
+        // the default hook would print every caught panic to stderr
+        let hook = panic::take_hook();
+        panic::set_hook(Box::new(|_| {}));
+        for i in 0..iterations {
+            self.case(Rng::case_seed(self.seed, i));
+        }
+        panic::set_hook(hook);
+        &self.failures
+    }
+
+    // replay checks the single case with the seed a failure reported,
+    // returning it again if it still panics
+    pub fn replay(&mut self, seed: u64) -> &[FuzzFailure] {
+        let hook = panic::take_hook();
+        panic::set_hook(Box::new(|_| {}));
+        self.case(seed);
+        panic::set_hook(hook);
+        &self.failures
+    }
+
+    // case generates and checks one case for each target from the seed
+    fn case(&mut self, seed: u64) {
+        let mut rng = Rng::new(seed);
+        let debug = self.debug;
+        let source = match rng.range(0, 1) {
+            0 => random_bytes(&mut rng),
+            _ => random_source(&mut rng),
+        };
+        self.check("assembler", seed, source.join("\n"), || {
+            fuzz_source(&source, debug)
+        });
+        let (instructions, inputs) = random_program(&mut rng);
+        let input = instructions
+            .iter()
+            .map(|number| number.to_string())
+            .collect::<Vec<String>>()
+            .join(" ");
+        self.check("vm", seed, input, || {
+            fuzz_program(&Program::from_instructions(instructions), &inputs, debug)
+        });
+    }
+
+    // check runs the case recording a failure if it panics
+    fn check(&mut self, target: &'static str, seed: u64, input: String, case: impl FnOnce()) {
+        if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(case)) {
+            let message = match payload.downcast_ref::<&str>() {
+                Some(message) => message.to_string(),
+                None => payload
+                    .downcast_ref::<String>()
+                    .cloned()
+                    .unwrap_or_else(|| "unknown panic".to_string()),
+            };
+            self.failures.push(FuzzFailure {
+                target,
+                seed,
+                input,
+                message,
+            });
+        }
+    }
+}
+
+// random_bytes returns a random byte stream decoded as text
+fn random_bytes(rng: &mut Rng) -> Vec<String> {
+    let length = rng.range(0, 512) as usize;
+    let bytes = (0..length)
+        .map(|_| rng.range(0, 255) as u8)
+        .collect::<Vec<u8>>();
+    text::normalize(&String::from_utf8_lossy(&bytes)).lines
+}
+
+// random_source returns up to 120 lines of words that look like assembly
+fn random_source(rng: &mut Rng) -> Vec<String> {
+    let lines = rng.range(0, 120);
+    (0..lines)
+        .map(|_| {
+            let words = rng.range(0, 4);
+            (0..words)
+                .map(|_| random_word(rng))
+                .collect::<Vec<String>>()
+                .join(" ")
+        })
+        .collect()
+}
+
+// random_word returns a mnemonic, one of WORDS or a random number
+fn random_word(rng: &mut Rng) -> String {
+    match rng.range(0, 2) {
+        0 => MNEMONICS[rng.range(0, MNEMONICS.len() as i16 - 1) as usize].to_string(),
+        1 => WORDS[rng.range(0, WORDS.len() as i16 - 1) as usize].to_string(),
+        _ => rng.range(-2000, 2000).to_string(),
+    }
+}
+
+// random_program returns 100 random mailbox values, biased towards valid
+// instructions, along with some input for them
+pub fn random_program(rng: &mut Rng) -> (Vec<ThreeDigitNumber>, Vec<ThreeDigitNumber>) {
+    let instructions = (0..100)
+        .map(|_| {
+            let value = match rng.range(0, 3) {
+                0 => rng.range(0, 999),
+                1 => rng.range(901, 902),
+                _ => rng.range(0, 8) * 100 + rng.range(0, 99),
+            };
+            ThreeDigitNumber::new(value).unwrap()
+        })
+        .collect();
+    let count = rng.range(0, 10);
+    let inputs = (0..count)
+        .map(|_| ThreeDigitNumber::new(rng.range(0, 999)).unwrap())
+        .collect();
+    (instructions, inputs)
+}
+
+// fuzz_source assembles the source every way the CLI can, running it if it
+// assembles
+fn fuzz_source(source: &[String], debug: bool) {
+    for format in [SourceFormat::Free, SourceFormat::Fixed] {
+        let free = source::to_free(source, format);
+        source::format(source, format);
+        syntax::tokenize(&free);
+        for partial in [false, true] {
+            let mut asm = Assembler::new(debug, debug);
+            asm.set_partial(partial);
+            if let Ok(program) = asm.assemble_program(&mut free.clone()) {
+                fuzz_program(&program, &[], debug);
+            }
+        }
+    }
+}
+
+// fuzz_program runs the program with the inputs, limiting it to the inputs
+// given so it never waits on stdin
+fn fuzz_program(program: &Program, inputs: &[ThreeDigitNumber], debug: bool) {
+    let mut lmc = LMC::new(debug, debug, true, FUZZ_MAX_CYCLES);
+    lmc.set_counter_wrap(CounterWrap::Wrap);
+    lmc.set_io_limits(Some(inputs.len()), None);
+    lmc.load_input(inputs);
+    if lmc.load_program(program).is_err() {
+        return;
+    }
+    lmc.load_assertions(&program.assertions);
+    lmc.execute_program();
+    lmc.state();
+}
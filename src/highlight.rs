@@ -0,0 +1,120 @@
+use serde_json::json;
+
+use crate::assembler::{ALIASES, MNEMONICS};
+
+// PSEUDO_INSTRUCTIONS are the words the assembler accepts besides opcodes
+const PSEUDO_INSTRUCTIONS: [&str; 3] = ["ASSERT", "MACRO", "ENDM"];
+
+// COMPARISONS are the operators accepted in ASSERT expressions
+const COMPARISONS: [&str; 6] = ["==", "!=", "<=", ">=", "<", ">"];
+
+// HighlightFormat is an editor syntax definition format
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum HighlightFormat {
+    // TmLanguage is a TextMate grammar in JSON, used by VS Code and others
+    TmLanguage,
+    Vim,
+    Pygments,
+}
+
+impl HighlightFormat {
+    // from_str converts a string to a highlight format
+    pub fn from_str(format: &str) -> Option<HighlightFormat> {
+        match format {
+            "tmlanguage" => Some(HighlightFormat::TmLanguage),
+            "vim" => Some(HighlightFormat::Vim),
+            "pygments" => Some(HighlightFormat::Pygments),
+            _ => None,
+        }
+    }
+}
+
+// generate returns the syntax definition in the format, built from the
+// assembler's own table of mnemonics so that it never falls out of date
+pub fn generate(format: HighlightFormat) -> String {
+    // opcodes may be written in any case and by any of their aliases
+    let opcodes = MNEMONICS
+        .iter()
+        .chain(ALIASES.iter().map(|(alias, _)| alias))
+        .copied()
+        .collect::<Vec<&str>>();
+    let mnemonics = opcodes.join("|");
+    let pseudo = PSEUDO_INSTRUCTIONS.join("|");
+    let comparisons = COMPARISONS.join("|");
+    match format {
+        HighlightFormat::TmLanguage => {
+            let grammar = json!({
+                "$schema": "https://raw.githubusercontent.com/martinring/tmlanguage/master/tmlanguage.json",
+                "name": "LMC Assembly",
+                "scopeName": "source.lmc",
+                "fileTypes": ["asm", "lmc"],
+                "patterns": [
+                    { "name": "comment.line.number-sign.lmc", "match": "#.*$" },
+                    { "name": "keyword.control.lmc", "match": format!("\\b({})\\b", pseudo) },
+                    { "name": "keyword.other.opcode.lmc", "match": format!("(?i)\\b({})\\b", mnemonics) },
+                    { "name": "variable.language.lmc", "match": "\\bACC\\b" },
+                    { "name": "keyword.operator.comparison.lmc", "match": comparisons },
+                    { "name": "constant.numeric.lmc", "match": "-?\\b[0-9]+\\b" },
+                    { "name": "entity.name.label.lmc", "match": "^[^\\s#]+" },
+                ],
+            });
+            serde_json::to_string_pretty(&grammar).unwrap_or_default()
+        }
+        HighlightFormat::Vim => [
+            "\" Vim syntax file for LMC assembly, generated by lmc highlight",
+            "if exists(\"b:current_syntax\")",
+            "  finish",
+            "endif",
+            "",
+            "syntax case ignore",
+            &format!("syntax keyword lmcOpcode {}", opcodes.join(" ")),
+            "syntax case match",
+            &format!("syntax keyword lmcPseudo {}", PSEUDO_INSTRUCTIONS.join(" ")),
+            "syntax keyword lmcCalculator ACC",
+            &format!(
+                "syntax match lmcComparison \"\\V{}\"",
+                COMPARISONS.join("\\|")
+            ),
+            "syntax match lmcNumber \"\\<-\\?\\d\\+\\>\"",
+            "syntax match lmcLabel \"^[^ \\t#]\\+\"",
+            "syntax match lmcComment \"#.*$\"",
+            "",
+            "highlight default link lmcOpcode Statement",
+            "highlight default link lmcPseudo PreProc",
+            "highlight default link lmcCalculator Identifier",
+            "highlight default link lmcComparison Operator",
+            "highlight default link lmcNumber Number",
+            "highlight default link lmcLabel Label",
+            "highlight default link lmcComment Comment",
+            "",
+            "let b:current_syntax = \"lmc\"",
+        ]
+        .join("\n"),
+        HighlightFormat::Pygments => [
+            "# Pygments lexer for LMC assembly, generated by lmc highlight",
+            "from pygments.lexer import RegexLexer, bygroups",
+            "from pygments.token import Comment, Keyword, Name, Number, Operator, Text",
+            "",
+            "",
+            "class LMCLexer(RegexLexer):",
+            "    name = \"LMC Assembly\"",
+            "    aliases = [\"lmc\"]",
+            "    filenames = [\"*.asm\", \"*.lmc\"]",
+            "",
+            "    tokens = {",
+            "        \"root\": [",
+            "            (r\"#.*$\", Comment.Single),",
+            "            (r\"^([^\\s#]+)\", bygroups(Name.Label)),",
+            &format!("            (r\"\\b({})\\b\", Keyword.Pseudo),", pseudo),
+            &format!("            (r\"(?i)\\b({})\\b\", Keyword),", mnemonics),
+            "            (r\"\\bACC\\b\", Name.Builtin),",
+            &format!("            (r\"{}\", Operator),", comparisons),
+            "            (r\"-?\\b[0-9]+\\b\", Number.Integer),",
+            "            (r\"[^\\s#]+\", Name.Variable),",
+            "            (r\"\\s+\", Text),",
+            "        ],",
+            "    }",
+        ]
+        .join("\n"),
+    }
+}
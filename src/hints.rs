@@ -0,0 +1,171 @@
+use crate::{
+    instruction::Instruction,
+    lmc::{HaltReason, LMCError},
+    numbers::ThreeDigitNumber,
+    program::Program,
+};
+
+// Failure is what is known about where a run stopped, enough to explain the
+// error in terms of the program rather than the machine
+pub struct Failure<'a> {
+    // counter is the address of the instruction that failed
+    pub counter: usize,
+    // previous is the address of the instruction executed before it, if any
+    pub previous: Option<usize>,
+    // mailboxes are the contents of memory when the run stopped
+    pub mailboxes: &'a [ThreeDigitNumber],
+}
+
+// hint explains a run that stopped without halting the way a teacher would,
+// pointing at the instructions of the program most likely to be the cause,
+// or None if there is nothing to add to the error itself
+pub fn hint(reason: &HaltReason, program: &Program, failure: &Failure) -> Option<String> {
+    let error = match reason {
+        HaltReason::MaxCycles(_) => return Some(endless_loop(program)),
+        HaltReason::Error(error) => error,
+        _ => return None,
+    };
+    let hint = match error {
+        LMCError::InvalidOpcode(_) => invalid_opcode(program, failure),
+        LMCError::ExecutedData(address) => executed_data(program, failure, *address),
+        LMCError::JumpedIntoData(from, to) => format!(
+            "the {} at {} sends the program to {}, which holds data rather than an \
+             instruction, did you mean to branch to another label?",
+            mnemonic(failure, *from),
+            place(program, *from),
+            place(program, *to)
+        ),
+        LMCError::UninitializedRead(address) => format!(
+            "nothing was ever stored in {}, give it a value with DAT or STO to it \
+             before reading it",
+            place(program, *address)
+        ),
+        LMCError::FellOffEnd(_) => "the program ran through every mailbox without \
+             halting, add a HLT where it should stop"
+            .to_string(),
+        LMCError::MaxCyclesHit(_) => endless_loop(program),
+        LMCError::MaxInputsHit(_) => "the program asked for more inputs than allowed, \
+             check that every loop around an IN ends once the input does"
+            .to_string(),
+        LMCError::InputUnderflow { .. } => "the program asked for more inputs than the \
+             test gives it, check the number of IN instructions run against the \
+             inputs of the test"
+            .to_string(),
+        LMCError::MaxOutputsHit(_) => "the program gave more outputs than allowed, \
+             check that every loop around an OUT ends"
+            .to_string(),
+        LMCError::AssertionFailed(address, _) => format!(
+            "the assertion before {} did not hold, step through the program up to \
+             it with the debugger to see where the values went wrong",
+            place(program, *address)
+        ),
+        LMCError::NumberError(_) => "a calculation went out of range, LMC numbers \
+             are between 000 and 999"
+            .to_string(),
+        _ => return None,
+    };
+    Some(hint)
+}
+
+// invalid_opcode explains a mailbox that does not hold an instruction, which
+// is usually data reached by a branch or by running past the last instruction
+fn invalid_opcode(program: &Program, failure: &Failure) -> String {
+    let address = failure.counter;
+    if program.is_data(address) || branched_from(failure, address).is_some() {
+        return executed_data(program, failure, address);
+    }
+    if !program.instructions.is_empty() && address >= program.instructions.len() {
+        return format!(
+            "{} is past the end of the program, you may be missing a HLT after \
+             the last instruction",
+            place(program, address)
+        );
+    }
+    // an instruction that no longer decodes must have been overwritten
+    let stores = program
+        .instructions
+        .iter()
+        .enumerate()
+        .filter(|(_, value)| Instruction::decode(**value) == Instruction::Sto(address))
+        .map(|(store, _)| format!("{:02}", store))
+        .collect::<Vec<String>>();
+    if !stores.is_empty() {
+        return format!(
+            "{} was overwritten while running, check the STO at {}",
+            place(program, address),
+            stores.join(", ")
+        );
+    }
+    format!(
+        "{} does not hold an instruction, check how the program got there",
+        place(program, address)
+    )
+}
+
+// executed_data explains reaching a DAT cell, either through a branch or by
+// running on from the instruction above it
+fn executed_data(program: &Program, failure: &Failure, address: usize) -> String {
+    match branched_from(failure, address) {
+        Some(branch) => format!(
+            "you may have branched into a DAT cell, {} holds data, check the \
+             target of the {} at address {:02}",
+            place(program, address),
+            mnemonic(failure, branch),
+            branch
+        ),
+        None => format!(
+            "the program ran on into a DAT cell, {} holds data, add a HLT before \
+             the data or branch around it",
+            place(program, address)
+        ),
+    }
+}
+
+// branched_from returns the address of the branch that led to the address,
+// if the instruction executed last was one
+fn branched_from(failure: &Failure, address: usize) -> Option<usize> {
+    failure.previous.filter(|previous| {
+        let instruction = Instruction::decode(failure.mailboxes[*previous]);
+        instruction.is_branch() && instruction.operand() == Some(address)
+    })
+}
+
+// endless_loop explains running out of cycles by listing the loops of the
+// program, the branches back to an earlier address
+fn endless_loop(program: &Program) -> String {
+    let loops = program
+        .instructions
+        .iter()
+        .enumerate()
+        .filter(|(address, _)| !program.is_data(*address))
+        .filter_map(|(address, value)| {
+            let instruction = Instruction::decode(*value);
+            match instruction.operand() {
+                Some(target) if instruction.is_branch() && target <= address => {
+                    Some(format!("{} at {:02}", instruction.mnemonic(), address))
+                }
+                _ => None,
+            }
+        })
+        .collect::<Vec<String>>();
+    match loops.is_empty() {
+        true => "the program never reached a HLT, check that it has one".to_string(),
+        false => format!(
+            "a loop may never exit, check the condition of the branch back at {}",
+            loops.join(", ")
+        ),
+    }
+}
+
+// place names a mailbox by its label if it has one, e.g. address 12 (count)
+fn place(program: &Program, address: usize) -> String {
+    match program.label_of(address) {
+        Some(label) => format!("address {:02} ({})", address, label),
+        None => format!("address {:02}", address),
+    }
+}
+
+// mnemonic returns the mnemonic of the instruction held at the address
+fn mnemonic(failure: &Failure, address: usize) -> &'static str {
+    Instruction::decode(failure.mailboxes[address]).mnemonic()
+}
@@ -0,0 +1,91 @@
+use std::fmt;
+
+use crate::numbers::ThreeDigitNumber;
+
+// Instruction is a mailbox value decoded into the operation it performs
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Instruction {
+    Add(usize),
+    Sub(usize),
+    Sto(usize),
+    Lda(usize),
+    Br(usize),
+    Brz(usize),
+    Brp(usize),
+    In,
+    Out,
+    Hlt,
+    // Invalid is a value that does not decode to any instruction
+    Invalid(i16),
+}
+
+impl Instruction {
+    // decode converts a mailbox value into the instruction it encodes
+    pub fn decode(number: ThreeDigitNumber) -> Self {
+        let value = number.value();
+        let operand = (value % 100) as usize;
+        match value / 100 {
+            0 if value == 0 => Instruction::Hlt,
+            1 => Instruction::Add(operand),
+            2 => Instruction::Sub(operand),
+            3 => Instruction::Sto(operand),
+            5 => Instruction::Lda(operand),
+            6 => Instruction::Br(operand),
+            7 => Instruction::Brz(operand),
+            8 => Instruction::Brp(operand),
+            9 if operand == 1 => Instruction::In,
+            9 if operand == 2 => Instruction::Out,
+            _ => Instruction::Invalid(value),
+        }
+    }
+
+    // mnemonic returns the assembly mnemonic of the instruction
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Instruction::Add(_) => "ADD",
+            Instruction::Sub(_) => "SUB",
+            Instruction::Sto(_) => "STO",
+            Instruction::Lda(_) => "LDA",
+            Instruction::Br(_) => "BR",
+            Instruction::Brz(_) => "BRZ",
+            Instruction::Brp(_) => "BRP",
+            Instruction::In => "IN",
+            Instruction::Out => "OUT",
+            Instruction::Hlt => "HLT",
+            Instruction::Invalid(_) => "???",
+        }
+    }
+
+    // operand returns the mailbox address the instruction refers to
+    pub fn operand(&self) -> Option<usize> {
+        match self {
+            Instruction::Add(address)
+            | Instruction::Sub(address)
+            | Instruction::Sto(address)
+            | Instruction::Lda(address)
+            | Instruction::Br(address)
+            | Instruction::Brz(address)
+            | Instruction::Brp(address) => Some(*address),
+            _ => None,
+        }
+    }
+
+    // is_branch returns whether the instruction may change the counter
+    pub fn is_branch(&self) -> bool {
+        matches!(
+            self,
+            Instruction::Br(_) | Instruction::Brz(_) | Instruction::Brp(_)
+        )
+    }
+}
+
+// Display shows the instruction as assembly with a numeric operand, e.g. STO 15
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self, self.operand()) {
+            (Instruction::Invalid(value), _) => write!(f, "{} {:03}", self.mnemonic(), value),
+            (_, Some(address)) => write!(f, "{} {:02}", self.mnemonic(), address),
+            (_, None) => write!(f, "{}", self.mnemonic()),
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use std::{
+    ops::RangeInclusive,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{sync_channel, Receiver, RecvTimeoutError, SyncSender},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use crate::{devices::Rng, numbers::ThreeDigitNumber};
+
+// Input supplies the values read by IN once the input tray is empty, in
+// place of reading from stdin
+pub trait Input {
+    // read returns the next value, or None once there are no more
+    fn read(&mut self) -> Option<ThreeDigitNumber>;
+
+    // poll waits at most the timeout for the next value, so that an LMC
+    // waiting on an input that blocks can give up when its run is cancelled.
+    // Inputs that never block simply read.
+    fn poll(&mut self, _timeout: Duration) -> Poll {
+        match self.read() {
+            Some(value) => Poll::Ready(value),
+            None => Poll::Closed,
+        }
+    }
+}
+
+// Poll is the outcome of waiting for the next value of an input
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Poll {
+    Ready(ThreeDigitNumber),
+    // Waiting means no value arrived before the timeout
+    Waiting,
+    // Closed means there will be no more values
+    Closed,
+}
+
+// Output is handed every value written by OUT, along with the output tray
+pub trait Output {
+    fn write(&mut self, value: ThreeDigitNumber);
+}
+
+// FixedInput supplies the values given in order, then no more
+pub struct FixedInput {
+    values: Vec<ThreeDigitNumber>,
+    next: usize,
+}
+
+impl FixedInput {
+    // new creates an input supplying the values once each
+    pub fn new(values: Vec<ThreeDigitNumber>) -> Self {
+        FixedInput { values, next: 0 }
+    }
+}
+
+impl Input for FixedInput {
+    fn read(&mut self) -> Option<ThreeDigitNumber> {
+        let value = self.values.get(self.next).copied();
+        self.next += 1;
+        value
+    }
+}
+
+// CyclicInput supplies the values given in order, starting over from the
+// first once they run out, and never runs out unless given none
+pub struct CyclicInput {
+    values: Vec<ThreeDigitNumber>,
+    next: usize,
+}
+
+impl CyclicInput {
+    // new creates an input supplying the values over and over
+    pub fn new(values: Vec<ThreeDigitNumber>) -> Self {
+        CyclicInput { values, next: 0 }
+    }
+}
+
+impl Input for CyclicInput {
+    fn read(&mut self) -> Option<ThreeDigitNumber> {
+        if self.values.is_empty() {
+            return None;
+        }
+        let value = self.values[self.next % self.values.len()];
+        self.next += 1;
+        Some(value)
+    }
+}
+
+// RandomInput supplies an endless stream of pseudo-random values from the
+// range, the same stream for the same seed. Negative values are supplied in
+// ten's complement.
+pub struct RandomInput {
+    rng: Rng,
+    range: RangeInclusive<i16>,
+}
+
+impl RandomInput {
+    // new creates an input supplying random values from the range
+    pub fn new(seed: u64, range: RangeInclusive<i16>) -> Self {
+        RandomInput {
+            rng: Rng::new(seed),
+            range,
+        }
+    }
+}
+
+impl Input for RandomInput {
+    fn read(&mut self) -> Option<ThreeDigitNumber> {
+        let value = self.rng.range(*self.range.start(), *self.range.end());
+        ThreeDigitNumber::new(value.rem_euclid(1000)).ok()
+    }
+}
+
+// CollectingOutput keeps every value written. It can be cloned, one copy
+// handed to the LMC and the other used to read the values once it has run.
+#[derive(Clone, Default)]
+pub struct CollectingOutput {
+    values: Arc<Mutex<Vec<ThreeDigitNumber>>>,
+}
+
+impl CollectingOutput {
+    // new creates an output with nothing written to it yet
+    pub fn new() -> Self {
+        CollectingOutput::default()
+    }
+
+    // values returns every value written so far, oldest first
+    pub fn values(&self) -> Vec<ThreeDigitNumber> {
+        match self.values.lock() {
+            Ok(values) => values.clone(),
+            Err(_) => Vec::new(),
+        }
+    }
+}
+
+impl Output for CollectingOutput {
+    fn write(&mut self, value: ThreeDigitNumber) {
+        if let Ok(mut values) = self.values.lock() {
+            values.push(value);
+        }
+    }
+}
+
+// NullOutput discards every value written, it is the output of an LMC until
+// another is set
+pub struct NullOutput;
+
+impl Output for NullOutput {
+    fn write(&mut self, _value: ThreeDigitNumber) {}
+}
+
+// ChannelInput reads values sent from another thread, blocking until one
+// arrives, and runs out once the sender is dropped
+pub struct ChannelInput {
+    receiver: Receiver<ThreeDigitNumber>,
+}
+
+impl ChannelInput {
+    // new creates an input reading from the receiving end of a channel
+    pub fn new(receiver: Receiver<ThreeDigitNumber>) -> Self {
+        ChannelInput { receiver }
+    }
+}
+
+impl Input for ChannelInput {
+    fn read(&mut self) -> Option<ThreeDigitNumber> {
+        self.receiver.recv().ok()
+    }
+
+    fn poll(&mut self, timeout: Duration) -> Poll {
+        match self.receiver.recv_timeout(timeout) {
+            Ok(value) => Poll::Ready(value),
+            Err(RecvTimeoutError::Timeout) => Poll::Waiting,
+            Err(RecvTimeoutError::Disconnected) => Poll::Closed,
+        }
+    }
+}
+
+// ChannelOutput sends values to another thread. The channel is bounded so
+// OUT blocks while it is full, holding the LMC back until the other thread
+// catches up. Values are dropped once the receiver is.
+pub struct ChannelOutput {
+    sender: SyncSender<ThreeDigitNumber>,
+}
+
+impl ChannelOutput {
+    // new creates an output writing to the sending end of a channel
+    pub fn new(sender: SyncSender<ThreeDigitNumber>) -> Self {
+        ChannelOutput { sender }
+    }
+}
+
+impl Output for ChannelOutput {
+    fn write(&mut self, value: ThreeDigitNumber) {
+        let _ = self.sender.send(value);
+    }
+}
+
+// pipe connects an output to an input through a channel holding up to bound
+// values, so that one LMC can read what another writes while each runs on
+// its own thread
+pub fn pipe(bound: usize) -> (ChannelOutput, ChannelInput) {
+    let (sender, receiver) = sync_channel(bound);
+    (ChannelOutput::new(sender), ChannelInput::new(receiver))
+}
+
+// CancelToken is used by a host to stop a run from another thread. The LMC
+// checks it between cycles and while waiting for input, ending the run as
+// cancelled. It can be cloned, every copy cancelling the same runs.
+#[derive(Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    // new creates a token that has not been cancelled
+    pub fn new() -> Self {
+        CancelToken::default()
+    }
+
+    // cancel stops every run the token was given to
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    // is_cancelled returns whether cancel has been called
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
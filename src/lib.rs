@@ -0,0 +1,58 @@
+#![allow(clippy::upper_case_acronyms, clippy::should_implement_trait)]
+
+// lmc is a Little Man Computer: an assembler, a VM to run the machine code on
+// and tools for testing, measuring and grading programs. The lmc binary is a
+// command line front-end to this library.
+
+pub mod annotate;
+pub mod assembler;
+pub mod bundle;
+pub mod cache;
+pub mod canonical;
+pub mod config;
+pub mod conformance;
+pub mod debugger;
+pub mod debuginfo;
+pub mod devices;
+pub mod editor;
+pub mod exercise;
+pub mod expr;
+pub mod fuzz;
+pub mod highlight;
+pub mod hints;
+pub mod instruction;
+pub mod io;
+pub mod listing;
+pub mod lmc;
+pub mod lockstep;
+pub mod logger;
+pub mod macros;
+pub mod metrics;
+pub mod numbers;
+pub mod oracle;
+pub mod patch;
+pub mod profile;
+pub mod program;
+pub mod query;
+pub mod quick;
+pub mod refactor;
+pub mod report;
+pub mod runner;
+pub mod scheduler;
+pub mod schema;
+pub mod session;
+pub mod similarity;
+pub mod source;
+pub mod suggest;
+pub mod syntax;
+pub mod tests;
+pub mod text;
+pub mod tournament;
+pub mod trace;
+pub mod tui;
+pub mod version;
+
+pub use assembler::{Assembler, AssemblerError};
+pub use lmc::{HaltReason, LMCError, RunResult, LMC};
+pub use numbers::{NumberError, ThreeDigitNumber, TwoDigitNumber};
+pub use program::Program;
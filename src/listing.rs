@@ -0,0 +1,55 @@
+use crate::program::Program;
+
+// listing returns a classic assembler listing of the program: every line of
+// source beside the address and machine code of the mailboxes assembled from
+// it, followed by the symbol table. A line assembling into several mailboxes,
+// such as a macro invocation, shows the line beside the first of them and
+// lines assembling into none show the line alone. Mailboxes added by padding
+// are listed last, marked fill.
+pub fn listing(source: &[String], program: &Program) -> Vec<String> {
+    let mut lines = vec!["Addr  Code  Line  Source".to_string()];
+    for (index, text) in source.iter().enumerate() {
+        let line = index + 1;
+        let addresses = (0..program.instructions.len())
+            .filter(|address| program.source_map.get(*address) == Some(&line))
+            .collect::<Vec<usize>>();
+        let text = format!("{:>4}  {}", line, text.trim_end());
+        match addresses.split_first() {
+            Some((first, rest)) => {
+                lines.push(row(*first, program, &text));
+                lines.extend(rest.iter().map(|address| row(*address, program, "")));
+            }
+            None => lines.push(format!("{:10}  {}", "", text)),
+        }
+    }
+    // mailboxes without a source line were added by padding
+    for address in program.source_map.len()..program.instructions.len() {
+        lines.push(row(address, program, "      fill"));
+    }
+    lines.push(String::new());
+    lines.push("Symbols:".to_string());
+    let width = program.labels.keys().map(|label| label.len()).max();
+    for (label, address) in &program.labels {
+        lines.push(format!(
+            "  {:width$}  {:02}",
+            label,
+            address,
+            width = width.unwrap_or(0)
+        ));
+    }
+    if width.is_none() {
+        lines.push("  none".to_string());
+    }
+    lines
+        .into_iter()
+        .map(|line| line.trim_end().to_string())
+        .collect()
+}
+
+// row shows a mailbox's address and machine code followed by the text
+fn row(address: usize, program: &Program, text: &str) -> String {
+    format!(
+        "{:02}    {}   {}",
+        address, program.instructions[address], text
+    )
+}
@@ -1,14 +1,54 @@
 use std::{
-    collections::VecDeque,
+    collections::{HashMap, VecDeque},
     fmt,
-    io::{stdin, stdout, Write},
+    io::{stderr, stdin, Write},
+    sync::{
+        mpsc::{channel, Receiver, RecvTimeoutError},
+        Mutex, OnceLock,
+    },
+    thread,
+    time::{Duration, Instant},
 };
 
 use crate::{
-    logger::{LogLevel, Logger},
+    devices::Device,
+    instruction::Instruction,
+    io::{CancelToken, Input, NullOutput, Output, Poll},
+    logger::{Component, LogLevel, Logger},
     numbers::{Flag, NumberError, ThreeDigitNumber, TwoDigitNumber},
+    program::{Assertion, Program},
+    schema::STATE_SCHEMA,
+    session::Session,
+    trace::{BranchEvent, Trace, TraceEvent},
 };
 
+// POLL_INTERVAL is how long the LMC waits for input at a time before
+// checking whether the run has been cancelled or run out of time
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+// stdin_lines returns a channel receiving the lines typed on stdin from a
+// thread started on first use, so that waiting for a line can be given up
+// without losing the line once it is typed
+fn stdin_lines() -> &'static Mutex<Receiver<std::io::Result<String>>> {
+    static LINES: OnceLock<Mutex<Receiver<std::io::Result<String>>>> = OnceLock::new();
+    LINES.get_or_init(|| {
+        let (sender, receiver) = channel();
+        thread::spawn(move || loop {
+            let mut line = String::new();
+            let result = match stdin().read_line(&mut line) {
+                // the sender is dropped at the end of input, closing the channel
+                Ok(0) => return,
+                Ok(_) => Ok(line),
+                Err(e) => Err(e),
+            };
+            if sender.send(result).is_err() {
+                return;
+            }
+        });
+        Mutex::new(receiver)
+    })
+}
+
 // LMCError is used to indicate an error with the LMC VM
 #[derive(Debug, PartialEq)]
 pub enum LMCError {
@@ -17,6 +57,21 @@ pub enum LMCError {
     IOError(String),
     InvalidOpcode(String),
     MaxCyclesHit(usize),
+    AssertionFailed(usize, String),
+    UninitializedRead(usize),
+    ExecutedData(usize),
+    JumpedIntoData(usize, usize),
+    MaxInputsHit(usize),
+    // InputUnderflow is an IN executed once every input supplied was used,
+    // given as the number of IN operations of the run so far, counting this
+    // one, and the number of values read from the in basket before it
+    InputUnderflow { requested: usize, supplied: usize },
+    MaxOutputsHit(usize),
+    FellOffEnd(usize),
+    TimeLimitHit(Duration),
+    MaxMailboxesHit(usize),
+    InvalidState(String),
+    Cancelled,
 }
 
 // Implement the display trait for easy printing.
@@ -26,10 +81,60 @@ impl fmt::Display for LMCError {
             LMCError::ProgramTooLarge(value) => {
                 write!(f, "program too large: got {} instructions", value)
             }
-            LMCError::IOError(value) => write!(f, "IO error: {}", value.to_string()),
+            LMCError::IOError(value) => write!(f, "IO error: {}", value),
             LMCError::InvalidOpcode(value) => write!(f, "invalid opcode: {}", value),
-            LMCError::NumberError(value) => write!(f, "number error: {}", value.to_string()),
+            LMCError::NumberError(value) => write!(f, "number error: {}", value),
             LMCError::MaxCyclesHit(value) => write!(f, "max cycles hit: {}", value),
+            LMCError::AssertionFailed(address, assertion) => {
+                write!(f, "assertion failed at {:02}: {}", address, assertion)
+            }
+            LMCError::UninitializedRead(address) => {
+                write!(f, "read of uninitialized mailbox: {:02}", address)
+            }
+            LMCError::ExecutedData(address) => {
+                write!(f, "executed DAT cell as instruction at {:02}", address)
+            }
+            LMCError::JumpedIntoData(from, to) => {
+                write!(f, "jumped into data at {:02} from {:02}", to, from)
+            }
+            LMCError::MaxInputsHit(value) => write!(f, "max inputs hit: {}", value),
+            LMCError::InputUnderflow {
+                requested,
+                supplied,
+            } => write!(
+                f,
+                "input underflow: requested {} inputs, only {} supplied",
+                requested, supplied
+            ),
+            LMCError::MaxOutputsHit(value) => write!(f, "max outputs hit: {}", value),
+            LMCError::TimeLimitHit(limit) => {
+                write!(f, "time limit hit: {}ms", limit.as_millis())
+            }
+            LMCError::MaxMailboxesHit(value) => write!(f, "max mailboxes hit: {}", value),
+            LMCError::InvalidState(value) => write!(f, "invalid state: {}", value),
+            LMCError::Cancelled => write!(f, "cancelled"),
+            LMCError::FellOffEnd(address) => {
+                write!(f, "fell off the end of memory at {:02}", address)
+            }
+        }
+    }
+}
+
+// Implement the error trait so the error can be used with the ? operator
+// alongside other errors.
+impl std::error::Error for LMCError {}
+
+impl LMCError {
+    // limit returns the name of the resource limit that stopped the run, if
+    // the error is one, so a grader can tell a limit apart from a bug
+    pub fn limit(&self) -> Option<&'static str> {
+        match self {
+            LMCError::MaxCyclesHit(_) => Some("cycles"),
+            LMCError::MaxInputsHit(_) => Some("inputs"),
+            LMCError::MaxOutputsHit(_) => Some("outputs"),
+            LMCError::TimeLimitHit(_) => Some("time"),
+            LMCError::MaxMailboxesHit(_) => Some("mailboxes"),
+            _ => None,
         }
     }
 }
@@ -41,6 +146,410 @@ impl From<NumberError> for LMCError {
     }
 }
 
+// MachineState is a snapshot of the LMC's registers and mailboxes
+#[derive(PartialEq, Clone)]
+pub struct MachineState {
+    pub cycle: usize,
+    pub counter: TwoDigitNumber,
+    pub calculator: ThreeDigitNumber,
+    pub flag: Option<Flag>,
+    pub output: Option<ThreeDigitNumber>,
+    pub mailboxes: [ThreeDigitNumber; 100],
+    // inputs are the values waiting in the in_basket, next to be read first
+    pub inputs: Vec<ThreeDigitNumber>,
+    // outputs are every value output during the run, oldest first
+    pub outputs: Vec<ThreeDigitNumber>,
+}
+
+// join_numbers lists the numbers separated by spaces, or - if there are none
+pub fn join_numbers(numbers: &[ThreeDigitNumber]) -> String {
+    if numbers.is_empty() {
+        return "-".to_string();
+    }
+    numbers
+        .iter()
+        .map(|number| number.to_string())
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+// Display shows the registers, the baskets and every non-zero mailbox, e.g.:
+// counter: 03  calculator: 007  flag: -  output: -  cycle: 3
+// inputs: 004 005  outputs: -
+// mailboxes: 00=901 01=308 02=902 08=007
+impl fmt::Display for MachineState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let flag = self.flag.map_or("-".to_string(), |flag| flag.to_string());
+        let output = self
+            .output
+            .map_or("-".to_string(), |number| number.to_string());
+        writeln!(
+            f,
+            "counter: {}  calculator: {}  flag: {}  output: {}  cycle: {}",
+            self.counter, self.calculator, flag, output, self.cycle
+        )?;
+        writeln!(
+            f,
+            "inputs: {}  outputs: {}",
+            join_numbers(&self.inputs),
+            join_numbers(&self.outputs)
+        )?;
+        let mailboxes = self
+            .mailboxes
+            .iter()
+            .enumerate()
+            .filter(|(_, number)| number.value() != 0)
+            .map(|(address, number)| format!("{:02}={}", address, number))
+            .collect::<Vec<String>>();
+        if mailboxes.is_empty() {
+            write!(f, "mailboxes: all zero")
+        } else {
+            write!(f, "mailboxes: {}", mailboxes.join(" "))
+        }
+    }
+}
+
+// Debug shows every register and all 100 mailboxes as a 10x10 grid
+impl fmt::Debug for MachineState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "MachineState {{")?;
+        writeln!(f, "    cycle: {}", self.cycle)?;
+        writeln!(f, "    counter: {}", self.counter)?;
+        writeln!(f, "    calculator: {}", self.calculator)?;
+        writeln!(f, "    flag: {:?}", self.flag)?;
+        writeln!(
+            f,
+            "    output: {:?}",
+            self.output.map(|number| number.value())
+        )?;
+        writeln!(f, "    inputs: {}", join_numbers(&self.inputs))?;
+        writeln!(f, "    outputs: {}", join_numbers(&self.outputs))?;
+        writeln!(f, "    mailboxes:")?;
+        writeln!(f, "         0   1   2   3   4   5   6   7   8   9")?;
+        for (row, numbers) in self.mailboxes.chunks(10).enumerate() {
+            let values = numbers
+                .iter()
+                .map(|number| number.to_string())
+                .collect::<Vec<String>>();
+            writeln!(f, "    {}0  {}", row, values.join(" "))?;
+        }
+        write!(f, "}}")
+    }
+}
+
+// StateFormat is the format the machine state is printed in after a run
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum StateFormat {
+    // Full shows every register and all 100 mailboxes as a grid
+    Full,
+    // NonZero shows every register and only the non-zero mailboxes
+    NonZero,
+    JSON,
+}
+
+impl StateFormat {
+    // from_str converts a string to a state format
+    pub fn from_str(format: &str) -> Option<StateFormat> {
+        match format {
+            "full" => Some(StateFormat::Full),
+            "nonzero" => Some(StateFormat::NonZero),
+            "json" => Some(StateFormat::JSON),
+            _ => None,
+        }
+    }
+}
+
+impl MachineState {
+    // format renders the state at the end of a run in the given format,
+    // along with why the run stopped
+    pub fn format(&self, format: StateFormat, result: &RunResult) -> String {
+        match format {
+            StateFormat::Full => format!("{:?}\nhalt: {}", self, result),
+            StateFormat::NonZero => format!("{}\nhalt: {}", self, result),
+            StateFormat::JSON => {
+                let state = serde_json::json!({
+                    "schema": STATE_SCHEMA,
+                    "halt": result.to_json(),
+                    "cycle": self.cycle,
+                    "counter": self.counter.value(),
+                    "calculator": self.calculator.value(),
+                    "flag": self.flag.map(|flag| flag.to_string()),
+                    "output": self.output.map(|number| number.value()),
+                    "inputs": self.inputs.iter().map(|number| number.value()).collect::<Vec<i16>>(),
+                    "outputs": self.outputs.iter().map(|number| number.value()).collect::<Vec<i16>>(),
+                    "mailboxes": self
+                        .mailboxes
+                        .iter()
+                        .map(|number| number.value())
+                        .collect::<Vec<i16>>(),
+                });
+                serde_json::to_string_pretty(&state).unwrap_or_default()
+            }
+        }
+    }
+}
+
+impl MachineState {
+    // from_json reads a state written by --print-state=json or --checkpoint so
+    // that the run can be resumed, the halt is ignored and the inputs and
+    // outputs may be left out
+    pub fn from_json(text: &str) -> Result<MachineState, LMCError> {
+        let invalid = |message: &str| LMCError::InvalidState(message.to_string());
+        let state: serde_json::Value =
+            serde_json::from_str(text).map_err(|e| invalid(&e.to_string()))?;
+        if state["schema"] != STATE_SCHEMA {
+            return Err(invalid(&format!("expected schema {}", STATE_SCHEMA)));
+        }
+        let number = |value: &serde_json::Value, name: &str| {
+            value
+                .as_i64()
+                .and_then(|value| ThreeDigitNumber::new(value as i16).ok())
+                .ok_or_else(|| invalid(&format!("{} is not a 3-digit number", name)))
+        };
+        let numbers = |name: &str| match &state[name] {
+            serde_json::Value::Null => Ok(Vec::new()),
+            serde_json::Value::Array(values) => {
+                values.iter().map(|value| number(value, name)).collect()
+            }
+            _ => Err(invalid(&format!("{} is not an array", name))),
+        };
+        let mailboxes: [ThreeDigitNumber; 100] = numbers("mailboxes")?
+            .try_into()
+            .map_err(|_| invalid("mailboxes must hold 100 values"))?;
+        let counter = state["counter"]
+            .as_u64()
+            .and_then(|value| TwoDigitNumber::new(value as u8).ok())
+            .filter(|counter| state["counter"].as_u64() == Some(counter.value() as u64))
+            .ok_or_else(|| invalid("counter is not a 2-digit number"))?;
+        let flag = match state["flag"].as_str() {
+            None => None,
+            Some("NEG") => Some(Flag::NEG),
+            Some("OVERFLOW") => Some(Flag::OVERFLOW),
+            Some(flag) => return Err(invalid(&format!("unknown flag {}", flag))),
+        };
+        let output = match &state["output"] {
+            serde_json::Value::Null => None,
+            value => Some(number(value, "output")?),
+        };
+        Ok(MachineState {
+            cycle: state["cycle"].as_u64().unwrap_or(0) as usize,
+            counter,
+            calculator: number(&state["calculator"], "calculator")?,
+            flag,
+            output,
+            mailboxes,
+            inputs: numbers("inputs")?,
+            outputs: numbers("outputs")?,
+        })
+    }
+}
+
+// WorkingSet is how much of memory a run used, to compare against the 100
+// mailbox limit as the program actually ran rather than by its size
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct WorkingSet {
+    // executed is the number of distinct mailboxes instructions were fetched from
+    pub executed: usize,
+    // accessed is the number of distinct mailboxes read or written as data
+    pub accessed: usize,
+    // touched is the number of distinct mailboxes used either way
+    pub touched: usize,
+    // highest is the highest address used either way, if any
+    pub highest: Option<usize>,
+}
+
+// Implement the display trait for easy printing.
+impl fmt::Display for WorkingSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} mailboxes touched ({} executed, {} read or written)",
+            self.touched, self.executed, self.accessed
+        )?;
+        match self.highest {
+            Some(highest) => write!(f, ", highest address {:02}", highest),
+            None => Ok(()),
+        }
+    }
+}
+
+// InputRetention decides whether values left unconsumed in the in_basket at
+// the end of a run are kept, in order, for the next run or discarded
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum InputRetention {
+    Retain,
+    Discard,
+}
+
+// HaltReason is why a run of the program stopped
+#[derive(Debug, PartialEq)]
+pub enum HaltReason {
+    // Halted means a HLT instruction was executed at the address
+    Halted(usize),
+    // MaxCycles means the cycle budget ran out before the program halted
+    MaxCycles(usize),
+    // Error means the run was stopped by an error
+    Error(LMCError),
+    // Cancelled means the run was stopped through its cancel token
+    Cancelled,
+}
+
+// Implement the display trait for easy printing.
+impl fmt::Display for HaltReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HaltReason::Halted(address) => write!(f, "halted at {:02}", address),
+            HaltReason::MaxCycles(_) => write!(f, "max cycles hit"),
+            HaltReason::Error(error) => write!(f, "{}", error),
+            HaltReason::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+// RunResult is the outcome of a run along with the number of cycles it took
+#[derive(Debug, PartialEq)]
+pub struct RunResult {
+    pub reason: HaltReason,
+    pub cycles: usize,
+}
+
+impl RunResult {
+    // is_halted returns whether the program stopped by executing HLT
+    pub fn is_halted(&self) -> bool {
+        matches!(self.reason, HaltReason::Halted(_))
+    }
+
+    // to_json returns the result as a JSON object
+    pub fn to_json(&self) -> serde_json::Value {
+        let (reason, address, error, limit) = match &self.reason {
+            HaltReason::Halted(address) => ("halted", Some(*address), None, None),
+            HaltReason::MaxCycles(_) => ("max_cycles", None, None, Some("cycles")),
+            HaltReason::Error(error) => ("error", None, Some(error.to_string()), error.limit()),
+            HaltReason::Cancelled => ("cancelled", None, None, None),
+        };
+        serde_json::json!({
+            "reason": reason,
+            "address": address,
+            "error": error,
+            "limit": limit,
+            "cycles": self.cycles,
+        })
+    }
+}
+
+// Display shows the reason followed by the cycle count, for example:
+// halted at 03 after 4 cycles
+impl fmt::Display for RunResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} after {} cycles", self.reason, self.cycles)
+    }
+}
+
+// StepResult is the state of the LMC after a single fetch-execute cycle
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum StepResult {
+    Running,
+    Halted,
+}
+
+// Display shows the state of the machine, see MachineState
+impl fmt::Display for LMC {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.state())
+    }
+}
+
+// Debug shows the full state of the machine along with its configuration
+impl fmt::Debug for LMC {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let in_basket = self
+            .in_basket
+            .iter()
+            .map(|number| number.value())
+            .collect::<Vec<i16>>();
+        let mut devices = self
+            .devices
+            .iter()
+            .map(|(address, device)| format!("{:02}={}", address, device.name()))
+            .collect::<Vec<String>>();
+        devices.sort();
+        writeln!(f, "{:?}", self.state())?;
+        writeln!(f, "in_basket: {:?}", in_basket)?;
+        writeln!(f, "max_cycles: {}", self.max_cycles)?;
+        writeln!(
+            f,
+            "max_inputs: {:?}  max_outputs: {:?}",
+            self.max_inputs, self.max_outputs
+        )?;
+        writeln!(
+            f,
+            "max_time: {:?}  max_mailboxes: {:?}",
+            self.max_time, self.max_mailboxes
+        )?;
+        writeln!(f, "inputs: {}  outputs: {}", self.inputs, self.outputs)?;
+        writeln!(
+            f,
+            "strict: {}  quiet: {}  signed: {}",
+            self.strict, self.quiet, self.signed
+        )?;
+        writeln!(f, "uninitialized_policy: {:?}", self.uninitialized_policy)?;
+        writeln!(f, "poison: {:?}", self.poison)?;
+        writeln!(f, "input_retention: {:?}", self.input_retention)?;
+        writeln!(f, "input_underflow: {}", self.input_underflow)?;
+        writeln!(f, "assertions: {}", self.assertions.len())?;
+        write!(f, "devices: [{}]", devices.join(", "))
+    }
+}
+
+// UninitializedPolicy decides what happens when an instruction reads a
+// mailbox that was neither loaded with the program nor written by STO
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum UninitializedPolicy {
+    Ignore,
+    Warn,
+    Error,
+}
+
+impl UninitializedPolicy {
+    // from_str converts a string to an uninitialized read policy
+    pub fn from_str(policy: &str) -> Option<UninitializedPolicy> {
+        match policy {
+            "ignore" => Some(UninitializedPolicy::Ignore),
+            "warn" => Some(UninitializedPolicy::Warn),
+            "error" => Some(UninitializedPolicy::Error),
+            _ => None,
+        }
+    }
+}
+
+// CounterWrap decides what happens when the counter is incremented past the
+// last mailbox, 99, which usually means the program is missing a HLT
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CounterWrap {
+    // Wrap continues from mailbox 00 without a warning
+    Wrap,
+    // Warn continues from mailbox 00 and logs a warning
+    Warn,
+    // Error stops the program
+    Error,
+}
+
+impl CounterWrap {
+    // from_str converts a string to a counter wrap policy
+    pub fn from_str(policy: &str) -> Option<CounterWrap> {
+        match policy {
+            "wrap" => Some(CounterWrap::Wrap),
+            "warn" => Some(CounterWrap::Warn),
+            "error" => Some(CounterWrap::Error),
+            _ => None,
+        }
+    }
+}
+
+// DEFAULT_PROMPT is the prompt shown on stderr when IN reads stdin, unless
+// another is set with LMC::set_prompt
+pub const DEFAULT_PROMPT: &str = "Input";
+
 // LMC defines the structure of the Little Minion Computer and is the VM
 // responsible for executing any programs. The LMC is a toy-example of a
 // computer architecture used to teach the fundamentals of architectures
@@ -55,6 +564,8 @@ pub struct LMC {
     in_basket: VecDeque<ThreeDigitNumber>,
     // out_basket is an optional 3-digit decimal number
     out_basket: Option<ThreeDigitNumber>,
+    // out_history holds every value output during the current run
+    out_history: Vec<ThreeDigitNumber>,
     // 2-digit counter is the program counter and provides the indexes
     // for the mailboxes during the fetch-execute cycle
     counter: TwoDigitNumber,
@@ -67,222 +578,713 @@ pub struct LMC {
     // max_cycle count is used to keep track of the max number of fetch-execute
     // cycles the LMC can perform during the execution of a program
     max_cycles: usize,
+    // max_inputs and max_outputs optionally limit the number of IN and OUT
+    // operations performed during the execution of a program
+    max_inputs: Option<usize>,
+    max_outputs: Option<usize>,
+    // max_time and max_mailboxes optionally limit the wall time a run may take
+    // and the number of distinct mailboxes it may touch, so that untrusted
+    // programs cannot wedge a shared grader
+    max_time: Option<Duration>,
+    max_mailboxes: Option<usize>,
+    // deadline is when the current run hits max_time
+    deadline: Option<Instant>,
+    // inputs and outputs count the IN and OUT operations of the current run
+    inputs: usize,
+    outputs: usize,
+    // supplied counts the values IN read from the in basket in the current
+    // run, which retained or restored inputs keep apart from inputs
+    supplied: usize,
+    // session holds the frames recorded during execution when recording is enabled
+    session: Option<Session>,
+    // trace holds the instructions executed when tracing is enabled
+    trace: Option<Trace>,
+    // assertions are checked before the instruction at their address executes
+    assertions: Vec<Assertion>,
+    // initialized tracks which mailboxes were loaded or written by STO
+    initialized: [bool; 100],
+    // loaded holds the mailboxes as the program was loaded, which reset
+    // restores along with the mailboxes that were initialized by loading it
+    loaded: [ThreeDigitNumber; 100],
+    loaded_initialized: [bool; 100],
+    // uninitialized_policy decides how reads of uninitialized mailboxes are handled
+    uninitialized_policy: UninitializedPolicy,
+    // poison is loaded into the mailboxes after the program instead of 000
+    poison: Option<ThreeDigitNumber>,
+    // data marks the mailboxes the loaded program declared as DAT cells
+    data: [bool; 100],
+    // strict enables errors when DAT cells are executed or branched into
+    strict: bool,
+    // counter_wrap decides what happens when the counter passes 99
+    counter_wrap: CounterWrap,
+    // wrapped is set when the counter wrapped during the current step
+    wrapped: bool,
+    // previous is the address of the last instruction executed in full
+    previous: Option<usize>,
+    // devices maps mailbox addresses to the devices reads and writes are routed to
+    devices: HashMap<usize, Box<dyn Device>>,
+    // cycles is the number of fetch-execute cycles executed in the current run
+    cycles: usize,
+    // input_retention decides what happens to unconsumed inputs after a run
+    input_retention: InputRetention,
+    // input_underflow makes IN fail once the input tray is empty rather than
+    // reading from the input or stdin
+    input_underflow: bool,
+    // input supplies values once the input tray is empty, stdin if None
+    input: Option<Box<dyn Input>>,
+    // output is handed every value written by OUT
+    output: Box<dyn Output>,
+    // cancel is checked between cycles and while waiting for input
+    cancel: Option<CancelToken>,
+    // input_retries is the number of times to re-prompt for invalid input
+    input_retries: usize,
+    // signed enables ten's complement input and output of negative numbers
+    signed: bool,
+    // prompt is shown when reading interactive input, None suppresses it
+    prompt: Option<String>,
+    // explain prints why each branch was or was not taken to stderr
+    explain: bool,
+    // executed and accessed track the mailboxes used during the current run,
+    // see WorkingSet
+    executed: [bool; 100],
+    accessed: [bool; 100],
 }
 
 impl LMC {
     // new creates a new LMC with all values initialized to 0
     pub fn new(verbose: bool, debug: bool, quiet: bool, max_cycles: usize) -> Self {
+        let mut logger = Logger::new(verbose, debug);
+        logger.set_component(Component::VM);
         LMC {
             mailboxes: [ThreeDigitNumber::new(0).unwrap(); 100],
             calculator: ThreeDigitNumber::new(0).unwrap(),
             in_basket: VecDeque::new(),
             out_basket: None,
+            out_history: Vec::new(),
             counter: TwoDigitNumber::new(0).unwrap(),
             flag: None,
-            logger: Logger::new(verbose, debug),
+            logger,
             quiet,
             max_cycles,
+            max_inputs: None,
+            max_outputs: None,
+            max_time: None,
+            max_mailboxes: None,
+            deadline: None,
+            inputs: 0,
+            outputs: 0,
+            supplied: 0,
+            session: None,
+            trace: None,
+            assertions: Vec::new(),
+            initialized: [false; 100],
+            loaded: [ThreeDigitNumber::new(0).unwrap(); 100],
+            loaded_initialized: [false; 100],
+            uninitialized_policy: UninitializedPolicy::Ignore,
+            poison: None,
+            data: [false; 100],
+            strict: false,
+            counter_wrap: CounterWrap::Warn,
+            wrapped: false,
+            previous: None,
+            devices: HashMap::new(),
+            cycles: 0,
+            input_retention: InputRetention::Retain,
+            input_underflow: false,
+            input: None,
+            output: Box::new(NullOutput),
+            cancel: None,
+            input_retries: 3,
+            signed: false,
+            prompt: Some(DEFAULT_PROMPT.to_string()),
+            explain: false,
+            executed: [false; 100],
+            accessed: [false; 100],
         }
     }
 
     // load_program loads an assembled program into the LMC's mailboxes ready for execution
     // NOTE: This does not verify the program is valid only that it is not too large
-    pub fn load_program(self: &mut Self, program: &Vec<ThreeDigitNumber>) -> Result<(), LMCError> {
+    pub fn load_program(&mut self, program: &Program) -> Result<(), LMCError> {
+        let instructions = &program.instructions;
         self.logger.log(
             &LogLevel::Info,
-            &format!("Loading program with {} instructions", program.len()),
+            &format!("Loading program with {} instructions", instructions.len()),
         );
-        if program.len() > 100 {
-            return Err(LMCError::ProgramTooLarge(program.len()));
+        if instructions.len() > 100 {
+            return Err(LMCError::ProgramTooLarge(instructions.len()));
         }
-        for (i, instruction) in program.iter().enumerate() {
+        for (i, instruction) in instructions.iter().enumerate() {
             self.mailboxes[i] = *instruction;
+            self.initialized[i] = true;
+            self.data[i] = program.is_data(i);
         }
+        if let Some(poison) = self.poison {
+            for mailbox in self.mailboxes.iter_mut().skip(instructions.len()) {
+                *mailbox = poison;
+            }
+        }
+        self.loaded = self.mailboxes;
+        self.loaded_initialized = self.initialized;
         Ok(())
     }
 
+    // reset returns the LMC to how it was once the program was loaded: the
+    // mailboxes are restored, undoing anything stored by a previous run, the
+    // calculator, flag and counter are cleared and both baskets are emptied
+    pub fn reset(&mut self) {
+        self.logger
+            .log(&LogLevel::Debug, "resetting to the loaded program\n");
+        self.mailboxes = self.loaded;
+        self.initialized = self.loaded_initialized;
+        self.calculator = ThreeDigitNumber::new(0).unwrap();
+        self.flag = None;
+        self.counter = TwoDigitNumber::new(0).unwrap();
+        self.in_basket.clear();
+        self.out_basket = None;
+        self.out_history.clear();
+    }
+
     // execute_program executes the program loaded into the LMC's mailboxes, iterating
     // through each instruction and executing it. The program counter is incremented
     // after each instruction is executed and the program exits when the counter
     // reaches the end of the program, signified by a 000 instruction.
-    pub fn execute_program(self: &mut Self) -> Result<(), LMCError> {
+    // The returned result tells a clean halt apart from running out of cycles or
+    // an error, along with the number of cycles executed.
+    pub fn execute_program(&mut self) -> RunResult {
+        self.execute_with(|_| {})
+    }
+
+    // execute_with executes the program like execute_program, calling on_cycle
+    // with the number of cycles so far after every fetch-execute cycle that
+    // does not stop the program
+    pub fn execute_with(&mut self, mut on_cycle: impl FnMut(usize)) -> RunResult {
         self.logger.log(&LogLevel::Info, "executing program...");
-        // set a counter for the number of fetch-execute cycles
+        self.logger
+            .log(&LogLevel::Debug, &format!("initial machine:\n{:?}", self));
+        self.start();
+        self.run_to_end(&mut on_cycle)
+    }
+
+    // resume continues a run from the machine state, see restore, without
+    // resetting the outputs so far. Only the cycles of the resumed part of the
+    // run are counted towards the cycle limit.
+    pub fn resume(&mut self) -> RunResult {
+        self.logger.log(
+            &LogLevel::Info,
+            &format!("resuming program at {}", self.counter),
+        );
+        self.cycles = 0;
+        self.deadline = self.max_time.map(|max_time| Instant::now() + max_time);
+        self.run_to_end(&mut |_| {})
+    }
+
+    // restore replaces the mailboxes, registers and baskets with the state,
+    // every mailbox counts as initialized as nothing records which were
+    pub fn restore(&mut self, state: &MachineState) {
+        self.mailboxes = state.mailboxes;
+        self.initialized = [true; 100];
+        self.counter = state.counter;
+        self.calculator = state.calculator;
+        self.flag = state.flag;
+        self.out_basket = state.output;
+        self.in_basket = state.inputs.iter().copied().collect();
+        self.out_history = state.outputs.clone();
+    }
+
+    // run_to_end steps through the program until it halts or stops
+    fn run_to_end(&mut self, on_cycle: &mut dyn FnMut(usize)) -> RunResult {
         // loop infinitely until we reach the end of the program
-        let mut cycles = 0;
-        loop {
-            // increment the number of cycles
-            cycles += 1;
-            if self.max_cycles == cycles {
-                return Err(LMCError::MaxCyclesHit(self.max_cycles));
+        let reason = loop {
+            match self.step() {
+                Ok(StepResult::Running) => on_cycle(self.cycles),
+                Ok(StepResult::Halted) => break HaltReason::Halted(self.counter.value() as usize),
+                Err(LMCError::MaxCyclesHit(value)) => break HaltReason::MaxCycles(value),
+                Err(LMCError::Cancelled) => break HaltReason::Cancelled,
+                Err(e) => break HaltReason::Error(e),
             }
-            // fetch the instruction from the mailbox at the counter
-            let instruction = self.mailboxes[self.counter.value() as usize];
-            // retrieve the opcode and operand from the instruction
-            let opcode = instruction.value() / 100;
-            let operand = (instruction.value() % 100) as usize;
-            // execute the instruction
-            self.logger.log(
-                &LogLevel::Debug,
+        };
+        self.finish();
+        RunResult {
+            reason,
+            cycles: self.cycles,
+        }
+    }
+
+    // execute_from executes the loaded program starting from the instruction at
+    // the given address rather than 00, so that a routine placed part way
+    // through memory can be exercised without running the code before it
+    pub fn execute_from(&mut self, address: usize) -> RunResult {
+        self.execute_from_with(address, |_| {})
+    }
+
+    // execute_from_with executes the program from the address like
+    // execute_from, calling on_cycle like execute_with
+    pub fn execute_from_with(&mut self, address: usize, on_cycle: impl FnMut(usize)) -> RunResult {
+        if address > 99 {
+            return RunResult {
+                reason: HaltReason::Error(LMCError::NumberError(NumberError::OutOfBounds(
+                    address as i32,
+                ))),
+                cycles: 0,
+            };
+        }
+        self.logger
+            .log(&LogLevel::Debug, &format!("starting at {:02}\n", address));
+        self.counter = TwoDigitNumber::new(address as u8).unwrap();
+        self.execute_with(on_cycle)
+    }
+
+    // finish applies the input retention policy once a run has ended,
+    // whether the program halted or failed
+    fn finish(&mut self) {
+        if self.in_basket.is_empty() {
+            return;
+        }
+        match self.input_retention {
+            InputRetention::Retain => self.logger.log(
+                &LogLevel::Info,
                 &format!(
-                    "executing instruction: {:03} (opcode: {:01}, operand: {:02})",
-                    instruction, opcode, operand
+                    "retaining {} unconsumed inputs for the next run",
+                    self.in_basket.len()
                 ),
-            );
-            match opcode {
-                1 => match self.add(operand) {
-                    Ok(_) => {}
-                    Err(e) => return Err(e),
-                },
-                2 => match self.sub(operand) {
-                    Ok(_) => {}
-                    Err(e) => return Err(e),
-                },
-                3 => match self.sto(operand) {
-                    Ok(_) => {}
-                    Err(e) => return Err(e),
-                },
-                5 => match self.lda(operand) {
-                    Ok(_) => {}
-                    Err(e) => return Err(e),
-                },
-                6 => self.br(operand),
-                7 => match self.brz(operand) {
-                    Ok(_) => {}
-                    Err(e) => return Err(e),
-                },
-                8 => match self.brp(operand) {
-                    Ok(_) => {}
-                    Err(e) => return Err(e),
-                },
-                9 => match operand {
-                    1 => match self.read_input() {
-                        Ok(_) => {}
-                        Err(e) => return Err(e),
-                    },
-                    2 => {
-                        match self.write_output() {
-                            Ok(_) => {}
-                            Err(e) => return Err(e),
-                        }
-                        self.show_output();
-                    }
-                    // there are only 2 I/O opcodes so any other is invalid
-                    _ => return Err(LMCError::InvalidOpcode(format!("9{:02}", opcode))),
-                },
-                // 0 is the halt instruction and signifies the end of the program
-                0 => {
-                    self.logger.log(
-                        &LogLevel::Info,
-                        &format!("program halted after {} cycles", cycles),
-                    );
-                    return Ok(());
+            ),
+            InputRetention::Discard => self.clear_input(),
+        }
+    }
+
+    // start prepares the LMC for a new run of the loaded program, resetting the
+    // number of fetch-execute cycles and I/O operations performed so far
+    pub fn start(&mut self) {
+        self.cycles = 0;
+        self.inputs = 0;
+        self.outputs = 0;
+        self.supplied = 0;
+        self.out_history.clear();
+        self.executed = [false; 100];
+        self.accessed = [false; 100];
+        self.previous = None;
+        self.deadline = self.max_time.map(|max_time| Instant::now() + max_time);
+        self.record_frame();
+    }
+
+    // step performs a single fetch-execute cycle, returning whether the
+    // program is still running or has halted
+    pub fn step(&mut self) -> Result<StepResult, LMCError> {
+        // increment the number of cycles
+        self.cycles += 1;
+        let cycles = self.cycles;
+        if self.max_cycles == cycles {
+            return Err(LMCError::MaxCyclesHit(self.max_cycles));
+        }
+        self.check_interrupted()?;
+        // fetch the instruction from the mailbox at the counter
+        let address = self.counter.value();
+        self.check_assertions(address as usize)?;
+        if self.strict && self.data[address as usize] {
+            return Err(LMCError::ExecutedData(address as usize));
+        }
+        let instruction = self.mailboxes[address as usize];
+        self.executed[address as usize] = true;
+        // retrieve the opcode and operand from the instruction
+        let opcode = instruction.value() / 100;
+        let operand = (instruction.value() % 100) as usize;
+        // execute the instruction
+        self.logger.log(
+            &LogLevel::Debug,
+            &format!(
+                "executing instruction: {:03} (opcode: {:01}, operand: {:02})",
+                instruction, opcode, operand
+            ),
+        );
+        // the decision of a branch depends on the registers before it executes
+        let branch = self.branch_event(opcode, operand);
+        self.wrapped = false;
+        match opcode {
+            1 => self.add(operand)?,
+            2 => self.sub(operand)?,
+            3 => self.sto(operand)?,
+            5 => self.lda(operand)?,
+            6 => self.br(operand),
+            7 => self.brz(operand)?,
+            8 => self.brp(operand)?,
+            9 => match operand {
+                1 => self.read_input()?,
+                2 => {
+                    self.write_output()?;
+                    self.show_output();
                 }
-                // any other opcode is invalid
-                _ => return Err(LMCError::InvalidOpcode(format!("{:03}", opcode))),
+                // there are only 2 I/O opcodes so any other is invalid
+                _ => return Err(LMCError::InvalidOpcode(format!("9{:02}", opcode))),
+            },
+            // 0 is the halt instruction and signifies the end of the program
+            0 => {
+                self.record_trace_event(cycles, address, instruction, None);
+                self.logger.log(
+                    &LogLevel::Info,
+                    &format!("program halted after {} cycles\n{}", cycles, self),
+                );
+                return Ok(StepResult::Halted);
             }
+            // any other opcode is invalid
+            _ => return Err(LMCError::InvalidOpcode(format!("{:03}", opcode))),
+        }
+        // a taken branch into a DAT cell is reported at the branch itself
+        let target = self.counter.value() as usize;
+        let taken = (6..=8).contains(&opcode) && target != (address as usize + 1) % 100;
+        if self.strict && taken && self.data[target] {
+            return Err(LMCError::JumpedIntoData(address as usize, target));
+        }
+        if let (true, Some(branch)) = (self.explain, &branch) {
+            eprintln!(
+                "{:02}: {} {}",
+                address,
+                Instruction::decode(instruction),
+                branch
+            );
+        }
+        if let Some(max_mailboxes) = self.max_mailboxes {
+            if self.working_set().touched > max_mailboxes {
+                return Err(LMCError::MaxMailboxesHit(max_mailboxes));
+            }
+        }
+        self.record_frame();
+        self.record_trace_event(cycles, address, instruction, branch);
+        self.previous = Some(address as usize);
+        Ok(StepResult::Running)
+    }
+
+    // previous returns the address of the last instruction executed in full
+    // in the current run, the one that led to the next, if there was one
+    pub fn previous(&self) -> Option<usize> {
+        self.previous
+    }
+
+    // cycles returns the number of fetch-execute cycles performed in the current run
+    pub fn cycles(&self) -> usize {
+        self.cycles
+    }
+
+    // inputs returns the number of IN operations performed in the current run
+    pub fn inputs(&self) -> usize {
+        self.inputs
+    }
+
+    // working_set returns how much of memory the current run has used
+    pub fn working_set(&self) -> WorkingSet {
+        let touched = (0..100)
+            .filter(|address| self.executed[*address] || self.accessed[*address])
+            .collect::<Vec<usize>>();
+        WorkingSet {
+            executed: self.executed.iter().filter(|used| **used).count(),
+            accessed: self.accessed.iter().filter(|used| **used).count(),
+            touched: touched.len(),
+            highest: touched.last().copied(),
+        }
+    }
+
+    // outputs returns the number of OUT operations performed in the current run
+    pub fn outputs(&self) -> usize {
+        self.outputs
+    }
+
+    // set_uninitialized_policy sets how reads of uninitialized mailboxes are handled
+    pub fn set_uninitialized_policy(&mut self, policy: UninitializedPolicy) {
+        self.uninitialized_policy = policy;
+    }
+
+    // set_poison sets the value loaded into every mailbox after the program,
+    // such as the invalid instruction 400, so that running off the end of the
+    // program or into unset data fails rather than halting on 000
+    pub fn set_poison(&mut self, poison: Option<ThreeDigitNumber>) {
+        self.poison = poison;
+    }
+
+    // attach_device maps the mailbox at the address to the device so that
+    // every read and write of the mailbox is routed to it
+    pub fn attach_device(&mut self, address: usize, device: Box<dyn Device>) {
+        self.logger.log(
+            &LogLevel::Info,
+            &format!("Attaching {} at {:02}", device.name(), address),
+        );
+        self.devices.insert(address, device);
+    }
+
+    // render_devices returns the rendered output of every attached device
+    // that has something to show, ordered by address
+    pub fn render_devices(&self) -> Vec<String> {
+        let mut addresses = self.devices.keys().collect::<Vec<&usize>>();
+        addresses.sort();
+        addresses
+            .into_iter()
+            .filter_map(|address| self.devices[address].render())
+            .collect()
+    }
+
+    // set_counter_wrap sets what happens when the counter passes 99
+    pub fn set_counter_wrap(&mut self, policy: CounterWrap) {
+        self.counter_wrap = policy;
+    }
+
+    // set_strict enables errors when DAT cells are executed or branched into
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    // load_assertions sets the assertions to evaluate during execution
+    pub fn load_assertions(&mut self, assertions: &[Assertion]) {
+        self.logger.log(
+            &LogLevel::Info,
+            &format!("Loading {} assertions", assertions.len()),
+        );
+        self.assertions = assertions.to_vec();
+    }
+
+    // check_assertions evaluates every assertion guarding the given address
+    // returning an error for the first one that does not hold
+    fn check_assertions(&self, address: usize) -> Result<(), LMCError> {
+        for assertion in self.assertions.iter().filter(|a| a.address == address) {
+            self.logger.log(
+                &LogLevel::Debug,
+                &format!("checking assertion: {}", assertion),
+            );
+            if !assertion.holds(self.calculator, &self.mailboxes) {
+                return Err(LMCError::AssertionFailed(address, assertion.to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    // record_session enables recording of a frame after every fetch-execute cycle
+    pub fn record_session(&mut self) {
+        self.session = Some(Session::new());
+    }
+
+    // take_session returns the recorded session if any, disabling recording
+    pub fn take_session(&mut self) -> Option<Session> {
+        self.session.take()
+    }
+
+    // record_trace enables recording of every instruction executed
+    pub fn record_trace(&mut self) {
+        self.trace = Some(Trace::new());
+    }
+
+    // take_trace returns the recorded trace if any, disabling tracing
+    pub fn take_trace(&mut self) -> Option<Trace> {
+        self.trace.take()
+    }
+
+    // branch_event describes the decision a branch instruction is about to
+    // make, None for any other instruction or when nothing would use it
+    fn branch_event(&self, opcode: i16, operand: usize) -> Option<BranchEvent> {
+        if !self.explain && self.trace.is_none() {
+            return None;
+        }
+        let (taken, reason) = match opcode {
+            6 => (true, "unconditional".to_string()),
+            7 => (
+                self.calculator.value() == 0,
+                format!("calculator is {}", self.calculator),
+            ),
+            8 => match self.flag {
+                Some(flag) => (flag != Flag::NEG, format!("flag is {}", flag)),
+                None => (true, "no flag is set".to_string()),
+            },
+            _ => return None,
+        };
+        Some(BranchEvent {
+            taken,
+            target: operand as u8,
+            reason,
+        })
+    }
+
+    // record_trace_event appends the instruction just executed to the trace
+    fn record_trace_event(
+        &mut self,
+        cycle: usize,
+        address: u8,
+        instruction: ThreeDigitNumber,
+        branch: Option<BranchEvent>,
+    ) {
+        let calculator = self.calculator.value();
+        let flag = self.flag.map(|flag| flag.to_string());
+        if let Some(trace) = self.trace.as_mut() {
+            trace.record(TraceEvent {
+                cycle,
+                address,
+                instruction: instruction.value(),
+                calculator,
+                signed: self.signed.then(|| self.calculator.signed_value()),
+                flag,
+                branch,
+                wrapped: self.wrapped,
+            });
+        }
+    }
+
+    // state returns a snapshot of the LMC's registers and mailboxes
+    pub fn state(&self) -> MachineState {
+        MachineState {
+            cycle: self.cycles,
+            counter: self.counter,
+            calculator: self.calculator,
+            flag: self.flag,
+            output: self.out_basket,
+            mailboxes: self.mailboxes,
+            inputs: self.in_basket.iter().copied().collect(),
+            outputs: self.out_history.clone(),
+        }
+    }
+
+    // record_frame snapshots the current state into the session when recording
+    fn record_frame(&mut self) {
+        if self.session.is_none() {
+            return;
+        }
+        let state = self.state();
+        if let Some(session) = self.session.as_mut() {
+            session.record(state);
         }
     }
 
     // add adds the value in the mailbox at the operand to the calculator
-    fn add(self: &mut Self, operand: usize) -> Result<(), LMCError> {
-        let value = self.mailboxes[operand];
+    fn add(&mut self, operand: usize) -> Result<(), LMCError> {
+        let value = self.read_mailbox(operand)?;
         self.logger.log(
             &LogLevel::Debug,
-            &format!(
-                "adding: {} + {}",
-                self.calculator.to_string(),
-                value.to_string()
-            ),
+            &format!("adding: {} + {}", self.calculator, value),
         );
         self.calculator += value;
         match self.calculator.flag() {
             Some(flag) => {
-                self.logger.log(
-                    &LogLevel::Debug,
-                    &format!("setting flag: {}", flag.to_string()),
-                );
+                self.logger
+                    .log(&LogLevel::Debug, &format!("setting flag: {}", flag));
                 self.flag = Some(flag)
             }
             None => self.flag = None,
         }
+        self.set_sign_flag();
         self.logger
-            .log(&LogLevel::Debug, &format!("incrementing counter by 1\n",));
-        self.counter += match TwoDigitNumber::new(1) {
-            Ok(number) => number,
-            Err(e) => return Err(e.into()),
-        };
+            .log(&LogLevel::Debug, "incrementing counter by 1\n");
+        self.increment_counter()?;
         Ok(())
     }
 
     // sub subtracts the value at the operand from the calculator
-    fn sub(self: &mut Self, operand: usize) -> Result<(), LMCError> {
-        let value = self.mailboxes[operand];
+    fn sub(&mut self, operand: usize) -> Result<(), LMCError> {
+        let value = self.read_mailbox(operand)?;
         self.logger.log(
             &LogLevel::Debug,
-            &format!(
-                "subtracting: {} - {}",
-                self.calculator.to_string(),
-                value.to_string()
-            ),
+            &format!("subtracting: {} - {}", self.calculator, value),
         );
         self.calculator -= value;
         match self.calculator.flag() {
             Some(flag) => {
-                self.logger.log(
-                    &LogLevel::Debug,
-                    &format!("setting flag: {}", flag.to_string()),
-                );
+                self.logger
+                    .log(&LogLevel::Debug, &format!("setting flag: {}", flag));
                 self.flag = Some(flag)
             }
             None => self.flag = None,
         }
+        self.set_sign_flag();
         self.logger
-            .log(&LogLevel::Debug, &format!("incrementing counter by 1\n",));
-        self.counter += match TwoDigitNumber::new(1) {
-            Ok(number) => number,
-            Err(e) => return Err(e.into()),
-        };
+            .log(&LogLevel::Debug, "incrementing counter by 1\n");
+        self.increment_counter()?;
         Ok(())
     }
 
+    // read_mailbox returns the value in the mailbox at the operand, applying
+    // the uninitialized read policy if the mailbox was never written to
+    fn read_mailbox(&mut self, operand: usize) -> Result<ThreeDigitNumber, LMCError> {
+        self.accessed[operand] = true;
+        if let Some(device) = self.devices.get_mut(&operand) {
+            let value = device.read(self.cycles);
+            self.logger.log(
+                &LogLevel::Debug,
+                &format!("reading {} from {}: {}", device.name(), operand, value),
+            );
+            return Ok(value);
+        }
+        if !self.initialized[operand] {
+            match self.uninitialized_policy {
+                UninitializedPolicy::Ignore => {}
+                UninitializedPolicy::Warn => self.logger.log(
+                    &LogLevel::Warn,
+                    &format!(
+                        "reading uninitialized mailbox {:02} at {}",
+                        operand, self.counter
+                    ),
+                ),
+                UninitializedPolicy::Error => return Err(LMCError::UninitializedRead(operand)),
+            }
+        }
+        Ok(self.mailboxes[operand])
+    }
+
     // sto stores the value in the calculator into the mailbox at the operand
-    fn sto(self: &mut Self, operand: usize) -> Result<(), LMCError> {
+    fn sto(&mut self, operand: usize) -> Result<(), LMCError> {
         let value = self.calculator;
-        self.mailboxes[operand] = value;
-        self.logger.log(
-            &LogLevel::Debug,
-            &format!("storing to {}: {}", operand as u8, value.to_string()),
-        );
+        self.accessed[operand] = true;
+        if let Some(device) = self.devices.get_mut(&operand) {
+            device.write(value);
+            self.logger.log(
+                &LogLevel::Debug,
+                &format!("writing {} to {}: {}", device.name(), operand, value),
+            );
+        } else {
+            self.mailboxes[operand] = value;
+            self.initialized[operand] = true;
+            self.logger.log(
+                &LogLevel::Debug,
+                &format!("storing to {}: {}", operand as u8, value),
+            );
+        }
         self.logger
-            .log(&LogLevel::Debug, &format!("incrementing counter by 1\n",));
-        self.counter += match TwoDigitNumber::new(1) {
-            Ok(number) => number,
-            Err(e) => return Err(e.into()),
-        };
+            .log(&LogLevel::Debug, "incrementing counter by 1\n");
+        self.increment_counter()?;
         Ok(())
     }
 
     // lda loads the value from the mailbox at the operand into the calculator
-    fn lda(self: &mut Self, operand: usize) -> Result<(), LMCError> {
-        let value = self.mailboxes[operand];
+    fn lda(&mut self, operand: usize) -> Result<(), LMCError> {
+        let value = self.read_mailbox(operand)?;
         self.calculator = value;
         self.flag = None;
+        self.set_sign_flag();
         self.logger.log(
             &LogLevel::Debug,
-            &format!("loading from {}: {}", operand as u8, value.to_string()),
+            &format!("loading from {}: {}", operand as u8, value),
         );
         self.logger
-            .log(&LogLevel::Debug, &format!("incrementing counter by 1\n",));
-        self.counter += match TwoDigitNumber::new(1) {
-            Ok(number) => number,
-            Err(e) => return Err(e.into()),
-        };
+            .log(&LogLevel::Debug, "incrementing counter by 1\n");
+        self.increment_counter()?;
+        Ok(())
+    }
+
+    // increment_counter moves the counter on to the next mailbox, applying the
+    // counter wrap policy when it is already at the last mailbox
+    fn increment_counter(&mut self) -> Result<(), LMCError> {
+        let address = self.counter.value();
+        if address == 99 {
+            let message = format!(
+                "counter wrapped from 99 back to 00 after cycle {}",
+                self.cycles
+            );
+            match self.counter_wrap {
+                CounterWrap::Wrap => self.logger.log(&LogLevel::Info, &message),
+                CounterWrap::Warn => self.logger.log(&LogLevel::Warn, &message),
+                CounterWrap::Error => return Err(LMCError::FellOffEnd(99)),
+            }
+            self.wrapped = true;
+        }
+        self.counter = TwoDigitNumber::new((address + 1) % 100)?;
         Ok(())
     }
 
     // br sets the program counter to the operand (branch unconditional)
-    fn br(self: &mut Self, operand: usize) {
+    fn br(&mut self, operand: usize) {
         self.logger.log(
             &LogLevel::Debug,
             &format!("branch: setting counter to {}\n", operand as u8),
@@ -292,7 +1294,7 @@ impl LMC {
 
     // brz sets the program counter to the operand if the calculator is 0
     // if the calculator is not 0 then the counter is incremented by 1 (branch zero)
-    fn brz(self: &mut Self, operand: usize) -> Result<(), LMCError> {
+    fn brz(&mut self, operand: usize) -> Result<(), LMCError> {
         if self.calculator.value() == 0 {
             self.logger.log(
                 &LogLevel::Debug,
@@ -300,49 +1302,26 @@ impl LMC {
             );
             self.counter = TwoDigitNumber::new(operand as u8).unwrap();
         } else {
-            self.logger.log(
-                &LogLevel::Debug,
-                &format!("branch zero: incrementing counter by 1\n"),
-            );
-            self.counter += match TwoDigitNumber::new(1) {
-                Ok(number) => number,
-                Err(e) => return Err(e.into()),
-            };
+            self.logger
+                .log(&LogLevel::Debug, "branch zero: incrementing counter by 1\n");
+            self.increment_counter()?;
         }
         Ok(())
     }
 
     // brp sets the program counter to the operand if the LMC's flag is not NEG
     // if the flag is NEG then the counter is incremented by 1 (branch positive)
-    fn brp(self: &mut Self, operand: usize) -> Result<(), LMCError> {
+    fn brp(&mut self, operand: usize) -> Result<(), LMCError> {
         match self.flag {
-            Some(flag) => match flag {
-                Flag::NEG => {
-                    self.logger.log(
-                        &LogLevel::Debug,
-                        &format!("branch positive: incrementing counter by 1\n",),
-                    );
-                    self.counter += match TwoDigitNumber::new(1) {
-                        Ok(number) => number,
-                        Err(e) => return Err(e.into()),
-                    };
-                    return Ok(());
-                }
-                _ => {
-                    self.counter = match TwoDigitNumber::new(operand as u8) {
-                        Ok(number) => {
-                            self.logger.log(
-                                &LogLevel::Debug,
-                                &format!("branch positive: setting counter to {}\n", number),
-                            );
-                            number
-                        }
-                        Err(e) => return Err(e.into()),
-                    };
-                    return Ok(());
-                }
-            },
-            None => {
+            Some(Flag::NEG) => {
+                self.logger.log(
+                    &LogLevel::Debug,
+                    "branch positive: incrementing counter by 1\n",
+                );
+                self.increment_counter()?;
+                Ok(())
+            }
+            _ => {
                 self.counter = match TwoDigitNumber::new(operand as u8) {
                     Ok(number) => {
                         self.logger.log(
@@ -353,7 +1332,7 @@ impl LMC {
                     }
                     Err(e) => return Err(e.into()),
                 };
-                return Ok(());
+                Ok(())
             }
         }
     }
@@ -361,84 +1340,295 @@ impl LMC {
     // read_input reads a 3-digit decimal number from the input_tray or if
     // the tray is empty then it reads from stdin blocking until input is
     // received. It will error on invalid input.
-    fn read_input(self: &mut Self) -> Result<(), LMCError> {
-        self.calculator = match self.in_basket.pop_front() {
-            Some(number) => number,
-            None => match self.read_blocking() {
-                Ok(number) => number,
-                Err(e) => return Err(e.into()),
-            },
+    fn read_input(&mut self) -> Result<(), LMCError> {
+        self.inputs += 1;
+        if let Some(max_inputs) = self.max_inputs {
+            if self.inputs > max_inputs {
+                return Err(LMCError::MaxInputsHit(max_inputs));
+            }
+        }
+        self.calculator = match (self.in_basket.pop_front(), self.input.is_some()) {
+            (Some(number), _) => {
+                self.supplied += 1;
+                number
+            }
+            (None, _) if self.input_underflow => {
+                return Err(LMCError::InputUnderflow {
+                    requested: self.inputs,
+                    supplied: self.supplied,
+                });
+            }
+            (None, true) => self.wait_for_input()?,
+            (None, false) => self.read_blocking()?,
         };
-        self.counter += match TwoDigitNumber::new(1) {
-            Ok(number) => number,
-            Err(e) => return Err(e.into()),
+        self.set_sign_flag();
+        self.increment_counter()?;
+        Ok(())
+    }
+
+    // set_sign_flag sets the NEG flag in signed mode from the sign of the
+    // calculator, clearing any flag when it is not negative, so that BRP
+    // branches on the value held rather than on how it was reached
+    fn set_sign_flag(&mut self) {
+        if !self.signed {
+            return;
+        }
+        self.flag = match self.calculator.signed_value() < 0 {
+            true => Some(Flag::NEG),
+            false => None,
         };
+    }
+
+    // check_interrupted returns an error once the run has been cancelled or
+    // has run out of time
+    fn check_interrupted(&self) -> Result<(), LMCError> {
+        if self
+            .cancel
+            .as_ref()
+            .is_some_and(|cancel| cancel.is_cancelled())
+        {
+            return Err(LMCError::Cancelled);
+        }
+        if let (Some(deadline), Some(max_time)) = (self.deadline, self.max_time) {
+            if Instant::now() > deadline {
+                return Err(LMCError::TimeLimitHit(max_time));
+            }
+        }
         Ok(())
     }
 
+    // wait_for_input polls the input set with set_input until it supplies a
+    // value, giving up if the run is cancelled or runs out of time meanwhile
+    fn wait_for_input(&mut self) -> Result<ThreeDigitNumber, LMCError> {
+        loop {
+            self.check_interrupted()?;
+            let input = match &mut self.input {
+                Some(input) => input,
+                None => return Err(LMCError::IOError("end of input".to_string())),
+            };
+            match input.poll(POLL_INTERVAL) {
+                Poll::Ready(value) => return Ok(value),
+                Poll::Waiting => continue,
+                Poll::Closed => return Err(LMCError::IOError("end of input".to_string())),
+            }
+        }
+    }
+
+    // read_line reads a line from stdin. When the run can be cancelled or
+    // has a time limit the line is waited for in turns, so that the run can
+    // stop while waiting, otherwise it blocks until the line is typed.
+    fn read_line(&self) -> Result<String, LMCError> {
+        if self.cancel.is_none() && self.max_time.is_none() {
+            let mut input = String::new();
+            return match stdin().read_line(&mut input) {
+                // there is no point prompting again once stdin is closed
+                Ok(0) => Err(LMCError::IOError("end of input".to_string())),
+                Ok(_) => Ok(input),
+                Err(e) => Err(LMCError::IOError(e.to_string())),
+            };
+        }
+        let lines = stdin_lines()
+            .lock()
+            .map_err(|e| LMCError::IOError(e.to_string()))?;
+        loop {
+            self.check_interrupted()?;
+            match lines.recv_timeout(POLL_INTERVAL) {
+                Ok(line) => return line.map_err(|e| LMCError::IOError(e.to_string())),
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(LMCError::IOError("end of input".to_string()))
+                }
+            }
+        }
+    }
+
     // read_blocking reads a 3-digit decimal number from stdin blocking
-    // until input is received. It will error on invalid input.
-    fn read_blocking(self: &Self) -> Result<ThreeDigitNumber, LMCError> {
-        print!("Input: ");
-        match stdout().flush() {
-            Ok(_) => {}
-            Err(e) => return Err(LMCError::IOError(e.to_string())),
-        }
-        let mut input = String::new();
-        match stdin().read_line(&mut input) {
-            Ok(_) => {}
-            Err(e) => return Err(LMCError::IOError(e.to_string())),
-        }
-        let trimmed = input.trim();
-        match trimmed.parse::<i16>() {
-            Ok(number) => match ThreeDigitNumber::new(number) {
-                Ok(number) => return Ok(number),
-                Err(e) => return Err(e.into()),
-            },
-            Err(e) => return Err(LMCError::IOError(e.to_string())),
+    // until input is received. Invalid input is met with a new prompt up to
+    // the configured number of retries after which it is an error.
+    fn read_blocking(&self) -> Result<ThreeDigitNumber, LMCError> {
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            // the prompt goes to stderr so stdout only holds the program's output
+            if let Some(prompt) = &self.prompt {
+                if self.logger.is_verbose() {
+                    eprint!("{} [{}]: ", prompt, self.counter);
+                } else {
+                    eprint!("{}: ", prompt);
+                }
+                if let Err(e) = stderr().flush() {
+                    return Err(LMCError::IOError(e.to_string()));
+                }
+            }
+            let input = self.read_line()?;
+            let trimmed = input.trim();
+            let error = match trimmed.parse::<i16>() {
+                Ok(number) => {
+                    let number = if self.signed {
+                        ThreeDigitNumber::from_signed(number)
+                    } else {
+                        ThreeDigitNumber::new(number)
+                    };
+                    match number {
+                        Ok(number) => return Ok(number),
+                        Err(e) => LMCError::from(e),
+                    }
+                }
+                Err(e) => LMCError::IOError(e.to_string()),
+            };
+            if attempts > self.input_retries {
+                return Err(error);
+            }
+            let range = if self.signed {
+                "-500 and 499"
+            } else {
+                "0 and 999"
+            };
+            eprintln!(
+                "Invalid input '{}': enter a number between {}",
+                trimmed, range
+            );
         }
     }
 
     // write_output writes the value in the calculator to the output_tray
-    fn write_output(self: &mut Self) -> Result<(), LMCError> {
+    fn write_output(&mut self) -> Result<(), LMCError> {
+        self.outputs += 1;
+        if let Some(max_outputs) = self.max_outputs {
+            if self.outputs > max_outputs {
+                return Err(LMCError::MaxOutputsHit(max_outputs));
+            }
+        }
         self.out_basket = Some(self.calculator);
-        self.counter += match TwoDigitNumber::new(1) {
-            Ok(number) => number,
-            Err(e) => return Err(e.into()),
-        };
+        self.out_history.push(self.calculator);
+        self.output.write(self.calculator);
+        self.increment_counter()?;
         Ok(())
     }
 
     // show_output prints the value in the output_tray to stdout
-    pub fn show_output(self: &Self) {
+    pub fn show_output(&self) {
         if self.quiet {
             return;
         }
-        match self.out_basket {
-            Some(number) => println!("{}", number.value()),
-            None => (),
+        if let Some(number) = self.out_basket {
+            if self.signed {
+                println!("{}", number.signed_value())
+            } else {
+                println!("{}", number.value())
+            }
         }
     }
 
-    pub fn get_output(self: &Self) -> Option<ThreeDigitNumber> {
+    // get_output returns the value in the output tray, the last one output
+    pub fn get_output(&self) -> Option<ThreeDigitNumber> {
         self.out_basket
     }
 
+    // get_outputs returns every value output during the current run, oldest
+    // first, where the output tray only holds the last
+    pub fn get_outputs(&self) -> Vec<ThreeDigitNumber> {
+        self.out_history.clone()
+    }
+
     // reset_counter resets the program counter to 0
-    pub fn reset_counter(self: &mut Self) {
+    pub fn reset_counter(&mut self) {
         self.logger
-            .log(&LogLevel::Debug, &format!("resetting counter to 0\n",));
+            .log(&LogLevel::Debug, "resetting counter to 0\n");
         self.counter = TwoDigitNumber::new(0).unwrap();
     }
 
-    pub fn set_max_cycles(self: &mut Self, max_cycles: usize) {
+    pub fn set_max_cycles(&mut self, max_cycles: usize) {
         self.max_cycles = max_cycles;
     }
 
-    // load_input fills the input queue with the provided values
-    pub fn load_input(self: &mut Self, input: &Vec<ThreeDigitNumber>) {
+    // set_io_limits limits the number of IN and OUT operations per run
+    pub fn set_io_limits(&mut self, max_inputs: Option<usize>, max_outputs: Option<usize>) {
+        self.max_inputs = max_inputs;
+        self.max_outputs = max_outputs;
+    }
+
+    // set_resource_limits limits the wall time of each run, which includes any
+    // time spent waiting for input, and the number of distinct mailboxes it may
+    // touch, see WorkingSet
+    pub fn set_resource_limits(
+        &mut self,
+        max_time: Option<Duration>,
+        max_mailboxes: Option<usize>,
+    ) {
+        self.max_time = max_time;
+        self.max_mailboxes = max_mailboxes;
+    }
+
+    // load_input appends the provided values to the back of the input queue,
+    // after any values retained from a previous run
+    pub fn load_input(&mut self, input: &[ThreeDigitNumber]) {
         for number in input {
             self.in_basket.push_back(*number);
         }
     }
+
+    // set_input_retries sets how many times invalid interactive input is
+    // re-prompted for before it is treated as an error, by default 3
+    pub fn set_input_retries(&mut self, retries: usize) {
+        self.input_retries = retries;
+    }
+
+    // set_prompt sets the prompt shown on stderr when reading interactive
+    // input, None suppresses the prompt entirely
+    pub fn set_prompt(&mut self, prompt: Option<String>) {
+        self.prompt = prompt;
+    }
+
+    // set_explain enables printing why each branch was or was not taken
+    pub fn set_explain(&mut self, explain: bool) {
+        self.explain = explain;
+    }
+
+    // set_signed enables ten's complement mode where negative numbers may be
+    // entered at the Input: prompt and OUT shows values from 500-999 as negative
+    pub fn set_signed(&mut self, signed: bool) {
+        self.signed = signed;
+    }
+
+    // clear_input discards every value waiting in the input queue
+    pub fn clear_input(&mut self) {
+        if !self.in_basket.is_empty() {
+            self.logger.log(
+                &LogLevel::Info,
+                &format!("discarding {} unconsumed inputs", self.in_basket.len()),
+            );
+        }
+        self.in_basket.clear();
+    }
+
+    // set_input sets where IN reads from once the input tray is empty, in
+    // place of stdin
+    pub fn set_input(&mut self, input: Box<dyn Input>) {
+        self.input = Some(input);
+    }
+
+    // set_output sets the output handed every value written by OUT
+    pub fn set_output(&mut self, output: Box<dyn Output>) {
+        self.output = output;
+    }
+
+    // set_cancel_token sets the token used to stop the run from another
+    // thread, see CancelToken
+    pub fn set_cancel_token(&mut self, cancel: CancelToken) {
+        self.cancel = Some(cancel);
+    }
+
+    // set_input_retention sets whether unconsumed inputs are kept for the next
+    // run, by default they are retained
+    pub fn set_input_retention(&mut self, retention: InputRetention) {
+        self.input_retention = retention;
+    }
+
+    // set_input_underflow makes IN fail with an input underflow once the
+    // input tray is empty, so that a run given a fixed set of inputs never
+    // waits on stdin for more
+    pub fn set_input_underflow(&mut self, input_underflow: bool) {
+        self.input_underflow = input_underflow;
+    }
 }
@@ -0,0 +1,166 @@
+use crate::{
+    conformance::Dialect,
+    lmc::{join_numbers, MachineState, StepResult, LMC},
+    numbers::ThreeDigitNumber,
+    program::Program,
+};
+
+// Side is one of the two LMCs run in lockstep, configured as a dialect
+struct Side {
+    dialect: Dialect,
+    lmc: LMC,
+    // status is how the last step ended: running, halted or the error
+    status: String,
+}
+
+impl Side {
+    // new loads the program and inputs into an LMC configured as the dialect
+    fn new(dialect: Dialect, program: &Program, inputs: &[ThreeDigitNumber]) -> Self {
+        let mut lmc = LMC::new(false, false, true, usize::MAX);
+        dialect.configure(&mut lmc);
+        // running out of inputs ends the run rather than waiting on stdin
+        lmc.set_io_limits(Some(inputs.len()), None);
+        lmc.load_input(inputs);
+        let status = match lmc.load_program(program) {
+            Ok(()) => "running".to_string(),
+            Err(err) => format!("error: {}", err),
+        };
+        lmc.start();
+        Side {
+            dialect,
+            lmc,
+            status,
+        }
+    }
+
+    // step runs a single cycle unless the run has already ended
+    fn step(&mut self) {
+        if self.status != "running" {
+            return;
+        }
+        self.status = match self.lmc.step() {
+            Ok(StepResult::Running) => "running".to_string(),
+            Ok(StepResult::Halted) => "halted".to_string(),
+            Err(err) => format!("error: {}", err),
+        };
+    }
+}
+
+// Divergence is the first cycle at which the two runs differ
+#[derive(Debug, Clone)]
+pub struct Divergence {
+    pub cycle: usize,
+    // address and instruction are what the first LMC executed in the cycle
+    pub address: usize,
+    pub instruction: ThreeDigitNumber,
+    // differences describe every way the runs differ after the cycle
+    pub differences: Vec<String>,
+}
+
+// Comparison is the outcome of running a program in two dialects
+#[derive(Debug, Clone)]
+pub enum Comparison {
+    // Agreed is how both runs ended, or running if they hit max_cycles,
+    // when they never diverged
+    Agreed { cycles: usize, status: String },
+    Diverged(Divergence),
+}
+
+// compare_modes runs the program on two LMCs, configured as the dialects
+// a and b and given the same inputs, a cycle at a time until either run ends
+// or max_cycles is hit, returning the first cycle where their state differs.
+// Inputs are given as written, negative numbers only being accepted by the
+// Higginson dialect.
+pub fn compare_modes(
+    program: &Program,
+    inputs: &[i16],
+    a: Dialect,
+    b: Dialect,
+    max_cycles: usize,
+) -> Result<Comparison, String> {
+    let mut a = Side::new(a, program, &convert(inputs, a)?);
+    let mut b = Side::new(b, program, &convert(inputs, b)?);
+    for cycle in 1..=max_cycles {
+        let before = a.lmc.state();
+        let address = before.counter.value() as usize;
+        a.step();
+        b.step();
+        let differences = differences(&a, &b);
+        if !differences.is_empty() {
+            return Ok(Comparison::Diverged(Divergence {
+                cycle,
+                address,
+                instruction: before.mailboxes[address],
+                differences,
+            }));
+        }
+        if a.status != "running" {
+            return Ok(Comparison::Agreed {
+                cycles: cycle,
+                status: a.status,
+            });
+        }
+    }
+    Ok(Comparison::Agreed {
+        cycles: max_cycles,
+        status: "running".to_string(),
+    })
+}
+
+// convert reads the inputs as the dialect would
+fn convert(inputs: &[i16], dialect: Dialect) -> Result<Vec<ThreeDigitNumber>, String> {
+    inputs
+        .iter()
+        .map(|value| match dialect.signed() {
+            true => ThreeDigitNumber::from_signed(*value),
+            false => ThreeDigitNumber::new(*value),
+        })
+        .collect::<Result<Vec<ThreeDigitNumber>, _>>()
+        .map_err(|err| format!("{} dialect: {}", dialect, err))
+}
+
+// differences describes every way the state of the two sides differs
+fn differences(a: &Side, b: &Side) -> Vec<String> {
+    let (state_a, state_b) = (a.lmc.state(), b.lmc.state());
+    let mut differences = Vec::new();
+    let mut check = |what: &str, value_a: String, value_b: String| {
+        if value_a != value_b {
+            differences.push(format!(
+                "{}: {} {}, {} {}",
+                what, a.dialect, value_a, b.dialect, value_b
+            ));
+        }
+    };
+    check("status", a.status.clone(), b.status.clone());
+    check(
+        "counter",
+        state_a.counter.to_string(),
+        state_b.counter.to_string(),
+    );
+    check(
+        "calculator",
+        state_a.calculator.to_string(),
+        state_b.calculator.to_string(),
+    );
+    check("flag", flag(&state_a), flag(&state_b));
+    check(
+        "outputs",
+        join_numbers(&state_a.outputs),
+        join_numbers(&state_b.outputs),
+    );
+    for address in 0..100 {
+        check(
+            &format!("mailbox {:02}", address),
+            state_a.mailboxes[address].to_string(),
+            state_b.mailboxes[address].to_string(),
+        );
+    }
+    differences
+}
+
+// flag names the flag set in the state, or none
+fn flag(state: &MachineState) -> String {
+    state
+        .flag
+        .map_or("none".to_string(), |flag| flag.to_string())
+}
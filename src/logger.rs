@@ -4,6 +4,7 @@ use std::fmt;
 pub enum LogLevel {
     Info,
     Debug,
+    Warn,
     Error,
 }
 
@@ -13,37 +14,92 @@ impl fmt::Display for LogLevel {
         match self {
             LogLevel::Info => write!(f, "INFO"),
             LogLevel::Debug => write!(f, "DEBUG"),
+            LogLevel::Warn => write!(f, "WARN"),
             LogLevel::Error => write!(f, "ERROR"),
         }
     }
 }
 
+// Component is a part of the program whose messages can be shown separately.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Component {
+    VM,
+    ASM,
+    Batch,
+}
+
+// COMPONENTS lists every component, used when verbosity is not filtered.
+pub const COMPONENTS: [Component; 3] = [Component::VM, Component::ASM, Component::Batch];
+
+impl Component {
+    // Convert a string to a component.
+    pub fn from_str(component: &str) -> Option<Component> {
+        match component {
+            "vm" => Some(Component::VM),
+            "asm" => Some(Component::ASM),
+            "batch" => Some(Component::Batch),
+            _ => None,
+        }
+    }
+}
+
+// Implement the Display trait for Component.
+impl fmt::Display for Component {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Component::VM => write!(f, "vm"),
+            Component::ASM => write!(f, "asm"),
+            Component::Batch => write!(f, "batch"),
+        }
+    }
+}
+
 // Logger is a simple logger that can be used to print messages to the console.
 pub struct Logger {
     verbose: bool,
     debug: bool,
+    // component tags every message so its source can be told apart
+    component: Option<Component>,
 }
 
 impl Logger {
     // Create a new Logger.
     pub fn new(verbose: bool, debug: bool) -> Self {
-        Logger { verbose, debug }
+        Logger {
+            verbose,
+            debug,
+            component: None,
+        }
+    }
+
+    // Tag every message with the component it comes from.
+    pub fn set_component(&mut self, component: Component) {
+        self.component = Some(component);
+    }
+
+    // Return whether info messages are being shown.
+    pub fn is_verbose(&self) -> bool {
+        self.verbose
     }
 
     // Log a message at the given level.
     pub fn log(&self, level: &LogLevel, message: &str) {
+        let show = match level {
+            LogLevel::Info => self.verbose,
+            LogLevel::Debug => self.debug,
+            LogLevel::Warn | LogLevel::Error => true,
+        };
+        if !show {
+            return;
+        }
+        let line = match self.component {
+            Some(component) => format!("{} [{}]: {}", level, component, message),
+            None => format!("{}: {}", level, message),
+        };
+        // warnings and errors go to stderr so stdout only holds the output
         match level {
-            LogLevel::Info => {
-                if self.verbose {
-                    println!("{}: {}", level, message)
-                }
-            }
-            LogLevel::Debug => {
-                if self.debug {
-                    println!("{}: {}", level, message)
-                }
-            }
-            LogLevel::Error => println!("{}: {}", level, message),
+            LogLevel::Warn | LogLevel::Error => eprintln!("{}", line),
+            LogLevel::Info | LogLevel::Debug => println!("{}", line),
         }
     }
 }
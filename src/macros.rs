@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+
+use crate::assembler::{is_mnemonic, AssemblerError};
+
+// MAX_DEPTH is how deeply macros may invoke one another, far more than any
+// program needs while still stopping a macro that invokes itself
+const MAX_DEPTH: usize = 16;
+
+// Macro is a MACRO ... ENDM definition
+struct Macro {
+    params: Vec<String>,
+    body: Vec<String>,
+}
+
+// Expansion keeps track of the macros being expanded
+struct Expansion {
+    macros: HashMap<String, Macro>,
+    // invocations counts every expansion so far, numbering the labels of
+    // each one apart from those of every other
+    invocations: usize,
+    lines: Vec<(usize, String)>,
+}
+
+// expand removes every macro definition from the numbered lines of source,
+// comments already stripped, and replaces each invocation with the body of
+// the macro. Parameters are replaced by the arguments given in the same
+// position and the labels a macro defines are renamed for each invocation,
+// e.g. loop in the second invocation of MUL becomes MUL.2.loop, so that a
+// macro can be used more than once. A label on the invocation labels the
+// first line of the body. Every expanded line keeps the line number of its
+// invocation. An error is returned along with the line number it is on.
+pub fn expand(
+    lines: Vec<(usize, String)>,
+) -> Result<Vec<(usize, String)>, (usize, AssemblerError)> {
+    let mut expansion = Expansion {
+        macros: HashMap::new(),
+        invocations: 0,
+        lines: Vec::new(),
+    };
+    let mut rest = Vec::new();
+    let mut lines = lines.into_iter();
+    while let Some((line, text)) = lines.next() {
+        let words = text.split_whitespace().collect::<Vec<&str>>();
+        if is_keyword(&words, "ENDM") {
+            return Err((line, invalid("ENDM without MACRO")));
+        }
+        if !is_keyword(&words, "MACRO") {
+            rest.push((line, text));
+            continue;
+        }
+        let name = match words.get(1) {
+            Some(name) => name.to_string(),
+            None => return Err((line, invalid("MACRO without a name"))),
+        };
+        if is_mnemonic(&name) || expansion.macros.contains_key(&name) {
+            return Err((line, invalid(&format!("{} is already defined", name))));
+        }
+        if let Some(param) = words[2..].iter().find(|param| is_mnemonic(param)) {
+            return Err((line, invalid(&format!("{} cannot be a parameter", param))));
+        }
+        let mut body = Vec::new();
+        loop {
+            match lines.next() {
+                Some((_, text)) if is_keyword(&split(&text), "ENDM") => break,
+                Some((inner, text)) if is_keyword(&split(&text), "MACRO") => {
+                    return Err((inner, invalid("a macro cannot be defined inside another")))
+                }
+                Some((_, text)) => body.push(text),
+                None => return Err((line, invalid(&format!("{} has no ENDM", name)))),
+            }
+        }
+        let params = words[2..].iter().map(|param| param.to_string()).collect();
+        expansion.macros.insert(name, Macro { params, body });
+    }
+    for (line, text) in rest {
+        expansion
+            .expand_line(line, &text, 0)
+            .map_err(|err| (line, err))?;
+    }
+    Ok(expansion.lines)
+}
+
+impl Expansion {
+    // expand_line adds the line to the expanded source, expanding it first
+    // if it invokes a macro
+    fn expand_line(&mut self, line: usize, text: &str, depth: usize) -> Result<(), AssemblerError> {
+        let words = split(text);
+        let (label, name, args) = match words.as_slice() {
+            [name, args @ ..] if self.macros.contains_key(*name) => (None, *name, args),
+            [label, name, args @ ..] if self.macros.contains_key(*name) => {
+                (Some(*label), *name, args)
+            }
+            _ => {
+                self.lines.push((line, text.to_string()));
+                return Ok(());
+            }
+        };
+        if depth >= MAX_DEPTH {
+            return Err(invalid(&format!("{} is nested too deeply", name)));
+        }
+        self.invocations += 1;
+        let definition = &self.macros[name];
+        if args.len() != definition.params.len() {
+            return Err(invalid(&format!(
+                "{} takes {} arguments: got {}",
+                name,
+                definition.params.len(),
+                args.len()
+            )));
+        }
+        let first = match definition.body.first() {
+            Some(first) => self.label_of(first),
+            None if label.is_some() => {
+                return Err(invalid(&format!("{} is empty so cannot be labelled", name)))
+            }
+            None => return Ok(()),
+        };
+        let mut names = definition
+            .params
+            .iter()
+            .cloned()
+            .zip(args.iter().map(|arg| arg.to_string()))
+            .collect::<HashMap<String, String>>();
+        for defined in definition
+            .body
+            .iter()
+            .filter_map(|text| self.label_of(text))
+        {
+            let renamed = format!("{}.{}.{}", name, self.invocations, defined);
+            names.insert(defined.to_string(), renamed);
+        }
+        // a label on the invocation labels the first line of the body, taking
+        // the place of any label the body gives it
+        if let (Some(label), Some(first)) = (label, first) {
+            names.insert(first.to_string(), label.to_string());
+        }
+        let mut body = definition
+            .body
+            .iter()
+            .map(|text| {
+                split(text)
+                    .iter()
+                    .map(|word| names.get(*word).map_or(*word, |name| name.as_str()))
+                    .collect::<Vec<&str>>()
+                    .join(" ")
+            })
+            .collect::<Vec<String>>();
+        if let (Some(label), None) = (label, first) {
+            body[0] = format!("{} {}", label, body[0]);
+        }
+        for text in body {
+            self.expand_line(line, &text, depth + 1)?;
+        }
+        Ok(())
+    }
+
+    // label_of returns the label a line of a macro body defines, if any
+    fn label_of<'a>(&self, text: &'a str) -> Option<&'a str> {
+        match split(text).as_slice() {
+            [first, _, ..] if !is_mnemonic(first) && !self.macros.contains_key(*first) => {
+                Some(*first)
+            }
+            _ => None,
+        }
+    }
+}
+
+// split splits a line into its whitespace separated words
+fn split(text: &str) -> Vec<&str> {
+    text.split_whitespace().collect()
+}
+
+// is_keyword returns whether a line starts with the keyword, in any case
+fn is_keyword(words: &[&str], keyword: &str) -> bool {
+    words
+        .first()
+        .is_some_and(|word| word.eq_ignore_ascii_case(keyword))
+}
+
+// invalid returns an error for a macro that cannot be defined or expanded
+fn invalid(message: &str) -> AssemblerError {
+    AssemblerError::InvalidMacro(message.to_string())
+}
@@ -1,57 +1,72 @@
+#![allow(clippy::upper_case_acronyms)]
+
+use serde_json::{json, Value};
 use std::{
     env,
-    fs::File,
-    io::{prelude::*, BufReader},
+    fs::{self, File},
+    io::{prelude::*, IsTerminal},
+    path::{Path, PathBuf},
     process::exit,
+    sync::OnceLock,
+    thread,
+    time::Duration,
 };
 
-mod assembler;
-mod lmc;
-mod logger;
-mod numbers;
-
-use assembler::Assembler;
-use lmc::LMC;
-use logger::{LogLevel, Logger};
-use numbers::ThreeDigitNumber;
+mod cli;
 
-struct Test {
-    name: String,
-    input: Option<Vec<ThreeDigitNumber>>,
-    result: Option<ThreeDigitNumber>,
-    cycles: usize,
-}
+use ::lmc::{
+    annotate, assembler, bundle, cache, canonical, config, conformance, debugger, debuginfo,
+    devices, editor, exercise, fuzz, highlight, hints, io, listing, lmc, lockstep, logger, metrics,
+    numbers, oracle, patch, profile, program, query, refactor, report, runner, scheduler, schema,
+    session, similarity, source, syntax, tests, text, tournament, trace, tui, version,
+};
 
-impl Test {
-    pub fn new(
-        name: &String,
-        input: Option<Vec<ThreeDigitNumber>>,
-        result: Option<ThreeDigitNumber>,
-        cycles: usize,
-    ) -> Test {
-        Test {
-            name: name.clone(),
-            input,
-            result,
-            cycles,
-        }
-    }
-}
+use assembler::{Assembler, AssemblerError};
+use bundle::Bundle;
+use cache::{Cache, CACHE_FILE};
+use conformance::{ConformanceSuite, Dialect};
+use debugger::Debugger;
+use debuginfo::DebugInfo;
+use devices::{CycleCounter, Display, Keyboard, Rng};
+use editor::LineEditor;
+use exercise::Progress;
+use fuzz::Fuzzer;
+use highlight::HighlightFormat;
+use io::{CancelToken, ChannelInput, CollectingOutput, CyclicInput, FixedInput, RandomInput};
+use lmc::{
+    CounterWrap, InputRetention, MachineState, StateFormat, UninitializedPolicy, DEFAULT_PROMPT,
+    LMC,
+};
+use lockstep::Comparison;
+use logger::{Component, LogLevel, Logger, COMPONENTS};
+use numbers::ThreeDigitNumber;
+use program::{Checksum, Diagnostic, Program};
+use query::Query;
+use report::{Report, ReportFormat, Status};
+use runner::{Comparator, Domain, Marker, Suite, SuiteEvent, Test};
+use scheduler::Scheduler;
+use schema::{SchemaKind, DIAGNOSTICS_SCHEMA};
+use session::Session;
+use source::SourceFormat;
+use tournament::LeaderboardFormat;
+use trace::Trace;
+use tui::{Player, Tui};
 
 fn main() {
     // Collect all arguments into a vector
     let mut args: Vec<String> = env::args().collect();
     args.remove(0);
-    if args.len() < 2 {
+    if args.is_empty() {
         print_usage();
     }
 
     let logger = Logger::new(false, false);
 
     // Extract flags
+    // a lone - is not a flag but a file name meaning stdin
     let flags = args
         .iter()
-        .filter(|arg| arg.starts_with("-"))
+        .filter(|arg| arg.starts_with("-") && arg.as_str() != "-")
         .map(|arg| arg.to_string())
         .map(|arg| arg.trim_start_matches("-").to_string())
         .collect::<Vec<String>>();
@@ -59,24 +74,178 @@ fn main() {
     if flags.contains(&"h".to_string()) || flags.contains(&"help".to_string()) {
         print_usage();
     }
+    if flags.contains(&"version".to_string()) {
+        println!("{}", version::version());
+        exit(0);
+    }
+    // a misspelt command or flag is an error naming what was likely meant
+    // rather than being ignored
+    let first = args
+        .iter()
+        .find(|arg| !arg.starts_with("-") || arg.as_str() == "-");
+    if let Err(err) = first
+        .map_or(Ok(()), |command| cli::check_command(command))
+        .and_then(|_| cli::check_flags(&flags))
+    {
+        logger.log(&LogLevel::Error, &format!("{}", err));
+        exit(1);
+    }
     // Check for other flags
-    let verbose = flags.contains(&"v".to_string()) || flags.contains(&"verbose".to_string());
-    let debug = flags.contains(&"d".to_string()) || flags.contains(&"debug".to_string());
+    let verbose = component_flag(&logger, &flags, "v", "verbose");
+    let debug = component_flag(&logger, &flags, "d", "debug");
+    let (vm_verbose, vm_debug) = (
+        verbose.contains(&Component::VM),
+        debug.contains(&Component::VM),
+    );
+    let (asm_verbose, asm_debug) = (
+        verbose.contains(&Component::ASM),
+        debug.contains(&Component::ASM),
+    );
+    let record_session = flag_value(&flags, "record-session");
+    let trace_file = flag_value(&flags, "trace");
+    let checked = flags.contains(&"checked".to_string());
+    let strict = flags.contains(&"strict".to_string());
+    let signed = flags.contains(&"signed".to_string());
+    let explain = flags.contains(&"explain".to_string());
+    let plain = flags.contains(&"plain".to_string());
+    let teaching = flags.contains(&"teaching".to_string());
+    let stats = flags.contains(&"stats".to_string());
+    let checkpoint = flag_value(&flags, "checkpoint");
+    // the prompt shown when IN reads stdin, none with --no-prompt
+    let prompt = match flags.contains(&"no-prompt".to_string()) {
+        true => None,
+        false => Some(flag_value(&flags, "prompt").unwrap_or(DEFAULT_PROMPT.to_string())),
+    };
+    let devices = flags.contains(&"devices".to_string());
+    let keyboard = flags.contains(&"keyboard".to_string());
+    let max_inputs = numeric_flag(&logger, &flags, "max-inputs");
+    let max_outputs = numeric_flag(&logger, &flags, "max-outputs");
+    let max_time =
+        numeric_flag(&logger, &flags, "max-time").map(|ms| Duration::from_millis(ms as u64));
+    let max_mailboxes = numeric_flag(&logger, &flags, "max-mailboxes");
+    // a cycle limit of 0 lifts the limit altogether
+    let max_cycles = numeric_flag(&logger, &flags, "max-cycles").map(|cycles| match cycles {
+        0 => usize::MAX,
+        cycles => cycles,
+    });
+    let input_retries = numeric_flag(&logger, &flags, "input-retries");
+    let source_format = match flag_value(&flags, "source-format") {
+        Some(format) => match SourceFormat::from_str(&format) {
+            Some(format) => format,
+            None => {
+                logger.log(
+                    &LogLevel::Error,
+                    &format!("Invalid source format: {}", format),
+                );
+                exit(1);
+            }
+        },
+        None => SourceFormat::Free,
+    };
+    let verify_checksum = flags.contains(&"verify-checksum".to_string());
+    let partial = flags.contains(&"partial".to_string());
+    let start_at = numeric_flag(&logger, &flags, "start-at");
+    let entry = flag_value(&flags, "entry");
+    // --print-state on its own prints only the non-zero mailboxes
+    let print_state = if flags.contains(&"print-state".to_string()) {
+        Some(StateFormat::NonZero)
+    } else {
+        flag_value(&flags, "print-state").map(|format| match StateFormat::from_str(&format) {
+            Some(format) => format,
+            None => {
+                logger.log(
+                    &LogLevel::Error,
+                    &format!("Invalid state format: {}", format),
+                );
+                exit(1);
+            }
+        })
+    };
+    let uninitialized_policy = match flag_value(&flags, "uninitialized") {
+        Some(policy) => match UninitializedPolicy::from_str(&policy) {
+            Some(policy) => policy,
+            None => {
+                logger.log(
+                    &LogLevel::Error,
+                    &format!("Invalid uninitialized read policy: {}", policy),
+                );
+                exit(1);
+            }
+        },
+        None => UninitializedPolicy::Ignore,
+    };
+    // poison defaults to 400, an invalid instruction, when given no value
+    let poison = match flag_value(&flags, "poison") {
+        Some(value) => match value.parse::<i16>().map(ThreeDigitNumber::new) {
+            Ok(Ok(value)) => Some(value),
+            _ => {
+                logger.log(
+                    &LogLevel::Error,
+                    &format!("Invalid value for --poison: {}", value),
+                );
+                exit(1);
+            }
+        },
+        None if flags.contains(&"poison".to_string()) => Some(ThreeDigitNumber::new(400).unwrap()),
+        None => None,
+    };
+    let counter_wrap = match flag_value(&flags, "counter-wrap") {
+        Some(policy) => match CounterWrap::from_str(&policy) {
+            Some(policy) => policy,
+            None => {
+                logger.log(
+                    &LogLevel::Error,
+                    &format!("Invalid counter wrap policy: {}", policy),
+                );
+                exit(1);
+            }
+        },
+        None => CounterWrap::Warn,
+    };
 
     let commands = args
         .iter()
-        .filter(|arg| !arg.starts_with("-"))
+        .filter(|arg| !arg.starts_with("-") || arg.as_str() == "-")
         .collect::<Vec<&String>>();
-    if commands.len() < 2 {
+    // every command but highlight, selftest and verify-vm needs at least one argument
+    if commands.is_empty()
+        || (commands.len() < 2
+            && !["highlight", "selftest", "verify-vm", "conformance"]
+                .contains(&commands[0].as_str()))
+    {
         print_usage();
     }
-    if commands.len() > 3 {
+    // schedule, pipe, metrics, grep, refactor and bundle are the only commands
+    // that accept more than two arguments
+    if commands.len() > 3
+        && !["schedule", "pipe", "metrics", "grep", "refactor", "bundle"]
+            .contains(&commands[0].as_str())
+    {
         print_usage();
     }
 
     // Execute the command
-    let cmd = *commands.get(0).unwrap();
-    if cmd == &"assemble".to_string() {
+    let cmd = *commands.first().unwrap();
+    if cmd == &"assemble".to_string() && flags.contains(&"all".to_string()) {
+        let (dir, out) = match (commands.get(1), flag_value(&flags, "out")) {
+            (Some(dir), Some(out)) => (dir, out),
+            _ => {
+                print_usage();
+                return;
+            }
+        };
+        let mut asm = Assembler::new(asm_verbose, asm_debug);
+        // assemble partially so that every error of every file is reported
+        asm.set_partial(true);
+        assemble_all(
+            &logger,
+            &asm,
+            &flags,
+            Path::new(dir),
+            Path::new(&out),
+            source_format,
+        );
+    } else if cmd == &"assemble".to_string() {
         let input_file = match commands.get(1) {
             Some(file) => file,
             None => {
@@ -84,30 +253,103 @@ fn main() {
                 return;
             }
         };
+        // a dry run checks the source without writing an output file
+        let dry_run = flags.contains(&"dry-run".to_string());
         let output_file = match commands.get(2) {
             Some(file) => file,
+            None if dry_run => "",
             None => {
                 print_usage();
                 return;
             }
         };
-        let mut input = BufReader::new(File::open(input_file).unwrap())
-            .lines()
-            .map(|line| line.unwrap())
-            .collect::<Vec<String>>();
-        let asm = Assembler::new(verbose, debug);
-        let program = match asm.assemble(&mut input) {
+        // JSON diagnostics replace the errors and warnings logged to stderr
+        let json_diagnostics = match flag_value(&flags, "diagnostics").as_deref() {
+            None | Some("text") => false,
+            Some("json") => true,
+            Some(format) => {
+                logger.log(
+                    &LogLevel::Error,
+                    &format!("Invalid diagnostics format: {}", format),
+                );
+                exit(1);
+            }
+        };
+        let mut input = read_lines(&logger, input_file);
+        let mut asm = Assembler::new(asm_verbose, asm_debug);
+        if flags.contains(&"fix".to_string()) {
+            input = fix_source(&logger, &asm, input, source_format);
+            if let Err(err) = fs::write(input_file, input.join("\n") + "\n") {
+                logger.log(&LogLevel::Error, &format!("{}", err));
+                exit(1);
+            }
+        }
+        if dry_run {
+            let source = source::to_free(&input, source_format);
+            // assemble partially so that every error is reported, not just the first
+            asm.set_partial(true);
+            let program = match asm.assemble_program(&mut source.clone()) {
+                Ok(program) => program,
+                Err(err) if json_diagnostics => {
+                    print_diagnostics_json(&source, Some(&err), &[], &[]);
+                    exit(1);
+                }
+                Err(err) => {
+                    logger.log(&LogLevel::Error, &format!("{}", err));
+                    exit(1);
+                }
+            };
+            if json_diagnostics {
+                let unused = unused_labels(&source, &program);
+                print_diagnostics_json(&source, None, &program.diagnostics, &unused);
+            } else {
+                print_dry_run(&logger, &source, &program);
+            }
+            if !program.diagnostics.is_empty() {
+                exit(1);
+            }
+            return;
+        }
+        let source = source::to_free(&input, source_format);
+        // every error is reported as JSON, not just the first
+        asm.set_partial(json_diagnostics);
+        let mut program = match asm.assemble_program(&mut source.clone()) {
             Ok(program) => program,
+            Err(err) if json_diagnostics => {
+                print_diagnostics_json(&source, Some(&err), &[], &[]);
+                exit(1);
+            }
             Err(err) => {
                 logger.log(&LogLevel::Error, &format!("{}", err));
                 exit(1);
             }
         };
+        if json_diagnostics {
+            print_diagnostics_json(&source, None, &program.diagnostics, &[]);
+            if !program.diagnostics.is_empty() {
+                exit(1);
+            }
+        }
+        if let Some(size) = numeric_flag(&logger, &flags, "pad-to") {
+            pad_program(&logger, &flags, &mut program, size);
+        }
+        let annotate = flags.contains(&"annotate".to_string());
         let mut output = File::create(output_file).unwrap();
-        for instruction in program {
-            writeln!(output, "{}", instruction.to_string()).unwrap();
+        write_machine_code(&mut output, &source, &program, annotate).unwrap();
+        if let Some(file) = flag_value(&flags, "debug-info") {
+            if let Err(err) = DebugInfo::new(input_file, &source, &program).save(&file) {
+                logger.log(&LogLevel::Error, &format!("{}: {}", file, err));
+                exit(1);
+            }
+        }
+        if let Some(file) = flag_value(&flags, "listing") {
+            let listing = listing::listing(&source, &program).join("\n") + "\n";
+            if let Err(err) = fs::write(&file, listing) {
+                logger.log(&LogLevel::Error, &format!("{}: {}", file, err));
+                exit(1);
+            }
         }
-    } else if cmd == &"execute".to_string() {
+    } else if ["execute", "run", "resume"].contains(&cmd.as_str()) {
         let program_file = match commands.get(1) {
             Some(file) => file,
             None => {
@@ -115,200 +357,2305 @@ fn main() {
                 return;
             }
         };
-        let input = parse_program_file(&logger, program_file);
-        let mut lmc = LMC::new(verbose, debug, false, 50000);
-        match lmc.load_program(&input) {
+        // resume continues from a saved state rather than loading a program
+        let state = (cmd == &"resume".to_string()).then(|| {
+            let text = fs::read_to_string(program_file).unwrap_or_else(|err| {
+                logger.log(&LogLevel::Error, &format!("{}: {}", program_file, err));
+                exit(1);
+            });
+            MachineState::from_json(&text).unwrap_or_else(|err| {
+                logger.log(&LogLevel::Error, &format!("{}: {}", program_file, err));
+                exit(1);
+            })
+        });
+        // execute loads machine code whereas run assembles the source first
+        let mut program = if let Some(state) = &state {
+            Program::from_instructions(state.mailboxes.to_vec())
+        } else if cmd == &"run".to_string() {
+            let source = read_lines(&logger, program_file);
+            assemble_source(
+                &logger,
+                &Assembler::new(asm_verbose, asm_debug),
+                &source,
+                source_format,
+            )
+        } else {
+            Program::from_instructions(parse_program_file(&logger, program_file, verify_checksum))
+        };
+        // debug info gives machine code the labels and source lines it came from
+        let debug_info = match cmd == &"execute".to_string() {
+            true => load_debug_info(&logger, &flags, &mut program),
+            false => None,
+        };
+        let mut lmc = LMC::new(vm_verbose, vm_debug, false, max_cycles.unwrap_or(50000));
+        lmc.set_uninitialized_policy(uninitialized_policy);
+        lmc.set_poison(poison);
+        lmc.set_counter_wrap(counter_wrap);
+        lmc.set_io_limits(max_inputs, max_outputs);
+        lmc.set_resource_limits(max_time, max_mailboxes);
+        set_stdin_options(&mut lmc, signed, prompt.as_deref(), input_retries);
+        set_input_source(&logger, &flags, &mut lmc, signed);
+        let collected = flag_value(&flags, "outputs").map(|path| {
+            let output = CollectingOutput::new();
+            lmc.set_output(Box::new(output.clone()));
+            (path, output)
+        });
+        if devices {
+            attach_standard_devices(&logger, &flags, &mut lmc);
+        }
+        if keyboard {
+            attach_keyboard(&mut lmc);
+        }
+        lmc.set_strict(strict);
+        lmc.set_explain(explain);
+        match lmc.load_program(&program) {
             Ok(_) => (),
             Err(err) => {
                 logger.log(&LogLevel::Error, &format!("{}", err));
                 exit(1);
             }
         }
-        match lmc.execute_program() {
-            Ok(_) => (),
-            Err(err) => {
+        if checked {
+            lmc.load_assertions(&program.assertions);
+        }
+        if record_session.is_some() {
+            lmc.record_session();
+        }
+        if trace_file.is_some() {
+            lmc.record_trace();
+        }
+        // --entry names a label, or address, to start from in place of --start-at
+        let start_at = match &entry {
+            Some(entry) => Some(resolve_entry(&logger, &program, entry)),
+            None => start_at,
+        };
+        let result = match (&state, start_at) {
+            (Some(state), _) => {
+                lmc.restore(state);
+                lmc.resume()
+            }
+            (None, Some(address)) => lmc.execute_from(address),
+            (None, None) => lmc.execute_program(),
+        };
+        for rendering in lmc.render_devices() {
+            print!("{}", rendering);
+        }
+        if let Some(format) = print_state {
+            println!("{}", lmc.state().format(format, &result));
+        }
+        // a checkpoint is written however the run stopped so it can be resumed
+        if let Some(path) = &checkpoint {
+            let state = lmc.state().format(StateFormat::JSON, &result);
+            if let Err(err) = fs::write(path, state + "\n") {
+                logger.log(&LogLevel::Error, &format!("{}: {}", path, err));
+                exit(1);
+            }
+        }
+        if stats {
+            eprintln!(
+                "cycles: {}  inputs: {}  outputs: {}",
+                result.cycles,
+                lmc.inputs(),
+                lmc.outputs()
+            );
+            eprintln!("working set: {}", lmc.working_set());
+        }
+        // Save the session and trace even if execution failed so the failure can be replayed
+        if let (Some(path), Some(session)) = (&record_session, lmc.take_session()) {
+            if let Err(err) = session.save(path) {
                 logger.log(&LogLevel::Error, &format!("{}", err));
                 exit(1);
             }
         }
-    } else if cmd == &"batch".to_string() {
-        let program_file = match commands.get(1) {
+        if let (Some(path), Some(trace)) = (&trace_file, lmc.take_trace()) {
+            if let Err(err) = trace.save(path) {
+                logger.log(&LogLevel::Error, &format!("{}", err));
+                exit(1);
+            }
+        }
+        // the outputs are written however the run stopped, like a checkpoint
+        if let Some((path, output)) = &collected {
+            let values = output
+                .values()
+                .iter()
+                .map(|value| match signed {
+                    true => format!("{}\n", value.signed_value()),
+                    false => format!("{}\n", value.value()),
+                })
+                .collect::<String>();
+            if let Err(err) = fs::write(path, values) {
+                logger.log(&LogLevel::Error, &format!("{}: {}", path, err));
+                exit(1);
+            }
+        }
+        if !result.is_halted() {
+            logger.log(&LogLevel::Error, &format!("{}", result));
+            let counter = lmc.state().counter.value() as usize;
+            if let Some(context) = debug_info.and_then(|info| info.context(counter)) {
+                eprintln!("at {}", context);
+            }
+            if teaching {
+                let state = lmc.state();
+                let failure = hints::Failure {
+                    counter: state.counter.value() as usize,
+                    previous: lmc.previous(),
+                    mailboxes: &state.mailboxes,
+                };
+                if let Some(hint) = hints::hint(&result.reason, &program, &failure) {
+                    eprintln!("Hint: {}", hint);
+                }
+            }
+            exit(1);
+        }
+    } else if cmd == &"annotate".to_string() {
+        let source_file = match commands.get(1) {
             Some(file) => file,
             None => {
                 print_usage();
                 return;
             }
         };
-        let test_file = match commands.get(2) {
+        let trace_file = match trace_file {
             Some(file) => file,
             None => {
                 print_usage();
                 return;
             }
         };
-        let input = parse_program_file(&logger, program_file);
-        let tests = parse_test_file(&logger, test_file);
-        let mut lmc = LMC::new(verbose, debug, true, 50000);
-        match lmc.load_program(&input) {
-            Ok(_) => (),
+        let source = read_lines(&logger, source_file);
+        let program = assemble_source(
+            &logger,
+            &Assembler::new(asm_verbose, asm_debug),
+            &source,
+            source_format,
+        );
+        let trace = match Trace::load(&trace_file) {
+            Ok(trace) => trace,
             Err(err) => {
                 logger.log(&LogLevel::Error, &format!("{}", err));
                 exit(1);
             }
+        };
+        for line in annotate::annotate(&source, &program.source_map, &trace) {
+            println!("{}", line);
         }
-        'outer: for test in tests {
-            lmc.set_max_cycles(test.cycles);
-            println!("Running test: {} [{} max cycles]", test.name, test.cycles);
-            match &test.input {
-                Some(input) => lmc.load_input(input),
-                None => (),
-            }
-            match lmc.execute_program() {
-                Ok(_) => (),
-                Err(err) => {
-                    logger.log(&LogLevel::Error, &format!("{}", err));
-                    exit(1);
-                }
+    } else if cmd == &"profile".to_string() {
+        let (program_file, trace_file) = match (commands.get(1), trace_file) {
+            (Some(program_file), Some(trace_file)) => (program_file, trace_file),
+            _ => {
+                print_usage();
+                return;
             }
-            let got = match lmc.get_output() {
-                Some(result) => format!("{:03}", result.value().to_string()),
-                None => "None".to_string(),
-            };
-            let expected = match test.result {
-                Some(result) => format!("{:03}", result.value().to_string()),
-                None => "None".to_string(),
-            };
-            if got != expected {
-                let inputs = match test.input {
-                    Some(ref input) => input
-                        .iter()
-                        .map(|number| format!("{:03}", number.value().to_string()))
-                        .collect::<Vec<String>>(),
-                    None => vec![],
-                };
-                logger.log(
-                    &LogLevel::Error,
-                    &format!(
-                        "[{}] Incorrect result for inputs [{:?}]: got {}, expected {}",
-                        test.name, inputs, got, expected,
-                    ),
-                );
+        };
+        let program = load_program(
+            &logger,
+            &Assembler::new(asm_verbose, asm_debug),
+            program_file,
+            source_format,
+            verify_checksum,
+        );
+        let trace = match Trace::load(&trace_file) {
+            Ok(trace) => trace,
+            Err(err) => {
+                logger.log(&LogLevel::Error, &format!("{}", err));
+                exit(1);
             }
-            lmc.reset_counter();
-        }
-    } else {
-        print_usage();
-    }
-}
-
-fn parse_program_file(logger: &Logger, program_file: &str) -> Vec<ThreeDigitNumber> {
-    let input = BufReader::new(match File::open(program_file) {
-        Ok(file) => file,
-        Err(err) => {
-            logger.log(&LogLevel::Error, &format!("{}", err));
-            exit(1);
-        }
-    })
-    .lines()
-    .map(|line| match line {
-        Ok(line) => line,
-        Err(err) => {
-            logger.log(&LogLevel::Error, &format!("{}", err));
-            exit(1);
-        }
-    })
-    .map(|line| match line.trim().parse::<i16>() {
-        Ok(number) => number,
-        Err(err) => {
-            logger.log(&LogLevel::Error, &format!("{}", err));
-            exit(1);
-        }
-    })
-    .map(|instruction| match ThreeDigitNumber::new(instruction) {
-        Ok(number) => number,
-        Err(err) => {
-            logger.log(&LogLevel::Error, &format!("{}", err));
-            exit(1);
-        }
-    })
-    .collect::<Vec<ThreeDigitNumber>>();
-    input
-}
-
-fn parse_test_file(logger: &Logger, test_file: &str) -> Vec<Test> {
-    let mut tests = Vec::new();
-    let input = BufReader::new(match File::open(test_file) {
-        Ok(file) => file,
-        Err(err) => {
-            logger.log(&LogLevel::Error, &format!("{}", err));
-            exit(1);
+        };
+        let loops = profile::profile(&program, &trace);
+        if loops.is_empty() {
+            println!("No loops found");
+            return;
         }
-    })
-    .lines()
-    .map(|line| match line {
-        Ok(line) => line,
-        Err(err) => {
-            logger.log(&LogLevel::Error, &format!("{}", err));
-            exit(1);
+        let total = trace.events.len().max(1);
+        println!("header      body  entries  iterations  cycles  cycles/iteration  share");
+        for found in loops {
+            // name the header by its label when the program has one
+            let label = program.label_of(found.header).unwrap_or("");
+            println!(
+                "{:02} {:8} {:>4}  {:>7}  {:>10}  {:>6}  {:>16.2}  {:>4.1}%",
+                found.header,
+                label,
+                found.body.len(),
+                found.entries,
+                found.iterations,
+                found.cycles,
+                found.cycles_per_iteration(),
+                found.cycles as f64 * 100.0 / total as f64
+            );
         }
-    });
-    for line in input.into_iter() {
-        let parts = line
-            .trim()
-            .split(";")
-            .map(|part| part.to_string())
-            .collect::<Vec<String>>();
-        let parts_len = parts.len();
-        if parts_len < 4 {
-            logger.log(
-                &LogLevel::Error,
-                &format!("Invalid test file format: {}", line),
+    } else if cmd == &"metrics".to_string() {
+        let mut asm = Assembler::new(asm_verbose, asm_debug);
+        asm.set_partial(partial);
+        let rows = commands
+            .iter()
+            .skip(1)
+            .map(|file| {
+                let program = load_program(&logger, &asm, file, source_format, verify_checksum);
+                (file.to_string(), metrics::measure(&program))
+            })
+            .collect::<Vec<_>>();
+        let width = rows.iter().map(|(file, _)| file.len()).max().unwrap_or(0);
+        println!(
+            "{:width$}  instructions  dat  labels  branches  branch density  loop nesting  complexity",
+            "program",
+            width = width
+        );
+        for (file, metrics) in rows {
+            println!(
+                "{:width$}  {:>12}  {:>3}  {:>6}  {:>8}  {:>14.2}  {:>12}  {:>10}",
+                file,
+                metrics.instructions,
+                metrics.data,
+                metrics.labels,
+                metrics.branches,
+                metrics.branch_density,
+                metrics.loop_nesting,
+                metrics.complexity,
+                width = width
             );
-            exit(1);
         }
-        let name = &parts[0];
-        let cycles = match parts[3].parse::<usize>() {
-            Ok(cycles) => cycles,
+    } else if cmd == &"similarity".to_string() {
+        let dir = commands[1];
+        let mut files = match fs::read_dir(dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| {
+                    path.extension()
+                        .is_some_and(|ext| ext == "asm" || ext == "lmc")
+                })
+                .collect::<Vec<_>>(),
             Err(err) => {
-                logger.log(
-                    &LogLevel::Error,
-                    &format!("Invalid number of cycles: {}", err),
-                );
+                logger.log(&LogLevel::Error, &format!("{}: {}", dir, err));
                 exit(1);
             }
         };
-        let input_values = parts[1]
-            .split(",")
-            .map(|part| match part.parse::<i16>() {
-                Ok(value) => value,
-                Err(err) => {
-                    logger.log(&LogLevel::Error, &format!("Invalid input value: {}", err));
-                    exit(1);
-                }
-            })
-            .map(|value| match ThreeDigitNumber::new(value) {
-                Ok(number) => number,
-                Err(err) => {
-                    logger.log(&LogLevel::Error, &format!("{}", err));
-                    exit(1);
-                }
+        files.sort();
+        let mut asm = Assembler::new(asm_verbose, asm_debug);
+        asm.set_partial(partial);
+        let programs = files
+            .iter()
+            .map(|path| {
+                let file = path.display().to_string();
+                let program = load_program(&logger, &asm, &file, source_format, verify_checksum);
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                (name.to_string(), program)
             })
-            .collect::<Vec<ThreeDigitNumber>>();
-        let test_result = match parts[2].parse::<i16>() {
-            Ok(value) => match ThreeDigitNumber::new(value) {
-                Ok(number) => Some(number),
+            .collect::<Vec<_>>();
+        let width = programs
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or(0);
+        for pair in similarity::rank(&programs) {
+            println!(
+                "{:5.1}%  {:width$}  {}",
+                pair.similarity * 100.0,
+                pair.a,
+                pair.b,
+                width = width
+            );
+        }
+    } else if cmd == &"canonicalize".to_string() {
+        let file = match commands.get(1) {
+            Some(file) => file,
+            None => {
+                print_usage();
+                return;
+            }
+        };
+        let mut asm = Assembler::new(asm_verbose, asm_debug);
+        asm.set_partial(partial);
+        let program = load_program(&logger, &asm, file, source_format, verify_checksum);
+        let canonical = canonical::canonicalize(&program);
+        if !canonical.relocated && canonical::data_cells(&program).contains(&true) {
+            logger.log(
+                &LogLevel::Warn,
+                "the DAT cells were left in place as moving them could change what the program does",
+            );
+        }
+        for line in canonical.source {
+            println!("{}", line);
+        }
+    } else if cmd == &"grep".to_string() {
+        let query = match commands.get(1) {
+            Some(query) => match Query::parse(query) {
+                Ok(query) => query,
                 Err(err) => {
                     logger.log(&LogLevel::Error, &format!("{}", err));
                     exit(1);
                 }
             },
-            Err(_) => None,
+            None => {
+                print_usage();
+                return;
+            }
+        };
+        let mut asm = Assembler::new(asm_verbose, asm_debug);
+        asm.set_partial(partial);
+        let mut found = false;
+        for file in commands.iter().skip(2) {
+            // show the source line of each match when searching assembly source
+            let source = if file.ends_with(".asm") {
+                read_lines(&logger, file)
+            } else {
+                Vec::new()
+            };
+            let program = load_program(&logger, &asm, file, source_format, verify_checksum);
+            for found_match in query.search(&program) {
+                found = true;
+                match found_match.line.and_then(|line| source.get(line - 1)) {
+                    Some(text) => println!(
+                        "{}:{}:{:02}: {}",
+                        file,
+                        found_match.line.unwrap_or(0),
+                        found_match.address,
+                        text.trim()
+                    ),
+                    None => println!(
+                        "{}:{:02}: {}{}",
+                        file,
+                        found_match.address,
+                        found_match.instruction,
+                        program
+                            .data_preview(found_match.instruction, &program.instructions)
+                            .map_or(String::new(), |preview| format!(" {}", preview))
+                    ),
+                }
+            }
+        }
+        // exit like grep does when nothing matched
+        if !found {
+            exit(1);
+        }
+    } else if cmd == &"refactor".to_string() {
+        let asm = Assembler::new(asm_verbose, asm_debug);
+        // refactor rename <old> <new> <source file> or refactor move-dat <source file>
+        let (source_file, result) = match (commands.get(1).map(|c| c.as_str()), &commands[2..]) {
+            (Some("rename"), [from, to, file]) => {
+                let source = source::to_free(&read_lines(&logger, file), source_format);
+                (file, refactor::rename(&asm, &source, from, to))
+            }
+            (Some("move-dat"), [file]) => {
+                let range = flag_value(&flags, "to").unwrap_or("90..99".to_string());
+                let source = source::to_free(&read_lines(&logger, file), source_format);
+                let result = refactor::parse_range(&range)
+                    .and_then(|(start, end)| refactor::move_data(&asm, &source, start, end));
+                (file, result)
+            }
+            _ => {
+                print_usage();
+                return;
+            }
+        };
+        let lines = match result {
+            Ok(lines) => lines,
+            Err(err) => {
+                logger.log(&LogLevel::Error, &format!("{}", err));
+                exit(1);
+            }
         };
-        if input_values.len() == 0 {
-            tests.push(Test::new(&name, None, test_result, cycles));
+        if flags.contains(&"in-place".to_string()) {
+            if let Err(err) = fs::write(source_file, lines.join("\n") + "\n") {
+                logger.log(&LogLevel::Error, &format!("{}", err));
+                exit(1);
+            }
         } else {
-            tests.push(Test::new(&name, Some(input_values), test_result, cycles));
+            for line in lines {
+                println!("{}", line);
+            }
+        }
+    } else if cmd == &"patch".to_string() {
+        let program_file = match commands.get(1) {
+            Some(file) => file,
+            None => {
+                print_usage();
+                return;
+            }
+        };
+        // edits from the patch file come first, then those of the flags
+        let mut lines = match commands.get(2) {
+            Some(file) => read_lines(&logger, file),
+            None => Vec::new(),
+        };
+        lines.extend(
+            flag_values(&flags, "set")
+                .iter()
+                .map(|edit| format!("set {}", edit.replace(',', " "))),
+        );
+        lines.extend(
+            flag_values(&flags, "replace")
+                .iter()
+                .map(|edit| format!("replace {}", edit.replace(',', " "))),
+        );
+        let mut instructions = parse_program_file(&logger, program_file, verify_checksum);
+        let changes = patch::parse(&lines.join("\n"))
+            .and_then(|edits| patch::apply(&mut instructions, &edits));
+        match changes {
+            Ok(changes) => {
+                for change in changes {
+                    eprintln!("{}", change);
+                }
+            }
+            Err(err) => {
+                logger.log(&LogLevel::Error, &format!("{}", err));
+                exit(1);
+            }
+        }
+        let mut lines = instructions
+            .iter()
+            .map(|instruction| instruction.to_string())
+            .collect::<Vec<String>>();
+        lines.push(Checksum::of(&instructions).to_string());
+        if flags.contains(&"in-place".to_string()) {
+            if let Err(err) = fs::write(program_file, lines.join("\n") + "\n") {
+                logger.log(&LogLevel::Error, &format!("{}", err));
+                exit(1);
+            }
+        } else {
+            for line in lines {
+                println!("{}", line);
+            }
+        }
+    } else if cmd == &"format".to_string() {
+        let source_file = match commands.get(1) {
+            Some(file) => file,
+            None => {
+                print_usage();
+                return;
+            }
+        };
+        for line in source::format(&read_lines(&logger, source_file), source_format) {
+            println!("{}", line);
+        }
+    } else if cmd == &"parse".to_string() {
+        let source_file = match commands.get(1) {
+            Some(file) => file,
+            None => {
+                print_usage();
+                return;
+            }
+        };
+        let source = source::to_free(&read_lines(&logger, source_file), source_format);
+        // assemble partially so that source part way through being edited
+        // can still be parsed, reporting errors as diagnostics
+        let mut asm = Assembler::new(asm_verbose, asm_debug);
+        asm.set_partial(true);
+        let program = match asm.assemble_program(&mut source.clone()) {
+            Ok(program) => program,
+            Err(err) => {
+                logger.log(&LogLevel::Error, &format!("{}", err));
+                exit(1);
+            }
+        };
+        let document = syntax::to_json(&syntax::tokenize(&source), &program);
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&document).unwrap_or_default()
+        );
+    } else if cmd == &"highlight".to_string() {
+        let format = match flag_value(&flags, "format") {
+            Some(format) => match HighlightFormat::from_str(&format) {
+                Some(format) => format,
+                None => {
+                    logger.log(
+                        &LogLevel::Error,
+                        &format!("Invalid highlight format: {}", format),
+                    );
+                    exit(1);
+                }
+            },
+            None => HighlightFormat::TmLanguage,
+        };
+        println!("{}", highlight::generate(format));
+    } else if cmd == &"selftest".to_string() {
+        if !flags.contains(&"fuzz".to_string()) {
+            print_usage();
+            return;
+        }
+        let iterations = numeric_flag(&logger, &flags, "iterations").unwrap_or(200);
+        let seed = seed_flag(&logger, &flags);
+        let replay = replay_flag(&logger, &flags);
+        let mut fuzzer = Fuzzer::new(seed);
+        let (cases, failures) = match replay {
+            // a replayed case is run under the debugger
+            Some(replay) => {
+                println!("Replaying the case with seed {}", replay);
+                fuzzer.set_debug(true);
+                (2, fuzzer.replay(replay))
+            }
+            None => {
+                println!("Fuzzing with seed {} for {} iterations", seed, iterations);
+                (iterations * 2, fuzzer.run(iterations))
+            }
+        };
+        for failure in failures {
+            logger.log(&LogLevel::Error, &format!("{}", failure));
+            logger.log(&LogLevel::Error, &replay_hint(failure.seed));
+        }
+        println!("{} cases, {} panicked", cases, failures.len());
+        if !failures.is_empty() {
+            exit(1);
+        }
+    } else if cmd == &"verify-vm".to_string() {
+        let iterations = numeric_flag(&logger, &flags, "iterations").unwrap_or(200);
+        let seed = seed_flag(&logger, &flags);
+        let (programs, mismatches) = match replay_flag(&logger, &flags) {
+            // a replayed program is run under the debugger
+            Some(replay) => {
+                println!("Replaying the program with seed {}", replay);
+                (1, oracle::replay(replay, true).into_iter().collect())
+            }
+            None => {
+                println!(
+                    "Comparing the VM to the oracle with seed {} for {} programs",
+                    seed, iterations
+                );
+                (iterations, oracle::verify(seed, iterations))
+            }
+        };
+        for mismatch in &mismatches {
+            logger.log(&LogLevel::Error, &format!("mismatch\n{}", mismatch));
+            logger.log(&LogLevel::Error, &replay_hint(mismatch.seed));
+        }
+        println!("{} programs, {} mismatched", programs, mismatches.len());
+        if !mismatches.is_empty() {
+            exit(1);
+        }
+    } else if cmd == &"debug".to_string() {
+        // assembly is read here rather than by load_program so that the
+        // debugger can show it
+        let source = match commands[1].ends_with(".asm") {
+            true => read_lines(&logger, commands[1]),
+            false => Vec::new(),
+        };
+        let mut program = match source.is_empty() {
+            true => load_program(
+                &logger,
+                &Assembler::new(asm_verbose, asm_debug),
+                commands[1],
+                source_format,
+                verify_checksum,
+            ),
+            false => assemble_source(
+                &logger,
+                &Assembler::new(asm_verbose, asm_debug),
+                &source,
+                source_format,
+            ),
+        };
+        // machine code is shown with the source its debug info points to,
+        // as far as the debug info knows it if the source cannot be read
+        let source = match source.is_empty() {
+            true => match load_debug_info(&logger, &flags, &mut program) {
+                Some(info) => match fs::read_to_string(&info.source) {
+                    Ok(text) => text.lines().map(String::from).collect(),
+                    Err(_) => info.source_lines(),
+                },
+                None => source,
+            },
+            false => source,
+        };
+        let mut lmc = LMC::new(vm_verbose, vm_debug, false, max_cycles.unwrap_or(50000));
+        lmc.set_uninitialized_policy(uninitialized_policy);
+        lmc.set_poison(poison);
+        lmc.set_counter_wrap(counter_wrap);
+        set_stdin_options(&mut lmc, signed, prompt.as_deref(), input_retries);
+        set_input_source(&logger, &flags, &mut lmc, signed);
+        if devices {
+            attach_standard_devices(&logger, &flags, &mut lmc);
+        }
+        // keys are pressed with the key command, stdin being read for commands
+        let keyboard = keyboard.then(|| {
+            let (keyboard, handle) = Keyboard::new();
+            lmc.attach_device(96, Box::new(keyboard));
+            handle
+        });
+        lmc.set_strict(strict);
+        if let Err(err) = lmc.load_program(&program) {
+            logger.log(&LogLevel::Error, &format!("{}", err));
+            exit(1);
+        }
+        // tab completes labels as well as the debugger's commands
+        let labels = program.labels.keys().cloned().collect();
+        let mut editor = LineEditor::new(debugger::commands(), labels, config::history_path());
+        let mut debugger = Debugger::new(lmc, program);
+        debugger.set_source(source);
+        debugger.set_plain(plain);
+        if let Some(keyboard) = keyboard {
+            debugger.set_keyboard(keyboard);
+        }
+        // a config file that cannot be read leaves the default layout
+        if let Some(path) = config::path() {
+            match config::load(&path) {
+                Ok(config) => debugger.set_layout(config.debugger),
+                Err(err) => logger.log(&LogLevel::Warn, &format!("{}: {}", path.display(), err)),
+            }
+            debugger.set_config(path);
+        }
+        println!(
+            "Debugging {}, type help for a list of commands",
+            commands[1]
+        );
+        loop {
+            // the end of stdin quits like quit does
+            let line = match editor.read_line("(lmc) ") {
+                Some(line) => line,
+                None => {
+                    println!();
+                    break;
+                }
+            };
+            match debugger.command(&line) {
+                Some(reply) if reply.is_empty() => {}
+                Some(reply) => println!("{}", reply),
+                None => break,
+            }
+        }
+    } else if cmd == &"tui".to_string() {
+        let program_file = match commands.get(1) {
+            Some(file) => file,
+            None => {
+                print_usage();
+                return;
+            }
+        };
+        // assembly is read here rather than by load_program so that the
+        // next instruction can be shown as its source line
+        let asm = Assembler::new(asm_verbose, asm_debug);
+        let (mut program, source) = match program_file.ends_with(".asm") {
+            true => {
+                let source = read_lines(&logger, program_file);
+                (
+                    assemble_source(&logger, &asm, &source, source_format),
+                    source,
+                )
+            }
+            false => (
+                load_program(&logger, &asm, program_file, source_format, verify_checksum),
+                Vec::new(),
+            ),
+        };
+        let source = match source.is_empty() {
+            true => load_debug_info(&logger, &flags, &mut program)
+                .map_or(source, |info| info.source_lines()),
+            false => source,
+        };
+        let speed = numeric_flag(&logger, &flags, "speed").unwrap_or(4).max(1);
+        let mut lmc = LMC::new(vm_verbose, vm_debug, true, max_cycles.unwrap_or(50000));
+        lmc.set_uninitialized_policy(uninitialized_policy);
+        lmc.set_poison(poison);
+        lmc.set_counter_wrap(counter_wrap);
+        lmc.set_signed(signed);
+        // inputs typed into the tui stand in for stdin, which it reads commands from
+        let interactive =
+            inputs_flag(&logger, &flags).is_none() && flag_value(&flags, "random-inputs").is_none();
+        set_input_source(&logger, &flags, &mut lmc, signed);
+        lmc.set_input_underflow(interactive);
+        if devices {
+            attach_standard_devices(&logger, &flags, &mut lmc);
+        }
+        // keys are pressed in the tui's keyboard mode, the terminal being
+        // read for the tui's own keys
+        let keyboard = keyboard.then(|| {
+            let (keyboard, handle) = Keyboard::new();
+            lmc.attach_device(96, Box::new(keyboard));
+            handle
+        });
+        lmc.set_strict(strict);
+        if let Err(err) = lmc.load_program(&program) {
+            logger.log(&LogLevel::Error, &format!("{}", err));
+            exit(1);
+        }
+        if record_session.is_some() {
+            lmc.record_session();
+        }
+        let mut tui = Tui::new(lmc, program, program_file);
+        tui.set_source(source);
+        tui.set_delay(Duration::from_millis(1000 / speed as u64));
+        tui.set_interactive(interactive);
+        tui.set_signed(signed);
+        tui.set_plain(plain);
+        if let Some(keyboard) = keyboard {
+            tui.set_keyboard(keyboard);
+        }
+        // a config file that cannot be read leaves the default layout
+        if let Some(path) = config::path() {
+            match config::load(&path) {
+                Ok(config) => tui.set_layout(config.tui),
+                Err(err) => logger.log(&LogLevel::Warn, &format!("{}: {}", path.display(), err)),
+            }
+            tui.set_config(path);
+        }
+        match tui.run() {
+            Ok(Some(stopped)) => println!("{}", stopped),
+            Ok(None) => {}
+            Err(err) => {
+                logger.log(&LogLevel::Error, &format!("tui needs a terminal: {}", err));
+                exit(1);
+            }
+        }
+        if let (Some(path), Some(session)) = (&record_session, tui.take_session()) {
+            if let Err(err) = session.save(path) {
+                logger.log(&LogLevel::Error, &format!("{}", err));
+                exit(1);
+            }
+        }
+    } else if cmd == &"conformance".to_string() {
+        let dialect =
+            flag_value(&flags, "dialect").map(|dialect| match Dialect::from_str(&dialect) {
+                Some(dialect) => dialect,
+                None => {
+                    logger.log(&LogLevel::Error, &format!("Unknown dialect: {}", dialect));
+                    exit(1);
+                }
+            });
+        // a suite file is run on the dialect it was written for unless one is given
+        let suite = match flag_value(&flags, "suite") {
+            Some(path) => ConformanceSuite::load(&path),
+            None => ConformanceSuite::parse(dialect.unwrap_or(Dialect::Classic).suite()),
+        };
+        let (suite, dialect) = match suite.and_then(|suite| {
+            let dialect = dialect.map_or_else(|| suite.dialect(), Ok)?;
+            Ok((suite, dialect))
+        }) {
+            Ok(suite) => suite,
+            Err(err) => {
+                logger.log(&LogLevel::Error, &format!("{}", err));
+                exit(1);
+            }
+        };
+        println!(
+            "Running {} conformance cases on the {} dialect",
+            suite.size(),
+            dialect
+        );
+        let results = match suite.run(dialect) {
+            Ok(results) => results,
+            Err(err) => {
+                logger.log(&LogLevel::Error, &format!("{}", err));
+                exit(1);
+            }
+        };
+        for result in &results {
+            if result.differences.is_empty() {
+                println!("[{}] passed", result.name);
+                continue;
+            }
+            logger.log(
+                &LogLevel::Error,
+                &format!("[{}] failed: {}", result.name, result.description),
+            );
+            for difference in &result.differences {
+                println!("  {}", difference);
+            }
+        }
+        let passed = results
+            .iter()
+            .filter(|result| result.differences.is_empty())
+            .count();
+        println!("{}/{} cases conform", passed, results.len());
+        if passed != results.len() {
+            exit(1);
+        }
+    } else if cmd == &"compare-modes".to_string() {
+        let (program_file, a, b) = match (
+            commands.get(1),
+            flag_value(&flags, "a"),
+            flag_value(&flags, "b"),
+        ) {
+            (Some(file), Some(a), Some(b)) => (file, a, b),
+            _ => {
+                print_usage();
+                return;
+            }
+        };
+        let [a, b] = [a, b].map(|dialect| match Dialect::from_str(&dialect) {
+            Some(dialect) => dialect,
+            None => {
+                logger.log(&LogLevel::Error, &format!("Unknown dialect: {}", dialect));
+                exit(1);
+            }
+        });
+        let inputs = inputs_flag(&logger, &flags).unwrap_or_default();
+        let program = load_program(
+            &logger,
+            &Assembler::new(asm_verbose, asm_debug),
+            program_file,
+            source_format,
+            verify_checksum,
+        );
+        println!("Comparing the {} and {} dialects on {}", a, b, program_file);
+        match lockstep::compare_modes(&program, &inputs, a, b, 50000) {
+            Ok(Comparison::Agreed { cycles, status }) => {
+                println!("No divergence in {} cycles, both {}", cycles, status);
+            }
+            Ok(Comparison::Diverged(divergence)) => {
+                println!(
+                    "Diverged at cycle {}, executing {} at {:02}:",
+                    divergence.cycle, divergence.instruction, divergence.address
+                );
+                for difference in &divergence.differences {
+                    println!("  {}", difference);
+                }
+                exit(1);
+            }
+            Err(err) => {
+                logger.log(&LogLevel::Error, &err);
+                exit(1);
+            }
+        }
+    } else if cmd == &"schema".to_string() {
+        let kind = match commands.get(1) {
+            Some(kind) => kind,
+            None => {
+                print_usage();
+                return;
+            }
+        };
+        match SchemaKind::from_str(kind) {
+            Some(kind) => println!(
+                "{}",
+                serde_json::to_string_pretty(&kind.json_schema()).unwrap_or_default()
+            ),
+            None => {
+                logger.log(&LogLevel::Error, &format!("Unknown schema: {}", kind));
+                exit(1);
+            }
+        }
+    } else if cmd == &"play".to_string() {
+        let session_file = match commands.get(1) {
+            Some(file) => file,
+            None => {
+                print_usage();
+                return;
+            }
+        };
+        let speed = match flag_value(&flags, "speed") {
+            Some(speed) => match speed.parse::<u64>() {
+                Ok(speed) if speed > 0 => speed,
+                _ => {
+                    logger.log(
+                        &LogLevel::Error,
+                        &format!("Invalid playback speed: {}", speed),
+                    );
+                    exit(1);
+                }
+            },
+            None => 4,
+        };
+        let session = match Session::load(session_file) {
+            Ok(session) => session,
+            Err(err) => {
+                logger.log(&LogLevel::Error, &format!("{}", err));
+                exit(1);
+            }
+        };
+        // the console renderer stands in where there is no terminal to take over
+        if plain || !std::io::stdout().is_terminal() {
+            session.play(Duration::from_millis(1000 / speed), plain);
+        } else {
+            let mut player = Player::new(session, session_file);
+            player.set_delay(Duration::from_millis(1000 / speed));
+            if let Err(err) = player.run() {
+                logger.log(&LogLevel::Error, &format!("play needs a terminal: {}", err));
+                exit(1);
+            }
+        }
+    } else if cmd == &"schedule".to_string() {
+        let slice = numeric_flag(&logger, &flags, "slice").unwrap_or(10);
+        let mut scheduler = Scheduler::new(slice);
+        for program_file in commands.iter().skip(1) {
+            let input = parse_program_file(&logger, program_file, verify_checksum);
+            let mut lmc = LMC::new(vm_verbose, vm_debug, true, 50000);
+            lmc.set_uninitialized_policy(uninitialized_policy);
+            lmc.set_poison(poison);
+            lmc.set_counter_wrap(counter_wrap);
+            lmc.set_io_limits(max_inputs, max_outputs);
+            lmc.set_resource_limits(max_time, max_mailboxes);
+            set_stdin_options(&mut lmc, signed, prompt.as_deref(), input_retries);
+            match lmc.load_program(&Program::from_instructions(input)) {
+                Ok(_) => (),
+                Err(err) => {
+                    logger.log(&LogLevel::Error, &format!("{}", err));
+                    exit(1);
+                }
+            }
+            scheduler.add(program_file, lmc);
+        }
+        scheduler.run(|event| println!("{}", event));
+    } else if cmd == &"pipe".to_string() {
+        let asm = Assembler::new(asm_verbose, asm_debug);
+        let programs = commands
+            .iter()
+            .skip(1)
+            .map(|file| load_program(&logger, &asm, file, source_format, verify_checksum))
+            .collect::<Vec<Program>>();
+        // each program runs on its own thread, reading what the one before it
+        // writes through a channel holding a single value, so a program
+        // writing faster than the next reads waits for it to catch up
+        let mut input: Option<ChannelInput> = None;
+        let mut stages = Vec::new();
+        // a program that fails cancels the rest rather than leave them waiting
+        let cancel = CancelToken::new();
+        for (i, program) in programs.into_iter().enumerate() {
+            let last = i == commands.len() - 2;
+            let (output, next) = match last {
+                true => (None, None),
+                false => {
+                    let (output, next) = io::pipe(1);
+                    (Some(output), Some(next))
+                }
+            };
+            let stage_input = std::mem::replace(&mut input, next);
+            let cancel = cancel.clone();
+            let prompt = prompt.clone();
+            stages.push(thread::spawn(move || {
+                // only the last program's outputs are printed, the first reads stdin
+                let mut lmc = LMC::new(vm_verbose, vm_debug, !last, 50000);
+                lmc.set_uninitialized_policy(uninitialized_policy);
+                lmc.set_poison(poison);
+                lmc.set_counter_wrap(counter_wrap);
+                set_stdin_options(&mut lmc, signed, prompt.as_deref(), input_retries);
+                if let Some(input) = stage_input {
+                    lmc.set_input(Box::new(input));
+                }
+                if let Some(output) = output {
+                    lmc.set_output(Box::new(output));
+                }
+                lmc.set_cancel_token(cancel.clone());
+                let result = lmc
+                    .load_program(&program)
+                    .map(|_| lmc.execute_program())
+                    .map_err(|err| err.to_string());
+                if !result.as_ref().is_ok_and(|result| result.is_halted()) {
+                    cancel.cancel();
+                }
+                result
+            }));
+        }
+        let mut failed = false;
+        for (program_file, stage) in commands.iter().skip(1).zip(stages) {
+            let error = match stage.join() {
+                Ok(Ok(result)) if result.is_halted() => continue,
+                Ok(Ok(result)) => result.to_string(),
+                Ok(Err(err)) => err,
+                Err(_) => "panicked".to_string(),
+            };
+            logger.log(&LogLevel::Error, &format!("{}: {}", program_file, error));
+            failed = true;
+        }
+        if failed {
+            exit(1);
+        }
+    } else if cmd == &"tournament".to_string() {
+        let dir = match commands.get(1) {
+            Some(dir) => dir,
+            None => {
+                print_usage();
+                return;
+            }
+        };
+        let spec = match flag_value(&flags, "spec") {
+            Some(spec) => spec,
+            None => {
+                print_usage();
+                return;
+            }
+        };
+        let format = match flag_value(&flags, "format") {
+            Some(format) => match LeaderboardFormat::from_str(&format) {
+                Some(format) => format,
+                None => {
+                    logger.log(
+                        &LogLevel::Error,
+                        &format!("Invalid leaderboard format: {}", format),
+                    );
+                    exit(1);
+                }
+            },
+            None => LeaderboardFormat::Markdown,
+        };
+        let spec_path = spec;
+        let mut spec = match tournament::load_spec(&spec_path) {
+            Ok(spec) => spec,
+            Err(err) => {
+                logger.log(&LogLevel::Error, &format!("{}", err));
+                exit(1);
+            }
+        };
+        // the seed flag overrides any seed the spec sets for random cases
+        if let Some(generate) = &mut spec.generate {
+            let seed = match (flag_value(&flags, "seed"), generate.seed) {
+                (None, Some(seed)) => seed,
+                _ => seed_flag(&logger, &flags),
+            };
+            eprintln!("Random cases seeded with {}", seed);
+            generate.seed = Some(seed);
+        }
+        let spec_dir = Path::new(&spec_path).parent().unwrap_or(Path::new("."));
+        match tournament::run_tournament(dir, &spec, spec_dir) {
+            Ok(standings) => print!("{}", tournament::leaderboard(&standings, format)),
+            Err(err) => {
+                logger.log(&LogLevel::Error, &format!("{}", err));
+                exit(1);
+            }
+        }
+    } else if cmd == &"exercise".to_string() {
+        // progress is kept next to the problem set unless told otherwise
+        let progress_file =
+            flag_value(&flags, "progress").unwrap_or("lmc-progress.toml".to_string());
+        let progress_path = Path::new(&progress_file);
+        let mut progress = match Progress::load(progress_path) {
+            Ok(progress) => progress,
+            Err(err) => {
+                logger.log(&LogLevel::Error, &format!("{}: {}", progress_file, err));
+                exit(1);
+            }
+        };
+        let (spec_file, program_file) = match (commands.get(1), commands.get(2)) {
+            (Some(status), None) if status.as_str() == "status" => {
+                print!("{}", progress.status());
+                return;
+            }
+            (Some(spec_file), Some(program_file)) => (spec_file, program_file),
+            _ => {
+                print_usage();
+                return;
+            }
+        };
+        let spec = match exercise::load_spec(Path::new(spec_file)) {
+            Ok(spec) => spec,
+            Err(err) => {
+                logger.log(&LogLevel::Error, &format!("{}: {}", spec_file, err));
+                exit(1);
+            }
+        };
+        let name = spec.name.clone().unwrap_or_default();
+        if let Some(description) = &spec.description {
+            println!("{}: {}", name, description.trim());
+        }
+        let program = load_program(
+            &logger,
+            &Assembler::new(asm_verbose, asm_debug),
+            program_file,
+            source_format,
+            verify_checksum,
+        );
+        let attempt = match exercise::check(&spec, &program) {
+            Ok(attempt) => attempt,
+            Err(err) => {
+                logger.log(&LogLevel::Error, &format!("{}: {}", spec_file, err));
+                exit(1);
+            }
+        };
+        match attempt.cycles {
+            Some(cycles) => println!(
+                "{}: solved, {}/{} cases passed in {} cycles",
+                name, attempt.passed, attempt.total, cycles
+            ),
+            None => println!(
+                "{}: {}/{} cases passed",
+                name, attempt.passed, attempt.total
+            ),
+        }
+        progress.record(&name, &attempt, exercise::now());
+        if let Err(err) = progress.save(progress_path) {
+            logger.log(&LogLevel::Error, &format!("{}: {}", progress_file, err));
+            exit(1);
+        }
+        if !attempt.is_solved() {
+            exit(1);
+        }
+    } else if cmd == &"bundle".to_string() {
+        match (
+            commands.get(1).map(|action| action.as_str()),
+            commands.get(2),
+            commands.get(3),
+        ) {
+            (Some("pack"), Some(dir), Some(bundle_file)) => {
+                let bundle = Bundle::pack(Path::new(dir)).unwrap_or_else(|err| {
+                    logger.log(&LogLevel::Error, &format!("{}: {}", dir, err));
+                    exit(1);
+                });
+                if let Err(err) = bundle.save(Path::new(bundle_file)) {
+                    logger.log(&LogLevel::Error, &format!("{}: {}", bundle_file, err));
+                    exit(1);
+                }
+                println!("Packed {} files into {}", bundle.files.len(), bundle_file);
+            }
+            (Some("unpack"), Some(bundle_file), dir) => {
+                let bundle = Bundle::load(Path::new(bundle_file)).unwrap_or_else(|err| {
+                    logger.log(&LogLevel::Error, &format!("{}: {}", bundle_file, err));
+                    exit(1);
+                });
+                // the bundle is unpacked into a directory named after it by default
+                let dir = dir.map_or(bundle.name.clone(), |dir| dir.to_string());
+                match bundle.unpack(Path::new(&dir)) {
+                    Ok(paths) => {
+                        for path in &paths {
+                            println!("{}", path.display());
+                        }
+                        println!("Unpacked {} files into {}", paths.len(), dir);
+                    }
+                    Err(err) => {
+                        logger.log(&LogLevel::Error, &format!("{}: {}", bundle_file, err));
+                        exit(1);
+                    }
+                }
+            }
+            _ => print_usage(),
+        }
+    } else if cmd == &"batch".to_string() {
+        let program_file = match commands.get(1) {
+            Some(file) => file,
+            None => {
+                print_usage();
+                return;
+            }
+        };
+        // tests come from the test file, stdin if it is -, and any --test flags
+        let inline_tests = flag_values(&flags, "test");
+        let test_file = commands.get(2);
+        if test_file.is_none() && inline_tests.is_empty() {
+            print_usage();
+            return;
+        }
+        // assembly source is assembled so that tests can use its labels as entry points
+        let mut asm = Assembler::new(asm_verbose, asm_debug);
+        asm.set_partial(partial);
+        let program = load_program(&logger, &asm, program_file, source_format, verify_checksum);
+        let warnings = program
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.to_string())
+            .collect::<Vec<String>>();
+        // a .toml test file is in the TOML format, anything else semicolon separated
+        let (mut tests, mut matrix) = match test_file {
+            Some(test_file) if tests::is_toml(test_file) => match tests::load(test_file) {
+                Ok(suite) => (suite.tests, suite.matrix),
+                Err(err) => {
+                    logger.log(&LogLevel::Error, &format!("{}: {}", test_file, err));
+                    exit(1);
+                }
+            },
+            Some(test_file) => (
+                parse_tests(&logger, &read_lines(&logger, test_file)),
+                Vec::new(),
+            ),
+            None => (Vec::new(), Vec::new()),
+        };
+        tests.extend(parse_tests(&logger, &inline_tests));
+        // the matrix flag replaces the dialects the test file runs on
+        if let Some(dialects) = flag_value(&flags, "matrix") {
+            matrix = dialects
+                .split(',')
+                .map(|dialect| match Dialect::from_str(dialect.trim()) {
+                    Some(dialect) => dialect,
+                    None => {
+                        logger.log(&LogLevel::Error, &format!("Unknown dialect: {}", dialect));
+                        exit(1);
+                    }
+                })
+                .collect();
+        }
+        let mut batch_logger = Logger::new(
+            verbose.contains(&Component::Batch),
+            debug.contains(&Component::Batch),
+        );
+        batch_logger.set_component(Component::Batch);
+        batch_logger.log(&LogLevel::Info, &format!("loaded {} tests", tests.len()));
+        // a replayed case is run under the debugger
+        let replay = replay_flag(&logger, &flags);
+        // every dialect of the matrix gets a machine of its own, set up as
+        // the dialect after the flags
+        let new_lmc = |dialect: Option<Dialect>| {
+            let mut lmc = LMC::new(vm_verbose, vm_debug || replay.is_some(), true, 50000);
+            lmc.set_uninitialized_policy(uninitialized_policy);
+            lmc.set_poison(poison);
+            lmc.set_counter_wrap(counter_wrap);
+            lmc.set_io_limits(max_inputs, max_outputs);
+            lmc.set_resource_limits(max_time, max_mailboxes);
+            lmc.set_input_retention(InputRetention::Discard);
+            if devices {
+                attach_standard_devices(&logger, &flags, &mut lmc);
+            }
+            if let Some(dialect) = dialect {
+                dialect.configure(&mut lmc);
+            }
+            lmc
+        };
+        // the cycle flag replaces the limit of every test
+        if let Some(max_cycles) = max_cycles {
+            for test in tests.iter_mut() {
+                test.cycles = max_cycles;
+            }
+        }
+        let seed = seed_flag(&logger, &flags);
+        let samples = numeric_flag(&logger, &flags, "samples").unwrap_or(10000);
+        let report_format = match flag_value(&flags, "report") {
+            Some(format) => match ReportFormat::from_str(&format) {
+                Some(format) => format,
+                None => {
+                    logger.log(
+                        &LogLevel::Error,
+                        &format!("Invalid report format: {}", format),
+                    );
+                    exit(1);
+                }
+            },
+            None => ReportFormat::Text,
+        };
+        let baseline = flag_value(&flags, "baseline").map(|path| match Report::load(&path) {
+            Ok(baseline) => baseline,
+            Err(err) => {
+                logger.log(&LogLevel::Error, &format!("{}: {}", path, err));
+                exit(1);
+            }
+        });
+        let threshold = numeric_flag(&logger, &flags, "threshold").unwrap_or(10) as f64;
+        // any report but text is the only thing written to stdout so it can be
+        // piped
+        let text = report_format == ReportFormat::Text;
+        let progress = |event: SuiteEvent| match event {
+            SuiteEvent::TestStarted { test } => {
+                if text {
+                    println!("Running test: {} [{} max cycles]", test.name, test.cycles);
+                    if test
+                        .domain
+                        .as_ref()
+                        .is_some_and(|domain| !domain.is_exhaustive())
+                    {
+                        match replay {
+                            Some(replay) => println!(
+                                "[{}] replaying the random inputs with seed {}",
+                                test.name, replay
+                            ),
+                            None => println!(
+                                "[{}] testing {} random inputs with seed {}",
+                                test.name, samples, seed
+                            ),
+                        }
+                    }
+                }
+                if let Some(input) = &test.input {
+                    batch_logger.log(
+                        &LogLevel::Debug,
+                        &format!("[{}] loading {} inputs", test.name, input.len()),
+                    );
+                }
+            }
+            SuiteEvent::IterationFinished {
+                test,
+                iteration,
+                result,
+            } => {
+                batch_logger.log(
+                    &LogLevel::Debug,
+                    &format!(
+                        "[{}] case {} with inputs {:?}: {}",
+                        test.name,
+                        iteration,
+                        result.inputs,
+                        match result.passed {
+                            true => "passed",
+                            false => "failed",
+                        }
+                    ),
+                );
+            }
+            // show that a long running test is still making progress
+            SuiteEvent::CyclesElapsed { test, cycles } => {
+                batch_logger.log(
+                    &LogLevel::Info,
+                    &format!("[{}] {} cycles so far", test.name, cycles),
+                );
+            }
+            SuiteEvent::Shrunk { test, from, to } => {
+                let join = |values: &[i16]| {
+                    values
+                        .iter()
+                        .map(|value| format!("{:03}", value))
+                        .collect::<Vec<String>>()
+                        .join(",")
+                };
+                if text {
+                    println!(
+                        "[{}] shrunk counterexample from inputs {} to {}",
+                        test.name,
+                        join(from),
+                        join(to)
+                    );
+                }
+            }
+            SuiteEvent::TestSkipped { test } => {
+                if text {
+                    println!("Skipping test: {}", test.name);
+                }
+            }
+            SuiteEvent::TestFinished { test, run, result } => {
+                if text {
+                    println!("[{}] {}", test.name, run);
+                    match &test.domain {
+                        Some(domain) if result.passed && domain.is_exhaustive() => {
+                            println!("[{}] passed for all {} inputs", test.name, domain.size())
+                        }
+                        Some(_) if result.passed && replay.is_some() => {
+                            println!("[{}] passed for the replayed inputs", test.name)
+                        }
+                        Some(_) if result.passed => {
+                            println!("[{}] passed for {} random inputs", test.name, samples)
+                        }
+                        _ => {}
+                    }
+                }
+                match (result.status(), &result.xfail) {
+                    (Status::Failed, _) if text => {
+                        let (got, expected) = result.describe();
+                        let inputs = result
+                            .inputs
+                            .iter()
+                            .map(|value| format!("{:03}", value.to_string()))
+                            .collect::<Vec<String>>();
+                        batch_logger.log(
+                            &LogLevel::Error,
+                            &format!(
+                                "[{}] Incorrect result for inputs [{:?}]: got {}, expected {}",
+                                test.name, inputs, got, expected,
+                            ),
+                        );
+                        if let Some(seed) = result.seed {
+                            batch_logger.log(
+                                &LogLevel::Error,
+                                &format!("[{}] {}", test.name, replay_hint(seed)),
+                            );
+                        }
+                    }
+                    (Status::Passed, _) => {
+                        batch_logger.log(&LogLevel::Info, &format!("[{}] passed", test.name))
+                    }
+                    (Status::ExpectedFailure, Some(reason)) if text => {
+                        println!("[{}] failed as expected: {}", test.name, reason)
+                    }
+                    (Status::UnexpectedPass, Some(reason)) if text => batch_logger.log(
+                        &LogLevel::Warn,
+                        &format!(
+                            "[{}] unexpectedly passed, marked as expected to fail: {}",
+                            test.name, reason
+                        ),
+                    ),
+                    _ => {}
+                }
+            }
+        };
+        // without a matrix the tests run once, on the machine as configured
+        let dialects = match matrix.is_empty() {
+            true => vec![None],
+            false => matrix.into_iter().map(Some).collect(),
+        };
+        let mut report = Report::new();
+        for dialect in dialects {
+            if let (Some(dialect), true) = (dialect, text) {
+                println!("Running on the {} dialect", dialect);
+            }
+            let mut suite = match Suite::new(new_lmc(dialect), program.clone()) {
+                Ok(suite) => suite,
+                Err(err) => {
+                    logger.log(&LogLevel::Error, &format!("{}", err));
+                    exit(1);
+                }
+            };
+            for test in &tests {
+                suite.add(test.clone());
+            }
+            suite.set_sampling(seed, samples);
+            if let Some(replay) = replay {
+                suite.set_replay(replay);
+            }
+            match suite.run_suite_with(&progress) {
+                Ok(results) => {
+                    for mut result in results.results {
+                        result.dialect = dialect.map(|dialect| dialect.to_string());
+                        report.add(result);
+                    }
+                }
+                Err(err) => {
+                    logger.log(&LogLevel::Error, &format!("{}", err));
+                    exit(1);
+                }
+            }
+        }
+        let regressions = baseline
+            .as_ref()
+            .map(|baseline| report.compare(baseline, threshold));
+        match report_format {
+            ReportFormat::Text => {
+                print!("{}", report.summary());
+                if let Some(regressions) = &regressions {
+                    println!("Regressions against baseline: {}", regressions.len());
+                    for regression in regressions {
+                        batch_logger.log(&LogLevel::Error, &format!("{}", regression));
+                    }
+                }
+            }
+            ReportFormat::JSON => println!(
+                "{}",
+                serde_json::to_string_pretty(&report.to_json(regressions.as_deref()))
+                    .unwrap_or_default()
+            ),
+            ReportFormat::Prometheus => print!("{}", report.to_prometheus(regressions.as_deref())),
+            ReportFormat::Markdown => print!(
+                "{}",
+                report.to_markdown(program_file, &warnings, regressions.as_deref())
+            ),
+        }
+        // any test failing or any regression fails the batch, once every test has run
+        if report.count(Status::Failed) > 0
+            || regressions.is_some_and(|regressions| !regressions.is_empty())
+        {
+            exit(1);
+        }
+    } else {
+        print_usage();
+    }
+}
+
+// flag_value returns the value of a flag given in the form --name=value
+fn flag_value(flags: &[String], name: &str) -> Option<String> {
+    flag_values(flags, name).into_iter().next()
+}
+
+// flag_values returns the values of every occurrence of a flag given in the
+// form --name=value, for flags that may be repeated
+fn flag_values(flags: &[String], name: &str) -> Vec<String> {
+    flags
+        .iter()
+        .filter_map(|flag| {
+            flag.strip_prefix(name)
+                .and_then(|rest| rest.strip_prefix('='))
+                .map(|value| value.to_string())
+        })
+        .collect()
+}
+
+// attach_standard_devices maps the standard devices onto the top mailboxes:
+// a display at 97, a cycle counter at 98 and a random number generator at 99
+// seeded by the seed flag
+fn attach_standard_devices(logger: &Logger, flags: &[String], lmc: &mut LMC) {
+    let seed = seed_flag(logger, flags);
+    eprintln!("Random number generator seeded with {}", seed);
+    lmc.attach_device(97, Box::new(Display::new()));
+    lmc.attach_device(98, Box::new(CycleCounter));
+    // a stream of its own so that it does not repeat any random inputs
+    lmc.attach_device(99, Box::new(Rng::new(Rng::case_seed(seed, 0))));
+}
+
+// attach_keyboard maps a keyboard device to mailbox 96 and starts a thread
+// that reports the first character of every line typed on stdin as a key
+// press, using its character code
+fn attach_keyboard(lmc: &mut LMC) {
+    let (keyboard, handle) = Keyboard::new();
+    lmc.attach_device(96, Box::new(keyboard));
+    thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => return,
+            };
+            if let Some(key) = line.chars().next() {
+                handle.press(key as u32 as u16);
+            }
+        }
+    });
+}
+
+// resolve_entry returns the mailbox the entry label or address refers to
+// exiting with an error if the program has no such entry point
+fn resolve_entry(logger: &Logger, program: &Program, entry: &str) -> usize {
+    match program.resolve_address(entry) {
+        Some(address) => address,
+        None => {
+            logger.log(&LogLevel::Error, &format!("Unknown entry point: {}", entry));
+            exit(1);
+        }
+    }
+}
+
+// component_flag returns the components a verbosity flag applies to, every
+// component for -v or --verbose and only those listed for --verbose=vm,asm
+fn component_flag(logger: &Logger, flags: &[String], short: &str, name: &str) -> Vec<Component> {
+    if flags.contains(&short.to_string()) || flags.contains(&name.to_string()) {
+        return COMPONENTS.to_vec();
+    }
+    let list = match flag_value(flags, name) {
+        Some(list) => list,
+        None => return Vec::new(),
+    };
+    list.split(',')
+        .map(|component| match Component::from_str(component.trim()) {
+            Some(component) => component,
+            None => {
+                logger.log(
+                    &LogLevel::Error,
+                    &format!("Invalid component for --{}: {}", name, component),
+                );
+                exit(1);
+            }
+        })
+        .collect()
+}
+
+// numeric_flag returns the value of a flag given in the form --name=n
+// exiting with an error if the value is not a non-negative integer
+fn numeric_flag(logger: &Logger, flags: &[String], name: &str) -> Option<usize> {
+    let value = flag_value(flags, name)?;
+    match value.parse::<usize>() {
+        Ok(number) => Some(number),
+        Err(_) => {
+            logger.log(
+                &LogLevel::Error,
+                &format!("Invalid value for --{}: {}", name, value),
+            );
+            exit(1);
+        }
+    }
+}
+
+// read_lines reads a text file, or stdin if the path is -, into a vector of
+// lines, normalizing line endings and stray unicode from word processors and
+// warning if it had any
+fn read_lines(logger: &Logger, path: &str) -> Vec<String> {
+    let text = if path == "-" {
+        let mut text = String::new();
+        std::io::stdin().read_to_string(&mut text).map(|_| text)
+    } else {
+        fs::read_to_string(path)
+    };
+    let text = match text {
+        Ok(text) => text,
+        Err(err) => {
+            logger.log(&LogLevel::Error, &format!("{}", err));
+            exit(1);
+        }
+    };
+    let normalized = text::normalize(&text);
+    // the warning goes to stderr, so a run's redirected output is unaffected
+    if !normalized.changes.is_empty() {
+        logger.log(
+            &LogLevel::Warn,
+            &format!("{}: {}", path, normalized.changes.join(", ")),
+        );
+    }
+    normalized.lines
+}
+
+// assemble_source assembles the source lines, laid out in the given format,
+// into a program exiting on error
+fn assemble_source(
+    logger: &Logger,
+    asm: &Assembler,
+    source: &[String],
+    format: SourceFormat,
+) -> Program {
+    match asm.assemble_program(&mut source::to_free(source, format)) {
+        Ok(program) => {
+            for diagnostic in &program.diagnostics {
+                logger.log(&LogLevel::Warn, &format!("{}", diagnostic));
+            }
+            program
+        }
+        Err(err) => {
+            logger.log(&LogLevel::Error, &format!("{}", err));
+            exit(1);
+        }
+    }
+}
+
+// write_machine_code writes the assembled program to the output followed by
+// its checksum, annotating each mailbox with its source line if asked to
+fn write_machine_code(
+    output: &mut File,
+    source: &[String],
+    program: &Program,
+    annotate: bool,
+) -> std::io::Result<()> {
+    for (address, instruction) in program.instructions.iter().enumerate() {
+        if annotate {
+            // show the mailbox and the source line it was assembled from,
+            // comments removed and spacing collapsed
+            let line = match program.source_map.get(address) {
+                Some(line) => source[line - 1].split('#').next().unwrap_or(""),
+                None => "fill",
+            };
+            let text = line.split_whitespace().collect::<Vec<&str>>().join(" ");
+            writeln!(output, "{:02}  {}  # {}", address, instruction, text)?;
+        } else {
+            writeln!(output, "{}", instruction)?;
+        }
+    }
+    writeln!(output, "{}", Checksum::of(&program.instructions))
+}
+
+// assemble_all assembles every source file under the directory into a
+// machine code file of the same name under the output directory, keeping the
+// layout of subdirectories. Files that have not changed since they were last
+// assembled with the same options are skipped unless forced. Each file is
+// listed with how it went followed by every error found, exiting with an
+// error if any file failed.
+fn assemble_all(
+    logger: &Logger,
+    asm: &Assembler,
+    flags: &[String],
+    dir: &Path,
+    out: &Path,
+    format: SourceFormat,
+) {
+    let mut files = Vec::new();
+    if let Err(err) = source_files(dir, &mut files) {
+        logger.log(&LogLevel::Error, &format!("{}: {}", dir.display(), err));
+        exit(1);
+    }
+    files.sort();
+    let annotate = flags.contains(&"annotate".to_string());
+    let force = flags.contains(&"force".to_string());
+    let options = format!(
+        "annotate={} pad-to={:?} fill={:?}",
+        annotate,
+        flag_value(flags, "pad-to"),
+        flag_value(flags, "fill")
+    );
+    let cache_file = out.join(CACHE_FILE);
+    let mut cache = Cache::load(&cache_file);
+    let width = files
+        .iter()
+        .map(|file| file.display().to_string().len())
+        .max()
+        .unwrap_or(0);
+    let mut errors = Vec::new();
+    let (mut failed, mut unchanged) = (0, 0);
+    for file in &files {
+        let name = file.display().to_string();
+        let relative = file.strip_prefix(dir).unwrap_or(file);
+        let output_file = out.join(relative).with_extension("lmc");
+        let cached = relative.display().to_string();
+        let source = source::to_free(&read_lines(logger, &name), format);
+        let key = Cache::key(&source, &options);
+        if !force && cache.is_fresh(&cached, &key) && output_file.exists() {
+            println!(
+                "  cached  {:width$}  -> {}",
+                name,
+                output_file.display(),
+                width = width
+            );
+            unchanged += 1;
+            continue;
+        }
+        cache.remove(&cached);
+        let mut program = match asm.assemble_program(&mut source.clone()) {
+            Ok(program) if program.diagnostics.is_empty() => program,
+            Ok(program) => {
+                println!(
+                    "  failed  {:width$}  errors: {}",
+                    name,
+                    program.diagnostics.len(),
+                    width = width
+                );
+                errors.extend(
+                    program
+                        .diagnostics
+                        .iter()
+                        .map(|diagnostic| format!("{}: {}", name, diagnostic)),
+                );
+                failed += 1;
+                continue;
+            }
+            Err(err) => {
+                println!("  failed  {:width$}  errors: 1", name, width = width);
+                errors.push(format!("{}: {}", name, err));
+                failed += 1;
+                continue;
+            }
+        };
+        if let Some(size) = numeric_flag(logger, flags, "pad-to") {
+            pad_program(logger, flags, &mut program, size);
+        }
+        let written = output_file
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| File::create(&output_file))
+            .and_then(|mut output| write_machine_code(&mut output, &source, &program, annotate));
+        match written {
+            Ok(()) => {
+                println!(
+                    "  ok      {:width$}  -> {}  {} mailboxes",
+                    name,
+                    output_file.display(),
+                    program.instructions.len(),
+                    width = width
+                );
+                cache.insert(&cached, &key);
+            }
+            Err(err) => {
+                println!("  failed  {:width$}  not written", name, width = width);
+                errors.push(format!("{}: {}", output_file.display(), err));
+                failed += 1;
+            }
+        }
+    }
+    if !errors.is_empty() {
+        println!("Errors:");
+        for error in &errors {
+            println!("  {}", error);
+        }
+    }
+    if let Err(err) = fs::create_dir_all(out).and_then(|_| cache.save(&cache_file)) {
+        logger.log(
+            &LogLevel::Warn,
+            &format!("{}: {}", cache_file.display(), err),
+        );
+    }
+    println!(
+        "{} files: {} assembled, {} unchanged, {} failed",
+        files.len(),
+        files.len() - failed - unchanged,
+        unchanged,
+        failed
+    );
+    if failed > 0 {
+        exit(1);
+    }
+}
+
+// source_files adds the path of every assembly source file under the
+// directory to the list
+fn source_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            source_files(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "asm") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+// pad_program fills the mailboxes after the program with the value of the
+// fill flag, 000 unless given, until it is the given size
+fn pad_program(logger: &Logger, flags: &[String], program: &mut Program, size: usize) {
+    if size > 100 || size < program.instructions.len() {
+        logger.log(
+            &LogLevel::Error,
+            &format!(
+                "Invalid value for --pad-to: {}, the program needs {} to 100 mailboxes",
+                size,
+                program.instructions.len()
+            ),
+        );
+        exit(1);
+    }
+    let fill = flag_value(flags, "fill").unwrap_or_else(|| "000".to_string());
+    let fill = match fill.parse::<i16>().map(ThreeDigitNumber::new) {
+        Ok(Ok(fill)) => fill,
+        _ => {
+            logger.log(
+                &LogLevel::Error,
+                &format!("Invalid value for --fill: {}", fill),
+            );
+            exit(1);
+        }
+    };
+    program.instructions.resize(size, fill);
+}
+
+// inputs_flag returns the comma separated numbers given by the inputs flag
+fn inputs_flag(logger: &Logger, flags: &[String]) -> Option<Vec<i16>> {
+    let inputs = flag_value(flags, "inputs")?;
+    let values = inputs
+        .split(',')
+        .map(|value| match value.trim().parse::<i16>() {
+            Ok(value) => value,
+            Err(_) => {
+                logger.log(&LogLevel::Error, &format!("Invalid input: {}", value));
+                exit(1);
+            }
+        })
+        .collect();
+    Some(values)
+}
+
+// set_stdin_options sets how the LMC reads values typed on stdin: whether
+// they are signed, the prompt shown for each, if any, and how many times an
+// invalid one is asked for again, so that every command reading stdin
+// honours --signed, --prompt, --no-prompt and --input-retries alike
+fn set_stdin_options(lmc: &mut LMC, signed: bool, prompt: Option<&str>, retries: Option<usize>) {
+    lmc.set_signed(signed);
+    lmc.set_prompt(prompt.map(String::from));
+    if let Some(retries) = retries {
+        lmc.set_input_retries(retries);
+    }
+}
+
+// set_input_source has IN read the numbers given by the inputs flag, over
+// and over with the cycle-inputs flag, or random numbers from the range given
+// by the random-inputs flag, in place of stdin
+fn set_input_source(logger: &Logger, flags: &[String], lmc: &mut LMC, signed: bool) {
+    let number = |value: i16| {
+        let number = match signed {
+            true => ThreeDigitNumber::from_signed(value),
+            false => ThreeDigitNumber::new(value),
+        };
+        number.unwrap_or_else(|err| {
+            logger.log(&LogLevel::Error, &format!("Invalid input: {}", err));
+            exit(1);
+        })
+    };
+    if let Some(inputs) = inputs_flag(logger, flags) {
+        let inputs = inputs.into_iter().map(number).collect();
+        match flags.contains(&"cycle-inputs".to_string()) {
+            true => lmc.set_input(Box::new(CyclicInput::new(inputs))),
+            false => lmc.set_input(Box::new(FixedInput::new(inputs))),
+        }
+    } else if let Some(range) = flag_value(flags, "random-inputs") {
+        let (min, max) = match range
+            .split_once("..")
+            .map(|(min, max)| (min.parse::<i16>(), max.parse::<i16>()))
+        {
+            Some((Ok(min), Ok(max))) if min <= max => (min, max),
+            _ => {
+                logger.log(
+                    &LogLevel::Error,
+                    &format!("Invalid value for --random-inputs: {}", range),
+                );
+                exit(1);
+            }
+        };
+        // both ends must be valid inputs, negative only with --signed
+        number(min);
+        number(max);
+        // the seed goes to stderr so stdout only holds the program's output
+        let seed = seed_flag(logger, flags);
+        eprintln!("Random inputs seeded with {}", seed);
+        lmc.set_input(Box::new(RandomInput::new(seed, min..=max)));
+    }
+}
+
+// seed_flag returns the seed given by the seed flag, otherwise one taken
+// from the clock, the one seed behind everything random in a run. Commands
+// using it print the seed so that a run can be repeated.
+fn seed_flag(logger: &Logger, flags: &[String]) -> u64 {
+    // every use in a run shares the seed, so the one printed repeats it all
+    static SEED: OnceLock<u64> = OnceLock::new();
+    *SEED.get_or_init(|| match numeric_flag(logger, flags, "seed") {
+        Some(seed) => seed as u64,
+        None => Rng::random_seed(),
+    })
+}
+
+// print_dry_run prints every error and warning found assembling the source,
+// the symbol table and how much of memory the program fills
+fn print_dry_run(logger: &Logger, source: &[String], program: &Program) {
+    for diagnostic in &program.diagnostics {
+        logger.log(&LogLevel::Error, &format!("{}", diagnostic));
+    }
+    let unused = unused_labels(source, program);
+    for (line, label) in &unused {
+        logger.log(
+            &LogLevel::Warn,
+            &format!("line {}: label {} is never used", line, label),
+        );
+    }
+    let mut symbols = program.labels.iter().collect::<Vec<(&String, &usize)>>();
+    symbols.sort_by_key(|(label, address)| (**address, label.as_str()));
+    if !symbols.is_empty() {
+        println!("Symbols:");
+        let width = symbols
+            .iter()
+            .map(|(label, _)| label.len())
+            .max()
+            .unwrap_or(0);
+        for (label, address) in symbols {
+            println!("  {:width$}  {:02}", label, address, width = width);
+        }
+    }
+    let used = program.instructions.len();
+    let data = program.data.iter().filter(|data| **data).count();
+    println!(
+        "Size: {} of 100 mailboxes ({} instructions, {} DAT), {} free, {} assertions",
+        used,
+        used - data,
+        data,
+        100 - used,
+        program.assertions.len()
+    );
+    println!(
+        "{} errors, {} warnings",
+        program.diagnostics.len(),
+        unused.len()
+    );
+}
+
+// unused_labels returns the line and name of every label the program
+// defines that is never used
+fn unused_labels(source: &[String], program: &Program) -> Vec<(usize, String)> {
+    // a line that failed to assemble may look like it defines a label
+    syntax::unused_labels(&syntax::tokenize(source))
+        .into_iter()
+        .filter(|(_, label)| program.labels.contains_key(label))
+        .collect()
+}
+
+// print_diagnostics_json prints an error that stopped assembly, or the
+// errors recovered from and warnings for unused labels, as a JSON array of
+// diagnostics in source order
+fn print_diagnostics_json(
+    source: &[String],
+    err: Option<&AssemblerError>,
+    errors: &[Diagnostic],
+    unused: &[(usize, String)],
+) {
+    let mut diagnostics = errors
+        .iter()
+        .map(|diagnostic| diagnostic.to_json("error"))
+        .collect::<Vec<Value>>();
+    for (line, label) in unused {
+        let column = source[line - 1].find(label.as_str()).unwrap_or(0) + 1;
+        diagnostics.push(json!({
+            "severity": "warning",
+            "line": line,
+            "column": column,
+            "code": "unused-label",
+            "message": format!("label {} is never used", label),
+        }));
+    }
+    match err {
+        Some(AssemblerError::Located(span, err)) => diagnostics.push(
+            Diagnostic {
+                span: *span,
+                code: err.code(),
+                message: err.to_string(),
+            }
+            .to_json("error"),
+        ),
+        Some(err) => diagnostics.push(json!({
+            "severity": "error",
+            "line": null,
+            "column": null,
+            "code": err.code(),
+            "message": err.to_string(),
+        })),
+        None => (),
+    }
+    diagnostics.sort_by_key(|diagnostic| {
+        (
+            diagnostic["line"].as_u64().unwrap_or(0),
+            diagnostic["column"].as_u64().unwrap_or(0),
+        )
+    });
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&json!({
+            "schema": DIAGNOSTICS_SCHEMA,
+            "diagnostics": diagnostics,
+        }))
+        .unwrap_or_default()
+    );
+}
+
+// replay_flag returns the seed of a single generated case to replay given by
+// the replay flag
+fn replay_flag(logger: &Logger, flags: &[String]) -> Option<u64> {
+    numeric_flag(logger, flags, "replay").map(|seed| seed as u64)
+}
+
+// replay_hint describes how to reproduce the generated case with the seed:
+// the command line lmc was run with, with any seed or replay flag replaced by
+// one replaying just that case
+fn replay_hint(seed: u64) -> String {
+    let mut words = vec!["lmc".to_string()];
+    words.extend(env::args().skip(1).filter(|arg| {
+        let flag = arg.trim_start_matches('-');
+        !arg.starts_with('-') || !(flag.starts_with("seed=") || flag.starts_with("replay="))
+    }));
+    words.push(format!("--replay={}", seed));
+    // quote any word the shell would otherwise split or expand
+    let quoted = words
+        .iter()
+        .map(|word| {
+            let plain = !word.is_empty()
+                && word
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c));
+            match plain {
+                true => word.clone(),
+                false => format!("'{}'", word.replace('\'', "'\\''")),
+            }
+        })
+        .collect::<Vec<String>>();
+    format!(
+        "generated from seed {}, replay with: {}",
+        seed,
+        quoted.join(" ")
+    )
+}
+
+// fix_source repeatedly assembles the source and, while it fails with an
+// error that has a suggested fix, asks on stderr whether to apply the fix to
+// each line containing the mistake, returning the source once it assembles
+// or no further fixes are accepted
+fn fix_source(
+    logger: &Logger,
+    asm: &Assembler,
+    mut source: Vec<String>,
+    format: SourceFormat,
+) -> Vec<String> {
+    let stdin = std::io::stdin();
+    loop {
+        let err = match asm.assemble_program(&mut source::to_free(&source, format)) {
+            Ok(_) => return source,
+            Err(err) => err,
+        };
+        let (word, suggestion) = match err.fix() {
+            Some((word, suggestion)) => (word.to_string(), suggestion.to_string()),
+            None => return source,
+        };
+        let mut fixed = false;
+        for (i, line) in source.iter_mut().enumerate() {
+            let replaced = refactor::replace_word(line, &word, &suggestion);
+            if replaced == *line {
+                continue;
+            }
+            eprint!(
+                "{}\n{:>4}: {}\nreplace {} with {}? [y/N] ",
+                err,
+                i + 1,
+                line,
+                word,
+                suggestion
+            );
+            let mut answer = String::new();
+            if stdin.read_line(&mut answer).is_err() {
+                return source;
+            }
+            if answer.trim().eq_ignore_ascii_case("y") {
+                *line = replaced;
+                fixed = true;
+            }
+        }
+        if !fixed {
+            logger.log(&LogLevel::Warn, "no fixes applied");
+            return source;
+        }
+    }
+}
+
+// load_program assembles the file if it is assembly source ending in .asm,
+// otherwise it is loaded as machine code
+fn load_program(
+    logger: &Logger,
+    asm: &Assembler,
+    path: &str,
+    format: SourceFormat,
+    verify: bool,
+) -> Program {
+    if path.ends_with(".asm") {
+        assemble_source(logger, asm, &read_lines(logger, path), format)
+    } else {
+        Program::from_instructions(parse_program_file(logger, path, verify))
+    }
+}
+
+// load_debug_info reads the debug info given by --debug-info, if any, and
+// applies it to the program loaded from machine code. Debug info written for
+// another image is warned about and left out.
+fn load_debug_info(logger: &Logger, flags: &[String], program: &mut Program) -> Option<DebugInfo> {
+    let path = flag_value(flags, "debug-info")?;
+    let info = DebugInfo::load(&path).unwrap_or_else(|err| {
+        logger.log(&LogLevel::Error, &format!("{}: {}", path, err));
+        exit(1);
+    });
+    if let Some(problem) = info.verify(&program.instructions) {
+        logger.log(
+            &LogLevel::Warn,
+            &format!("{} {}, running without it", path, problem),
+        );
+        return None;
+    }
+    info.apply(program);
+    Some(info)
+}
+
+// parse_program_file reads a machine code file, skipping the checksum line
+// written by the assembler unless asked to verify it
+fn parse_program_file(logger: &Logger, program_file: &str, verify: bool) -> Vec<ThreeDigitNumber> {
+    let lines = read_lines(logger, program_file);
+    let checksum = lines.iter().find_map(|line| Checksum::parse(line));
+    let input = lines
+        .iter()
+        .filter_map(|line| match program::machine_code_value(line) {
+            Ok(number) => number,
+            Err(err) => {
+                logger.log(&LogLevel::Error, &err);
+                exit(1);
+            }
+        })
+        .map(|instruction| match ThreeDigitNumber::new(instruction) {
+            Ok(number) => number,
+            Err(err) => {
+                logger.log(&LogLevel::Error, &format!("{}", err));
+                exit(1);
+            }
+        })
+        .collect::<Vec<ThreeDigitNumber>>();
+    if verify {
+        let problem = match checksum {
+            Some(checksum) => checksum.verify(&input),
+            None => Some("has no checksum line".to_string()),
+        };
+        if let Some(problem) = problem {
+            logger.log(&LogLevel::Warn, &format!("{} {}", program_file, problem));
+        }
+    }
+    input
+}
+
+// parse_tests parses tests in the form name;inputs;result;max_cycles[;entry]
+// one per line, exiting on error
+fn parse_tests(logger: &Logger, lines: &[String]) -> Vec<Test> {
+    let mut tests = Vec::new();
+    for line in lines.iter().filter(|line| !line.trim().is_empty()) {
+        let mut parts = line
+            .trim()
+            .split(";")
+            .map(|part| part.trim().to_string())
+            .collect::<Vec<String>>();
+        // skip = true and xfail = "reason" may be given after the required
+        // fields, in place of or after the optional ones
+        let mut marker = None;
+        let mut i = 4;
+        while i < parts.len() {
+            match parts[i]
+                .split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+            {
+                Some(("skip", value)) => {
+                    if value == "true" {
+                        marker = Some(Marker::Skip);
+                    } else if value != "false" {
+                        logger.log(&LogLevel::Error, &format!("Invalid skip marker: {}", value));
+                        exit(1);
+                    }
+                }
+                Some(("xfail", reason)) => {
+                    let reason = reason.trim_matches('"').to_string();
+                    marker = Some(Marker::ExpectedFailure(reason));
+                }
+                _ => {
+                    i += 1;
+                    continue;
+                }
+            }
+            parts.remove(i);
+        }
+        let parts_len = parts.len();
+        if parts_len < 4 {
+            logger.log(
+                &LogLevel::Error,
+                &format!("Invalid test file format: {}", line),
+            );
+            exit(1);
+        }
+        let name = &parts[0];
+        let cycles = match parts[3].parse::<usize>() {
+            Ok(cycles) => cycles,
+            Err(err) => {
+                logger.log(
+                    &LogLevel::Error,
+                    &format!("Invalid number of cycles: {}", err),
+                );
+                exit(1);
+            }
+        };
+        // inputs given as ranges are an input domain tested exhaustively
+        let domain =
+            Domain::is_domain(&parts[1]).then(|| match Domain::parse(&parts[1], &parts[2]) {
+                Ok(domain) => domain,
+                Err(err) => {
+                    logger.log(&LogLevel::Error, &format!("{}: {}", name, err));
+                    exit(1);
+                }
+            });
+        let input_values = parts[1]
+            .split(",")
+            .filter(|_| domain.is_none())
+            .map(|part| match part.trim().parse::<i16>() {
+                Ok(value) => value,
+                Err(err) => {
+                    logger.log(&LogLevel::Error, &format!("Invalid input value: {}", err));
+                    exit(1);
+                }
+            })
+            .map(|value| match ThreeDigitNumber::new(value) {
+                Ok(number) => number,
+                Err(err) => {
+                    logger.log(&LogLevel::Error, &format!("{}", err));
+                    exit(1);
+                }
+            })
+            .collect::<Vec<ThreeDigitNumber>>();
+        // the result may list several outputs, an empty field or - means no
+        // output is expected
+        let no_output = matches!(parts[2].trim(), "" | "-");
+        let test_result = parts[2]
+            .split(",")
+            .filter(|_| domain.is_none() && !no_output)
+            .map(|part| match part.trim().parse::<i16>() {
+                Ok(value) => value,
+                Err(err) => {
+                    logger.log(
+                        &LogLevel::Error,
+                        &format!("Invalid expected value: {}: {}", part.trim(), err),
+                    );
+                    exit(1);
+                }
+            })
+            .map(|value| match ThreeDigitNumber::new(value) {
+                Ok(number) => number,
+                Err(err) => {
+                    logger.log(&LogLevel::Error, &format!("{}", err));
+                    exit(1);
+                }
+            })
+            .collect::<Vec<ThreeDigitNumber>>();
+        // the entry point is an optional fifth field
+        let entry = parts
+            .get(4)
+            .map(|entry| entry.trim().to_string())
+            .filter(|entry| !entry.is_empty());
+        let mut test = if input_values.is_empty() {
+            Test::new(name, None, test_result, cycles, entry)
+        } else {
+            Test::new(name, Some(input_values), test_result, cycles, entry)
+        };
+        if let Some(domain) = domain {
+            test.set_domain(domain);
+        }
+        // the comparator is an optional sixth field
+        if let Some(comparator) = parts.get(5).filter(|comparator| !comparator.is_empty()) {
+            match Comparator::from_str(comparator) {
+                Some(comparator) => test.set_comparator(comparator),
+                None => {
+                    logger.log(
+                        &LogLevel::Error,
+                        &format!("Invalid comparator: {}", comparator),
+                    );
+                    exit(1);
+                }
+            }
+        }
+        if let Some(marker) = marker {
+            test.set_marker(marker);
         }
+        tests.push(test);
     }
 
     tests
@@ -319,12 +2666,120 @@ fn print_usage() {
     println!();
     println!("Commands:");
     println!("\tassemble <input file> <output file>");
+    println!("\tassemble <input file> --dry-run");
+    println!("\tassemble --all <source directory> --out=<output directory> [--force]");
     println!("\texecute <input file>");
-    println!("\tbatch <program file> <batch file>");
+    println!("\trun <source file>");
+    println!("\tresume <state file>");
+    println!("\tdebug <program file>");
+    println!("\ttui <program file>");
+    println!("\tbatch <program file> [batch file|-] [--test=<test>...]");
+    println!("\tschedule <program file>...");
+    println!("\tpipe <program file>...");
+    println!("\ttournament <directory> --spec=<spec file>");
+    println!("\texercise <spec file> <program file> [--progress=<file>]");
+    println!("\texercise status [--progress=<file>]");
+    println!("\tbundle pack <directory> <bundle file>");
+    println!("\tbundle unpack <bundle file> [directory]");
+    println!("\tplay <session file>");
+    println!("\tannotate <input file> --trace=<trace file>");
+    println!("\tprofile <program file> --trace=<trace file>");
+    println!("\tmetrics <program file>...");
+    println!("\tsimilarity <directory>");
+    println!("\tcanonicalize <program file>");
+    println!("\tgrep <query> <program file>...");
+    println!("\trefactor rename <old label> <new label> <source file>");
+    println!("\trefactor move-dat <source file> [--to=<start>..<end>]");
+    println!("\tpatch <program file> [patch file] [--set=<nn>,<old>,<new>...] [--replace=<old>,<new>...]");
+    println!("\tformat <source file>");
+    println!("\tparse <source file>");
+    println!("\tselftest --fuzz [--iterations=<n>] [--seed=<n>] [--replay=<seed>]");
+    println!("\tverify-vm [--iterations=<n>] [--seed=<n>] [--replay=<seed>]");
+    println!("\tconformance [--dialect=<classic|extended|higginson>] [--suite=<file>]");
+    println!("\tcompare-modes <program file> --a=<dialect> --b=<dialect> [--inputs=<n,...>]");
+    println!("\thighlight [--format=<tmlanguage|vim|pygments>]");
+    println!("\tschema <trace|state|report|syntax|conformance|bundle|debug-info|diagnostics>");
     println!();
     println!("Flags:");
     println!("\t-h, --help\tShow this help message");
-    println!("\t-v, --verbose\tShow verbose output");
-    println!("\t-d, --debug\tShow debug output");
+    println!("\t--version\tShow the version, git commit and dialects of this build");
+    println!("\t-v, --verbose[=<vm,asm,batch>]\tShow verbose output, optionally only for some components");
+    println!(
+        "\t-d, --debug[=<vm,asm,batch>]\tShow debug output, optionally only for some components"
+    );
+    println!("\t--source-format=<free|fixed>\tLayout of assembly source (default free)");
+    println!("\t--annotate\tWrite assemble's output with each mailbox's address and source line");
+    println!("\t--listing=<file>\tWrite a listing of assemble's output beside the source, with the symbol table");
+    println!("\t--debug-info=<file>\tWrite debug info tying assemble's output to the source, or read it for execute and debug");
+    println!("\t--pad-to=<n>\tPad assemble's output to n mailboxes, filled with --fill=<value> (default 000)");
+    println!("\t--dry-run\tCheck assemble's input, showing every error, the symbols and size, without writing output");
+    println!("\t--all\tAssemble every .asm file under a directory into --out=<directory>, keeping its layout");
+    println!("\t--force\tAssemble every file with --all, even those unchanged since last time");
+    println!("\t--diagnostics=<text|json>\tFormat of assemble's errors and warnings, json printing them to stdout as an lmc/diagnostics@1 document (default text)");
+    println!("\t--fix\tOffer to fix misspelt opcodes and labels when assembling");
+    println!("\t--partial\tAssemble what can be of invalid source for metrics, grep and batch");
+    println!("\t--iterations=<n>\tCases generated by selftest --fuzz and verify-vm (default 200)");
+    println!("\t--seed=<n>\tSeed for everything random in a run: generated cases, random inputs and the random number generator device (default the clock, printed)");
+    println!("\t--replay=<seed>\tRe-run only the generated case with the seed a failure printed, under the debugger");
+    println!("\t--in-place\tRewrite the file given to refactor or patch instead of printing it");
+    println!("\t--explain\tShow on stderr why each branch was or was not taken");
+    println!("\t--checked\tEvaluate ASSERT pseudo-instructions when using run");
+    println!("\t--strict\tError when run executes or branches into a DAT cell");
+    println!(
+        "\t--teaching\tFollow a runtime error of execute or run with a hint at its likely cause"
+    );
+    println!("\t--signed\tAccept and show negative numbers using ten's complement");
+    println!("\t--uninitialized=<ignore|warn|error>\tHandle reads of uninitialized mailboxes");
+    println!(
+        "\t--inputs=<n,...>\tInputs read by execute or run in place of stdin, or by compare-modes"
+    );
+    println!("\t--cycle-inputs\tRead the inputs given by --inputs over and over");
+    println!("\t--random-inputs=<min>..<max>\tRead random inputs from the range, seeded by --seed, in place of stdin");
+    println!(
+        "\t--outputs=<file>\tWrite the values output by execute or run to a file, one per line"
+    );
+    println!("\t--poison[=<value>]\tLoad the mailboxes after the program with value (default 400) instead of 000");
+    println!(
+        "\t--counter-wrap=<wrap|warn|error>\tHandle the counter passing mailbox 99 (default warn)"
+    );
+    println!("\t--devices\tMap a display, cycle counter and RNG to mailboxes 97-99");
+    println!("\t--keyboard\tMap a keyboard to mailbox 96, fed by lines typed on stdin or by keys pressed in debug and tui");
+    println!("\t--verify-checksum\tWarn when a machine code file's checksum does not match");
+    println!("\t--start-at=<nn>\tStart execute or run at mailbox nn instead of 00");
+    println!("\t--entry=<label>\tStart run at the mailbox of the label instead of 00");
+    println!("\t--print-state[=<full|nonzero|json>]\tPrint the machine state after execute or run");
+    println!("\t--checkpoint=<file>\tSave the machine state after execute, run or resume to resume later");
+    println!("\t--stats\tShow the cycles, I/O and mailboxes used by execute or run on stderr");
+    println!("\t--prompt=<text>\tText of the prompt shown on stderr for input (default Input)");
+    println!("\t--no-prompt\tDo not show a prompt when reading input");
+    println!("\t--input-retries=<n>\tRe-prompt for invalid input n times (default 3)");
+    println!("\t--max-cycles=<n>\tStop execute, run, debug or tui after n cycles (default 50000), or every batch test, 0 for no limit");
+    println!("\t--max-inputs=<n>\tError after more than n IN operations in a run");
+    println!("\t--max-outputs=<n>\tError after more than n OUT operations in a run");
+    println!("\t--max-time=<ms>\tError when a run takes longer than ms milliseconds");
+    println!("\t--max-mailboxes=<n>\tError when a run touches more than n distinct mailboxes");
+    println!("\t--test=<name;inputs;result;max_cycles>\tAdd a batch test without a test file");
+    println!(
+        "\t--report=<text|json|prometheus|markdown>\tFormat of the batch report (default text)"
+    );
+    println!("\t--samples=<n>\tRandom inputs tested for input domains too large to test exhaustively (default 10000)");
+    println!("\t--matrix=<dialect,...>\tRun every batch test on each of the dialects");
+    println!("\t--baseline=<report>\tFail batch on regressions against a saved JSON report");
+    println!("\t--threshold=<n>\tPercent more cycles allowed against the baseline (default 10)");
+    println!("\t--slice=<n>\tCycles each program runs per turn when scheduled (default 10)");
+    println!("\t--format=<markdown|csv>\tLeaderboard format for tournament (default markdown)");
+    println!(
+        "\t--format=<tmlanguage|vim|pygments>\tSyntax definition format for highlight (default tmlanguage)"
+    );
+    println!(
+        "\t--record-session=<file>\tRecord every cycle of execute, run or tui to a session file"
+    );
+    println!(
+        "\t--trace=<file>\tWrite a JSON trace of execute, or read one for annotate and profile"
+    );
+    println!(
+        "\t--speed=<n>\tPlay back a session, or run in tui, at n frames per second (default 4)"
+    );
+    println!("\t--plain\tShow debug, tui and play as plain lines of text without grids or styles, for screen readers");
     exit(0);
 }
@@ -0,0 +1,182 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::program::Program;
+
+// Metrics are static measurements of a program, used to compare the style of
+// different solutions to the same problem
+#[derive(Debug, PartialEq, Clone)]
+pub struct Metrics {
+    // instructions is the number of mailboxes holding instructions
+    pub instructions: usize,
+    // data is the number of mailboxes holding DAT values
+    pub data: usize,
+    pub labels: usize,
+    // branches is the number of BR, BRZ and BRP instructions
+    pub branches: usize,
+    // branch_density is the fraction of instructions that are branches
+    pub branch_density: f64,
+    // loop_nesting is the deepest nesting of loops in the control flow graph
+    pub loop_nesting: usize,
+    // complexity is the cyclomatic complexity estimated from the control
+    // flow graph as the number of conditional branches plus one
+    pub complexity: usize,
+}
+
+// successors returns the addresses control can pass to after the instruction
+fn successors(address: usize, instruction: i16) -> Vec<usize> {
+    let next = address + 1;
+    let target = (instruction % 100) as usize;
+    match instruction / 100 {
+        0 => vec![],
+        6 => vec![target],
+        7 | 8 => vec![next, target],
+        _ => vec![next],
+    }
+}
+
+// Graph is the control flow graph of the instructions reachable from 00
+struct Graph {
+    edges: BTreeMap<usize, Vec<usize>>,
+}
+
+impl Graph {
+    // new builds the control flow graph of the program, a mailbox counts as
+    // an instruction if control can reach it from 00 and it is not DAT
+    fn new(program: &Program) -> Self {
+        let mut edges = BTreeMap::new();
+        let mut pending = vec![0];
+        while let Some(address) = pending.pop() {
+            if address >= program.instructions.len()
+                || program.is_data(address)
+                || edges.contains_key(&address)
+            {
+                continue;
+            }
+            let next = successors(address, program.instructions[address].value())
+                .into_iter()
+                .filter(|next| *next < program.instructions.len())
+                .collect::<Vec<usize>>();
+            pending.extend(next.iter().copied());
+            edges.insert(address, next);
+        }
+        Graph { edges }
+    }
+
+    // predecessors returns the addresses that can pass control to the address
+    fn predecessors(&self, address: usize) -> Vec<usize> {
+        self.edges
+            .iter()
+            .filter(|(_, next)| next.contains(&address))
+            .map(|(from, _)| *from)
+            .collect()
+    }
+
+    // loops returns the body of every natural loop keyed by its header, found
+    // from the back edges of a depth first search starting at 00
+    fn loops(&self) -> BTreeMap<usize, BTreeSet<usize>> {
+        let mut back_edges = Vec::new();
+        let mut visited = BTreeSet::new();
+        let mut on_stack = BTreeSet::new();
+        self.find_back_edges(0, &mut visited, &mut on_stack, &mut back_edges);
+        let mut loops: BTreeMap<usize, BTreeSet<usize>> = BTreeMap::new();
+        for (from, header) in back_edges {
+            let body = loops.entry(header).or_default();
+            body.insert(header);
+            let mut pending = vec![from];
+            while let Some(address) = pending.pop() {
+                if body.insert(address) {
+                    pending.extend(self.predecessors(address));
+                }
+            }
+        }
+        loops
+    }
+
+    // find_back_edges walks the graph depth first from the address recording
+    // every edge that leads back to an address still being walked
+    fn find_back_edges(
+        &self,
+        address: usize,
+        visited: &mut BTreeSet<usize>,
+        on_stack: &mut BTreeSet<usize>,
+        back_edges: &mut Vec<(usize, usize)>,
+    ) {
+        if !visited.insert(address) {
+            return;
+        }
+        on_stack.insert(address);
+        for next in self.edges.get(&address).into_iter().flatten() {
+            if on_stack.contains(next) {
+                back_edges.push((address, *next));
+            } else {
+                self.find_back_edges(*next, visited, on_stack, back_edges);
+            }
+        }
+        on_stack.remove(&address);
+    }
+}
+
+// loop_nesting returns the length of the longest chain of loops each nested
+// within the body of the next
+fn loop_nesting(loops: &BTreeMap<usize, BTreeSet<usize>>) -> usize {
+    let bodies = loops.values().collect::<Vec<&BTreeSet<usize>>>();
+    // depth is the nesting of each loop, computed smallest body first so that
+    // every loop it encloses has already been measured
+    let mut order = (0..bodies.len()).collect::<Vec<usize>>();
+    order.sort_by_key(|i| bodies[*i].len());
+    let mut depth = vec![0; bodies.len()];
+    for (n, &i) in order.iter().enumerate() {
+        depth[i] = 1 + order[..n]
+            .iter()
+            .filter(|&&j| bodies[j].len() < bodies[i].len() && bodies[j].is_subset(bodies[i]))
+            .map(|&j| depth[j])
+            .max()
+            .unwrap_or(0);
+    }
+    depth.into_iter().max().unwrap_or(0)
+}
+
+// natural_loops returns the body of every natural loop in the program keyed
+// by the address of its header
+pub fn natural_loops(program: &Program) -> BTreeMap<usize, BTreeSet<usize>> {
+    Graph::new(program).loops()
+}
+
+// measure computes the metrics of the program. When it was loaded from
+// machine code any mailbox control cannot reach is counted as data.
+pub fn measure(program: &Program) -> Metrics {
+    let graph = Graph::new(program);
+    let code = if program.data.is_empty() {
+        graph.edges.keys().copied().collect::<Vec<usize>>()
+    } else {
+        (0..program.instructions.len())
+            .filter(|address| !program.is_data(*address))
+            .collect::<Vec<usize>>()
+    };
+    let instructions = code.len();
+    let opcodes = code
+        .iter()
+        .map(|address| program.instructions[*address].value() / 100)
+        .collect::<Vec<i16>>();
+    let branches = opcodes
+        .iter()
+        .filter(|opcode| (6..=8).contains(*opcode))
+        .count();
+    let conditional = opcodes
+        .iter()
+        .filter(|opcode| (7..=8).contains(*opcode))
+        .count();
+    Metrics {
+        instructions,
+        data: program.instructions.len() - instructions,
+        labels: program.labels.len(),
+        branches,
+        branch_density: if instructions == 0 {
+            0.0
+        } else {
+            branches as f64 / instructions as f64
+        },
+        loop_nesting: loop_nesting(&graph.loops()),
+        complexity: conditional + 1,
+    }
+}
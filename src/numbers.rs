@@ -6,7 +6,8 @@ use std::{
 // NumberError is used to indicate an error with a number
 #[derive(Debug, PartialEq)]
 pub enum NumberError {
-    OutOfBounds(usize),
+    OutOfBounds(i32),
+    OutOfSignedBounds(i16),
 }
 
 // Implement the display trait for easy printing.
@@ -14,10 +15,17 @@ impl fmt::Display for NumberError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             NumberError::OutOfBounds(value) => write!(f, "number out of bounds: got {}", value),
+            NumberError::OutOfSignedBounds(value) => {
+                write!(f, "signed number out of bounds: got {}", value)
+            }
         }
     }
 }
 
+// Implement the error trait so the error can be used with the ? operator
+// alongside other errors.
+impl std::error::Error for NumberError {}
+
 // Flag are used to indicate the state of the last operation
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Flag {
@@ -41,18 +49,18 @@ pub struct ThreeDigitNumber(i16, Option<Flag>);
 
 impl ThreeDigitNumber {
     pub fn new(value: i16) -> Result<Self, NumberError> {
-        if 0 <= value && value <= 999 {
+        if (0..=999).contains(&value) {
             Ok(ThreeDigitNumber(value, None))
         } else {
-            Err(NumberError::OutOfBounds(value as usize))
+            Err(NumberError::OutOfBounds(value as i32))
         }
     }
 
     pub fn new_with_flag(value: i16, flag: Option<Flag>) -> Result<Self, NumberError> {
-        if 0 <= value && value <= 999 {
+        if (0..=999).contains(&value) {
             Ok(ThreeDigitNumber(value, flag))
         } else {
-            Err(NumberError::OutOfBounds(value as usize))
+            Err(NumberError::OutOfBounds(value as i32))
         }
     }
 
@@ -63,6 +71,26 @@ impl ThreeDigitNumber {
     pub fn flag(&self) -> Option<Flag> {
         self.1
     }
+
+    // from_signed creates a number from a signed value from -500 to 499 using
+    // its ten's complement representation, so -1 is stored as 999
+    pub fn from_signed(value: i16) -> Result<Self, NumberError> {
+        if (-500..=499).contains(&value) {
+            ThreeDigitNumber::new(value.rem_euclid(1000))
+        } else {
+            Err(NumberError::OutOfSignedBounds(value))
+        }
+    }
+
+    // signed_value interprets the number as ten's complement, treating values
+    // from 500 to 999 as the negative numbers -500 to -1
+    pub fn signed_value(&self) -> i16 {
+        if self.0 >= 500 {
+            self.0 - 1000
+        } else {
+            self.0
+        }
+    }
 }
 
 // Implement the Add trait for ThreeDigitNumber.
@@ -123,7 +151,7 @@ impl TwoDigitNumber {
         if value <= 99 {
             Ok(TwoDigitNumber(value, None))
         } else {
-            Err(NumberError::OutOfBounds(value as usize))
+            Err(NumberError::OutOfBounds(value as i32))
         }
     }
 
@@ -131,7 +159,7 @@ impl TwoDigitNumber {
         if value <= 99 {
             Ok(TwoDigitNumber(value, flag))
         } else {
-            Err(NumberError::OutOfBounds(value as usize))
+            Err(NumberError::OutOfBounds(value as i32))
         }
     }
 
@@ -0,0 +1,217 @@
+use std::fmt;
+
+use crate::{
+    devices::Rng,
+    fuzz::random_program,
+    lmc::{CounterWrap, HaltReason, RunResult, LMC},
+    numbers::{Flag, ThreeDigitNumber},
+    program::Program,
+};
+
+// ORACLE_MAX_CYCLES is the most cycles each program is run for when verifying
+const ORACLE_MAX_CYCLES: usize = 500;
+
+// Halt is how a run ended, without the detail that differs between the
+// implementations such as the wording of an error
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Halt {
+    Halted,
+    MaxCycles,
+    Error,
+}
+
+// Outcome is everything observable about a finished run, used to compare the
+// VM against the oracle
+#[derive(Debug, PartialEq, Clone)]
+pub struct Outcome {
+    pub halt: Halt,
+    pub cycles: usize,
+    pub counter: usize,
+    pub calculator: i16,
+    pub flag: Option<Flag>,
+    pub outputs: Vec<i16>,
+    pub mailboxes: Vec<i16>,
+}
+
+// Mismatch is a program the VM and the oracle disagree about
+#[derive(Debug, Clone)]
+pub struct Mismatch {
+    // seed is the seed of the case, replaying it generates the same program
+    pub seed: u64,
+    pub instructions: Vec<ThreeDigitNumber>,
+    pub inputs: Vec<ThreeDigitNumber>,
+    pub vm: Outcome,
+    pub oracle: Outcome,
+}
+
+// Implement the display trait for easy printing.
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |numbers: &[ThreeDigitNumber]| {
+            numbers
+                .iter()
+                .map(|number| number.to_string())
+                .collect::<Vec<String>>()
+                .join(" ")
+        };
+        writeln!(f, "program: {}", join(&self.instructions))?;
+        writeln!(f, "inputs: {}", join(&self.inputs))?;
+        writeln!(f, "vm:     {:?}", self.vm)?;
+        write!(f, "oracle: {:?}", self.oracle)
+    }
+}
+
+// run executes the program with a deliberately simple interpreter written
+// straight from the instruction set, sharing no code with the VM so that it
+// can catch the VM's mistakes. IN reads from the inputs alone and a run that
+// needs more input than it was given ends in an error.
+pub fn run(
+    instructions: &[ThreeDigitNumber],
+    inputs: &[ThreeDigitNumber],
+    max_cycles: usize,
+) -> Outcome {
+    let mut mailboxes = vec![0i16; 100];
+    if instructions.len() > 100 {
+        return Outcome {
+            halt: Halt::Error,
+            cycles: 0,
+            counter: 0,
+            calculator: 0,
+            flag: None,
+            outputs: Vec::new(),
+            mailboxes,
+        };
+    }
+    for (i, instruction) in instructions.iter().enumerate() {
+        mailboxes[i] = instruction.value();
+    }
+    let mut inputs = inputs.iter().map(|input| input.value());
+    let (mut counter, mut calculator, mut flag) = (0usize, 0i16, None);
+    let mut outputs = Vec::new();
+    let mut cycles = 0;
+    let halt = loop {
+        cycles += 1;
+        if cycles == max_cycles {
+            break Halt::MaxCycles;
+        }
+        let instruction = mailboxes[counter];
+        let operand = (instruction % 100) as usize;
+        let mut next = (counter + 1) % 100;
+        match instruction / 100 {
+            0 => break Halt::Halted,
+            1 => {
+                calculator += mailboxes[operand];
+                flag = None;
+                if calculator > 999 {
+                    calculator -= 1000;
+                    flag = Some(Flag::OVERFLOW);
+                }
+            }
+            2 => {
+                calculator -= mailboxes[operand];
+                flag = None;
+                if calculator < 0 {
+                    calculator += 1000;
+                    flag = Some(Flag::NEG);
+                }
+            }
+            3 => mailboxes[operand] = calculator,
+            5 => {
+                calculator = mailboxes[operand];
+                flag = None;
+            }
+            6 => next = operand,
+            7 if calculator == 0 => next = operand,
+            7 => {}
+            8 if flag != Some(Flag::NEG) => next = operand,
+            8 => {}
+            9 if operand == 1 => match inputs.next() {
+                Some(input) => calculator = input,
+                None => break Halt::Error,
+            },
+            9 if operand == 2 => outputs.push(calculator),
+            _ => break Halt::Error,
+        }
+        counter = next;
+    };
+    Outcome {
+        halt,
+        cycles,
+        counter,
+        calculator,
+        flag,
+        outputs,
+        mailboxes,
+    }
+}
+
+// run_vm executes the program on the VM and observes the same things as run,
+// showing every step the VM takes when debug is set
+pub fn run_vm(
+    instructions: &[ThreeDigitNumber],
+    inputs: &[ThreeDigitNumber],
+    max_cycles: usize,
+    debug: bool,
+) -> Outcome {
+    let mut lmc = LMC::new(debug, debug, true, max_cycles);
+    lmc.set_counter_wrap(CounterWrap::Wrap);
+    lmc.set_io_limits(Some(inputs.len()), None);
+    lmc.load_input(inputs);
+    let program = Program::from_instructions(instructions.to_vec());
+    let result = match lmc.load_program(&program) {
+        Ok(()) => lmc.execute_program(),
+        Err(err) => RunResult {
+            reason: HaltReason::Error(err),
+            cycles: 0,
+        },
+    };
+    observe(&lmc, result)
+}
+
+// observe returns the outcome of the run that left the LMC in its state
+pub fn observe(lmc: &LMC, result: RunResult) -> Outcome {
+    let state = lmc.state();
+    Outcome {
+        halt: match result.reason {
+            HaltReason::Halted(_) => Halt::Halted,
+            HaltReason::MaxCycles(_) => Halt::MaxCycles,
+            HaltReason::Error(_) | HaltReason::Cancelled => Halt::Error,
+        },
+        cycles: result.cycles,
+        counter: state.counter.value() as usize,
+        calculator: state.calculator.value(),
+        flag: state.flag,
+        outputs: state.outputs.iter().map(|number| number.value()).collect(),
+        mailboxes: state
+            .mailboxes
+            .iter()
+            .map(|number| number.value())
+            .collect(),
+    }
+}
+
+// verify runs the number of random programs on both the VM and the oracle,
+// returning every program whose outcome differs
+pub fn verify(seed: u64, iterations: usize) -> Vec<Mismatch> {
+    (0..iterations)
+        .filter_map(|i| replay(Rng::case_seed(seed, i), false))
+        .collect()
+}
+
+// replay runs the single program generated from the seed of a case on both
+// the VM and the oracle, returning it if their outcomes differ
+pub fn replay(seed: u64, debug: bool) -> Option<Mismatch> {
+    let (instructions, inputs) = random_program(&mut Rng::new(seed));
+    let vm = run_vm(&instructions, &inputs, ORACLE_MAX_CYCLES, debug);
+    let oracle = run(&instructions, &inputs, ORACLE_MAX_CYCLES);
+    match vm == oracle {
+        true => None,
+        false => Some(Mismatch {
+            seed,
+            instructions,
+            inputs,
+            vm,
+            oracle,
+        }),
+    }
+}
@@ -0,0 +1,155 @@
+use std::fmt;
+
+use crate::numbers::ThreeDigitNumber;
+
+// PatchError is used to indicate an edit that could not be read or that does
+// not match the machine code it is applied to
+#[derive(Debug, PartialEq)]
+pub enum PatchError {
+    InvalidEdit(String),
+    // Mismatch is a mailbox that does not hold the value the edit expects,
+    // given as the address, the value expected and the value found
+    Mismatch(usize, ThreeDigitNumber, ThreeDigitNumber),
+    NotFound(ThreeDigitNumber),
+}
+
+// Implement the display trait for easy printing.
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchError::InvalidEdit(value) => write!(f, "invalid edit: {}", value),
+            PatchError::Mismatch(address, expected, found) => write!(
+                f,
+                "mailbox {:02} holds {} not {}, the patch is for a different image",
+                address, found, expected
+            ),
+            PatchError::NotFound(value) => write!(f, "no mailbox holds {}", value),
+        }
+    }
+}
+
+// Implement the error trait so the error can be used with the ? operator
+// alongside other errors.
+impl std::error::Error for PatchError {}
+
+// Edit is a single change to an assembled image, each stating what it
+// expects to find so that a patch is never applied to the wrong image
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Edit {
+    // Set changes the mailbox at the address from the old value to the new
+    Set {
+        address: usize,
+        old: ThreeDigitNumber,
+        new: ThreeDigitNumber,
+    },
+    // Replace changes every mailbox holding the old value to the new one
+    Replace {
+        old: ThreeDigitNumber,
+        new: ThreeDigitNumber,
+    },
+}
+
+impl Edit {
+    // parse reads an edit written as a line of a patch file, e.g.:
+    // set 42 104 105
+    // replace 600 700
+    pub fn parse(line: &str) -> Result<Edit, PatchError> {
+        let words = line.split_whitespace().collect::<Vec<&str>>();
+        match words.as_slice() {
+            ["set", address, old, new] => Ok(Edit::Set {
+                address: parse_address(address)?,
+                old: parse_value(old)?,
+                new: parse_value(new)?,
+            }),
+            ["replace", old, new] => Ok(Edit::Replace {
+                old: parse_value(old)?,
+                new: parse_value(new)?,
+            }),
+            _ => Err(PatchError::InvalidEdit(line.to_string())),
+        }
+    }
+
+    // apply makes the edit, returning a description of every mailbox
+    // changed. Mailboxes past the end of the image hold 000.
+    pub fn apply(
+        &self,
+        instructions: &mut Vec<ThreeDigitNumber>,
+    ) -> Result<Vec<String>, PatchError> {
+        match *self {
+            Edit::Set { address, old, new } => {
+                let zero = ThreeDigitNumber::new(0).unwrap();
+                let found = instructions.get(address).copied().unwrap_or(zero);
+                if found.value() != old.value() {
+                    return Err(PatchError::Mismatch(address, old, found));
+                }
+                if address >= instructions.len() {
+                    instructions.resize(address + 1, zero);
+                }
+                instructions[address] = new;
+                Ok(vec![describe(address, old, new)])
+            }
+            Edit::Replace { old, new } => {
+                let changes = instructions
+                    .iter_mut()
+                    .enumerate()
+                    .filter(|(_, value)| value.value() == old.value())
+                    .map(|(address, value)| {
+                        *value = new;
+                        describe(address, old, new)
+                    })
+                    .collect::<Vec<String>>();
+                match changes.is_empty() {
+                    true => Err(PatchError::NotFound(old)),
+                    false => Ok(changes),
+                }
+            }
+        }
+    }
+}
+
+// parse reads a patch file of one edit per line, ignoring blank lines and
+// anything after a #
+pub fn parse(text: &str) -> Result<Vec<Edit>, PatchError> {
+    text.lines()
+        .map(|line| line.split('#').next().unwrap_or("").trim())
+        .filter(|line| !line.is_empty())
+        .map(Edit::parse)
+        .collect()
+}
+
+// apply makes every edit in order, each seeing the changes of those before
+// it, returning a description of every mailbox changed. Nothing is changed
+// unless every edit matches.
+pub fn apply(
+    instructions: &mut Vec<ThreeDigitNumber>,
+    edits: &[Edit],
+) -> Result<Vec<String>, PatchError> {
+    let mut patched = instructions.clone();
+    let mut changes = Vec::new();
+    for edit in edits {
+        changes.extend(edit.apply(&mut patched)?);
+    }
+    *instructions = patched;
+    Ok(changes)
+}
+
+// describe shows a change to a mailbox, e.g. 42: 104 -> 105
+fn describe(address: usize, old: ThreeDigitNumber, new: ThreeDigitNumber) -> String {
+    format!("{:02}: {} -> {}", address, old, new)
+}
+
+// parse_address parses a mailbox address between 00 and 99
+fn parse_address(word: &str) -> Result<usize, PatchError> {
+    match word.parse::<usize>() {
+        Ok(address) if address <= 99 => Ok(address),
+        _ => Err(PatchError::InvalidEdit(format!("invalid address {}", word))),
+    }
+}
+
+// parse_value parses the value of a mailbox between 000 and 999
+fn parse_value(word: &str) -> Result<ThreeDigitNumber, PatchError> {
+    word.parse::<i16>()
+        .ok()
+        .and_then(|value| ThreeDigitNumber::new(value).ok())
+        .ok_or_else(|| PatchError::InvalidEdit(format!("invalid value {}", word)))
+}
@@ -0,0 +1,66 @@
+use std::collections::BTreeSet;
+
+use crate::{metrics::natural_loops, program::Program, trace::Trace};
+
+// LoopProfile is how a natural loop of the program behaved during a run
+#[derive(Debug, PartialEq, Clone)]
+pub struct LoopProfile {
+    // header is the address control enters the loop through
+    pub header: usize,
+    pub body: BTreeSet<usize>,
+    // entries is the number of times the loop was entered from outside
+    pub entries: usize,
+    // iterations is the number of times the header was executed, once per
+    // entry plus once for every time a back edge was taken
+    pub iterations: usize,
+    // cycles is the number of instructions executed within the body,
+    // including those of any loops nested inside it
+    pub cycles: usize,
+}
+
+impl LoopProfile {
+    // cycles_per_iteration returns the average cycles spent in each iteration
+    pub fn cycles_per_iteration(&self) -> f64 {
+        if self.iterations == 0 {
+            return 0.0;
+        }
+        self.cycles as f64 / self.iterations as f64
+    }
+}
+
+// profile counts the iterations and cycles of every natural loop in the
+// program's control flow graph from a trace of a run, ordered by the cycles
+// spent in them so the loop that dominates the runtime comes first
+pub fn profile(program: &Program, trace: &Trace) -> Vec<LoopProfile> {
+    let mut loops = natural_loops(program)
+        .into_iter()
+        .map(|(header, body)| {
+            let mut entries = 0;
+            let mut iterations = 0;
+            let mut cycles = 0;
+            let mut previous: Option<usize> = None;
+            for event in &trace.events {
+                let address = event.address as usize;
+                if body.contains(&address) {
+                    cycles += 1;
+                }
+                if address == header {
+                    iterations += 1;
+                    if !previous.is_some_and(|previous| body.contains(&previous)) {
+                        entries += 1;
+                    }
+                }
+                previous = Some(address);
+            }
+            LoopProfile {
+                header,
+                body,
+                entries,
+                iterations,
+                cycles,
+            }
+        })
+        .collect::<Vec<LoopProfile>>();
+    loops.sort_by(|a, b| b.cycles.cmp(&a.cycles).then(a.header.cmp(&b.header)));
+    loops
+}
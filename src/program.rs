@@ -0,0 +1,310 @@
+use serde_json::{json, Value};
+use std::{collections::BTreeMap, fmt};
+
+use crate::{instruction::Instruction, numbers::ThreeDigitNumber};
+
+// Program is an assembled LMC program along with the metadata the assembler
+// gathered about it that is not part of the machine code itself
+#[derive(Debug, Default, Clone)]
+pub struct Program {
+    // instructions are the mailbox contents, starting at address 00
+    pub instructions: Vec<ThreeDigitNumber>,
+    // source_map holds the 1-based source line number of each mailbox
+    pub source_map: Vec<usize>,
+    // assertions are the ASSERT pseudo-instructions found in the source
+    pub assertions: Vec<Assertion>,
+    // data marks which mailboxes were assembled from DAT rather than an
+    // instruction, it is empty when the program was loaded from machine code
+    pub data: Vec<bool>,
+    // labels is the symbol table mapping each label to its mailbox, ordered
+    // by name so that anything listing it is the same from run to run, it
+    // is empty when the program was loaded from machine code
+    pub labels: BTreeMap<String, usize>,
+    // diagnostics are the errors recovered from when assembling partially
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+// Span is where in the source something was found
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Span {
+    // line is the 1-based source line number
+    pub line: usize,
+    // start and end are the 0-based character columns spanned, the end being
+    // exclusive
+    pub start: usize,
+    pub end: usize,
+}
+
+// Implement the display trait for easy printing, columns are shown 1-based
+// as editors do.
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, col {}", self.line, self.start + 1)
+    }
+}
+
+// Diagnostic is an error in a line of source that was assembled as HLT
+#[derive(Debug, PartialEq, Clone)]
+pub struct Diagnostic {
+    pub span: Span,
+    // code names the kind of error, e.g. invalid-opcode
+    pub code: &'static str,
+    pub message: String,
+}
+
+// Implement the display trait for easy printing.
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.span, self.message)
+    }
+}
+
+impl Diagnostic {
+    // to_json describes the diagnostic for editors and autograders with the
+    // severity given, error or warning, its column 1-based as editors count
+    pub fn to_json(&self, severity: &str) -> Value {
+        json!({
+            "severity": severity,
+            "line": self.span.line,
+            "column": self.span.start + 1,
+            "code": self.code,
+            "message": self.message,
+        })
+    }
+}
+
+impl Program {
+    // from_instructions creates a program from machine code alone
+    pub fn from_instructions(instructions: Vec<ThreeDigitNumber>) -> Self {
+        Program {
+            instructions,
+            ..Default::default()
+        }
+    }
+
+    // resolve_address returns the mailbox a label refers to, or the address
+    // itself if given a literal 2-digit address such as 10
+    pub fn resolve_address(&self, name: &str) -> Option<usize> {
+        if let Some(address) = self.labels.get(name) {
+            return Some(*address);
+        }
+        match name.parse::<usize>() {
+            Ok(address) if address <= 99 => Some(address),
+            _ => None,
+        }
+    }
+
+    // is_data returns whether the mailbox at the address is known to hold data
+    pub fn is_data(&self, address: usize) -> bool {
+        self.data.get(address).copied().unwrap_or(false)
+    }
+
+    // label_of returns the label of the mailbox at the address, the first in
+    // alphabetical order if it has several
+    pub fn label_of(&self, address: usize) -> Option<&str> {
+        self.labels
+            .iter()
+            .filter(|(_, labelled)| **labelled == address)
+            .map(|(label, _)| label.as_str())
+            .min()
+    }
+
+    // data_preview shows the value of the data cell an instruction reads or
+    // writes, named by its label if it has one, e.g. (COUNT=7). Without the
+    // assembler's metadata every operand of ADD, SUB, STO and LDA is taken to
+    // be data, as is any mailbox past the end of the program.
+    pub fn data_preview(
+        &self,
+        instruction: Instruction,
+        mailboxes: &[ThreeDigitNumber],
+    ) -> Option<String> {
+        let address = match instruction {
+            Instruction::Add(address)
+            | Instruction::Sub(address)
+            | Instruction::Sto(address)
+            | Instruction::Lda(address) => address,
+            _ => return None,
+        };
+        if !self.data.get(address).copied().unwrap_or(true) {
+            return None;
+        }
+        let value = mailboxes.get(address)?;
+        let name = match self.label_of(address) {
+            Some(label) => label.to_string(),
+            None => format!("{:02}", address),
+        };
+        Some(format!("({}={})", name, value.value()))
+    }
+}
+
+// machine_code_value reads the value held by a line of machine code, or None
+// for a blank or comment line. A line written by assemble --annotate starts
+// with the mailbox address and ends with a comment, both of which are ignored.
+pub fn machine_code_value(line: &str) -> Result<Option<i16>, String> {
+    let code = line.split('#').next().unwrap_or("");
+    let value = match code.split_whitespace().collect::<Vec<&str>>().as_slice() {
+        [] => return Ok(None),
+        [value] => value.to_string(),
+        [address, value] if address.parse::<usize>().is_ok() => value.to_string(),
+        _ => return Err(format!("invalid machine code: {}", code.trim())),
+    };
+    value.parse::<i16>().map(Some).map_err(|e| e.to_string())
+}
+
+// fnv1a returns the 32-bit FNV-1a hash of the bytes, cheap to compute and good
+// enough to catch files that were truncated or edited by hand
+pub fn fnv1a(bytes: impl IntoIterator<Item = u8>) -> u32 {
+    let mut value: u32 = 0x811c9dc5;
+    for byte in bytes {
+        value ^= byte as u32;
+        value = value.wrapping_mul(0x01000193);
+    }
+    value
+}
+
+// Checksum is the integrity line written after the machine code of an
+// assembled program, in the form: # checksum=0b4fe2a1 length=12
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Checksum {
+    pub value: u32,
+    pub length: usize,
+}
+
+impl Checksum {
+    // of computes the FNV-1a hash of the instructions along with their count
+    pub fn of(instructions: &[ThreeDigitNumber]) -> Self {
+        let bytes = instructions
+            .iter()
+            .flat_map(|instruction| instruction.value().to_le_bytes());
+        Checksum {
+            value: fnv1a(bytes),
+            length: instructions.len(),
+        }
+    }
+
+    // parse reads a checksum line, returning None if the line is not one
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.trim().strip_prefix('#')?.split_whitespace();
+        let value = fields.next()?.strip_prefix("checksum=")?;
+        let length = fields.next()?.strip_prefix("length=")?;
+        Some(Checksum {
+            value: u32::from_str_radix(value, 16).ok()?,
+            length: length.parse().ok()?,
+        })
+    }
+
+    // verify compares the checksum against the instructions that were loaded
+    // returning a description of the problem if they do not match
+    pub fn verify(&self, instructions: &[ThreeDigitNumber]) -> Option<String> {
+        let actual = Checksum::of(instructions);
+        if actual.length < self.length {
+            Some(format!(
+                "appears truncated: got {} instructions, expected {}",
+                actual.length, self.length
+            ))
+        } else if actual != *self {
+            Some("checksum mismatch: appears to have been edited by hand".to_string())
+        } else {
+            None
+        }
+    }
+}
+
+// Display trait for easy printing.
+impl fmt::Display for Checksum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "# checksum={:08x} length={}", self.value, self.length)
+    }
+}
+
+// Operand is one side of an assertion's comparison
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Operand {
+    // Calculator refers to the value currently held in the calculator
+    Calculator,
+    // Mailbox refers to the value currently held in the mailbox at the address
+    Mailbox(usize),
+    // Literal is a constant 3-digit decimal number
+    Literal(i16),
+}
+
+impl Operand {
+    // value returns the current value of the operand given the machine state
+    pub fn value(&self, calculator: ThreeDigitNumber, mailboxes: &[ThreeDigitNumber]) -> i16 {
+        match self {
+            Operand::Calculator => calculator.value(),
+            Operand::Mailbox(address) => mailboxes[*address].value(),
+            Operand::Literal(value) => *value,
+        }
+    }
+}
+
+// Comparison is the relational operator used by an assertion
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Comparison {
+    // from_str converts a string to a comparison operator
+    pub fn from_str(operator: &str) -> Option<Comparison> {
+        match operator {
+            "==" => Some(Comparison::Eq),
+            "!=" => Some(Comparison::Ne),
+            "<" => Some(Comparison::Lt),
+            "<=" => Some(Comparison::Le),
+            ">" => Some(Comparison::Gt),
+            ">=" => Some(Comparison::Ge),
+            _ => None,
+        }
+    }
+
+    // compare applies the operator to the two values
+    pub fn compare(&self, left: i16, right: i16) -> bool {
+        match self {
+            Comparison::Eq => left == right,
+            Comparison::Ne => left != right,
+            Comparison::Lt => left < right,
+            Comparison::Le => left <= right,
+            Comparison::Gt => left > right,
+            Comparison::Ge => left >= right,
+        }
+    }
+}
+
+// Assertion is an invariant that is checked immediately before the
+// instruction at its address is executed when running in checked mode
+#[derive(Debug, PartialEq, Clone)]
+pub struct Assertion {
+    // address is the mailbox of the instruction the assertion guards
+    pub address: usize,
+    // line is the 1-based source line number of the assertion
+    pub line: usize,
+    // expression is the original source text of the assertion
+    pub expression: String,
+    pub left: Operand,
+    pub comparison: Comparison,
+    pub right: Operand,
+}
+
+impl Assertion {
+    // holds evaluates the assertion against the given machine state
+    pub fn holds(&self, calculator: ThreeDigitNumber, mailboxes: &[ThreeDigitNumber]) -> bool {
+        self.comparison.compare(
+            self.left.value(calculator, mailboxes),
+            self.right.value(calculator, mailboxes),
+        )
+    }
+}
+
+// Display trait for easy printing.
+impl fmt::Display for Assertion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ASSERT {} (line {})", self.expression, self.line)
+    }
+}
@@ -0,0 +1,118 @@
+use std::fmt;
+
+use crate::{instruction::Instruction, program::Program};
+
+// MNEMONICS are the mnemonics a query can select instructions by
+const MNEMONICS: [&str; 10] = [
+    "ADD", "SUB", "STO", "LDA", "BR", "BRZ", "BRP", "IN", "OUT", "HLT",
+];
+
+// QueryError is used to indicate a query that could not be parsed
+#[derive(Debug, PartialEq)]
+pub enum QueryError {
+    InvalidQuery(String),
+}
+
+// Implement the display trait for easy printing.
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::InvalidQuery(value) => write!(f, "invalid query: {}", value),
+        }
+    }
+}
+
+// Mnemonic is the kind of instruction a query selects
+#[derive(Debug, PartialEq, Clone)]
+enum Mnemonic {
+    // Any selects every instruction, written *
+    Any,
+    // Branch selects BR, BRZ and BRP, written BRANCH
+    Branch,
+    Exact(String),
+}
+
+// Query selects decoded instructions by their mnemonic and the mailbox their
+// operand refers to, given as a label or a 2-digit address. For example
+// "STO count" finds every store into count and "BRANCH 20" every branch
+// targeting mailbox 20, while "count" alone finds anything referring to it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Query {
+    mnemonic: Mnemonic,
+    operand: Option<String>,
+}
+
+// Match is an instruction selected by a query
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Match {
+    pub address: usize,
+    pub instruction: Instruction,
+    // line is the 1-based source line of the instruction, if known
+    pub line: Option<usize>,
+}
+
+impl Query {
+    // parse reads a query in the form [<mnemonic|*|BRANCH>] [<label|address>]
+    pub fn parse(text: &str) -> Result<Self, QueryError> {
+        let parts = text.split_whitespace().collect::<Vec<&str>>();
+        let mnemonic = |part: &str| match part.to_uppercase().as_str() {
+            "*" => Some(Mnemonic::Any),
+            "BRANCH" => Some(Mnemonic::Branch),
+            upper if MNEMONICS.contains(&upper) => Some(Mnemonic::Exact(upper.to_string())),
+            _ => None,
+        };
+        match parts.as_slice() {
+            [part] => Ok(match mnemonic(part) {
+                Some(mnemonic) => Query {
+                    mnemonic,
+                    operand: None,
+                },
+                None => Query {
+                    mnemonic: Mnemonic::Any,
+                    operand: Some(part.to_string()),
+                },
+            }),
+            [first, operand] => match mnemonic(first) {
+                Some(mnemonic) => Ok(Query {
+                    mnemonic,
+                    operand: Some(operand.to_string()),
+                }),
+                None => Err(QueryError::InvalidQuery(format!(
+                    "unknown mnemonic {}",
+                    first
+                ))),
+            },
+            _ => Err(QueryError::InvalidQuery(text.to_string())),
+        }
+    }
+
+    // search returns every instruction in the program the query selects, the
+    // operand is resolved using the program's labels so a label the program
+    // does not define selects nothing
+    pub fn search(&self, program: &Program) -> Vec<Match> {
+        let operand = match &self.operand {
+            Some(operand) => match program.resolve_address(operand) {
+                Some(address) => Some(address),
+                None => return Vec::new(),
+            },
+            None => None,
+        };
+        program
+            .instructions
+            .iter()
+            .enumerate()
+            .filter(|(address, _)| !program.is_data(*address))
+            .map(|(address, number)| Match {
+                address,
+                instruction: Instruction::decode(*number),
+                line: program.source_map.get(address).copied(),
+            })
+            .filter(|found| match &self.mnemonic {
+                Mnemonic::Any => true,
+                Mnemonic::Branch => found.instruction.is_branch(),
+                Mnemonic::Exact(mnemonic) => found.instruction.mnemonic() == mnemonic,
+            })
+            .filter(|found| operand.is_none() || found.instruction.operand() == operand)
+            .collect()
+    }
+}
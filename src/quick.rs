@@ -0,0 +1,101 @@
+use std::fmt;
+
+use crate::{
+    assembler::{Assembler, AssemblerError},
+    lmc::{LMCError, MachineState, RunResult, LMC},
+    numbers::{NumberError, ThreeDigitNumber},
+};
+
+// MAX_CYCLES is the most cycles a quick run may take, the same limit lmc
+// uses by default
+const MAX_CYCLES: usize = 50000;
+
+// QuickError is used to indicate a program that could not be assembled or
+// loaded, or an input that is not a mailbox value
+#[derive(Debug, PartialEq)]
+pub enum QuickError {
+    AssemblerError(AssemblerError),
+    NumberError(NumberError),
+    LMCError(LMCError),
+}
+
+// Implement the display trait for easy printing.
+impl fmt::Display for QuickError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuickError::AssemblerError(value) => write!(f, "{}", value),
+            QuickError::NumberError(value) => write!(f, "invalid input: {}", value),
+            QuickError::LMCError(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+// Implement the error trait so the error can be used with the ? operator
+// alongside other errors.
+impl std::error::Error for QuickError {}
+
+// Implement the from trait for AssemblerError.
+impl From<AssemblerError> for QuickError {
+    fn from(error: AssemblerError) -> Self {
+        QuickError::AssemblerError(error)
+    }
+}
+
+// Implement the from trait for NumberError.
+impl From<NumberError> for QuickError {
+    fn from(error: NumberError) -> Self {
+        QuickError::NumberError(error)
+    }
+}
+
+// Implement the from trait for LMCError.
+impl From<LMCError> for QuickError {
+    fn from(error: LMCError) -> Self {
+        QuickError::LMCError(error)
+    }
+}
+
+// Run is everything a quick run leaves behind: how it stopped and the cycles
+// it took, every value output in order and the machine as it was left
+#[derive(Debug, PartialEq)]
+pub struct Run {
+    pub result: RunResult,
+    pub outputs: Vec<i16>,
+    pub state: MachineState,
+}
+
+impl Run {
+    // is_halted returns whether the program stopped by executing HLT
+    pub fn is_halted(&self) -> bool {
+        self.result.is_halted()
+    }
+}
+
+// run assembles the source, loads it and executes it with the inputs in one
+// call, for tests and examples that only need to know what a program does.
+// A program asking for more inputs than given fails with an input underflow
+// rather than reading stdin, and one that does not halt within MAX_CYCLES
+// stops, both being reported by the result of the run rather than an error.
+pub fn run(source: &str, inputs: &[i16]) -> Result<Run, QuickError> {
+    let mut lines = source.lines().map(String::from).collect::<Vec<String>>();
+    let program = Assembler::new(false, false).assemble_program(&mut lines)?;
+    let inputs = inputs
+        .iter()
+        .map(|value| ThreeDigitNumber::new(*value))
+        .collect::<Result<Vec<ThreeDigitNumber>, NumberError>>()?;
+    let mut lmc = LMC::new(false, false, true, MAX_CYCLES);
+    lmc.load_program(&program)?;
+    lmc.set_input_underflow(true);
+    lmc.load_input(&inputs);
+    let result = lmc.execute_program();
+    let outputs = lmc
+        .get_outputs()
+        .iter()
+        .map(|value| value.value())
+        .collect::<Vec<i16>>();
+    Ok(Run {
+        result,
+        outputs,
+        state: lmc.state(),
+    })
+}
@@ -0,0 +1,239 @@
+use std::fmt;
+
+use crate::{
+    assembler::{is_mnemonic, Assembler},
+    instruction::Instruction,
+    numbers::ThreeDigitNumber,
+    program::{Operand, Program},
+};
+
+// RefactorError is used to indicate a refactoring that could not be applied
+// without changing what the program does
+#[derive(Debug, PartialEq)]
+pub enum RefactorError {
+    AssemblerError(String),
+    UnknownLabel(String),
+    InvalidLabel(String),
+    LabelExists(String),
+    InvalidRange(String),
+    NumericReference(usize),
+    FallsIntoData(usize),
+}
+
+// Implement the display trait for easy printing.
+impl fmt::Display for RefactorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RefactorError::AssemblerError(value) => write!(f, "assembler error: {}", value),
+            RefactorError::UnknownLabel(label) => write!(f, "unknown label: {}", label),
+            RefactorError::InvalidLabel(label) => write!(f, "invalid label: {}", label),
+            RefactorError::LabelExists(label) => write!(f, "label already exists: {}", label),
+            RefactorError::InvalidRange(value) => write!(f, "invalid range: {}", value),
+            RefactorError::NumericReference(address) => write!(
+                f,
+                "instruction at {:02} refers to a mailbox by number so cannot be moved safely",
+                address
+            ),
+            RefactorError::FallsIntoData(address) => write!(
+                f,
+                "instruction at {:02} runs on into data so cannot be moved safely",
+                address
+            ),
+        }
+    }
+}
+
+// assemble assembles the source for checking a refactoring
+fn assemble(asm: &Assembler, source: &[String]) -> Result<Program, RefactorError> {
+    asm.assemble_program(&mut source.to_vec())
+        .map_err(|e| RefactorError::AssemblerError(e.to_string()))
+}
+
+// replace_word replaces every whitespace separated word in the code of the
+// line, before any comment, that is exactly from with to, keeping the
+// spacing and comment of the line as they were. A label defined with a colon
+// keeps its colon.
+pub fn replace_word(line: &str, from: &str, to: &str) -> String {
+    let (code, comment) = match line.find('#') {
+        Some(index) => line.split_at(index),
+        None => (line, ""),
+    };
+    let mut result = String::new();
+    let mut word = String::new();
+    for c in code.chars().chain(std::iter::once(' ')) {
+        if c.is_whitespace() {
+            if word == from {
+                result.push_str(to);
+            } else if word.strip_suffix(':') == Some(from) {
+                result.push_str(&format!("{}:", to));
+            } else {
+                result.push_str(&word);
+            }
+            word.clear();
+            result.push(c);
+        } else {
+            word.push(c);
+        }
+    }
+    // drop the space added to flush the last word
+    result.pop();
+    result.push_str(comment);
+    result
+}
+
+// rename renames a label and every reference to it. The result is assembled
+// to check it produces exactly the same machine code as before.
+pub fn rename(
+    asm: &Assembler,
+    source: &[String],
+    from: &str,
+    to: &str,
+) -> Result<Vec<String>, RefactorError> {
+    let before = assemble(asm, source)?;
+    if !before.labels.contains_key(from) {
+        return Err(RefactorError::UnknownLabel(from.to_string()));
+    }
+    if before.labels.contains_key(to) {
+        return Err(RefactorError::LabelExists(to.to_string()));
+    }
+    if to.is_empty()
+        || is_mnemonic(to)
+        || to == "ACC"
+        || to.parse::<usize>().is_ok()
+        || to.contains(|c: char| c.is_whitespace() || c == '#' || c == ':')
+    {
+        return Err(RefactorError::InvalidLabel(to.to_string()));
+    }
+    let renamed = source
+        .iter()
+        .map(|line| replace_word(line, from, to))
+        .collect::<Vec<String>>();
+    let after = assemble(asm, &renamed)?;
+    // the text of each assertion names the label so only compare what it checks
+    let checks = |program: &Program| {
+        program
+            .assertions
+            .iter()
+            .map(|assertion| {
+                (
+                    assertion.address,
+                    assertion.left,
+                    assertion.comparison,
+                    assertion.right,
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    if after.instructions != before.instructions || checks(&after) != checks(&before) {
+        return Err(RefactorError::AssemblerError(
+            "renaming changed the machine code".to_string(),
+        ));
+    }
+    Ok(renamed)
+}
+
+// parse_range parses an inclusive range of mailboxes in the form 90..99
+pub fn parse_range(range: &str) -> Result<(usize, usize), RefactorError> {
+    let invalid = || RefactorError::InvalidRange(range.to_string());
+    let (start, end) = range.split_once("..").ok_or_else(invalid)?;
+    let start = start.trim().parse::<usize>().map_err(|_| invalid())?;
+    let end = end.trim().parse::<usize>().map_err(|_| invalid())?;
+    if start > end || end > 99 {
+        return Err(invalid());
+    }
+    Ok((start, end))
+}
+
+// move_data moves every DAT line to the end of the source, padded so that the
+// first lands in the mailbox at start, keeping them within start..=end. The
+// result is assembled to check every instruction still refers to the same
+// data and code as before.
+pub fn move_data(
+    asm: &Assembler,
+    source: &[String],
+    start: usize,
+    end: usize,
+) -> Result<Vec<String>, RefactorError> {
+    let before = assemble(asm, source)?;
+    let length = before.instructions.len();
+    let data = (0..length)
+        .filter(|address| before.is_data(*address))
+        .collect::<Vec<usize>>();
+    let code = length - data.len();
+    if code > start || start + data.len() > end + 1 {
+        return Err(RefactorError::InvalidRange(format!(
+            "{} instructions and {} DAT cells do not fit around {}..{}",
+            code,
+            data.len(),
+            start,
+            end
+        )));
+    }
+    // new_address maps each mailbox to where it is after the move
+    let mut new_address = vec![0; length];
+    let (mut next_code, mut next_data) = (0, start);
+    for (address, moved) in new_address.iter_mut().enumerate() {
+        if before.is_data(address) {
+            *moved = next_data;
+            next_data += 1;
+        } else {
+            *moved = next_code;
+            next_code += 1;
+        }
+    }
+    for address in (0..length).filter(|address| !before.is_data(*address)) {
+        let instruction = Instruction::decode(before.instructions[address]);
+        let falls_through = !matches!(instruction, Instruction::Hlt | Instruction::Br(_));
+        if falls_through && before.is_data(address + 1) {
+            return Err(RefactorError::FallsIntoData(address));
+        }
+    }
+    let data_lines = data
+        .iter()
+        .map(|address| before.source_map[*address] - 1)
+        .collect::<Vec<usize>>();
+    let mut moved = source
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !data_lines.contains(i))
+        .map(|(_, line)| line.clone())
+        .collect::<Vec<String>>();
+    for _ in code..start {
+        moved.push(format!("{:7} {:7} {:7} # padding", "", "DAT", "0"));
+    }
+    moved.extend(data_lines.iter().map(|i| source[*i].clone()));
+    let after = assemble(asm, &moved)?;
+    // every mailbox must hold the same value with any address it refers to
+    // moved along with it
+    let relocate = |address: usize| new_address.get(address).copied().unwrap_or(address);
+    for address in 0..length {
+        let value = before.instructions[address];
+        let expected = if before.is_data(address) {
+            value
+        } else {
+            match Instruction::decode(value).operand() {
+                Some(operand) => {
+                    ThreeDigitNumber::new(value.value() - operand as i16 + relocate(operand) as i16)
+                        .unwrap()
+                }
+                None => value,
+            }
+        };
+        if after.instructions[relocate(address)] != expected {
+            return Err(RefactorError::NumericReference(address));
+        }
+    }
+    let relocate_operand = |operand: Operand| match operand {
+        Operand::Mailbox(address) => Operand::Mailbox(relocate(address)),
+        operand => operand,
+    };
+    for (old, new) in before.assertions.iter().zip(&after.assertions) {
+        if new.address != relocate(old.address)
+            || new.left != relocate_operand(old.left)
+            || new.right != relocate_operand(old.right)
+        {
+            return Err(RefactorError::NumericReference(old.address));
+        }
+    }
+    Ok(moved)
+}
@@ -0,0 +1,899 @@
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{fmt, fs::File, io::BufReader};
+
+use crate::{schema::REPORT_SCHEMA, version};
+
+// ReportError is used to indicate an error reading a saved report
+#[derive(Debug, PartialEq)]
+pub enum ReportError {
+    IOError(String),
+    InvalidReport(String),
+}
+
+// Implement the display trait for easy printing.
+impl fmt::Display for ReportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReportError::IOError(value) => write!(f, "IO error: {}", value),
+            ReportError::InvalidReport(value) => write!(f, "invalid report: {}", value),
+        }
+    }
+}
+
+// HISTOGRAM_BUCKETS is the most buckets the cycles histogram is split into
+const HISTOGRAM_BUCKETS: usize = 10;
+
+// HISTOGRAM_WIDTH is the length of the bar drawn for the fullest bucket
+const HISTOGRAM_WIDTH: usize = 40;
+
+// PROMETHEUS_BUCKETS are the upper bounds of the cycles histogram in the
+// Prometheus report, fixed so that runs can be aggregated over time
+const PROMETHEUS_BUCKETS: [usize; 6] = [10, 50, 100, 500, 1000, 10000];
+
+// ReportFormat is the format a batch report is written in
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ReportFormat {
+    Text,
+    JSON,
+    // Prometheus is the text exposition format, to be picked up by the
+    // textfile collector of a node exporter
+    Prometheus,
+    // Markdown is a feedback document to paste into an LMS or send to a student
+    Markdown,
+}
+
+impl ReportFormat {
+    // from_str converts a string to a report format
+    pub fn from_str(format: &str) -> Option<ReportFormat> {
+        match format {
+            "text" => Some(ReportFormat::Text),
+            "json" => Some(ReportFormat::JSON),
+            "prometheus" => Some(ReportFormat::Prometheus),
+            "markdown" | "md" => Some(ReportFormat::Markdown),
+            _ => None,
+        }
+    }
+}
+
+// Status is how a test counts towards the summary, taking into account
+// whether it was marked to be skipped or expected to fail
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Status {
+    Passed,
+    Failed,
+    Skipped,
+    // ExpectedFailure is a test marked as expected to fail that failed
+    ExpectedFailure,
+    // UnexpectedPass is a test marked as expected to fail that passed
+    UnexpectedPass,
+}
+
+// Implement the display trait for easy printing.
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Status::Passed => write!(f, "passed"),
+            Status::Failed => write!(f, "failed"),
+            Status::Skipped => write!(f, "skipped"),
+            Status::ExpectedFailure => write!(f, "xfailed"),
+            Status::UnexpectedPass => write!(f, "xpassed"),
+        }
+    }
+}
+
+// TestResult is the outcome of a single batch test
+#[derive(Debug, Clone, Deserialize)]
+pub struct TestResult {
+    pub name: String,
+    pub passed: bool,
+    pub cycles: usize,
+    // halt is why the test's run stopped, see HaltReason
+    pub halt: String,
+    // inputs are the values the test was run with, reports saved before they
+    // were recorded have none
+    #[serde(default)]
+    pub inputs: Vec<i16>,
+    // output is the last value output and expected the last value expected
+    pub output: Option<i16>,
+    pub expected: Option<i16>,
+    // outputs are every value output and expected_outputs every value
+    // expected, compared using the comparator. Reports saved before they were
+    // recorded have neither and compared exactly.
+    #[serde(default)]
+    pub outputs: Vec<i16>,
+    #[serde(default)]
+    pub expected_outputs: Vec<i16>,
+    #[serde(default = "default_comparator")]
+    pub comparator: String,
+    // unconsumed is how many of the inputs the program never read and
+    // unchecked how many of the outputs the comparator never looked at,
+    // either usually meaning the test and the program disagree on what is
+    // read and written. Reports saved before they were recorded have neither.
+    #[serde(default)]
+    pub unconsumed: usize,
+    #[serde(default)]
+    pub unchecked: usize,
+    // touched is the number of distinct mailboxes the test used and highest
+    // the highest address it used, see WorkingSet. Reports saved before they
+    // were recorded have neither.
+    #[serde(default)]
+    pub touched: usize,
+    #[serde(default)]
+    pub highest: Option<usize>,
+    // seed replays the random inputs of a test with an input domain too large
+    // to test exhaustively
+    #[serde(default)]
+    pub seed: Option<u64>,
+    // skipped is a test marked to be skipped, which was not run, and xfail
+    // the reason given for a test marked as expected to fail
+    #[serde(default)]
+    pub skipped: bool,
+    #[serde(default)]
+    pub xfail: Option<String>,
+    // memory are the mailboxes the test checked once the program halted
+    #[serde(default)]
+    pub memory: Vec<CellResult>,
+    // dialect is the dialect the test ran on when the suite runs a matrix of
+    // them, see TestSuite
+    #[serde(default)]
+    pub dialect: Option<String>,
+}
+
+// CellResult is a mailbox checked by a test, named by the label or address
+// the test gave, with the value it held and the value expected
+#[derive(Debug, PartialEq, Clone, Deserialize)]
+pub struct CellResult {
+    pub cell: String,
+    pub got: i16,
+    pub expected: i16,
+}
+
+// default_comparator is the comparator of tests in reports saved before
+// comparators were added
+fn default_comparator() -> String {
+    "exact".to_string()
+}
+
+impl TestResult {
+    // skipped returns the result of a test marked to be skipped
+    pub fn skipped(name: &str) -> TestResult {
+        TestResult {
+            name: name.to_string(),
+            passed: false,
+            cycles: 0,
+            halt: "skipped".to_string(),
+            inputs: Vec::new(),
+            output: None,
+            expected: None,
+            outputs: Vec::new(),
+            expected_outputs: Vec::new(),
+            comparator: default_comparator(),
+            unconsumed: 0,
+            unchecked: 0,
+            touched: 0,
+            highest: None,
+            seed: None,
+            skipped: true,
+            xfail: None,
+            memory: Vec::new(),
+            dialect: None,
+        }
+    }
+
+    // label returns the name of the test, followed by the dialect it ran on
+    // if it was run on several, e.g. double (higginson)
+    pub fn label(&self) -> String {
+        match &self.dialect {
+            Some(dialect) => format!("{} ({})", self.name, dialect),
+            None => self.name.clone(),
+        }
+    }
+
+    // status returns how the test counts towards the summary
+    pub fn status(&self) -> Status {
+        match (self.skipped, self.passed, self.xfail.is_some()) {
+            (true, _, _) => Status::Skipped,
+            (false, true, false) => Status::Passed,
+            (false, false, false) => Status::Failed,
+            (false, false, true) => Status::ExpectedFailure,
+            (false, true, true) => Status::UnexpectedPass,
+        }
+    }
+
+    // describe returns what the test output and what it expected as text,
+    // only the last value unless the test compared several
+    pub fn describe(&self) -> (String, String) {
+        let list = |values: &[i16]| match values.is_empty() {
+            true => "None".to_string(),
+            false => values
+                .iter()
+                .map(|value| format!("{:03}", value))
+                .collect::<Vec<String>>()
+                .join(" "),
+        };
+        let single =
+            |value: Option<i16>| value.map_or("None".to_string(), |value| format!("{:03}", value));
+        // exact and tolerance comparisons of a single value only check the last output
+        let last_only = self.expected_outputs.len() <= 1
+            && (self.comparator == "exact" || self.comparator.starts_with("tolerance"));
+        let (mut got, mut expected) = match last_only {
+            true => (single(self.output), single(self.expected)),
+            false => (list(&self.outputs), list(&self.expected_outputs)),
+        };
+        if self.comparator != "exact" {
+            expected = format!("{} ({})", expected, self.comparator);
+        }
+        // mailboxes that did not hold the value expected follow the outputs
+        for cell in self.memory.iter().filter(|cell| cell.got != cell.expected) {
+            got.push_str(&format!(", {}={:03}", cell.cell, cell.got));
+            expected.push_str(&format!(", {}={:03}", cell.cell, cell.expected));
+        }
+        (got, expected)
+    }
+
+    // warnings describes the inputs the program never read and the outputs
+    // that were not checked, e.g. [sum] 1 of 3 inputs were never read
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if self.unconsumed > 0 {
+            warnings.push(format!(
+                "[{}] {} of {} inputs were never read",
+                self.label(),
+                self.unconsumed,
+                self.inputs.len()
+            ));
+        }
+        if self.unchecked > 0 {
+            warnings.push(format!(
+                "[{}] {} of {} outputs were not checked",
+                self.label(),
+                self.unchecked,
+                self.outputs.len()
+            ));
+        }
+        warnings
+    }
+}
+
+// Regression is a test that got worse compared to a baseline report
+#[derive(Debug, PartialEq, Clone)]
+pub enum Regression {
+    // NewlyFailing is a test that passed in the baseline but fails now
+    NewlyFailing(String),
+    // Slower is a passing test that takes more cycles than the threshold allows
+    Slower {
+        name: String,
+        before: usize,
+        after: usize,
+    },
+}
+
+// Implement the display trait for easy printing.
+impl fmt::Display for Regression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Regression::NewlyFailing(name) => write!(f, "[{}] newly failing", name),
+            Regression::Slower {
+                name,
+                before,
+                after,
+            } => write!(
+                f,
+                "[{}] slower: {} cycles, was {} ({:+.1}%)",
+                name,
+                after,
+                before,
+                percent_change(*before, *after)
+            ),
+        }
+    }
+}
+
+// percent_change returns how much larger after is than before as a percentage
+fn percent_change(before: usize, after: usize) -> f64 {
+    if before == 0 {
+        return if after == 0 { 0.0 } else { 100.0 };
+    }
+    (after as f64 - before as f64) * 100.0 / before as f64
+}
+
+// SavedReport is the part of a JSON report needed to use it as a baseline
+#[derive(Deserialize)]
+struct SavedReport {
+    schema: String,
+    tests: Vec<TestResult>,
+}
+
+// Bucket is a range of cycle counts and how many tests fell within it
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Bucket {
+    pub min: usize,
+    pub max: usize,
+    pub count: usize,
+}
+
+// Report collects the results of a batch run and summarises them
+#[derive(Debug, Default)]
+pub struct Report {
+    pub results: Vec<TestResult>,
+}
+
+impl Report {
+    // new creates an empty report
+    pub fn new() -> Self {
+        Report {
+            results: Vec::new(),
+        }
+    }
+
+    // load reads a report previously written with --report=json
+    pub fn load(path: &str) -> Result<Self, ReportError> {
+        let file = File::open(path).map_err(|e| ReportError::IOError(e.to_string()))?;
+        let saved: SavedReport = serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| ReportError::InvalidReport(e.to_string()))?;
+        if saved.schema != REPORT_SCHEMA {
+            return Err(ReportError::InvalidReport(format!(
+                "unsupported schema {}, expected {}",
+                saved.schema, REPORT_SCHEMA
+            )));
+        }
+        Ok(Report {
+            results: saved.tests,
+        })
+    }
+
+    // compare finds the tests that regressed since the baseline, either
+    // failing where they passed before or taking more than threshold percent
+    // more cycles. Tests missing from the baseline are not compared.
+    pub fn compare(&self, baseline: &Report, threshold: f64) -> Vec<Regression> {
+        let mut regressions = Vec::new();
+        for result in &self.results {
+            let before = match baseline
+                .results
+                .iter()
+                .find(|old| old.name == result.name && old.dialect == result.dialect)
+            {
+                Some(before) => before,
+                None => continue,
+            };
+            // a test marked to be skipped or expected to fail is not a regression
+            if before.passed && result.status() == Status::Failed {
+                regressions.push(Regression::NewlyFailing(result.label()));
+            } else if result.passed
+                && result.cycles > before.cycles
+                && percent_change(before.cycles, result.cycles) > threshold
+            {
+                regressions.push(Regression::Slower {
+                    name: result.label(),
+                    before: before.cycles,
+                    after: result.cycles,
+                });
+            }
+        }
+        regressions
+    }
+
+    // add records the result of a test
+    pub fn add(&mut self, result: TestResult) {
+        self.results.push(result);
+    }
+
+    // count returns the number of tests with the status
+    pub fn count(&self, status: Status) -> usize {
+        self.results
+            .iter()
+            .filter(|result| result.status() == status)
+            .count()
+    }
+
+    // passed returns the number of tests that passed
+    pub fn passed(&self) -> usize {
+        self.count(Status::Passed)
+    }
+
+    // counted returns the number of tests that count towards the pass rate,
+    // leaving out those marked to be skipped or expected to fail
+    pub fn counted(&self) -> usize {
+        self.passed() + self.count(Status::Failed)
+    }
+
+    // pass_rate returns the percentage of tests that passed
+    pub fn pass_rate(&self) -> f64 {
+        if self.counted() == 0 {
+            return 0.0;
+        }
+        self.passed() as f64 * 100.0 / self.counted() as f64
+    }
+
+    // ran returns the results of every test that was not skipped
+    fn ran(&self) -> impl DoubleEndedIterator<Item = &TestResult> {
+        self.results.iter().filter(|result| !result.skipped)
+    }
+
+    // total_cycles returns the number of cycles taken by every test together
+    pub fn total_cycles(&self) -> usize {
+        self.ran().map(|result| result.cycles).sum()
+    }
+
+    // slowest returns the test that took the most cycles, the first one
+    // listed if several took the same number
+    pub fn slowest(&self) -> Option<&TestResult> {
+        self.ran().rev().max_by_key(|result| result.cycles)
+    }
+
+    // markers describes how many tests were skipped, failed as expected or
+    // passed unexpectedly, if any were marked
+    fn markers(&self) -> Option<String> {
+        let counts = [
+            (self.count(Status::Skipped), "skipped"),
+            (self.count(Status::ExpectedFailure), "failed as expected"),
+            (self.count(Status::UnexpectedPass), "unexpectedly passed"),
+        ];
+        counts.iter().any(|(count, _)| *count > 0).then(|| {
+            counts
+                .iter()
+                .map(|(count, status)| format!("{} {}", count, status))
+                .collect::<Vec<String>>()
+                .join(", ")
+        })
+    }
+
+    // histogram splits the cycles taken per test into equal width buckets
+    // from the fastest test up to the slowest
+    pub fn histogram(&self) -> Vec<Bucket> {
+        let most = match self.slowest() {
+            Some(result) => result.cycles,
+            None => return Vec::new(),
+        };
+        let fewest = self.ran().map(|result| result.cycles).min().unwrap_or(0);
+        let width = (most - fewest) / HISTOGRAM_BUCKETS + 1;
+        let mut buckets = (0..=(most - fewest) / width)
+            .map(|i| Bucket {
+                min: fewest + i * width,
+                max: fewest + (i + 1) * width - 1,
+                count: 0,
+            })
+            .collect::<Vec<Bucket>>();
+        for result in self.ran() {
+            buckets[(result.cycles - fewest) / width].count += 1;
+        }
+        buckets
+    }
+
+    // peak_working_set returns the most mailboxes touched by any test and the
+    // highest address any test used
+    pub fn peak_working_set(&self) -> (usize, Option<usize>) {
+        let touched = self.results.iter().map(|result| result.touched).max();
+        let highest = self
+            .results
+            .iter()
+            .filter_map(|result| result.highest)
+            .max();
+        (touched.unwrap_or(0), highest)
+    }
+
+    // warnings returns the warnings of every test, in the order they ran
+    pub fn warnings(&self) -> Vec<String> {
+        self.results.iter().flat_map(TestResult::warnings).collect()
+    }
+
+    // matrix returns how many tests passed out of those counted on each
+    // dialect, in the order the dialects ran, empty unless the suite ran a
+    // matrix of them
+    pub fn matrix(&self) -> Vec<(String, usize, usize)> {
+        let mut matrix: Vec<(String, usize, usize)> = Vec::new();
+        for result in &self.results {
+            let dialect = match &result.dialect {
+                Some(dialect) => dialect,
+                None => continue,
+            };
+            let index = match matrix.iter().position(|(name, _, _)| name == dialect) {
+                Some(index) => index,
+                None => {
+                    matrix.push((dialect.clone(), 0, 0));
+                    matrix.len() - 1
+                }
+            };
+            match result.status() {
+                Status::Passed => {
+                    matrix[index].1 += 1;
+                    matrix[index].2 += 1;
+                }
+                Status::Failed => matrix[index].2 += 1,
+                _ => {}
+            }
+        }
+        matrix
+    }
+
+    // summary renders the aggregate statistics as text
+    pub fn summary(&self) -> String {
+        let mut text = self.table();
+        let warnings = self.warnings();
+        if !warnings.is_empty() {
+            text.push_str("Warnings:\n");
+            for warning in warnings {
+                text.push_str(&format!("  {}\n", warning));
+            }
+        }
+        text.push_str(&format!(
+            "Summary: {}/{} passed ({:.1}%), {} cycles in total\n",
+            self.passed(),
+            self.counted(),
+            self.pass_rate(),
+            self.total_cycles()
+        ));
+        let matrix = self.matrix();
+        if !matrix.is_empty() {
+            let dialects = matrix
+                .iter()
+                .map(|(dialect, passed, counted)| format!("{} {}/{}", dialect, passed, counted))
+                .collect::<Vec<String>>();
+            text.push_str(&format!("Matrix: {}\n", dialects.join(", ")));
+        }
+        if let Some(markers) = self.markers() {
+            text.push_str(&format!("Marked: {}\n", markers));
+        }
+        if let Some(slowest) = self.slowest() {
+            text.push_str(&format!(
+                "Slowest: {} ({} cycles)\n",
+                slowest.label(),
+                slowest.cycles
+            ));
+        }
+        if let (touched, Some(highest)) = self.peak_working_set() {
+            text.push_str(&format!(
+                "Peak working set: {} mailboxes, highest address {:02}\n",
+                touched, highest
+            ));
+        }
+        let buckets = self.histogram();
+        let fullest = buckets.iter().map(|bucket| bucket.count).max().unwrap_or(0);
+        if fullest > 0 {
+            text.push_str("Cycles per test:\n");
+            let labels = buckets
+                .iter()
+                .map(|bucket| match bucket.min == bucket.max {
+                    true => bucket.min.to_string(),
+                    false => format!("{}-{}", bucket.min, bucket.max),
+                })
+                .collect::<Vec<String>>();
+            let label_width = labels.iter().map(|label| label.len()).max().unwrap_or(0);
+            for (bucket, label) in buckets.iter().zip(labels) {
+                let bar = "#".repeat((bucket.count * HISTOGRAM_WIDTH).div_ceil(fullest));
+                text.push_str(&format!(
+                    "  {:width$}  {} {}\n",
+                    label,
+                    bar,
+                    bucket.count,
+                    width = label_width
+                ));
+            }
+        }
+        text
+    }
+
+    // table lists every test with its status and cycles, followed by what it
+    // output and expected if it failed, e.g.:
+    //   double  passed   5 cycles
+    //   triple  failed   4 cycles  got 012, expected 009
+    //   loop    failed  50 cycles  max cycles hit, got None, expected 001
+    fn table(&self) -> String {
+        if self.results.is_empty() {
+            return String::new();
+        }
+        let width = |column: &dyn Fn(&TestResult) -> String| {
+            self.results
+                .iter()
+                .map(|result| column(result).len())
+                .max()
+                .unwrap_or(0)
+        };
+        let name_width = width(&|result| result.label());
+        let cycles_width = width(&|result| result.cycles.to_string());
+        let mut text = "Results:\n".to_string();
+        for result in &self.results {
+            let status = result.status();
+            let mut line = format!(
+                "  {:name_width$}  {:7}",
+                result.label(),
+                status.to_string(),
+                name_width = name_width
+            );
+            if status != Status::Skipped {
+                line.push_str(&format!(
+                    "  {:>cycles_width$} cycles",
+                    result.cycles,
+                    cycles_width = cycles_width
+                ));
+            }
+            if status == Status::Failed {
+                line.push_str("  ");
+                // a test that did not halt also shows why it stopped
+                if !result.halt.starts_with("halted") {
+                    line.push_str(&format!("{}, ", result.halt));
+                }
+                let (got, expected) = result.describe();
+                line.push_str(&format!("got {}, expected {}", got, expected));
+            }
+            text.push_str(line.trim_end());
+            text.push('\n');
+        }
+        text
+    }
+
+    // to_markdown returns a feedback document for the program: a table of
+    // every test, the inputs of each failure along with what was expected and
+    // what the program output, the cycle statistics and any assembler warnings
+    pub fn to_markdown(
+        &self,
+        program: &str,
+        warnings: &[String],
+        regressions: Option<&[Regression]>,
+    ) -> String {
+        let value =
+            |value: Option<i16>| value.map_or("-".to_string(), |value| format!("{:03}", value));
+        let mut text = format!("# Results for {}\n\n", program);
+        text.push_str(&format!(
+            "{} of {} tests passed ({:.1}%).\n\n",
+            self.passed(),
+            self.counted(),
+            self.pass_rate()
+        ));
+        let matrix = self.matrix();
+        if !matrix.is_empty() {
+            for (dialect, passed, counted) in &matrix {
+                text.push_str(&format!(
+                    "- {}: {} of {} tests passed\n",
+                    dialect, passed, counted
+                ));
+            }
+            text.push('\n');
+        }
+        if let Some(markers) = self.markers() {
+            text.push_str(&format!("Marked: {}.\n\n", markers));
+        }
+        text.push_str("| Test | Result | Cycles | Output | Expected |\n");
+        text.push_str("| ---- | ------ | ------ | ------ | -------- |\n");
+        for result in &self.results {
+            let status = match (result.status(), &result.xfail) {
+                (Status::ExpectedFailure, Some(reason)) => {
+                    format!("failed as expected: {}", reason)
+                }
+                (Status::UnexpectedPass, Some(reason)) => {
+                    format!("unexpectedly passed, expected to fail: {}", reason)
+                }
+                (status, _) => status.to_string(),
+            };
+            text.push_str(&format!(
+                "| {} | {} | {} | {} | {} |\n",
+                result.label(),
+                status,
+                result.cycles,
+                value(result.output),
+                value(result.expected)
+            ));
+        }
+        let failures = self
+            .results
+            .iter()
+            .filter(|result| result.status() == Status::Failed)
+            .collect::<Vec<&TestResult>>();
+        if !failures.is_empty() {
+            text.push_str("\n## Failures\n\n");
+            for result in failures {
+                let inputs = result
+                    .inputs
+                    .iter()
+                    .map(|input| format!("{:03}", input))
+                    .collect::<Vec<String>>();
+                let quoted = |value: String| match value.as_str() {
+                    "None" => "no output".to_string(),
+                    _ => format!("`{}`", value),
+                };
+                let (output, expected) = result.describe();
+                let inputs = match inputs.is_empty() {
+                    true => "no inputs".to_string(),
+                    false => format!("inputs `{}`", inputs.join(", ")),
+                };
+                text.push_str(&format!(
+                    "- **{}**: with {} expected {} but got {}\n",
+                    result.label(),
+                    inputs,
+                    quoted(expected),
+                    quoted(output)
+                ));
+            }
+        }
+        text.push_str("\n## Cycles\n\n");
+        text.push_str(&format!("- Total: {}\n", self.total_cycles()));
+        if let Some(slowest) = self.slowest() {
+            text.push_str(&format!(
+                "- Slowest: {} ({} cycles)\n",
+                slowest.label(),
+                slowest.cycles
+            ));
+        }
+        if let (touched, Some(highest)) = self.peak_working_set() {
+            text.push_str(&format!(
+                "- Peak working set: {} mailboxes, highest address {:02}\n",
+                touched, highest
+            ));
+        }
+        let test_warnings = self.warnings();
+        if !test_warnings.is_empty() {
+            text.push_str("\n## Test Warnings\n\n");
+            for warning in test_warnings {
+                text.push_str(&format!("- {}\n", warning));
+            }
+        }
+        if let Some(regressions) = regressions.filter(|regressions| !regressions.is_empty()) {
+            text.push_str("\n## Regressions\n\n");
+            for regression in regressions {
+                text.push_str(&format!("- {}\n", regression));
+            }
+        }
+        if !warnings.is_empty() {
+            text.push_str("\n## Assembler Warnings\n\n");
+            for warning in warnings {
+                text.push_str(&format!("- {}\n", warning));
+            }
+        }
+        text
+    }
+
+    // to_prometheus returns the summary as metrics in the Prometheus text
+    // exposition format, and the number of regressions if the report was
+    // compared to a baseline
+    pub fn to_prometheus(&self, regressions: Option<&[Regression]>) -> String {
+        let mut text = String::new();
+        text.push_str("# HELP lmc_batch_tests_total Tests run by the batch.\n");
+        text.push_str("# TYPE lmc_batch_tests_total counter\n");
+        text.push_str(&format!(
+            "lmc_batch_tests_total{{result=\"passed\"}} {}\n",
+            self.passed()
+        ));
+        for status in [
+            Status::Failed,
+            Status::Skipped,
+            Status::ExpectedFailure,
+            Status::UnexpectedPass,
+        ] {
+            text.push_str(&format!(
+                "lmc_batch_tests_total{{result=\"{}\"}} {}\n",
+                status,
+                self.count(status)
+            ));
+        }
+        text.push_str("# HELP lmc_batch_cycles Cycles simulated per test.\n");
+        text.push_str("# TYPE lmc_batch_cycles histogram\n");
+        for le in PROMETHEUS_BUCKETS {
+            let count = self.ran().filter(|result| result.cycles <= le).count();
+            text.push_str(&format!(
+                "lmc_batch_cycles_bucket{{le=\"{}\"}} {}\n",
+                le, count
+            ));
+        }
+        text.push_str(&format!(
+            "lmc_batch_cycles_bucket{{le=\"+Inf\"}} {}\n",
+            self.ran().count()
+        ));
+        text.push_str(&format!("lmc_batch_cycles_sum {}\n", self.total_cycles()));
+        text.push_str(&format!("lmc_batch_cycles_count {}\n", self.ran().count()));
+        text.push_str("# HELP lmc_batch_peak_touched Most mailboxes touched by a test.\n");
+        text.push_str("# TYPE lmc_batch_peak_touched gauge\n");
+        text.push_str(&format!(
+            "lmc_batch_peak_touched {}\n",
+            self.peak_working_set().0
+        ));
+        if let Some(regressions) = regressions {
+            text.push_str("# HELP lmc_batch_regressions Regressions against the baseline.\n");
+            text.push_str("# TYPE lmc_batch_regressions gauge\n");
+            text.push_str(&format!("lmc_batch_regressions {}\n", regressions.len()));
+        }
+        text
+    }
+
+    // to_json returns every result along with the summary as a JSON object,
+    // and the regressions found if the report was compared to a baseline
+    pub fn to_json(&self, regressions: Option<&[Regression]>) -> Value {
+        let results = self
+            .results
+            .iter()
+            .map(|result| {
+                json!({
+                    "name": result.name,
+                    "passed": result.passed,
+                    "cycles": result.cycles,
+                    "halt": result.halt,
+                    "inputs": result.inputs,
+                    "output": result.output,
+                    "expected": result.expected,
+                    "outputs": result.outputs,
+                    "expected_outputs": result.expected_outputs,
+                    "comparator": result.comparator,
+                    "unconsumed": result.unconsumed,
+                    "unchecked": result.unchecked,
+                    "touched": result.touched,
+                    "highest": result.highest,
+                    "seed": result.seed,
+                    "status": result.status().to_string(),
+                    "skipped": result.skipped,
+                    "xfail": result.xfail,
+                    "dialect": result.dialect,
+                    "memory": result
+                        .memory
+                        .iter()
+                        .map(|cell| json!({
+                            "cell": cell.cell,
+                            "got": cell.got,
+                            "expected": cell.expected,
+                        }))
+                        .collect::<Vec<Value>>(),
+                })
+            })
+            .collect::<Vec<Value>>();
+        let histogram = self
+            .histogram()
+            .iter()
+            .map(|bucket| json!({ "min": bucket.min, "max": bucket.max, "count": bucket.count }))
+            .collect::<Vec<Value>>();
+        let mut report = json!({
+            "schema": REPORT_SCHEMA,
+            "build": version::build_info(),
+            "tests": results,
+            "summary": {
+                "total": self.results.len(),
+                "passed": self.passed(),
+                "failed": self.count(Status::Failed),
+                "skipped": self.count(Status::Skipped),
+                "xfailed": self.count(Status::ExpectedFailure),
+                "xpassed": self.count(Status::UnexpectedPass),
+                "pass_rate": self.pass_rate(),
+                "total_cycles": self.total_cycles(),
+                "slowest": self.slowest().map(|result| {
+                    json!({
+                        "name": result.name,
+                        "dialect": result.dialect,
+                        "cycles": result.cycles,
+                    })
+                }),
+                "matrix": self
+                    .matrix()
+                    .iter()
+                    .map(|(dialect, passed, counted)| json!({
+                        "dialect": dialect,
+                        "passed": passed,
+                        "failed": counted - passed,
+                    }))
+                    .collect::<Vec<Value>>(),
+                "histogram": histogram,
+                "peak_touched": self.peak_working_set().0,
+                "peak_highest": self.peak_working_set().1,
+            },
+        });
+        if let Some(regressions) = regressions {
+            report["regressions"] = regressions
+                .iter()
+                .map(|regression| match regression {
+                    Regression::NewlyFailing(name) => {
+                        json!({ "name": name, "kind": "newly_failing" })
+                    }
+                    Regression::Slower {
+                        name,
+                        before,
+                        after,
+                    } => json!({
+                        "name": name,
+                        "kind": "slower",
+                        "before": before,
+                        "after": after,
+                    }),
+                })
+                .collect::<Vec<Value>>()
+                .into();
+        }
+        report
+    }
+}
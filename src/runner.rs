@@ -0,0 +1,681 @@
+use std::{fmt, ops::RangeInclusive};
+
+use crate::{
+    devices::Rng,
+    expr::{split_top_level, Expr, ExprError},
+    lmc::{LMCError, RunResult, LMC},
+    numbers::ThreeDigitNumber,
+    program::Program,
+    report::{CellResult, Report, TestResult},
+};
+
+// Comparator decides whether the outputs of a test match what was expected
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Comparator {
+    // Exact compares the last output with a single expected value, or every
+    // output in order with several
+    Exact,
+    // Tolerance compares like Exact but allows each output to differ from the
+    // expected value by up to the given amount
+    Tolerance(i16),
+    // Set compares every output with the expected values in any order
+    Set,
+    // Prefix checks the outputs start with the expected values in order
+    Prefix,
+}
+
+impl Comparator {
+    // from_str converts a string such as exact, set, prefix or tolerance=2 to
+    // a comparator
+    pub fn from_str(comparator: &str) -> Option<Comparator> {
+        match comparator.split_once('=') {
+            Some(("tolerance", amount)) => amount.trim().parse().ok().map(Comparator::Tolerance),
+            Some(_) => None,
+            None => match comparator {
+                "exact" => Some(Comparator::Exact),
+                "set" => Some(Comparator::Set),
+                "prefix" => Some(Comparator::Prefix),
+                _ => None,
+            },
+        }
+    }
+
+    // matches returns whether the outputs match the expected values
+    pub fn matches(&self, expected: &[i16], outputs: &[i16]) -> bool {
+        // a single expected value, or none, is compared with the last output
+        let outputs = match (self, expected.len()) {
+            (Comparator::Exact | Comparator::Tolerance(_), 0 | 1) => match outputs.last() {
+                Some(last) => std::slice::from_ref(last),
+                None => &[],
+            },
+            _ => outputs,
+        };
+        match self {
+            Comparator::Exact => outputs == expected,
+            Comparator::Tolerance(amount) => {
+                outputs.len() == expected.len()
+                    && outputs.iter().zip(expected).all(|(output, expected)| {
+                        // a clamped expected value is far enough from
+                        // an output to overflow an i16
+                        (*output as i32 - *expected as i32).abs() <= *amount as i32
+                    })
+            }
+            Comparator::Set => {
+                let (mut outputs, mut expected) = (outputs.to_vec(), expected.to_vec());
+                outputs.sort();
+                expected.sort();
+                outputs == expected
+            }
+            Comparator::Prefix => outputs.starts_with(expected),
+        }
+    }
+
+    // unchecked returns how many of the outputs the comparison never looks
+    // at: those before the last when a single value is expected, or those
+    // after the expected values when checking a prefix
+    pub fn unchecked(&self, expected: &[i16], outputs: &[i16]) -> usize {
+        match (self, expected.len()) {
+            (Comparator::Exact | Comparator::Tolerance(_), 1) => outputs.len().saturating_sub(1),
+            (Comparator::Prefix, checked) => outputs.len().saturating_sub(checked),
+            _ => 0,
+        }
+    }
+}
+
+// Implement the display trait for easy printing.
+impl fmt::Display for Comparator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Comparator::Exact => write!(f, "exact"),
+            Comparator::Tolerance(amount) => write!(f, "tolerance={}", amount),
+            Comparator::Set => write!(f, "set"),
+            Comparator::Prefix => write!(f, "prefix"),
+        }
+    }
+}
+
+// MAX_DOMAIN_CASES is the most combinations of inputs tested exhaustively,
+// larger domains are sampled at random so that they cannot run for hours
+const MAX_DOMAIN_CASES: usize = 1_000_000;
+
+// MAX_SHRINK_RUNS is the most runs spent shrinking a counterexample
+const MAX_SHRINK_RUNS: usize = 1000;
+
+// PROGRESS_CYCLES is how often a long running test raises CyclesElapsed
+pub const PROGRESS_CYCLES: usize = 10000;
+
+// Domain is a range of values for every input of a test along with
+// expressions for the outputs expected, so that every combination of inputs
+// is tested rather than a few chosen by hand
+#[derive(Debug, Clone)]
+pub struct Domain {
+    pub ranges: Vec<RangeInclusive<i16>>,
+    pub expect: Vec<Expr>,
+}
+
+impl Domain {
+    // is_domain returns whether the inputs of a test are given as ranges
+    pub fn is_domain(inputs: &str) -> bool {
+        inputs.contains("..")
+    }
+
+    // parse reads the inputs as ranges such as 0..=20 or 0..21, or single
+    // values, and the result as expressions of them such as min(in1, in2)
+    pub fn parse(inputs: &str, result: &str) -> Result<Domain, RunnerError> {
+        let invalid = |value: &str| RunnerError::InvalidDomain(value.to_string());
+        let bound = |value: &str| {
+            value
+                .trim()
+                .parse::<i16>()
+                .ok()
+                .filter(|value| (0..=999).contains(value))
+                .ok_or_else(|| invalid(value))
+        };
+        let mut ranges = Vec::new();
+        for part in inputs.split(',') {
+            let range = match (part.split_once("..="), part.split_once("..")) {
+                (Some((start, end)), _) => bound(start)?..=bound(end)?,
+                (None, Some((start, end))) => bound(start)?..=bound(end)? - 1,
+                (None, None) => bound(part)?..=bound(part)?,
+            };
+            if range.is_empty() {
+                return Err(invalid(part));
+            }
+            ranges.push(range);
+        }
+        let expect = split_top_level(result)
+            .iter()
+            .filter(|expr| !expr.trim().is_empty())
+            .map(|expr| Expr::parse(expr).map_err(|e| invalid(&e.to_string())))
+            .collect::<Result<Vec<Expr>, RunnerError>>()?;
+        // every input an expression refers to must be in the domain
+        let highest = ranges
+            .iter()
+            .map(|range| *range.end() as i64)
+            .collect::<Vec<i64>>();
+        for expr in &expect {
+            if let Err(ExprError::UnknownInput(index)) = expr.eval(&highest) {
+                return Err(invalid(&format!("unknown input: in{}", index)));
+            }
+        }
+        Ok(Domain { ranges, expect })
+    }
+
+    // size returns the number of combinations of inputs in the domain
+    pub fn size(&self) -> usize {
+        self.ranges
+            .iter()
+            .map(|range| range.clone().count())
+            .fold(1, usize::saturating_mul)
+    }
+
+    // cases returns every combination of inputs, varying the last input fastest
+    pub fn cases(&self) -> impl Iterator<Item = Vec<i16>> + '_ {
+        (0..self.size()).map(move |mut index| {
+            let mut case = vec![0; self.ranges.len()];
+            for (value, range) in case.iter_mut().zip(&self.ranges).rev() {
+                let count = range.clone().count();
+                *value = range.start() + (index % count) as i16;
+                index /= count;
+            }
+            case
+        })
+    }
+
+    // is_exhaustive returns whether every combination of inputs is tested
+    pub fn is_exhaustive(&self) -> bool {
+        self.size() <= MAX_DOMAIN_CASES
+    }
+
+    // sample returns a combination of inputs chosen at random
+    pub fn sample(&self, rng: &mut Rng) -> Vec<i16> {
+        self.ranges
+            .iter()
+            .map(|range| rng.range(*range.start(), *range.end()))
+            .collect()
+    }
+
+    // shrink returns smaller combinations of inputs to try in place of a
+    // failing one, each with one input moved towards the start of its range
+    pub fn shrink(&self, inputs: &[i16]) -> Vec<Vec<i16>> {
+        let mut candidates = Vec::new();
+        for (i, range) in self.ranges.iter().enumerate() {
+            let value = inputs[i];
+            let start = *range.start();
+            for smaller in [start, start + (value - start) / 2, value - 1] {
+                let mut candidate = inputs.to_vec();
+                candidate[i] = smaller;
+                if smaller >= start && smaller < value && !candidates.contains(&candidate) {
+                    candidates.push(candidate);
+                }
+            }
+        }
+        candidates
+    }
+
+    // expected evaluates the expressions for the inputs
+    pub fn expected(&self, inputs: &[i16]) -> Result<Vec<i16>, RunnerError> {
+        let inputs = inputs
+            .iter()
+            .map(|value| *value as i64)
+            .collect::<Vec<i64>>();
+        self.expect
+            .iter()
+            .map(|expr| {
+                let value = expr
+                    .eval(&inputs)
+                    .map_err(|e| RunnerError::InvalidDomain(e.to_string()))?;
+                // a value no mailbox can hold never matches an output
+                Ok(value.clamp(i16::MIN as i64, i16::MAX as i64) as i16)
+            })
+            .collect()
+    }
+}
+
+// Marker marks a test that is not expected to pass yet, so that a suite for
+// a partially written program can be kept in full
+#[derive(Debug, PartialEq, Clone)]
+pub enum Marker {
+    // Skip is a test that is not run at all
+    Skip,
+    // ExpectedFailure is a test that is run but expected to fail, for the
+    // reason given
+    ExpectedFailure(String),
+}
+
+// Test is a single batch test: the inputs to run the program with, the output
+// expected and the most cycles it may take
+#[derive(Debug, Clone)]
+pub struct Test {
+    pub name: String,
+    pub input: Option<Vec<ThreeDigitNumber>>,
+    // result holds the outputs expected, compared using the comparator
+    pub result: Vec<ThreeDigitNumber>,
+    pub cycles: usize,
+    // entry is the label or address the test starts executing from
+    pub entry: Option<String>,
+    pub comparator: Comparator,
+    // domain replaces the input and result to test every combination of inputs
+    pub domain: Option<Domain>,
+    // marker marks the test to be skipped or expected to fail
+    pub marker: Option<Marker>,
+    // memory are the mailboxes expected to hold a value once the program halts
+    pub memory: Vec<Cell>,
+}
+
+// Cell is a mailbox a test expects to hold a value once the program halts,
+// named by a label or an address
+#[derive(Debug, Clone)]
+pub struct Cell {
+    pub name: String,
+    pub expected: ThreeDigitNumber,
+}
+
+impl Test {
+    pub fn new(
+        name: &str,
+        input: Option<Vec<ThreeDigitNumber>>,
+        result: Vec<ThreeDigitNumber>,
+        cycles: usize,
+        entry: Option<String>,
+    ) -> Test {
+        Test {
+            name: name.to_string(),
+            input,
+            result,
+            cycles,
+            entry,
+            comparator: Comparator::Exact,
+            domain: None,
+            marker: None,
+            memory: Vec::new(),
+        }
+    }
+
+    // set_marker marks the test to be skipped or expected to fail
+    pub fn set_marker(&mut self, marker: Marker) {
+        self.marker = Some(marker);
+    }
+
+    // xfail returns the reason the test is expected to fail, if it is
+    pub fn xfail(&self) -> Option<&String> {
+        match &self.marker {
+            Some(Marker::ExpectedFailure(reason)) => Some(reason),
+            _ => None,
+        }
+    }
+
+    // set_domain tests every combination of inputs in the domain
+    pub fn set_domain(&mut self, domain: Domain) {
+        self.domain = Some(domain);
+    }
+
+    // set_comparator sets how the outputs are compared with the result
+    pub fn set_comparator(&mut self, comparator: Comparator) {
+        self.comparator = comparator;
+    }
+
+    // set_memory sets the mailboxes checked once the program halts
+    pub fn set_memory(&mut self, memory: Vec<Cell>) {
+        self.memory = memory;
+    }
+}
+
+// RunnerError is used to indicate a suite that could not be run to the end
+#[derive(Debug, PartialEq)]
+pub enum RunnerError {
+    UnknownEntry(String),
+    UnknownCell(String),
+    InvalidDomain(String),
+}
+
+// Implement the display trait for easy printing.
+impl fmt::Display for RunnerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunnerError::UnknownEntry(entry) => write!(f, "Unknown entry point: {}", entry),
+            RunnerError::UnknownCell(cell) => write!(f, "Unknown memory cell: {}", cell),
+            RunnerError::InvalidDomain(value) => write!(f, "invalid input domain: {}", value),
+        }
+    }
+}
+
+// SuiteEvent is the progress of a suite as it runs, so that a caller can show
+// each test as it happens rather than waiting for the final report
+#[derive(Debug)]
+pub enum SuiteEvent<'a> {
+    TestStarted {
+        test: &'a Test,
+    },
+    // TestSkipped is raised in place of running a test marked to be skipped
+    TestSkipped {
+        test: &'a Test,
+    },
+    // IterationFinished is raised after every case of a test has run: once
+    // for a test with fixed inputs and once for every case of a domain tested,
+    // numbered from 0, but not for the runs spent shrinking a counterexample
+    IterationFinished {
+        test: &'a Test,
+        iteration: usize,
+        result: &'a TestResult,
+    },
+    // CyclesElapsed is raised every PROGRESS_CYCLES cycles of a case, so that
+    // a slow test can be told apart from a stuck one
+    CyclesElapsed {
+        test: &'a Test,
+        cycles: usize,
+    },
+    // Shrunk is raised when a random counterexample was shrunk to a smaller one
+    Shrunk {
+        test: &'a Test,
+        from: &'a [i16],
+        to: &'a [i16],
+    },
+    TestFinished {
+        test: &'a Test,
+        run: &'a RunResult,
+        result: &'a TestResult,
+    },
+}
+
+// Suite runs batch tests one after another against a single program on an LMC
+// configured by the caller
+pub struct Suite {
+    lmc: LMC,
+    program: Program,
+    tests: Vec<Test>,
+    // seed and samples choose the inputs tested for domains too large to
+    // test exhaustively
+    seed: u64,
+    samples: usize,
+    // replay is the seed of the single random case tested in their place
+    replay: Option<u64>,
+}
+
+impl Suite {
+    // new creates an empty suite, loading the program into the LMC
+    pub fn new(mut lmc: LMC, program: Program) -> Result<Self, LMCError> {
+        lmc.load_program(&program)?;
+        // a test supplies every input up front, so a program asking for more
+        // fails the test rather than waiting on stdin
+        lmc.set_input_underflow(true);
+        Ok(Suite {
+            lmc,
+            program,
+            tests: Vec::new(),
+            seed: 1,
+            samples: 10000,
+            replay: None,
+        })
+    }
+
+    // set_sampling sets the seed and the number of combinations of inputs
+    // tested for domains too large to test exhaustively
+    pub fn set_sampling(&mut self, seed: u64, samples: usize) {
+        self.seed = seed;
+        self.samples = samples;
+    }
+
+    // set_replay sets the seed of a random case reported by an earlier run, so
+    // that domains too large to test exhaustively test only that case
+    pub fn set_replay(&mut self, seed: u64) {
+        self.replay = Some(seed);
+    }
+
+    // add appends a test to be run after those already added
+    pub fn add(&mut self, test: Test) {
+        self.tests.push(test);
+    }
+
+    // run_suite_with runs every test in order, handing each event to the
+    // callback as it happens, and returns the report once all have passed or
+    // failed. A test that does not halt fails without stopping the suite.
+    pub fn run_suite_with(
+        &mut self,
+        mut progress: impl FnMut(SuiteEvent),
+    ) -> Result<Report, RunnerError> {
+        let mut starts = Vec::new();
+        for test in &self.tests {
+            let entry = match &test.entry {
+                Some(entry) => self
+                    .program
+                    .resolve_address(entry)
+                    .ok_or_else(|| RunnerError::UnknownEntry(entry.clone()))?,
+                None => 0,
+            };
+            let mut cells = Vec::new();
+            for cell in &test.memory {
+                let address = self
+                    .program
+                    .resolve_address(&cell.name)
+                    .ok_or_else(|| RunnerError::UnknownCell(cell.name.clone()))?;
+                cells.push((address, cell.clone()));
+            }
+            starts.push(Start { entry, cells });
+        }
+        let mut report = Report::new();
+        for (test, start) in self.tests.iter().zip(&starts) {
+            if test.marker == Some(Marker::Skip) {
+                progress(SuiteEvent::TestSkipped { test });
+                report.add(TestResult::skipped(&test.name));
+                continue;
+            }
+            progress(SuiteEvent::TestStarted { test });
+            let (run, result) = match &test.domain {
+                Some(domain) => {
+                    // a domain too large to test exhaustively is sampled at random,
+                    // each case from its own seed so that it can be replayed
+                    let seed = self.seed;
+                    let sample = |seed| (domain.sample(&mut Rng::new(seed)), Some(seed));
+                    let cases: Box<dyn Iterator<Item = (Vec<i16>, Option<u64>)>> =
+                        match (domain.is_exhaustive(), self.replay) {
+                            (true, _) => Box::new(domain.cases().map(|inputs| (inputs, None))),
+                            (false, Some(replay)) => Box::new(std::iter::once(sample(replay))),
+                            (false, None) => {
+                                Box::new((0..self.samples).map(|i| sample(Rng::case_seed(seed, i))))
+                            }
+                        };
+                    let mut worst: Option<(Vec<i16>, RunResult, TestResult)> = None;
+                    for (iteration, (inputs, seed)) in cases.enumerate() {
+                        let (run, result) = run_domain_case(
+                            &mut self.lmc,
+                            test,
+                            start,
+                            domain,
+                            &inputs,
+                            seed,
+                            &mut progress,
+                        )?;
+                        progress(SuiteEvent::IterationFinished {
+                            test,
+                            iteration,
+                            result: &result,
+                        });
+                        // the first counterexample is reported, otherwise the slowest case
+                        let failed = !result.passed;
+                        if failed
+                            || worst
+                                .as_ref()
+                                .is_none_or(|worst| run.cycles > worst.1.cycles)
+                        {
+                            worst = Some((inputs, run, result));
+                        }
+                        if failed {
+                            break;
+                        }
+                    }
+                    let (inputs, run, result) = worst.ok_or_else(|| {
+                        RunnerError::InvalidDomain(format!("{} has no inputs to test", test.name))
+                    })?;
+                    // the first counterexample of an exhaustive search is already
+                    // the smallest, a random one is shrunk unless it is replayed
+                    match result.passed || domain.is_exhaustive() || self.replay.is_some() {
+                        true => (run, result),
+                        false => {
+                            let seed = result.seed;
+                            let (shrunk, run, mut result) = shrink(
+                                &mut self.lmc,
+                                test,
+                                start,
+                                domain,
+                                (inputs.clone(), run, result),
+                                &mut progress,
+                            );
+                            if shrunk != inputs {
+                                progress(SuiteEvent::Shrunk {
+                                    test,
+                                    from: &inputs,
+                                    to: &shrunk,
+                                });
+                            }
+                            // the seed still replays the case that was shrunk
+                            result.seed = seed;
+                            (run, result)
+                        }
+                    }
+                }
+                None => {
+                    let inputs = test.input.clone().unwrap_or_default();
+                    let expected = test.result.iter().map(|number| number.value()).collect();
+                    let (run, result) =
+                        run_case(&mut self.lmc, test, start, &inputs, expected, &mut progress);
+                    progress(SuiteEvent::IterationFinished {
+                        test,
+                        iteration: 0,
+                        result: &result,
+                    });
+                    (run, result)
+                }
+            };
+            progress(SuiteEvent::TestFinished {
+                test,
+                run: &run,
+                result: &result,
+            });
+            report.add(result);
+        }
+        Ok(report)
+    }
+}
+
+// Start is where a test starts executing from and the mailboxes it checks
+// once the program halts, their labels resolved to addresses
+struct Start {
+    entry: usize,
+    cells: Vec<(usize, Cell)>,
+}
+
+// run_case runs the test once with the inputs and compares the outputs with
+// the values expected
+fn run_case(
+    lmc: &mut LMC,
+    test: &Test,
+    start: &Start,
+    inputs: &[ThreeDigitNumber],
+    expected: Vec<i16>,
+    progress: &mut impl FnMut(SuiteEvent),
+) -> (RunResult, TestResult) {
+    lmc.set_max_cycles(test.cycles);
+    // never let the mailboxes stored to or the inputs left over by the
+    // previous run leak into this one
+    lmc.reset();
+    lmc.load_input(inputs);
+    let on_cycle = |cycles| {
+        if cycles % PROGRESS_CYCLES == 0 {
+            progress(SuiteEvent::CyclesElapsed { test, cycles })
+        }
+    };
+    // a run that does not halt fails the test, with the reason it stopped
+    let run = lmc.execute_from_with(start.entry, on_cycle);
+    let outputs = lmc
+        .get_outputs()
+        .iter()
+        .map(|number| number.value())
+        .collect::<Vec<i16>>();
+    let mailboxes = lmc.state().mailboxes;
+    let memory = start
+        .cells
+        .iter()
+        .map(|(address, cell)| CellResult {
+            cell: cell.name.clone(),
+            got: mailboxes[*address].value(),
+            expected: cell.expected.value(),
+        })
+        .collect::<Vec<CellResult>>();
+    let unchecked = test.comparator.unchecked(&expected, &outputs);
+    let result = TestResult {
+        name: test.name.clone(),
+        passed: run.is_halted()
+            && test.comparator.matches(&expected, &outputs)
+            && memory.iter().all(|cell| cell.got == cell.expected),
+        cycles: run.cycles,
+        halt: run.reason.to_string(),
+        inputs: inputs.iter().map(|number| number.value()).collect(),
+        output: outputs.last().copied(),
+        expected: expected.last().copied(),
+        outputs,
+        expected_outputs: expected,
+        comparator: test.comparator.to_string(),
+        unconsumed: inputs.len().saturating_sub(lmc.inputs()),
+        unchecked,
+        touched: lmc.working_set().touched,
+        highest: lmc.working_set().highest,
+        seed: None,
+        skipped: false,
+        xfail: test.xfail().cloned(),
+        memory,
+        dialect: None,
+    };
+    (run, result)
+}
+
+// run_domain_case runs a test with an input domain once with the inputs,
+// generated from the seed when they were chosen at random
+fn run_domain_case(
+    lmc: &mut LMC,
+    test: &Test,
+    start: &Start,
+    domain: &Domain,
+    inputs: &[i16],
+    seed: Option<u64>,
+    progress: &mut impl FnMut(SuiteEvent),
+) -> Result<(RunResult, TestResult), RunnerError> {
+    let expected = domain.expected(inputs)?;
+    let inputs = inputs
+        .iter()
+        .map(|value| ThreeDigitNumber::new(*value).unwrap())
+        .collect::<Vec<ThreeDigitNumber>>();
+    let (run, result) = run_case(lmc, test, start, &inputs, expected, progress);
+    Ok((run, TestResult { seed, ..result }))
+}
+
+// shrink repeatedly replaces the failing inputs with smaller ones that still
+// fail, until none of the candidates fail or MAX_SHRINK_RUNS is reached,
+// returning the smallest failing inputs found with their result
+fn shrink(
+    lmc: &mut LMC,
+    test: &Test,
+    start: &Start,
+    domain: &Domain,
+    mut failing: (Vec<i16>, RunResult, TestResult),
+    progress: &mut impl FnMut(SuiteEvent),
+) -> (Vec<i16>, RunResult, TestResult) {
+    let mut runs = 0;
+    'shrink: while runs < MAX_SHRINK_RUNS {
+        for candidate in domain.shrink(&failing.0) {
+            runs += 1;
+            // a candidate that fails by halting when the counterexample did
+            // not, or the other way around, is a different failure so is skipped
+            if let Ok((run, result)) =
+                run_domain_case(lmc, test, start, domain, &candidate, None, progress)
+            {
+                if !result.passed && run.is_halted() == failing.1.is_halted() {
+                    failing = (candidate, run, result);
+                    continue 'shrink;
+                }
+            }
+        }
+        break;
+    }
+    failing
+}
@@ -0,0 +1,134 @@
+use std::fmt;
+
+use crate::{
+    lmc::{LMCError, StepResult, LMC},
+    numbers::ThreeDigitNumber,
+};
+
+// SchedulerEvent is something of note that happened to one of the scheduled
+// machines while it was running
+#[derive(Debug, PartialEq)]
+pub enum SchedulerEvent {
+    Output {
+        machine: String,
+        cycle: usize,
+        value: ThreeDigitNumber,
+    },
+    Halted {
+        machine: String,
+        cycles: usize,
+    },
+    Failed {
+        machine: String,
+        error: LMCError,
+    },
+}
+
+// Implement the display trait for easy printing.
+impl fmt::Display for SchedulerEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchedulerEvent::Output {
+                machine,
+                cycle,
+                value,
+            } => write!(f, "[{}] cycle {}: output {}", machine, cycle, value),
+            SchedulerEvent::Halted { machine, cycles } => {
+                write!(f, "[{}] halted after {} cycles", machine, cycles)
+            }
+            SchedulerEvent::Failed { machine, error } => write!(f, "[{}] {}", machine, error),
+        }
+    }
+}
+
+// Task is a single machine being run by the scheduler
+struct Task {
+    name: String,
+    lmc: LMC,
+    finished: bool,
+}
+
+// Scheduler round-robins a number of LMCs, running each for at most a fixed
+// budget of fetch-execute cycles per slice before moving on to the next, so
+// that many programs make progress together on a single thread
+pub struct Scheduler {
+    tasks: Vec<Task>,
+    // slice is the number of cycles each machine may run for per turn
+    slice: usize,
+}
+
+impl Scheduler {
+    // new creates an empty Scheduler with the given per-slice cycle budget
+    pub fn new(slice: usize) -> Self {
+        Scheduler {
+            tasks: Vec::new(),
+            slice: slice.max(1),
+        }
+    }
+
+    // add schedules an LMC with its program already loaded under the given name
+    pub fn add(&mut self, name: &str, mut lmc: LMC) {
+        lmc.start();
+        self.tasks.push(Task {
+            name: name.to_string(),
+            lmc,
+            finished: false,
+        });
+    }
+
+    // is_finished returns whether every scheduled machine has halted or failed
+    pub fn is_finished(&self) -> bool {
+        self.tasks.iter().all(|task| task.finished)
+    }
+
+    // run_round gives every unfinished machine one slice of cycles in turn,
+    // returning the events raised during the round in the order they occurred
+    pub fn run_round(&mut self) -> Vec<SchedulerEvent> {
+        let mut events = Vec::new();
+        for task in self.tasks.iter_mut().filter(|task| !task.finished) {
+            for _ in 0..self.slice {
+                let outputs = task.lmc.outputs();
+                match task.lmc.step() {
+                    Ok(result) => {
+                        if task.lmc.outputs() > outputs {
+                            if let Some(value) = task.lmc.get_output() {
+                                events.push(SchedulerEvent::Output {
+                                    machine: task.name.clone(),
+                                    cycle: task.lmc.cycles(),
+                                    value,
+                                });
+                            }
+                        }
+                        if result == StepResult::Halted {
+                            events.push(SchedulerEvent::Halted {
+                                machine: task.name.clone(),
+                                cycles: task.lmc.cycles(),
+                            });
+                            task.finished = true;
+                            break;
+                        }
+                    }
+                    Err(error) => {
+                        events.push(SchedulerEvent::Failed {
+                            machine: task.name.clone(),
+                            error,
+                        });
+                        task.finished = true;
+                        break;
+                    }
+                }
+            }
+        }
+        events
+    }
+
+    // run runs rounds until every machine has finished, handing each event
+    // to the callback as soon as its round completes
+    pub fn run(&mut self, mut on_event: impl FnMut(&SchedulerEvent)) {
+        while !self.is_finished() {
+            for event in self.run_round() {
+                on_event(&event);
+            }
+        }
+    }
+}
@@ -0,0 +1,517 @@
+use serde_json::{json, Value};
+
+// TRACE_SCHEMA identifies the version of the JSON trace format, see Trace
+pub const TRACE_SCHEMA: &str = "lmc/trace@1";
+
+// STATE_SCHEMA identifies the version of the JSON state snapshot format
+pub const STATE_SCHEMA: &str = "lmc/state@1";
+
+// REPORT_SCHEMA identifies the version of the JSON batch report format
+pub const REPORT_SCHEMA: &str = "lmc/report@1";
+
+// SYNTAX_SCHEMA identifies the version of the JSON tokenized source format
+pub const SYNTAX_SCHEMA: &str = "lmc/syntax@1";
+
+// CONFORMANCE_SCHEMA identifies the version of the JSON conformance suite
+// format, see ConformanceSuite
+pub const CONFORMANCE_SCHEMA: &str = "lmc/conformance@1";
+
+// BUNDLE_SCHEMA identifies the version of the JSON classroom bundle format,
+// see Bundle
+pub const BUNDLE_SCHEMA: &str = "lmc/bundle@1";
+
+// DEBUG_INFO_SCHEMA identifies the version of the JSON debug info format,
+// see DebugInfo
+pub const DEBUG_INFO_SCHEMA: &str = "lmc/debug-info@1";
+
+// DIAGNOSTICS_SCHEMA identifies the version of the JSON diagnostics format
+// printed by assemble --diagnostics=json
+pub const DIAGNOSTICS_SCHEMA: &str = "lmc/diagnostics@1";
+
+// SchemaKind is a kind of machine-readable JSON document written by lmc.
+// Every document carries a "schema" field naming its kind and version, the
+// version only changes when a field is removed or its meaning changes.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SchemaKind {
+    Trace,
+    State,
+    Report,
+    Syntax,
+    Conformance,
+    Bundle,
+    DebugInfo,
+    Diagnostics,
+}
+
+impl SchemaKind {
+    // from_str converts a string to a schema kind
+    pub fn from_str(kind: &str) -> Option<SchemaKind> {
+        match kind {
+            "trace" => Some(SchemaKind::Trace),
+            "state" => Some(SchemaKind::State),
+            "report" => Some(SchemaKind::Report),
+            "syntax" => Some(SchemaKind::Syntax),
+            "conformance" => Some(SchemaKind::Conformance),
+            "bundle" => Some(SchemaKind::Bundle),
+            "debug-info" => Some(SchemaKind::DebugInfo),
+            "diagnostics" => Some(SchemaKind::Diagnostics),
+            _ => None,
+        }
+    }
+
+    // id returns the versioned identifier written to the "schema" field
+    pub fn id(&self) -> &'static str {
+        match self {
+            SchemaKind::Trace => TRACE_SCHEMA,
+            SchemaKind::State => STATE_SCHEMA,
+            SchemaKind::Report => REPORT_SCHEMA,
+            SchemaKind::Syntax => SYNTAX_SCHEMA,
+            SchemaKind::Conformance => CONFORMANCE_SCHEMA,
+            SchemaKind::Bundle => BUNDLE_SCHEMA,
+            SchemaKind::DebugInfo => DEBUG_INFO_SCHEMA,
+            SchemaKind::Diagnostics => DIAGNOSTICS_SCHEMA,
+        }
+    }
+
+    // json_schema returns the JSON Schema describing documents of the kind
+    pub fn json_schema(&self) -> Value {
+        let (title, properties, required) = match self {
+            SchemaKind::Trace => (
+                "LMC execution trace",
+                json!({
+                    "schema": { "const": TRACE_SCHEMA },
+                    "events": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "cycle": { "type": "integer", "minimum": 1 },
+                                "address": mailbox_address(),
+                                "instruction": mailbox_value(),
+                                "calculator": mailbox_value(),
+                                "signed": { "type": "integer", "minimum": -500, "maximum": 499 },
+                                "flag": flag(),
+                                "branch": {
+                                    "type": "object",
+                                    "properties": {
+                                        "taken": { "type": "boolean" },
+                                        "target": mailbox_address(),
+                                        "reason": { "type": "string" },
+                                    },
+                                    "required": ["taken", "target", "reason"],
+                                },
+                                "wrapped": { "type": "boolean" },
+                            },
+                            "required": ["cycle", "address", "instruction", "calculator", "flag"],
+                        },
+                    },
+                }),
+                json!(["schema", "events"]),
+            ),
+            SchemaKind::State => (
+                "LMC machine state",
+                json!({
+                    "schema": { "const": STATE_SCHEMA },
+                    "halt": {
+                        "type": "object",
+                        "properties": {
+                            "reason": { "enum": ["halted", "max_cycles", "error", "cancelled"] },
+                            "address": {
+                                "oneOf": [mailbox_address(), { "type": "null" }],
+                            },
+                            "error": { "type": ["string", "null"] },
+                            "limit": {
+                                "enum": ["cycles", "inputs", "outputs", "time", "mailboxes", null],
+                            },
+                            "cycles": { "type": "integer", "minimum": 0 },
+                        },
+                        "required": ["reason", "address", "error", "cycles"],
+                    },
+                    "cycle": { "type": "integer", "minimum": 0 },
+                    "counter": mailbox_address(),
+                    "calculator": mailbox_value(),
+                    "flag": flag(),
+                    "output": {
+                        "oneOf": [mailbox_value(), { "type": "null" }],
+                    },
+                    "inputs": { "type": "array", "items": mailbox_value() },
+                    "outputs": { "type": "array", "items": mailbox_value() },
+                    "mailboxes": {
+                        "type": "array",
+                        "items": mailbox_value(),
+                        "minItems": 100,
+                        "maxItems": 100,
+                    },
+                }),
+                json!([
+                    "schema",
+                    "halt",
+                    "cycle",
+                    "counter",
+                    "calculator",
+                    "flag",
+                    "output",
+                    "mailboxes"
+                ]),
+            ),
+            SchemaKind::Report => (
+                "LMC batch report",
+                json!({
+                    "schema": { "const": REPORT_SCHEMA },
+                    "build": {
+                        "type": "object",
+                        "properties": {
+                            "version": { "type": "string" },
+                            "commit": { "type": "string" },
+                            "dialects": { "type": "array", "items": { "type": "string" } },
+                            "default_dialect": { "type": "string" },
+                        },
+                        "required": ["version", "commit"],
+                    },
+                    "tests": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "name": { "type": "string" },
+                                "passed": { "type": "boolean" },
+                                "cycles": { "type": "integer", "minimum": 0 },
+                                "halt": { "type": "string" },
+                                "inputs": { "type": "array", "items": mailbox_value() },
+                                "output": {
+                                    "oneOf": [mailbox_value(), { "type": "null" }],
+                                },
+                                "expected": {
+                                    "oneOf": [mailbox_value(), { "type": "null" }],
+                                },
+                                "outputs": { "type": "array", "items": mailbox_value() },
+                                "expected_outputs": { "type": "array", "items": mailbox_value() },
+                                "comparator": {
+                                    "type": "string",
+                                    "pattern": "^(exact|set|prefix|tolerance=[0-9]+)$",
+                                },
+                                "unconsumed": { "type": "integer", "minimum": 0 },
+                                "unchecked": { "type": "integer", "minimum": 0 },
+                                "touched": { "type": "integer", "minimum": 0, "maximum": 100 },
+                                "highest": {
+                                    "oneOf": [mailbox_address(), { "type": "null" }],
+                                },
+                                "seed": {
+                                    "oneOf": [
+                                        { "type": "integer", "minimum": 0 },
+                                        { "type": "null" },
+                                    ],
+                                },
+                                "status": {
+                                    "enum": ["passed", "failed", "skipped", "xfailed", "xpassed"],
+                                },
+                                "skipped": { "type": "boolean" },
+                                "xfail": { "type": ["string", "null"] },
+                                "dialect": { "type": ["string", "null"] },
+                                "memory": {
+                                    "type": "array",
+                                    "items": {
+                                        "type": "object",
+                                        "properties": {
+                                            "cell": { "type": "string" },
+                                            "got": mailbox_value(),
+                                            "expected": mailbox_value(),
+                                        },
+                                        "required": ["cell", "got", "expected"],
+                                    },
+                                },
+                            },
+                            "required": ["name", "passed", "cycles", "halt", "output", "expected"],
+                        },
+                    },
+                    "summary": {
+                        "type": "object",
+                        "properties": {
+                            "total": { "type": "integer", "minimum": 0 },
+                            "passed": { "type": "integer", "minimum": 0 },
+                            "failed": { "type": "integer", "minimum": 0 },
+                            "skipped": { "type": "integer", "minimum": 0 },
+                            "xfailed": { "type": "integer", "minimum": 0 },
+                            "xpassed": { "type": "integer", "minimum": 0 },
+                            "pass_rate": { "type": "number", "minimum": 0, "maximum": 100 },
+                            "total_cycles": { "type": "integer", "minimum": 0 },
+                            "slowest": {
+                                "oneOf": [
+                                    {
+                                        "type": "object",
+                                        "properties": {
+                                            "name": { "type": "string" },
+                                            "dialect": { "type": ["string", "null"] },
+                                            "cycles": { "type": "integer", "minimum": 0 },
+                                        },
+                                        "required": ["name", "cycles"],
+                                    },
+                                    { "type": "null" },
+                                ],
+                            },
+                            "matrix": {
+                                "type": "array",
+                                "items": {
+                                    "type": "object",
+                                    "properties": {
+                                        "dialect": { "type": "string" },
+                                        "passed": { "type": "integer", "minimum": 0 },
+                                        "failed": { "type": "integer", "minimum": 0 },
+                                    },
+                                    "required": ["dialect", "passed", "failed"],
+                                },
+                            },
+                            "peak_touched": { "type": "integer", "minimum": 0, "maximum": 100 },
+                            "peak_highest": {
+                                "oneOf": [mailbox_address(), { "type": "null" }],
+                            },
+                            "histogram": {
+                                "type": "array",
+                                "items": {
+                                    "type": "object",
+                                    "properties": {
+                                        "min": { "type": "integer", "minimum": 0 },
+                                        "max": { "type": "integer", "minimum": 0 },
+                                        "count": { "type": "integer", "minimum": 0 },
+                                    },
+                                    "required": ["min", "max", "count"],
+                                },
+                            },
+                        },
+                        "required": [
+                            "total",
+                            "passed",
+                            "pass_rate",
+                            "total_cycles",
+                            "slowest",
+                            "histogram"
+                        ],
+                    },
+                    "regressions": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "name": { "type": "string" },
+                                "kind": { "enum": ["newly_failing", "slower"] },
+                                "before": { "type": "integer", "minimum": 0 },
+                                "after": { "type": "integer", "minimum": 0 },
+                            },
+                            "required": ["name", "kind"],
+                        },
+                    },
+                }),
+                json!(["schema", "tests", "summary"]),
+            ),
+            SchemaKind::Syntax => (
+                "LMC tokenized source",
+                json!({
+                    "schema": { "const": SYNTAX_SCHEMA },
+                    "lines": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "line": { "type": "integer", "minimum": 1 },
+                                "address": {
+                                    "oneOf": [mailbox_address(), { "type": "null" }],
+                                },
+                                "tokens": {
+                                    "type": "array",
+                                    "items": {
+                                        "type": "object",
+                                        "properties": {
+                                            "kind": {
+                                                "enum": [
+                                                    "label",
+                                                    "mnemonic",
+                                                    "operand",
+                                                    "comparison",
+                                                    "comment"
+                                                ],
+                                            },
+                                            "text": { "type": "string" },
+                                            "start": { "type": "integer", "minimum": 0 },
+                                            "end": { "type": "integer", "minimum": 0 },
+                                        },
+                                        "required": ["kind", "text", "start", "end"],
+                                    },
+                                },
+                            },
+                            "required": ["line", "address", "tokens"],
+                        },
+                    },
+                    "symbols": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "label": { "type": "string" },
+                                "address": mailbox_address(),
+                            },
+                            "required": ["label", "address"],
+                        },
+                    },
+                    "diagnostics": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "line": { "type": "integer", "minimum": 1 },
+                                "start": { "type": "integer", "minimum": 0 },
+                                "end": { "type": "integer", "minimum": 0 },
+                                "message": { "type": "string" },
+                            },
+                            "required": ["line", "start", "end", "message"],
+                        },
+                    },
+                }),
+                json!(["schema", "lines", "symbols", "diagnostics"]),
+            ),
+            SchemaKind::Conformance => (
+                "LMC conformance suite",
+                json!({
+                    "schema": { "const": CONFORMANCE_SCHEMA },
+                    "dialect": { "enum": ["classic", "extended", "higginson"] },
+                    "cases": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "name": { "type": "string" },
+                                "description": { "type": "string" },
+                                "program": {
+                                    "type": "array",
+                                    "items": mailbox_value(),
+                                    "maxItems": 100,
+                                },
+                                "inputs": {
+                                    "type": "array",
+                                    "items": { "type": "integer", "minimum": -500, "maximum": 999 },
+                                },
+                                "max_cycles": { "type": "integer", "minimum": 1 },
+                                "expect": {
+                                    "type": "object",
+                                    "properties": {
+                                        "halt": { "enum": ["halted", "max_cycles", "error"] },
+                                        "outputs": {
+                                            "type": "array",
+                                            "items": {
+                                                "type": "integer",
+                                                "minimum": -500,
+                                                "maximum": 999,
+                                            },
+                                        },
+                                        "calculator": mailbox_value(),
+                                        "flag": flag(),
+                                        "cycles": { "type": "integer", "minimum": 0 },
+                                        "counter": mailbox_address(),
+                                        "mailboxes": {
+                                            "type": "object",
+                                            "propertyNames": { "pattern": "^[0-9]{1,2}$" },
+                                            "additionalProperties": mailbox_value(),
+                                        },
+                                    },
+                                    "required": ["halt"],
+                                    "additionalProperties": false,
+                                },
+                            },
+                            "required": ["name", "program", "expect"],
+                            "additionalProperties": false,
+                        },
+                    },
+                }),
+                json!(["schema", "dialect", "cases"]),
+            ),
+            SchemaKind::Bundle => (
+                "LMC classroom bundle",
+                json!({
+                    "schema": { "const": BUNDLE_SCHEMA },
+                    "name": { "type": "string" },
+                    "files": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "path": { "type": "string" },
+                                "hash": { "type": "string", "pattern": "^[0-9a-f]{8}$" },
+                                "contents": { "type": "string" },
+                            },
+                            "required": ["path", "hash", "contents"],
+                        },
+                    },
+                }),
+                json!(["schema", "name", "files"]),
+            ),
+            SchemaKind::DebugInfo => (
+                "LMC debug info",
+                json!({
+                    "schema": { "const": DEBUG_INFO_SCHEMA },
+                    "source": { "type": "string" },
+                    "checksum": {
+                        "type": "string",
+                        "pattern": "^# checksum=[0-9a-f]{8} length=[0-9]+$",
+                    },
+                    "mailboxes": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "address": mailbox_address(),
+                                "line": { "type": "integer", "minimum": 1 },
+                                "text": { "type": "string" },
+                                "label": { "type": ["string", "null"] },
+                            },
+                            "required": ["address", "line", "text"],
+                        },
+                    },
+                }),
+                json!(["schema", "source", "checksum", "mailboxes"]),
+            ),
+            SchemaKind::Diagnostics => (
+                "LMC assembler diagnostics",
+                json!({
+                    "schema": { "const": DIAGNOSTICS_SCHEMA },
+                    "diagnostics": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "severity": { "enum": ["error", "warning"] },
+                                "line": { "type": ["integer", "null"], "minimum": 1 },
+                                "column": { "type": ["integer", "null"], "minimum": 1 },
+                                "code": { "type": "string" },
+                                "message": { "type": "string" },
+                            },
+                            "required": ["severity", "line", "column", "code", "message"],
+                        },
+                    },
+                }),
+                json!(["schema", "diagnostics"]),
+            ),
+        };
+        json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "$id": self.id(),
+            "title": title,
+            "type": "object",
+            "properties": properties,
+            "required": required,
+        })
+    }
+}
+
+// mailbox_address is the schema of a 2-digit mailbox address
+fn mailbox_address() -> Value {
+    json!({ "type": "integer", "minimum": 0, "maximum": 99 })
+}
+
+// mailbox_value is the schema of a 3-digit value held in a mailbox
+fn mailbox_value() -> Value {
+    json!({ "type": "integer", "minimum": 0, "maximum": 999 })
+}
+
+// flag is the schema of the calculator's flag
+fn flag() -> Value {
+    json!({ "enum": ["NEG", "OVERFLOW", null] })
+}
@@ -0,0 +1,259 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{prelude::*, BufReader},
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    lmc::{join_numbers, MachineState},
+    numbers::{Flag, ThreeDigitNumber, TwoDigitNumber},
+};
+
+// SessionError is used to indicate an error with a recorded session
+#[derive(Debug, PartialEq)]
+pub enum SessionError {
+    IOError(String),
+    InvalidFrame(usize, String),
+}
+
+// Implement the display trait for easy printing.
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionError::IOError(value) => write!(f, "IO error: {}", value),
+            SessionError::InvalidFrame(line, value) => {
+                write!(f, "invalid frame on line {}: {}", line, value)
+            }
+        }
+    }
+}
+
+// A frame of a session is the MachineState after a single fetch-execute cycle
+impl MachineState {
+    // to_line serializes the frame into a single line of the session file in
+    // the form: cycle;counter;calculator;flag;output;m00,...,m99;inputs;outputs
+    // where the inputs and outputs are comma separated
+    pub fn to_line(&self) -> String {
+        let flag = match self.flag {
+            Some(flag) => flag.to_string(),
+            None => String::new(),
+        };
+        let output = match self.output {
+            Some(number) => number.to_string(),
+            None => String::new(),
+        };
+        let mailboxes = self
+            .mailboxes
+            .iter()
+            .map(|number| number.to_string())
+            .collect::<Vec<String>>()
+            .join(",");
+        let join = |numbers: &[ThreeDigitNumber]| {
+            numbers
+                .iter()
+                .map(|number| number.to_string())
+                .collect::<Vec<String>>()
+                .join(",")
+        };
+        format!(
+            "{};{};{};{};{};{};{};{}",
+            self.cycle,
+            self.counter,
+            self.calculator,
+            flag,
+            output,
+            mailboxes,
+            join(&self.inputs),
+            join(&self.outputs)
+        )
+    }
+
+    // from_line parses a single line of a session file back into a frame,
+    // sessions recorded before the baskets were added have no inputs or outputs
+    pub fn from_line(index: usize, line: &str) -> Result<MachineState, SessionError> {
+        let invalid = || SessionError::InvalidFrame(index, line.to_string());
+        let parts = line.trim().split(';').collect::<Vec<&str>>();
+        if parts.len() != 6 && parts.len() != 8 {
+            return Err(invalid());
+        }
+        let numbers = |field: Option<&&str>| -> Result<Vec<ThreeDigitNumber>, SessionError> {
+            match field {
+                None | Some(&"") => Ok(Vec::new()),
+                Some(field) => field
+                    .split(',')
+                    .map(|value| parse_number(value).ok_or_else(invalid))
+                    .collect(),
+            }
+        };
+        let cycle = parts[0].parse::<usize>().map_err(|_| invalid())?;
+        let counter = parts[1]
+            .parse::<u8>()
+            .ok()
+            .and_then(|value| TwoDigitNumber::new(value).ok())
+            .ok_or_else(invalid)?;
+        let calculator = parse_number(parts[2]).ok_or_else(invalid)?;
+        let flag = match parts[3] {
+            "" => None,
+            "NEG" => Some(Flag::NEG),
+            "OVERFLOW" => Some(Flag::OVERFLOW),
+            _ => return Err(invalid()),
+        };
+        let output = match parts[4] {
+            "" => None,
+            value => Some(parse_number(value).ok_or_else(invalid)?),
+        };
+        let values = parts[5].split(',').collect::<Vec<&str>>();
+        if values.len() != 100 {
+            return Err(invalid());
+        }
+        let mut mailboxes = [ThreeDigitNumber::new(0).unwrap(); 100];
+        for (i, value) in values.iter().enumerate() {
+            mailboxes[i] = parse_number(value).ok_or_else(invalid)?;
+        }
+        Ok(MachineState {
+            cycle,
+            counter,
+            calculator,
+            flag,
+            output,
+            mailboxes,
+            inputs: numbers(parts.get(6))?,
+            outputs: numbers(parts.get(7))?,
+        })
+    }
+
+    // render_plain describes the frame in a single line of plain text: the
+    // registers and any mailboxes changed since the previous frame
+    pub fn render_plain(&self, previous: Option<&MachineState>) -> String {
+        let flag = self
+            .flag
+            .map_or("none".to_string(), |flag| flag.to_string());
+        let output = self
+            .output
+            .map_or("none".to_string(), |number| number.to_string());
+        let mut text = format!(
+            "cycle {}: counter {}, calculator {}, flag {}, output {}",
+            self.cycle, self.counter, self.calculator, flag, output
+        );
+        if let Some(previous) = previous {
+            for address in 0..100 {
+                if self.mailboxes[address] != previous.mailboxes[address] {
+                    text.push_str(&format!(
+                        ", mailbox {:02} changed to {}",
+                        address, self.mailboxes[address]
+                    ));
+                }
+            }
+        }
+        text
+    }
+
+    // render draws the frame as a block of text: the registers followed by
+    // the mailboxes laid out as a 10x10 grid with the counter highlighted
+    pub fn render(&self) -> String {
+        let flag = match self.flag {
+            Some(flag) => flag.to_string(),
+            None => "-".to_string(),
+        };
+        let output = match self.output {
+            Some(number) => number.to_string(),
+            None => "-".to_string(),
+        };
+        let mut text = format!(
+            "cycle: {}  counter: {}  calculator: {}  flag: {}  output: {}\n",
+            self.cycle, self.counter, self.calculator, flag, output
+        );
+        text.push_str(&format!(
+            "inputs: {}  outputs: {}\n\n",
+            join_numbers(&self.inputs),
+            join_numbers(&self.outputs)
+        ));
+        for row in 0..10 {
+            for column in 0..10 {
+                let address = row * 10 + column;
+                if address == self.counter.value() as usize {
+                    text.push_str(&format!("[{}]", self.mailboxes[address]));
+                } else {
+                    text.push_str(&format!(" {} ", self.mailboxes[address]));
+                }
+            }
+            text.push('\n');
+        }
+        text
+    }
+}
+
+// parse_number parses a 3-digit decimal number from a session file field
+fn parse_number(value: &str) -> Option<ThreeDigitNumber> {
+    value
+        .parse::<i16>()
+        .ok()
+        .and_then(|value| ThreeDigitNumber::new(value).ok())
+}
+
+// Session is the ordered list of frames recorded during a single run
+#[derive(Default)]
+pub struct Session {
+    frames: Vec<MachineState>,
+}
+
+impl Session {
+    // new creates an empty Session ready for recording
+    pub fn new() -> Self {
+        Session { frames: Vec::new() }
+    }
+
+    // record appends a frame to the end of the session
+    pub fn record(&mut self, frame: MachineState) {
+        self.frames.push(frame);
+    }
+
+    // frames returns the frames recorded, oldest first
+    pub fn frames(&self) -> &[MachineState] {
+        &self.frames
+    }
+
+    // save writes every recorded frame to the given file, one per line
+    pub fn save(&self, path: &str) -> Result<(), SessionError> {
+        let mut file = File::create(path).map_err(|e| SessionError::IOError(e.to_string()))?;
+        for frame in &self.frames {
+            writeln!(file, "{}", frame.to_line())
+                .map_err(|e| SessionError::IOError(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    // load reads a previously saved session file
+    pub fn load(path: &str) -> Result<Self, SessionError> {
+        let file = File::open(path).map_err(|e| SessionError::IOError(e.to_string()))?;
+        let mut frames = Vec::new();
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|e| SessionError::IOError(e.to_string()))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            frames.push(MachineState::from_line(i + 1, &line)?);
+        }
+        Ok(Session { frames })
+    }
+
+    // play renders each frame to stdout in turn, clearing the terminal between
+    // frames and pausing for the given delay so the run can be followed along.
+    // Plain prints each frame as a line of text after the last instead,
+    // without clearing the terminal or drawing the mailboxes as a grid.
+    pub fn play(&self, delay: Duration, plain: bool) {
+        for (i, frame) in self.frames.iter().enumerate() {
+            match plain {
+                true => println!(
+                    "{}",
+                    frame.render_plain(i.checked_sub(1).map(|i| &self.frames[i]))
+                ),
+                false => print!("\x1b[2J\x1b[H{}", frame.render()),
+            }
+            let _ = std::io::stdout().flush();
+            thread::sleep(delay);
+        }
+    }
+}
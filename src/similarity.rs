@@ -0,0 +1,104 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::{canonical, instruction::Instruction, program::Program};
+
+// GRAM is the number of consecutive instructions in each n-gram of a
+// fingerprint, long enough that common idioms alone do not make two programs
+// look alike
+const GRAM: usize = 3;
+
+// Fingerprint is the set of instruction n-grams of a program, normalized so
+// that renaming labels or moving the DAT cells around does not change it
+#[derive(Debug, PartialEq, Clone)]
+pub struct Fingerprint {
+    grams: BTreeSet<Vec<String>>,
+}
+
+impl Fingerprint {
+    // of computes the fingerprint of a program. Data cells are left out and an
+    // operand is written as its data cell's name in the canonical form, e.g.
+    // LDA d0 for the first used, or as the distance in instructions to its
+    // target, e.g. BRZ +3.
+    pub fn of(program: &Program) -> Self {
+        let decoded = program
+            .instructions
+            .iter()
+            .map(|value| Instruction::decode(*value))
+            .collect::<Vec<Instruction>>();
+        let data = canonical::data_cells(program);
+        let cells = canonical::data_order(program, &data);
+        // code maps the address of every instruction to its place in the code
+        let code = (0..decoded.len())
+            .filter(|address| !data[*address])
+            .enumerate()
+            .map(|(index, address)| (address, index))
+            .collect::<HashMap<usize, usize>>();
+        let mut tokens = Vec::new();
+        for (address, instruction) in decoded.iter().enumerate() {
+            let index = match code.get(&address) {
+                Some(index) => *index as i64,
+                None => continue,
+            };
+            let token = match instruction.operand() {
+                Some(operand) => match (code.get(&operand), cells.get(&operand)) {
+                    (Some(target), _) => {
+                        format!("{} {:+}", instruction.mnemonic(), *target as i64 - index)
+                    }
+                    (None, Some(cell)) => format!("{} d{}", instruction.mnemonic(), cell),
+                    (None, None) => format!("{} {:02}", instruction.mnemonic(), operand),
+                },
+                None => instruction.mnemonic().to_string(),
+            };
+            tokens.push(token);
+        }
+        let grams = match tokens.len() < GRAM {
+            true => BTreeSet::from([tokens]),
+            false => tokens.windows(GRAM).map(|gram| gram.to_vec()).collect(),
+        };
+        Fingerprint { grams }
+    }
+
+    // similarity returns the fraction of n-grams the fingerprints share, from
+    // 0.0 for nothing in common to 1.0 for the same structure
+    pub fn similarity(&self, other: &Fingerprint) -> f64 {
+        let union = self.grams.union(&other.grams).count();
+        if union == 0 {
+            return 1.0;
+        }
+        self.grams.intersection(&other.grams).count() as f64 / union as f64
+    }
+}
+
+// Pair is two programs along with how similar their structure is
+#[derive(Debug, PartialEq, Clone)]
+pub struct Pair {
+    pub a: String,
+    pub b: String,
+    pub similarity: f64,
+}
+
+// rank compares every pair of the named programs, returning the pairs most
+// similar first
+pub fn rank(programs: &[(String, Program)]) -> Vec<Pair> {
+    let fingerprints = programs
+        .iter()
+        .map(|(name, program)| (name, Fingerprint::of(program)))
+        .collect::<Vec<_>>();
+    let mut pairs = Vec::new();
+    for (i, (a, first)) in fingerprints.iter().enumerate() {
+        for (b, second) in &fingerprints[i + 1..] {
+            pairs.push(Pair {
+                a: a.to_string(),
+                b: b.to_string(),
+                similarity: first.similarity(second),
+            });
+        }
+    }
+    pairs.sort_by(|x, y| {
+        y.similarity
+            .total_cmp(&x.similarity)
+            .then_with(|| x.a.cmp(&y.a))
+            .then_with(|| x.b.cmp(&y.b))
+    });
+    pairs
+}
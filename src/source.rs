@@ -0,0 +1,164 @@
+use crate::assembler::is_mnemonic;
+
+// FIXED_COLUMNS are the 0-based character ranges of the label, opcode and
+// operand fields in fixed-column source, anything after them is a comment
+const FIXED_COLUMNS: [(usize, usize); 3] = [(0, 8), (8, 16), (16, 24)];
+
+// TAB_WIDTH is the distance between tab stops when expanding tabs so that
+// fixed-column source lines up the way it does in an editor
+const TAB_WIDTH: usize = 8;
+
+// SourceFormat is the layout of an assembly source file
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SourceFormat {
+    // Free separates the label, opcode and operand with any whitespace
+    Free,
+    // Fixed puts the label in columns 1-8, the opcode in columns 9-16 and
+    // the operand in columns 17-24, like a punch card or coding sheet
+    Fixed,
+}
+
+impl SourceFormat {
+    // from_str converts a string to a source format
+    pub fn from_str(format: &str) -> Option<SourceFormat> {
+        match format {
+            "free" => Some(SourceFormat::Free),
+            "fixed" => Some(SourceFormat::Fixed),
+            _ => None,
+        }
+    }
+}
+
+// Fields are the parts of a single source line
+struct Fields {
+    label: String,
+    opcode: String,
+    operand: String,
+    comment: String,
+}
+
+// to_free converts source in the given format to the free-form syntax the
+// assembler reads, keeping one output line per input line so that line
+// numbers still refer to the original source
+pub fn to_free(source: &[String], format: SourceFormat) -> Vec<String> {
+    match format {
+        SourceFormat::Free => source.to_vec(),
+        SourceFormat::Fixed => source
+            .iter()
+            .map(|line| render(&fixed_fields(line)))
+            .collect(),
+    }
+}
+
+// format lays out source in the given format as tidy free-form source, with
+// the labels, opcodes, operands and comments each lined up in a column
+pub fn format(source: &[String], format: SourceFormat) -> Vec<String> {
+    source
+        .iter()
+        .map(|line| match format {
+            SourceFormat::Free => render(&free_fields(line)),
+            SourceFormat::Fixed => render(&fixed_fields(line)),
+        })
+        .collect()
+}
+
+// render writes the fields of a line as free-form source
+fn render(fields: &Fields) -> String {
+    let mut line = String::new();
+    if !(fields.label.is_empty() && fields.opcode.is_empty() && fields.operand.is_empty()) {
+        line = format!("{:7} {:7} {}", fields.label, fields.opcode, fields.operand);
+        line = line.trim_end().to_string();
+    }
+    if !fields.comment.is_empty() {
+        if line.is_empty() {
+            line = fields.comment.clone();
+        } else {
+            line = format!("{:23} {}", line, fields.comment);
+        }
+    }
+    line
+}
+
+// comment returns the text as a comment, adding the # if it is missing
+fn comment(text: &str) -> String {
+    let text = text.trim();
+    if text.is_empty() || text.starts_with('#') {
+        text.to_string()
+    } else {
+        format!("# {}", text)
+    }
+}
+
+// free_fields splits a free-form line into its fields
+fn free_fields(line: &str) -> Fields {
+    let (code, note) = match line.find('#') {
+        Some(index) => line.split_at(index),
+        None => (line, ""),
+    };
+    let mut fields = Fields {
+        label: String::new(),
+        opcode: String::new(),
+        operand: String::new(),
+        comment: comment(note),
+    };
+    let parts = code.split_whitespace().collect::<Vec<&str>>();
+    match parts.as_slice() {
+        [] => {}
+        ["ASSERT", expression @ ..] => {
+            fields.opcode = "ASSERT".to_string();
+            fields.operand = expression.join(" ");
+        }
+        [opcode] => fields.opcode = opcode.to_string(),
+        [first, second] if is_mnemonic(first) => {
+            fields.opcode = first.to_string();
+            fields.operand = second.to_string();
+        }
+        [label, opcode, rest @ ..] => {
+            fields.label = label.to_string();
+            fields.opcode = opcode.to_string();
+            fields.operand = rest.join(" ");
+        }
+    }
+    fields
+}
+
+// fixed_fields splits a fixed-column line into its fields, a line whose first
+// non-blank character is # is a comment line
+fn fixed_fields(line: &str) -> Fields {
+    if line.trim_start().starts_with('#') {
+        return Fields {
+            label: String::new(),
+            opcode: String::new(),
+            operand: String::new(),
+            comment: line.trim().to_string(),
+        };
+    }
+    let chars = expand_tabs(line).chars().collect::<Vec<char>>();
+    let column = |(start, end): (usize, usize)| {
+        chars[start.min(chars.len())..end.min(chars.len())]
+            .iter()
+            .collect::<String>()
+            .trim()
+            .to_string()
+    };
+    Fields {
+        label: column(FIXED_COLUMNS[0]),
+        opcode: column(FIXED_COLUMNS[1]),
+        operand: column(FIXED_COLUMNS[2]),
+        comment: comment(&column((FIXED_COLUMNS[2].1, chars.len()))),
+    }
+}
+
+// expand_tabs replaces every tab with spaces up to the next tab stop
+fn expand_tabs(line: &str) -> String {
+    let mut expanded = String::new();
+    for c in line.chars() {
+        if c == '\t' {
+            let width = TAB_WIDTH - expanded.chars().count() % TAB_WIDTH;
+            expanded.push_str(&" ".repeat(width));
+        } else {
+            expanded.push(c);
+        }
+    }
+    expanded
+}
@@ -0,0 +1,211 @@
+use serde_json::{json, Value};
+use std::fmt;
+
+use crate::{
+    assembler::is_mnemonic,
+    program::{Comparison, Program},
+    schema::SYNTAX_SCHEMA,
+};
+
+// TokenKind is the role a word plays in a line of assembly source
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TokenKind {
+    Label,
+    // Mnemonic is an opcode or the ASSERT pseudo-instruction
+    Mnemonic,
+    Operand,
+    // Comparison is the operator of an ASSERT expression, e.g. <=
+    Comparison,
+    Comment,
+}
+
+// Implement the display trait for easy printing.
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenKind::Label => write!(f, "label"),
+            TokenKind::Mnemonic => write!(f, "mnemonic"),
+            TokenKind::Operand => write!(f, "operand"),
+            TokenKind::Comparison => write!(f, "comparison"),
+            TokenKind::Comment => write!(f, "comment"),
+        }
+    }
+}
+
+// Token is a word of assembly source and where it was found
+#[derive(Debug, PartialEq, Clone)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub text: String,
+    // start and end are the 0-based character columns the token spans, the
+    // end being exclusive
+    pub start: usize,
+    pub end: usize,
+}
+
+// Line is a line of assembly source split into tokens
+#[derive(Debug, PartialEq, Clone)]
+pub struct Line {
+    // number is the 1-based source line number
+    pub number: usize,
+    pub tokens: Vec<Token>,
+}
+
+// words splits text into its whitespace separated words along with the
+// character column each starts at
+fn words(text: &str) -> Vec<(usize, String)> {
+    let mut words: Vec<(usize, String)> = Vec::new();
+    let mut in_word = false;
+    for (column, c) in text.chars().enumerate() {
+        if c.is_whitespace() {
+            in_word = false;
+        } else if in_word {
+            words.last_mut().unwrap().1.push(c);
+        } else {
+            words.push((column, c.to_string()));
+            in_word = true;
+        }
+    }
+    words
+}
+
+// tokenize_line splits a line into tokens the way the assembler reads it: a
+// line starting with a mnemonic has no label, otherwise the first word is the
+// label and the second the mnemonic, with everything after a # a comment
+pub fn tokenize_line(number: usize, text: &str) -> Line {
+    let (code, comment) = match text.find('#') {
+        Some(index) => text.split_at(index),
+        None => (text, ""),
+    };
+    let words = words(code);
+    // a label written with a colon may be on a line of its own
+    let labelled = match words.as_slice() {
+        [(_, word)] => word.len() > 1 && word.ends_with(':'),
+        [(_, word), ..] => !is_mnemonic(word),
+        [] => false,
+    };
+    let assertion = words.first().is_some_and(|(_, word)| word == "ASSERT");
+    let mut tokens = words
+        .into_iter()
+        .enumerate()
+        .map(|(i, (start, word))| {
+            let kind = match (i, labelled) {
+                (0, true) => TokenKind::Label,
+                (0, false) | (1, true) => TokenKind::Mnemonic,
+                _ if assertion && Comparison::from_str(&word).is_some() => TokenKind::Comparison,
+                _ => TokenKind::Operand,
+            };
+            // a label may end with a colon, which is not part of its name
+            let word = match kind {
+                TokenKind::Label if word.len() > 1 => {
+                    word.strip_suffix(':').unwrap_or(&word).to_string()
+                }
+                _ => word,
+            };
+            Token {
+                kind,
+                start,
+                end: start + word.chars().count(),
+                text: word,
+            }
+        })
+        .collect::<Vec<Token>>();
+    if !comment.is_empty() {
+        let start = code.chars().count();
+        tokens.push(Token {
+            kind: TokenKind::Comment,
+            text: comment.to_string(),
+            start,
+            end: start + comment.chars().count(),
+        });
+    }
+    Line { number, tokens }
+}
+
+// tokenize splits every non-blank line of the source into tokens
+pub fn tokenize(source: &[String]) -> Vec<Line> {
+    source
+        .iter()
+        .enumerate()
+        .map(|(i, text)| tokenize_line(i + 1, text))
+        .filter(|line| !line.tokens.is_empty())
+        .collect()
+}
+
+// unused_labels returns each label that is defined but never used as an
+// operand, whether of an instruction or an assertion, along with the line
+// number defining it
+pub fn unused_labels(lines: &[Line]) -> Vec<(usize, String)> {
+    let operands = lines
+        .iter()
+        .flat_map(|line| &line.tokens)
+        .filter(|token| token.kind == TokenKind::Operand)
+        .map(|token| token.text.as_str())
+        .collect::<Vec<&str>>();
+    lines
+        .iter()
+        .filter_map(|line| {
+            line.tokens
+                .iter()
+                .find(|token| token.kind == TokenKind::Label)
+                .filter(|label| !operands.contains(&label.text.as_str()))
+                .map(|label| (line.number, label.text.clone()))
+        })
+        .collect()
+}
+
+// to_json returns the tokenized lines along with the symbols and diagnostics
+// of the assembled program as a JSON object, each line gives the mailbox it
+// was assembled into if any
+pub fn to_json(lines: &[Line], program: &Program) -> Value {
+    let lines = lines
+        .iter()
+        .map(|line| {
+            let tokens = line
+                .tokens
+                .iter()
+                .map(|token| {
+                    json!({
+                        "kind": token.kind.to_string(),
+                        "text": token.text,
+                        "start": token.start,
+                        "end": token.end,
+                    })
+                })
+                .collect::<Vec<Value>>();
+            json!({
+                "line": line.number,
+                "address": program.source_map.iter().position(|number| *number == line.number),
+                "tokens": tokens,
+            })
+        })
+        .collect::<Vec<Value>>();
+    let mut symbols = program
+        .labels
+        .iter()
+        .map(|(label, address)| (*address, label))
+        .collect::<Vec<(usize, &String)>>();
+    symbols.sort();
+    let symbols = symbols
+        .iter()
+        .map(|(address, label)| json!({ "label": label, "address": address }))
+        .collect::<Vec<Value>>();
+    let diagnostics = program
+        .diagnostics
+        .iter()
+        .map(|diagnostic| {
+            json!({
+                "line": diagnostic.span.line,
+                "start": diagnostic.span.start,
+                "end": diagnostic.span.end,
+                "message": diagnostic.message,
+            })
+        })
+        .collect::<Vec<Value>>();
+    json!({
+        "schema": SYNTAX_SCHEMA,
+        "lines": lines,
+        "symbols": symbols,
+        "diagnostics": diagnostics,
+    })
+}
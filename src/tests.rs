@@ -0,0 +1,157 @@
+use serde::Deserialize;
+use std::{collections::BTreeMap, fmt, fs, path::Path};
+
+use crate::{
+    conformance::Dialect,
+    numbers::ThreeDigitNumber,
+    runner::{Cell, Comparator, Marker, Test},
+};
+
+// TestsError is used to indicate an error reading a TOML test file
+#[derive(Debug, PartialEq)]
+pub enum TestsError {
+    IOError(String),
+    InvalidTests(String),
+    // InvalidTest is a test that parsed but makes no sense, e.g. an output
+    // out of range, named along with the problem
+    InvalidTest(String, String),
+}
+
+// Implement the display trait for easy printing.
+impl fmt::Display for TestsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TestsError::IOError(value) => write!(f, "IO error: {}", value),
+            TestsError::InvalidTests(value) => write!(f, "invalid test file: {}", value),
+            TestsError::InvalidTest(name, value) => write!(f, "invalid test {}: {}", name, value),
+        }
+    }
+}
+
+// Implement the error trait so the error can be used with the ? operator
+// alongside other errors.
+impl std::error::Error for TestsError {}
+
+// TestFile is a TOML test file, a list of [[test]] tables along with the
+// dialects to run them on
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TestFile {
+    #[serde(default)]
+    matrix: Vec<String>,
+    #[serde(default)]
+    test: Vec<TestSpec>,
+}
+
+// TestSuite is the tests of a test file along with the matrix of dialects
+// every test is run on, empty to run them once as configured
+#[derive(Debug, Clone)]
+pub struct TestSuite {
+    pub tests: Vec<Test>,
+    pub matrix: Vec<Dialect>,
+}
+
+// TestSpec is a single test as written in a TOML test file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TestSpec {
+    name: String,
+    #[serde(default)]
+    inputs: Vec<i16>,
+    // outputs are every value the program is expected to output, in order
+    // unless a comparator says otherwise
+    #[serde(default)]
+    outputs: Vec<i16>,
+    max_cycles: usize,
+    entry: Option<String>,
+    comparator: Option<String>,
+    // memory maps labels or addresses to the value their mailbox is expected
+    // to hold once the program halts
+    #[serde(default)]
+    memory: BTreeMap<String, i16>,
+    #[serde(default)]
+    skip: bool,
+    xfail: Option<String>,
+}
+
+// is_toml returns whether the test file is in the TOML format rather than
+// the semicolon separated one, going by its extension
+pub fn is_toml(path: &str) -> bool {
+    Path::new(path).extension().is_some_and(|ext| ext == "toml")
+}
+
+// load reads and parses a TOML test file
+pub fn load(path: &str) -> Result<TestSuite, TestsError> {
+    let text = fs::read_to_string(path).map_err(|e| TestsError::IOError(e.to_string()))?;
+    parse(&text)
+}
+
+// parse reads the tests of a TOML test file, e.g.:
+// [[test]]
+// name = "double"
+// inputs = [4]
+// outputs = [8]
+// max_cycles = 20
+// memory = { total = 8 }
+// A matrix = ["classic", "higginson"] before the tests runs every test on
+// each of the dialects.
+pub fn parse(text: &str) -> Result<TestSuite, TestsError> {
+    let file: TestFile =
+        toml::from_str(text).map_err(|e| TestsError::InvalidTests(e.to_string()))?;
+    let matrix = file
+        .matrix
+        .iter()
+        .map(|dialect| {
+            Dialect::from_str(dialect).ok_or_else(|| {
+                TestsError::InvalidTests(format!("unknown dialect in matrix: {}", dialect))
+            })
+        })
+        .collect::<Result<Vec<Dialect>, TestsError>>()?;
+    let tests = file
+        .test
+        .into_iter()
+        .map(to_test)
+        .collect::<Result<Vec<Test>, TestsError>>()?;
+    Ok(TestSuite { tests, matrix })
+}
+
+// to_test converts a test as written into one the runner can run
+fn to_test(spec: TestSpec) -> Result<Test, TestsError> {
+    let name = spec.name;
+    let numbers = |values: &[i16]| {
+        values
+            .iter()
+            .map(|value| ThreeDigitNumber::new(*value))
+            .collect::<Result<Vec<ThreeDigitNumber>, _>>()
+            .map_err(|e| TestsError::InvalidTest(name.clone(), e.to_string()))
+    };
+    let inputs = numbers(&spec.inputs)?;
+    let outputs = numbers(&spec.outputs)?;
+    let mut memory = Vec::new();
+    for (cell, value) in &spec.memory {
+        memory.push(Cell {
+            name: cell.clone(),
+            expected: numbers(&[*value])?[0],
+        });
+    }
+    let inputs = (!inputs.is_empty()).then_some(inputs);
+    let mut test = Test::new(&name, inputs, outputs, spec.max_cycles, spec.entry);
+    if let Some(comparator) = &spec.comparator {
+        match Comparator::from_str(comparator) {
+            Some(comparator) => test.set_comparator(comparator),
+            None => {
+                return Err(TestsError::InvalidTest(
+                    name,
+                    format!("invalid comparator: {}", comparator),
+                ))
+            }
+        }
+    }
+    test.set_memory(memory);
+    match (spec.skip, spec.xfail) {
+        (true, _) => test.set_marker(Marker::Skip),
+        (false, Some(reason)) => test.set_marker(Marker::ExpectedFailure(reason)),
+        (false, None) => (),
+    }
+    Ok(test)
+}
@@ -0,0 +1,69 @@
+// BOM is the byte order mark some editors write at the start of a file
+const BOM: char = '\u{feff}';
+
+// ZERO_WIDTH are invisible characters that tend to come along with text
+// copied out of word processors and web pages
+const ZERO_WIDTH: [char; 4] = ['\u{200b}', '\u{200c}', '\u{200d}', '\u{2060}'];
+
+// Normalized is the text of a file split into lines, along with a
+// description of every change that was needed to make it plain text
+pub struct Normalized {
+    pub lines: Vec<String>,
+    pub changes: Vec<String>,
+}
+
+// normalize splits the text into lines accepting \n, \r\n and \r line
+// endings, strips any byte order mark and zero-width characters and replaces
+// non-ASCII whitespace such as non-breaking spaces with a plain space
+pub fn normalize(text: &str) -> Normalized {
+    let mut changes = Vec::new();
+    let text = match text.strip_prefix(BOM) {
+        Some(text) => {
+            changes.push("removed byte order mark".to_string());
+            text
+        }
+        None => text,
+    };
+    if text.contains("\r\n") {
+        changes.push("converted CRLF line endings".to_string());
+    }
+    let text = text.replace("\r\n", "\n");
+    if text.contains('\r') {
+        changes.push("converted CR line endings".to_string());
+    }
+    let text = text.replace('\r', "\n");
+    let mut spaces = 0;
+    let mut invisible = 0;
+    let mut lines = text
+        .split('\n')
+        .map(|line| {
+            line.chars()
+                .filter_map(|c| {
+                    if c == BOM || ZERO_WIDTH.contains(&c) {
+                        invisible += 1;
+                        None
+                    } else if c.is_whitespace() && !c.is_ascii() {
+                        spaces += 1;
+                        Some(' ')
+                    } else {
+                        Some(c)
+                    }
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<String>>();
+    // a trailing newline does not start another line
+    if text.ends_with('\n') {
+        lines.pop();
+    }
+    if spaces > 0 {
+        changes.push(format!(
+            "replaced {} non-breaking or unicode spaces",
+            spaces
+        ));
+    }
+    if invisible > 0 {
+        changes.push(format!("removed {} zero-width characters", invisible));
+    }
+    Normalized { lines, changes }
+}
@@ -0,0 +1,282 @@
+use serde::Deserialize;
+use std::{fmt, fs, path::Path};
+
+use crate::{
+    devices::Rng,
+    lmc::{CounterWrap, StepResult, LMC},
+    numbers::ThreeDigitNumber,
+    program::{self, Program},
+    text,
+};
+
+// TournamentError is used to indicate an error setting up a tournament
+#[derive(Debug, PartialEq)]
+pub enum TournamentError {
+    IOError(String),
+    InvalidSpec(String),
+    InvalidProgram(String, String),
+    NoEntrants,
+}
+
+// Implement the display trait for easy printing.
+impl fmt::Display for TournamentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TournamentError::IOError(value) => write!(f, "IO error: {}", value),
+            TournamentError::InvalidSpec(value) => write!(f, "invalid spec: {}", value),
+            TournamentError::InvalidProgram(name, value) => {
+                write!(f, "invalid program {}: {}", name, value)
+            }
+            TournamentError::NoEntrants => write!(f, "no programs found to compete"),
+        }
+    }
+}
+
+// TournamentSpec describes the cases every entrant is run against
+#[derive(Debug, Deserialize)]
+pub struct TournamentSpec {
+    // max_cycles is the cycle budget every entrant gets for each case
+    pub max_cycles: usize,
+    // cases are fixed inputs along with their expected outputs
+    #[serde(default)]
+    pub cases: Vec<CaseSpec>,
+    // generate optionally adds cases with random inputs, their expected
+    // outputs being whatever the reference program produces
+    pub generate: Option<GenerateSpec>,
+}
+
+// CaseSpec is a single set of inputs and the outputs expected from them
+#[derive(Debug, Clone, Deserialize)]
+pub struct CaseSpec {
+    #[serde(default)]
+    pub inputs: Vec<i16>,
+    #[serde(default)]
+    pub expected: Vec<i16>,
+}
+
+// GenerateSpec describes how to generate random cases
+#[derive(Debug, Deserialize)]
+pub struct GenerateSpec {
+    // reference is the machine code file used to compute expected outputs,
+    // relative to the spec file
+    pub reference: String,
+    // count is the number of cases to generate
+    pub count: usize,
+    // inputs is the number of inputs in each case
+    pub inputs: usize,
+    #[serde(default)]
+    pub min: i16,
+    #[serde(default = "default_max")]
+    pub max: i16,
+    // seed repeats the same cases, otherwise they are seeded from the clock
+    pub seed: Option<u64>,
+}
+
+fn default_max() -> i16 {
+    999
+}
+
+// Case is the inputs of a single case along with the outputs expected
+type Case = (Vec<ThreeDigitNumber>, Vec<ThreeDigitNumber>);
+
+// Standing is an entrant's result across every case of the tournament
+#[derive(Debug, Clone)]
+pub struct Standing {
+    pub name: String,
+    pub passed: usize,
+    pub total: usize,
+    pub cycles: usize,
+}
+
+// LeaderboardFormat is the format the ranked standings are written in
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum LeaderboardFormat {
+    Markdown,
+    CSV,
+}
+
+impl LeaderboardFormat {
+    // from_str converts a string to a leaderboard format
+    pub fn from_str(format: &str) -> Option<LeaderboardFormat> {
+        match format {
+            "markdown" | "md" => Some(LeaderboardFormat::Markdown),
+            "csv" => Some(LeaderboardFormat::CSV),
+            _ => None,
+        }
+    }
+}
+
+// load_spec reads and parses a TOML tournament spec
+pub fn load_spec(path: &str) -> Result<TournamentSpec, TournamentError> {
+    let text = fs::read_to_string(path).map_err(|e| TournamentError::IOError(e.to_string()))?;
+    toml::from_str(&text).map_err(|e| TournamentError::InvalidSpec(e.to_string()))
+}
+
+// load_machine_code reads a machine code file into a program
+fn load_machine_code(path: &Path) -> Result<Program, TournamentError> {
+    let name = path.display().to_string();
+    let text = fs::read_to_string(path).map_err(|e| TournamentError::IOError(e.to_string()))?;
+    let mut instructions = Vec::new();
+    // skip blank lines and the checksum line written by the assembler
+    for line in text::normalize(&text).lines {
+        let number = match program::machine_code_value(&line)
+            .map_err(|e| TournamentError::InvalidProgram(name.clone(), e))?
+        {
+            Some(number) => number,
+            None => continue,
+        };
+        instructions.push(
+            ThreeDigitNumber::new(number)
+                .map_err(|e| TournamentError::InvalidProgram(name.clone(), e.to_string()))?,
+        );
+    }
+    Ok(Program::from_instructions(instructions))
+}
+
+// to_numbers converts the raw values of a case into 3-digit numbers
+fn to_numbers(values: &[i16]) -> Result<Vec<ThreeDigitNumber>, TournamentError> {
+    values
+        .iter()
+        .map(|value| {
+            ThreeDigitNumber::new(*value).map_err(|e| TournamentError::InvalidSpec(e.to_string()))
+        })
+        .collect()
+}
+
+// run_case runs the program against the inputs of a single case returning
+// every value output and the number of cycles taken, or None if the program
+// did not halt cleanly within the cycle budget
+pub fn run_case(
+    program: &Program,
+    inputs: &[ThreeDigitNumber],
+    max_cycles: usize,
+) -> Option<(Vec<ThreeDigitNumber>, usize)> {
+    let mut lmc = LMC::new(false, false, true, max_cycles);
+    lmc.set_counter_wrap(CounterWrap::Wrap);
+    lmc.load_program(program).ok()?;
+    // never fall back to reading stdin once the case's inputs are used up
+    lmc.set_io_limits(Some(inputs.len()), None);
+    lmc.load_input(inputs);
+    lmc.start();
+    loop {
+        if lmc.step().ok()? == StepResult::Halted {
+            return Some((lmc.get_outputs(), lmc.cycles()));
+        }
+    }
+}
+
+// build_cases collects the fixed cases from the spec and generates any random
+// ones, running the reference program to find their expected outputs
+fn build_cases(spec: &TournamentSpec, spec_dir: &Path) -> Result<Vec<Case>, TournamentError> {
+    let mut cases = Vec::new();
+    for case in &spec.cases {
+        cases.push((to_numbers(&case.inputs)?, to_numbers(&case.expected)?));
+    }
+    if let Some(generate) = &spec.generate {
+        let reference = load_machine_code(&spec_dir.join(&generate.reference))?;
+        let mut rng = Rng::new(generate.seed.unwrap_or_else(Rng::random_seed));
+        for _ in 0..generate.count {
+            let values = (0..generate.inputs)
+                .map(|_| rng.range(generate.min, generate.max))
+                .collect::<Vec<i16>>();
+            let inputs = to_numbers(&values)?;
+            let expected = match run_case(&reference, &inputs, spec.max_cycles) {
+                Some((outputs, _)) => outputs,
+                None => {
+                    return Err(TournamentError::InvalidProgram(
+                        generate.reference.clone(),
+                        format!("reference failed on inputs {:?}", values),
+                    ))
+                }
+            };
+            cases.push((inputs, expected));
+        }
+    }
+    Ok(cases)
+}
+
+// run_tournament runs every machine code (.lmc) file in the directory against
+// the cases of the spec, loaded from the spec directory, and returns the
+// standings ranked by the number of cases passed and then by the fewest total
+// cycles used on passing cases
+pub fn run_tournament(
+    dir: &str,
+    spec: &TournamentSpec,
+    spec_dir: &Path,
+) -> Result<Vec<Standing>, TournamentError> {
+    let cases = build_cases(spec, spec_dir)?;
+    let mut entries = fs::read_dir(dir)
+        .map_err(|e| TournamentError::IOError(e.to_string()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().map(|ext| ext == "lmc").unwrap_or(false))
+        .collect::<Vec<_>>();
+    entries.sort();
+    if entries.is_empty() {
+        return Err(TournamentError::NoEntrants);
+    }
+    let mut standings = Vec::new();
+    for path in entries {
+        let program = load_machine_code(&path)?;
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let mut standing = Standing {
+            name,
+            passed: 0,
+            total: cases.len(),
+            cycles: 0,
+        };
+        for (inputs, expected) in &cases {
+            if let Some((outputs, cycles)) = run_case(&program, inputs, spec.max_cycles) {
+                if &outputs == expected {
+                    standing.passed += 1;
+                    standing.cycles += cycles;
+                }
+            }
+        }
+        standings.push(standing);
+    }
+    standings.sort_by(|a, b| {
+        b.passed
+            .cmp(&a.passed)
+            .then(a.cycles.cmp(&b.cycles))
+            .then(a.name.cmp(&b.name))
+    });
+    Ok(standings)
+}
+
+// leaderboard renders the ranked standings in the given format
+pub fn leaderboard(standings: &[Standing], format: LeaderboardFormat) -> String {
+    let mut text = String::new();
+    match format {
+        LeaderboardFormat::Markdown => {
+            text.push_str("| Rank | Program | Passed | Cycles |\n");
+            text.push_str("| ---- | ------- | ------ | ------ |\n");
+            for (i, standing) in standings.iter().enumerate() {
+                text.push_str(&format!(
+                    "| {} | {} | {}/{} | {} |\n",
+                    i + 1,
+                    standing.name,
+                    standing.passed,
+                    standing.total,
+                    standing.cycles
+                ));
+            }
+        }
+        LeaderboardFormat::CSV => {
+            text.push_str("rank,program,passed,total,cycles\n");
+            for (i, standing) in standings.iter().enumerate() {
+                text.push_str(&format!(
+                    "{},{},{},{},{}\n",
+                    i + 1,
+                    standing.name,
+                    standing.passed,
+                    standing.total,
+                    standing.cycles
+                ));
+            }
+        }
+    }
+    text
+}
@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+use std::{fmt, fs::File, io::BufReader};
+
+use crate::schema::TRACE_SCHEMA;
+
+// TraceError is used to indicate an error reading or writing a trace
+#[derive(Debug, PartialEq)]
+pub enum TraceError {
+    IOError(String),
+    InvalidTrace(String),
+}
+
+// Implement the display trait for easy printing.
+impl fmt::Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceError::IOError(value) => write!(f, "IO error: {}", value),
+            TraceError::InvalidTrace(value) => write!(f, "invalid trace: {}", value),
+        }
+    }
+}
+
+// TraceEvent records a single executed instruction and the state of the
+// calculator and flag once it had been executed
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct TraceEvent {
+    pub cycle: usize,
+    pub address: u8,
+    pub instruction: i16,
+    pub calculator: i16,
+    // signed is the calculator read as ten's complement, recorded only when
+    // running in signed mode, e.g. -2 for a calculator of 998
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signed: Option<i16>,
+    pub flag: Option<String>,
+    // branch explains the decision made by a BR, BRZ or BRP instruction
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<BranchEvent>,
+    // wrapped is set when the counter wrapped from 99 back to 00 after the
+    // instruction, see CounterWrap
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub wrapped: bool,
+}
+
+// is_false is used to leave wrapped out of events where it did not happen
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+// BranchEvent records whether a branch was taken and why
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct BranchEvent {
+    pub taken: bool,
+    pub target: u8,
+    // reason is the register or flag value that decided the branch, e.g.
+    // "calculator is 000" for BRZ or "flag is NEG" for BRP
+    pub reason: String,
+}
+
+// Display explains the decision, e.g. "taken to 15: calculator is 000"
+impl fmt::Display for BranchEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.taken {
+            true => write!(f, "taken to {:02}: {}", self.target, self.reason),
+            false => write!(f, "not taken: {}", self.reason),
+        }
+    }
+}
+
+// Trace is the ordered list of instructions executed during a single run
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Trace {
+    // schema is the versioned format of the trace, traces written before it
+    // was added have the same format as the first version
+    #[serde(default = "default_schema")]
+    pub schema: String,
+    pub events: Vec<TraceEvent>,
+}
+
+fn default_schema() -> String {
+    TRACE_SCHEMA.to_string()
+}
+
+impl Default for Trace {
+    fn default() -> Self {
+        Trace::new()
+    }
+}
+
+impl Trace {
+    // new creates an empty Trace ready for recording
+    pub fn new() -> Self {
+        Trace {
+            schema: default_schema(),
+            events: Vec::new(),
+        }
+    }
+
+    // record appends an event to the end of the trace
+    pub fn record(&mut self, event: TraceEvent) {
+        self.events.push(event);
+    }
+
+    // save writes the trace to the given file as JSON
+    pub fn save(&self, path: &str) -> Result<(), TraceError> {
+        let file = File::create(path).map_err(|e| TraceError::IOError(e.to_string()))?;
+        serde_json::to_writer_pretty(file, self).map_err(|e| TraceError::IOError(e.to_string()))
+    }
+
+    // load reads a trace previously written by save
+    pub fn load(path: &str) -> Result<Self, TraceError> {
+        let file = File::open(path).map_err(|e| TraceError::IOError(e.to_string()))?;
+        let trace: Trace = serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| TraceError::InvalidTrace(e.to_string()))?;
+        if trace.schema != TRACE_SCHEMA {
+            return Err(TraceError::InvalidTrace(format!(
+                "unsupported schema {}, expected {}",
+                trace.schema, TRACE_SCHEMA
+            )));
+        }
+        Ok(trace)
+    }
+}
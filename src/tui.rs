@@ -0,0 +1,882 @@
+use ratatui::{
+    crossterm::{
+        cursor::Show,
+        event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+        execute,
+    },
+    layout::{Constraint, Layout as Split, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Wrap},
+    DefaultTerminal, Frame,
+};
+use std::{
+    collections::BTreeSet,
+    io::{self, stdout},
+    ops::Range,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    config,
+    debugger::{Debugger, Layout, Panel},
+    devices::KeyboardHandle,
+    instruction::Instruction,
+    lmc::{join_numbers, MachineState, LMC},
+    numbers::ThreeDigitNumber,
+    program::Program,
+    session::Session,
+};
+
+// TUI_KEYS lists the keys shown at the bottom of the screen
+const TUI_KEYS: &str = "space step/pause  r run  +/- speed  i input  arrows cursor  g goto  \
+/ search  n next match  b bookmark  ' next bookmark  v view  1-5 show/hide panel  \
+tab select panel  [/] resize  k keyboard  q quit";
+
+// TYPING_KEYS is shown in place of TUI_KEYS while keys go to the keyboard
+const TYPING_KEYS: &str = "every key goes to the keyboard at 96  esc stop typing  ctrl-c quit";
+
+// PROMPT_KEYS is shown in place of TUI_KEYS while a prompt is open
+const PROMPT_KEYS: &str = "enter accept  esc cancel  ctrl-c quit";
+
+// PLAYER_KEYS lists the keys shown at the bottom of the screen while playing
+// back a session
+const PLAYER_KEYS: &str =
+    "space play/pause  +/- speed  left/right previous/next frame  home/end first/last frame  q quit";
+
+// PANELS are the panels the number keys show and hide, in key order
+const PANELS: [Panel; 5] = [
+    Panel::Registers,
+    Panel::Source,
+    Panel::Memory,
+    Panel::Io,
+    Panel::Trace,
+];
+
+// MIN_DELAY and MAX_DELAY bound the time a running program waits between
+// cycles as it is sped up and slowed down
+const MIN_DELAY: Duration = Duration::from_millis(10);
+const MAX_DELAY: Duration = Duration::from_millis(2000);
+
+// Prompt is what a line typed at the bottom of the screen is for
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Prompt {
+    Input,
+    Goto,
+    Search,
+}
+
+impl Prompt {
+    // label returns the text shown before the line being typed
+    fn label(&self) -> &str {
+        match self {
+            Prompt::Input => "input: ",
+            Prompt::Goto => "goto: ",
+            Prompt::Search => "search: ",
+        }
+    }
+}
+
+// Tui is a full-screen front-end to the debugger that runs a program while
+// drawing the panels of its layout, redrawn after every cycle. Keys act as
+// soon as they are pressed, so that a running program can be paused at any
+// time, and the terminal is put back as it was however the tui ends.
+pub struct Tui {
+    debugger: Debugger,
+    // name is the file the program was loaded from, shown in the title
+    name: String,
+    // delay is how long a running program waits between cycles
+    delay: Duration,
+    running: bool,
+    // interactive has IN wait for a value to be typed once the input tray
+    // is empty, rather than reading from the machine's input
+    interactive: bool,
+    signed: bool,
+    // plain draws the panels as the debugger's plain lines of text, without
+    // borders, grids or styles, for screen readers
+    plain: bool,
+    // typing sends every key pressed to the keyboard device
+    typing: bool,
+    // prompt is the line being typed at the bottom of the screen, if any
+    prompt: Option<(Prompt, String)>,
+    // selected is the panel the resize keys act on
+    selected: Option<Panel>,
+    // previous is the machine before the last cycle, so that the mailbox the
+    // cycle changed can be highlighted
+    previous: Option<MachineState>,
+    // config is the config file the layout is saved to when it changes
+    config: Option<PathBuf>,
+    message: String,
+}
+
+impl Tui {
+    // new creates a front-end for the LMC, which must already have the
+    // program loaded
+    pub fn new(lmc: LMC, program: Program, name: &str) -> Self {
+        let mut debugger = Debugger::new(lmc, program);
+        debugger.set_layout(Layout::full_screen());
+        Tui {
+            debugger,
+            name: name.to_string(),
+            delay: Duration::from_millis(250),
+            running: false,
+            interactive: true,
+            signed: false,
+            plain: false,
+            typing: false,
+            prompt: None,
+            selected: None,
+            previous: None,
+            config: None,
+            message: String::new(),
+        }
+    }
+
+    // set_source gives the assembly the program was assembled from, so that
+    // the source panel shows it
+    pub fn set_source(&mut self, source: Vec<String>) {
+        self.debugger.set_source(source);
+    }
+
+    // set_delay sets how long a running program waits between cycles
+    pub fn set_delay(&mut self, delay: Duration) {
+        self.delay = delay.clamp(MIN_DELAY, MAX_DELAY);
+    }
+
+    // set_interactive sets whether IN waits for a value to be typed once the
+    // input tray is empty, by default true. The LMC should be given an input
+    // of its own when it is not.
+    pub fn set_interactive(&mut self, interactive: bool) {
+        self.interactive = interactive;
+    }
+
+    // set_signed has typed inputs read as signed numbers, see --signed
+    pub fn set_signed(&mut self, signed: bool) {
+        self.signed = signed;
+    }
+
+    // set_plain sets whether to draw the panels as plain lines of text
+    pub fn set_plain(&mut self, plain: bool) {
+        self.plain = plain;
+        self.debugger.set_plain(plain);
+    }
+
+    // set_keyboard gives the handle of the keyboard device attached to the
+    // LMC, so that keys typed in keyboard mode press it
+    pub fn set_keyboard(&mut self, keyboard: KeyboardHandle) {
+        self.debugger.set_keyboard(keyboard);
+    }
+
+    // set_layout sets the panels drawn and their sizes
+    pub fn set_layout(&mut self, layout: Layout) {
+        self.debugger.set_layout(layout);
+    }
+
+    // set_config sets the config file the layout is saved to whenever it is
+    // changed, so that it is kept for the next time
+    pub fn set_config(&mut self, path: PathBuf) {
+        self.config = Some(path);
+    }
+
+    // run takes over the terminal until q or Ctrl-C is pressed, returning how
+    // the run ended if it did. The terminal is restored on the way out, on
+    // an error reading the keys and on a panic.
+    pub fn run(&mut self) -> io::Result<Option<String>> {
+        take_terminal(|terminal| self.event_loop(terminal))?;
+        let cycle = self.debugger.lmc().state().cycle;
+        Ok(self
+            .debugger
+            .stopped()
+            .map(|stopped| format!("{} after {} cycles", stopped, cycle)))
+    }
+
+    // take_session returns the session recorded by the LMC, see
+    // LMC::record_session, if any
+    pub fn take_session(&mut self) -> Option<Session> {
+        self.debugger.lmc_mut().take_session()
+    }
+
+    // event_loop draws the screen and handles keys until quit, stepping a
+    // running program whenever its delay is up
+    fn event_loop(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        let mut deadline = Instant::now() + self.delay;
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let active = self.running && self.prompt.is_none() && !self.finished();
+            let timeout = match active {
+                true => deadline.saturating_duration_since(Instant::now()),
+                false => Duration::from_secs(60),
+            };
+            if event::poll(timeout)? {
+                match event::read()? {
+                    Event::Key(key) if key.kind == KeyEventKind::Press && !self.key(key) => {
+                        return Ok(());
+                    }
+                    _ => {}
+                }
+            } else if active {
+                self.step();
+                deadline = Instant::now() + self.delay;
+            }
+        }
+    }
+
+    // key acts on a key press, returning false to quit
+    fn key(&mut self, key: KeyEvent) -> bool {
+        // Ctrl-C and Ctrl-D, the end of input, quit whatever is being typed
+        if key.modifiers.contains(KeyModifiers::CONTROL)
+            && matches!(key.code, KeyCode::Char('c' | 'd'))
+        {
+            return false;
+        }
+        if self.prompt.is_some() {
+            self.prompt_key(key.code);
+            return true;
+        }
+        if self.typing {
+            match key.code {
+                KeyCode::Esc => self.typing = false,
+                KeyCode::Enter => self.message = self.debugger.press('\n'),
+                KeyCode::Char(key) => self.message = self.debugger.press(key),
+                _ => {}
+            }
+            return true;
+        }
+        self.message.clear();
+        let cursor = self.debugger.cursor();
+        match key.code {
+            KeyCode::Char('q') => return false,
+            KeyCode::Char(' ' | 's') if self.running => self.running = false,
+            KeyCode::Char(' ' | 's') => self.step(),
+            KeyCode::Char('r') => self.running = !self.running,
+            KeyCode::Char('+' | '=') => self.delay = (self.delay / 2).max(MIN_DELAY),
+            KeyCode::Char('-') => self.delay = (self.delay * 2).min(MAX_DELAY),
+            KeyCode::Char('i') => self.prompt = Some((Prompt::Input, String::new())),
+            KeyCode::Char('g') => self.prompt = Some((Prompt::Goto, String::new())),
+            KeyCode::Char('/') => self.prompt = Some((Prompt::Search, String::new())),
+            KeyCode::Char('n') => self.find(""),
+            KeyCode::Char('b') => self.message = self.debugger.bookmark(cursor),
+            KeyCode::Char('\'') => {
+                let bookmarks = self.debugger.bookmarked();
+                match bookmarks.range(cursor + 1..).next().or(bookmarks.first()) {
+                    Some(address) => self.debugger.set_cursor(*address),
+                    None => self.message = "no bookmarks, b bookmarks the cursor".to_string(),
+                }
+            }
+            KeyCode::Char('v') => self.message = self.debugger.toggle_view(),
+            KeyCode::Char('k') => self.typing = true,
+            KeyCode::Char(number @ '1'..='5') => {
+                let panel = PANELS[number as usize - '1' as usize];
+                self.toggle_panel(panel);
+            }
+            KeyCode::Tab => self.select_panel(),
+            KeyCode::Char('[') => self.resize_panel(false),
+            KeyCode::Char(']') => self.resize_panel(true),
+            KeyCode::Left => self.debugger.set_cursor(cursor.saturating_sub(1)),
+            KeyCode::Right => self.debugger.set_cursor(cursor + 1),
+            KeyCode::Up => self.debugger.set_cursor(cursor.saturating_sub(10)),
+            KeyCode::Down => self.debugger.set_cursor(cursor + 10),
+            _ => {}
+        }
+        true
+    }
+
+    // prompt_key edits the line being typed at the prompt, acting on it once
+    // enter is pressed
+    fn prompt_key(&mut self, code: KeyCode) {
+        let (prompt, text) = match &mut self.prompt {
+            Some(prompt) => prompt,
+            None => return,
+        };
+        match code {
+            KeyCode::Char(key) => text.push(key),
+            KeyCode::Backspace => {
+                text.pop();
+            }
+            KeyCode::Esc => {
+                // a program left waiting for input is paused
+                self.running &= !self.needs_input();
+                self.prompt = None;
+            }
+            KeyCode::Enter => {
+                let (prompt, text) = (*prompt, text.trim().to_string());
+                self.prompt = None;
+                match prompt {
+                    Prompt::Input => self.input(&text),
+                    Prompt::Goto => match self.debugger.goto(&text) {
+                        Ok(()) => self.message = format!("cursor at {:02}", self.debugger.cursor()),
+                        Err(err) => self.message = err,
+                    },
+                    Prompt::Search => self.find(&text),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // input loads a typed value into the input tray, asking again if it is
+    // not a valid input
+    fn input(&mut self, text: &str) {
+        let number = text.parse::<i16>().map(|value| match self.signed {
+            true => ThreeDigitNumber::from_signed(value),
+            false => ThreeDigitNumber::new(value),
+        });
+        match number {
+            Ok(Ok(number)) => {
+                self.debugger.lmc_mut().load_input(&[number]);
+                return;
+            }
+            Ok(Err(err)) => self.message = format!("invalid input: {}", err),
+            Err(_) => self.message = format!("invalid input: {}", text),
+        }
+        self.prompt = Some((Prompt::Input, String::new()));
+    }
+
+    // find moves the cursor to the next mailbox matching the search
+    fn find(&mut self, query: &str) {
+        self.message = match self.debugger.find(query) {
+            Ok(found) => format!("{}, cursor at {:02}", found, self.debugger.cursor()),
+            Err(err) => err,
+        };
+    }
+
+    // step executes the next instruction, unless the run has ended or the
+    // instruction is waiting for an input to be typed, in which case the
+    // input prompt is opened
+    fn step(&mut self) {
+        if let Some(stopped) = self.debugger.stopped() {
+            self.message = format!("the program has {}", stopped);
+            return;
+        }
+        if !self.needs_input() {
+            self.previous = Some(self.debugger.lmc().state());
+            if self.debugger.step().is_some() {
+                self.running = false;
+            }
+        }
+        // IN with nothing to read asks for a value
+        if self.needs_input() {
+            self.prompt = Some((Prompt::Input, String::new()));
+        }
+    }
+
+    // finished returns whether the run has ended or is waiting for input
+    fn finished(&self) -> bool {
+        self.debugger.stopped().is_some() || self.needs_input()
+    }
+
+    // needs_input returns whether the next instruction is an IN waiting for
+    // a value to be typed
+    fn needs_input(&self) -> bool {
+        let state = self.debugger.lmc().state();
+        let next = state.mailboxes[state.counter.value() as usize];
+        self.interactive
+            && self.debugger.stopped().is_none()
+            && state.inputs.is_empty()
+            && Instruction::decode(next) == Instruction::In
+    }
+
+    // toggle_panel shows a hidden panel below the others or hides a shown one
+    fn toggle_panel(&mut self, panel: Panel) {
+        match self.debugger.layout().panels.contains(&panel) {
+            true => {
+                self.debugger.hide_panel(panel);
+                self.message = format!("hid the {} panel", panel);
+                if self.selected == Some(panel) {
+                    self.selected = None;
+                }
+            }
+            false => {
+                self.debugger.show_panel(panel);
+                self.message = format!("showing the {} panel", panel);
+            }
+        }
+        self.save_layout();
+    }
+
+    // select_panel selects the next panel shown for resizing, then none
+    fn select_panel(&mut self) {
+        let panels = &self.debugger.layout().panels;
+        let next = match self.selected {
+            Some(selected) => panels
+                .iter()
+                .position(|panel| *panel == selected)
+                .map(|i| i + 1),
+            None => Some(0),
+        };
+        self.selected = next.and_then(|next| panels.get(next)).copied();
+        if let Some(panel) = self.selected {
+            self.message = format!("selected the {} panel, [ and ] resize it", panel);
+        }
+    }
+
+    // resize_panel grows or shrinks the selected panel by a line
+    fn resize_panel(&mut self, grow: bool) {
+        let panel = match self.selected {
+            Some(panel) => panel,
+            None => {
+                self.message = "no panel is selected, tab selects one".to_string();
+                return;
+            }
+        };
+        let layout = self.debugger.layout();
+        let lines = match panel {
+            Panel::Registers => {
+                self.message = "the registers panel has a fixed size".to_string();
+                return;
+            }
+            Panel::Source => layout.source_lines,
+            Panel::Memory => layout.memory_rows,
+            Panel::Io => layout.io_values,
+            Panel::Trace => layout.trace_lines,
+        };
+        let lines = match grow {
+            true => lines + 1,
+            false => lines.saturating_sub(1).max(1),
+        };
+        // memory has only ten rows to show
+        let lines = match panel {
+            Panel::Memory => lines.min(10),
+            _ => lines,
+        };
+        self.debugger.resize_panel(panel, lines);
+        self.message = format!("the {} panel is {} long", panel, lines);
+        self.save_layout();
+    }
+
+    // save_layout writes the layout to the tui section of the config file,
+    // if there is one
+    fn save_layout(&mut self) {
+        let path = match &self.config {
+            Some(path) => path,
+            None => return,
+        };
+        let saved = config::load(path).and_then(|mut saved| {
+            saved.tui = self.debugger.layout().clone();
+            config::save(path, &saved)
+        });
+        if let Err(err) = saved {
+            self.message = format!("{}, layout not saved: {}", self.message, err);
+        }
+    }
+
+    // status describes the state of the run for the title
+    fn status(&self) -> String {
+        let cycle = self.debugger.lmc().state().cycle;
+        match self.debugger.stopped() {
+            Some(stopped) => format!("{} after {} cycles", stopped, cycle),
+            None if self.typing => "typing on the keyboard".to_string(),
+            None if self.needs_input() => "waiting for input".to_string(),
+            None if self.running => format!("running, {}ms per cycle", self.delay.as_millis()),
+            None => "paused".to_string(),
+        }
+    }
+
+    // draw draws the title, the panels of the layout with any devices to
+    // their right, the message or prompt and the keys
+    fn draw(&self, frame: &mut Frame) {
+        let help = match (&self.prompt, self.typing) {
+            (Some(_), _) => PROMPT_KEYS,
+            (None, true) => TYPING_KEYS,
+            (None, false) => TUI_KEYS,
+        };
+        // the keys wrap onto as many lines as the width of the screen needs
+        let width = frame.area().width.max(1) as usize;
+        let help_lines = (help.len() / width + 2).min(4) as u16;
+        let [title, body, message, keys] = Split::vertical([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(1),
+            Constraint::Length(help_lines),
+        ])
+        .areas(frame.area());
+        let title_text = match self.plain {
+            true => format!("LMC {}, {}", self.name, self.status()),
+            false => format!("LMC {}  [{}]", self.name, self.status()),
+        };
+        frame.render_widget(
+            Paragraph::new(title_text).style(self.style(Modifier::BOLD)),
+            title,
+        );
+
+        let devices = self.debugger.lmc().render_devices().concat();
+        let body = match devices.trim_end().is_empty() {
+            true => body,
+            false => {
+                let width = devices
+                    .lines()
+                    .map(|line| line.chars().count())
+                    .max()
+                    .unwrap_or(0);
+                let [panels, right] =
+                    Split::horizontal([Constraint::Min(0), Constraint::Length(width as u16 + 2)])
+                        .areas(body);
+                let lines = devices.trim_end().lines().count() as u16;
+                let area = Rect {
+                    height: (lines + 2).min(right.height),
+                    ..right
+                };
+                frame.render_widget(
+                    Paragraph::new(devices.trim_end().to_string())
+                        .block(self.block("devices", false)),
+                    area,
+                );
+                panels
+            }
+        };
+        self.draw_panels(frame, body);
+
+        match &self.prompt {
+            Some((prompt, text)) => {
+                let line = format!("{}{}", prompt.label(), text);
+                let column = line.chars().count() as u16;
+                frame.render_widget(Paragraph::new(line), message);
+                frame.set_cursor_position((message.x + column, message.y));
+            }
+            None => frame.render_widget(Paragraph::new(self.message.as_str()), message),
+        }
+        frame.render_widget(
+            Paragraph::new(help)
+                .style(self.style(Modifier::DIM))
+                .wrap(Wrap { trim: true }),
+            keys,
+        );
+    }
+
+    // draw_panels stacks the panels of the layout in the area, each as tall
+    // as its contents
+    fn draw_panels(&self, frame: &mut Frame, area: Rect) {
+        let panels = self
+            .debugger
+            .layout()
+            .panels
+            .iter()
+            .map(|panel| {
+                let text = self.debugger.panel(*panel);
+                let lines = match (panel, self.plain) {
+                    (Panel::Memory, false) => self.memory(),
+                    // plain lines are wrapped rather than cut off at the edge
+                    (_, true) => text
+                        .lines()
+                        .flat_map(|line| wrap(line, area.width as usize))
+                        .map(Line::from)
+                        .collect(),
+                    (_, false) => text
+                        .lines()
+                        .map(|line| Line::from(line.to_string()))
+                        .collect(),
+                };
+                (*panel, lines)
+            })
+            .collect::<Vec<(Panel, Vec<Line>)>>();
+        // plain has a line naming each panel in place of a border
+        let border = match self.plain {
+            true => 1,
+            false => 2,
+        };
+        let mut constraints = panels
+            .iter()
+            .map(|(_, lines)| Constraint::Length(lines.len() as u16 + border))
+            .collect::<Vec<Constraint>>();
+        constraints.push(Constraint::Min(0));
+        let areas = Split::vertical(constraints).split(area);
+        for ((panel, lines), area) in panels.into_iter().zip(areas.iter()) {
+            let number = PANELS.iter().position(|shown| *shown == panel).unwrap_or(0) + 1;
+            let title = format!("{} {}", number, panel);
+            let selected = self.selected == Some(panel);
+            frame.render_widget(
+                Paragraph::new(lines).block(self.block(&title, selected)),
+                *area,
+            );
+        }
+    }
+
+    // block returns the border drawn around a panel, or the line naming it
+    // when plain
+    fn block(&self, title: &str, selected: bool) -> Block<'static> {
+        if self.plain {
+            let selected = if selected { ", selected" } else { "" };
+            return Block::new()
+                .borders(Borders::NONE)
+                .title(format!("{} panel{}:", title, selected));
+        }
+        let style = match selected {
+            true => Style::new().fg(Color::Cyan),
+            false => Style::new(),
+        };
+        Block::new()
+            .borders(Borders::ALL)
+            .border_style(style)
+            .title(format!(" {} ", title))
+    }
+
+    // style returns a style with the modifier, or no style when plain
+    fn style(&self, modifier: Modifier) -> Style {
+        match self.plain {
+            true => Style::new(),
+            false => Style::new().add_modifier(modifier),
+        }
+    }
+
+    // memory draws the rows of the mailboxes the layout asks for around the
+    // cursor, see mailbox_rows
+    fn memory(&self) -> Vec<Line<'static>> {
+        let state = self.debugger.lmc().state();
+        let cursor = self.debugger.cursor();
+        let rows = self.debugger.layout().memory_rows.clamp(1, 10);
+        let first = (cursor / 10).saturating_sub(rows / 2).min(10 - rows);
+        let mut lines = vec![column_numbers()];
+        lines.extend(mailbox_rows(
+            &state,
+            self.previous.as_ref(),
+            first..first + rows,
+            Some(cursor),
+            self.debugger.bookmarked(),
+        ));
+        lines.push(Line::from(format!(
+            "cursor: {}",
+            self.debugger.machine_code(cursor)
+        )));
+        lines
+    }
+}
+
+// Player is a full-screen front-end that replays a recorded session, drawing
+// each frame as the tui draws a running program: the registers, the mailboxes
+// with the counter and the mailbox the last cycle changed highlighted, and the
+// baskets. Playback can be paused, sped up and slowed down and stepped through
+// a frame at a time in either direction.
+pub struct Player {
+    session: Session,
+    // name is the file the session was loaded from, shown in the title
+    name: String,
+    // delay is how long each frame is shown while playing
+    delay: Duration,
+    playing: bool,
+    // frame is the index of the frame shown
+    frame: usize,
+}
+
+impl Player {
+    // new creates a player for the session, playing from its first frame
+    pub fn new(session: Session, name: &str) -> Self {
+        Player {
+            session,
+            name: name.to_string(),
+            delay: Duration::from_millis(250),
+            playing: true,
+            frame: 0,
+        }
+    }
+
+    // set_delay sets how long each frame is shown while playing
+    pub fn set_delay(&mut self, delay: Duration) {
+        self.delay = delay.clamp(MIN_DELAY, MAX_DELAY);
+    }
+
+    // run takes over the terminal until q or Ctrl-C is pressed, stopping on
+    // the last frame once it is reached. The terminal is restored on the way
+    // out, on an error reading the keys and on a panic.
+    pub fn run(&mut self) -> io::Result<()> {
+        take_terminal(|terminal| self.event_loop(terminal))
+    }
+
+    // event_loop draws the frame and handles keys until quit, moving on to
+    // the next frame whenever its delay is up while playing
+    fn event_loop(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        let mut deadline = Instant::now() + self.delay;
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let last = self.session.frames().len().saturating_sub(1);
+            if self.frame >= last {
+                self.playing = false;
+            }
+            let timeout = match self.playing {
+                true => deadline.saturating_duration_since(Instant::now()),
+                false => Duration::from_secs(60),
+            };
+            if event::poll(timeout)? {
+                let key = match event::read()? {
+                    Event::Key(key) if key.kind == KeyEventKind::Press => key,
+                    _ => continue,
+                };
+                if key.modifiers.contains(KeyModifiers::CONTROL)
+                    && matches!(key.code, KeyCode::Char('c' | 'd'))
+                {
+                    return Ok(());
+                }
+                match key.code {
+                    KeyCode::Char('q') => return Ok(()),
+                    // playing on from the last frame starts again
+                    KeyCode::Char(' ') if self.frame >= last => {
+                        self.frame = 0;
+                        self.playing = true;
+                    }
+                    KeyCode::Char(' ') => self.playing = !self.playing,
+                    KeyCode::Char('+' | '=') => self.delay = (self.delay / 2).max(MIN_DELAY),
+                    KeyCode::Char('-') => self.delay = (self.delay * 2).min(MAX_DELAY),
+                    KeyCode::Left => {
+                        self.playing = false;
+                        self.frame = self.frame.saturating_sub(1);
+                    }
+                    KeyCode::Right => {
+                        self.playing = false;
+                        self.frame = (self.frame + 1).min(last);
+                    }
+                    KeyCode::Home => self.frame = 0,
+                    KeyCode::End => self.frame = last,
+                    _ => {}
+                }
+            } else if self.playing {
+                self.frame += 1;
+                deadline = Instant::now() + self.delay;
+            }
+        }
+    }
+
+    // draw draws the title, the frame and the keys
+    fn draw(&self, frame: &mut Frame) {
+        let frames = self.session.frames();
+        let status = match (self.playing, frames.len()) {
+            (_, 0) => "no frames recorded".to_string(),
+            (true, _) => format!("playing, {}ms per frame", self.delay.as_millis()),
+            (false, _) => "paused".to_string(),
+        };
+        let [title, registers, memory, io, keys] = Split::vertical([
+            Constraint::Length(1),
+            Constraint::Length(3),
+            Constraint::Length(13),
+            Constraint::Length(3),
+            Constraint::Min(0),
+        ])
+        .areas(frame.area());
+        frame.render_widget(
+            Paragraph::new(format!(
+                "LMC {}  [frame {} of {}, {}]",
+                self.name,
+                (self.frame + 1).min(frames.len()),
+                frames.len(),
+                status
+            ))
+            .style(Style::new().add_modifier(Modifier::BOLD)),
+            title,
+        );
+        if let Some(state) = frames.get(self.frame) {
+            let previous = self.frame.checked_sub(1).map(|i| &frames[i]);
+            let flag = state.flag.map_or("-".to_string(), |flag| flag.to_string());
+            let output = state
+                .output
+                .map_or("-".to_string(), |number| number.to_string());
+            let text = format!(
+                "counter: {}  calculator: {}  flag: {}  output: {}  cycle: {}",
+                state.counter, state.calculator, flag, output, state.cycle
+            );
+            frame.render_widget(Paragraph::new(text).block(bordered("registers")), registers);
+            let mut lines = vec![column_numbers()];
+            lines.extend(mailbox_rows(state, previous, 0..10, None, &BTreeSet::new()));
+            frame.render_widget(Paragraph::new(lines).block(bordered("memory")), memory);
+            let text = format!(
+                "inputs: {}  outputs: {}",
+                join_numbers(&state.inputs),
+                join_numbers(&state.outputs)
+            );
+            frame.render_widget(Paragraph::new(text).block(bordered("io")), io);
+        }
+        frame.render_widget(
+            Paragraph::new(PLAYER_KEYS)
+                .style(Style::new().add_modifier(Modifier::DIM))
+                .wrap(Wrap { trim: true }),
+            keys,
+        );
+    }
+}
+
+// take_terminal switches the terminal to the alternate screen and raw mode
+// for the loop, putting it back as it was once the loop returns, whether it
+// returns an error, and on a panic
+fn take_terminal<F>(event_loop: F) -> io::Result<()>
+where
+    F: FnOnce(&mut DefaultTerminal) -> io::Result<()>,
+{
+    let mut terminal = ratatui::try_init()?;
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = execute!(stdout(), Show);
+        hook(info);
+    }));
+    let result = event_loop(&mut terminal);
+    ratatui::restore();
+    let _ = execute!(stdout(), Show);
+    result
+}
+
+// bordered returns the border drawn around a panel with the title
+fn bordered(title: &str) -> Block<'static> {
+    Block::new()
+        .borders(Borders::ALL)
+        .title(format!(" {} ", title))
+}
+
+// column_numbers returns the line above the mailboxes numbering the columns
+fn column_numbers() -> Line<'static> {
+    Line::from(format!(
+        "   {}",
+        (0..10)
+            .map(|column| format!("  {}  ", column))
+            .collect::<String>()
+    ))
+}
+
+// mailbox_rows draws rows of the mailboxes ten to a row, the counter in
+// reverse video, the mailboxes changed since the previous state in bold,
+// bookmarks in yellow and the cursor, if there is one, in brackets
+fn mailbox_rows(
+    state: &MachineState,
+    previous: Option<&MachineState>,
+    rows: Range<usize>,
+    cursor: Option<usize>,
+    bookmarks: &BTreeSet<usize>,
+) -> Vec<Line<'static>> {
+    rows.map(|row| {
+        let mut spans = vec![Span::raw(format!("{}0 ", row))];
+        for address in row * 10..row * 10 + 10 {
+            let value = state.mailboxes[address];
+            let text = match cursor == Some(address) {
+                true => format!("[{}]", value),
+                false => format!(" {} ", value),
+            };
+            let mut style = Style::new();
+            if address == state.counter.value() as usize {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
+            if previous.is_some_and(|previous| previous.mailboxes[address] != value) {
+                style = style.add_modifier(Modifier::BOLD);
+            }
+            if bookmarks.contains(&address) {
+                style = style.fg(Color::Yellow);
+            }
+            spans.push(Span::styled(text, style));
+        }
+        Line::from(spans)
+    })
+    .collect()
+}
+
+// wrap breaks a line of text between words into lines no wider than the
+// width, a word wider than the width being given a line of its own
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![String::new()];
+    for word in text.split_whitespace() {
+        let line = lines.last_mut().unwrap();
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+            lines.push(word.to_string());
+        } else {
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+    }
+    lines
+}
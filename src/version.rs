@@ -0,0 +1,41 @@
+use serde_json::{json, Value};
+
+use crate::conformance::Dialect;
+
+// VERSION is the version of the crate lmc was built from
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// COMMIT is the short hash of the git commit lmc was built from, or unknown
+pub const COMMIT: &str = env!("LMC_GIT_COMMIT");
+
+// version describes the build for lmc --version, e.g.:
+// lmc 0.1.0 (commit 1a2b3c4)
+// dialects: classic, extended, higginson (default classic)
+pub fn version() -> String {
+    let dialects = Dialect::all()
+        .iter()
+        .map(|dialect| dialect.to_string())
+        .collect::<Vec<String>>();
+    format!(
+        "lmc {} (commit {})\ndialects: {} (default {})",
+        VERSION,
+        COMMIT,
+        dialects.join(", "),
+        Dialect::Classic
+    )
+}
+
+// build_info describes the build as JSON, so that a report records exactly
+// which lmc produced it
+pub fn build_info() -> Value {
+    let dialects = Dialect::all()
+        .iter()
+        .map(|dialect| dialect.to_string())
+        .collect::<Vec<String>>();
+    json!({
+        "version": VERSION,
+        "commit": COMMIT,
+        "dialects": dialects,
+        "default_dialect": Dialect::Classic.to_string(),
+    })
+}